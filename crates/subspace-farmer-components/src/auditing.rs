@@ -6,8 +6,11 @@
 
 use crate::proving::SolutionCandidates;
 use crate::sector::{sector_size, SectorContentsMap, SectorMetadataChecksummed};
-use crate::{ReadAtOffset, ReadAtSync};
+use crate::{ReadAtAsync, ReadAtOffset, ReadAtSync};
+use futures::stream::FuturesOrdered;
+use futures::TryStreamExt;
 use rayon::prelude::*;
+use rayon::ThreadPool;
 use std::collections::HashSet;
 use std::io;
 use subspace_core_primitives::crypto::Scalar;
@@ -185,6 +188,114 @@ where
         .collect()
 }
 
+/// Audit the whole plot and generate streams of results, similar to [`audit_plot_sync`], but
+/// s-buckets are read concurrently using [`ReadAtAsync`] implementation `async_plot`.
+///
+/// This allows storage to receive all reads at once (deep I/O queue) regardless of the number of
+/// threads, which is especially beneficial for HDDs. `plot` and `async_plot` must read the same
+/// data, `plot` is used by solution candidates during proving.
+///
+/// CPU-intensive parts of the audit run on `thread_pool`, while reads are awaited by whoever polls
+/// returned future, such that no thread pool threads are blocked waiting for I/O.
+#[allow(clippy::too_many_arguments)]
+pub async fn audit_plot_async<'a, 'b, Plot, AsyncPlot>(
+    public_key: &'a PublicKey,
+    global_challenge: &Blake3Hash,
+    solution_range: SolutionRange,
+    plot: &'a Plot,
+    async_plot: &AsyncPlot,
+    sectors_metadata: &'a [SectorMetadataChecksummed],
+    sectors_being_modified: &'b HashSet<SectorIndex>,
+    thread_pool: &ThreadPool,
+) -> Result<Vec<AuditResult<'a, ReadAtOffset<'a, Plot>>>, AuditingError>
+where
+    Plot: ReadAtSync + 'a,
+    AsyncPlot: ReadAtAsync,
+{
+    let public_key_hash = public_key.hash();
+
+    // Create auditing info for all sectors in parallel
+    let sectors_auditing_details = thread_pool.install(|| {
+        sectors_metadata
+            .par_iter()
+            .filter(|sector_metadata| {
+                // Skip sector that is being modified right now
+                !sectors_being_modified.contains(&sector_metadata.sector_index)
+            })
+            .map(|sector_metadata| {
+                (
+                    collect_sector_auditing_details(
+                        public_key_hash,
+                        global_challenge,
+                        sector_metadata,
+                    ),
+                    sector_metadata,
+                )
+            })
+            // Skip sectors with empty s-bucket
+            .filter(|(sector_auditing_info, _sector_metadata)| {
+                sector_auditing_info.s_bucket_audit_size != 0
+            })
+            .collect::<Vec<_>>()
+    });
+
+    // Read s-buckets of all sectors concurrently
+    let s_buckets = sectors_auditing_details
+        .iter()
+        .map(|(sector_auditing_info, sector_metadata)| async move {
+            let sector_offset = u64::from(sector_metadata.sector_index)
+                * sector_size(sector_metadata.pieces_in_sector) as u64;
+
+            async_plot
+                .read_at(
+                    vec![0; sector_auditing_info.s_bucket_audit_size],
+                    sector_offset + sector_auditing_info.s_bucket_audit_offset_in_sector,
+                )
+                .await
+                .map_err(|error| AuditingError::SBucketReading {
+                    sector_index: sector_metadata.sector_index,
+                    s_bucket_audit_index: sector_auditing_info.s_bucket_audit_index,
+                    error,
+                })
+        })
+        .collect::<FuturesOrdered<_>>()
+        .try_collect::<Vec<_>>()
+        .await?;
+
+    // Map s-buckets to winning chunks and then to audit results in parallel
+    Ok(thread_pool.install(|| {
+        sectors_auditing_details
+            .into_par_iter()
+            .zip(s_buckets)
+            .filter_map(|((sector_auditing_info, sector_metadata), s_bucket)| {
+                let winning_chunks = map_winning_chunks(
+                    &s_bucket,
+                    global_challenge,
+                    &sector_auditing_info.sector_slot_challenge,
+                    solution_range,
+                )?;
+
+                let sector = plot.offset(
+                    u64::from(sector_metadata.sector_index)
+                        * sector_size(sector_metadata.pieces_in_sector) as u64,
+                );
+
+                Some(AuditResult {
+                    sector_index: sector_metadata.sector_index,
+                    solution_candidates: SolutionCandidates::new(
+                        public_key,
+                        sector_auditing_info.sector_id,
+                        sector_auditing_info.s_bucket_audit_index,
+                        sector,
+                        sector_metadata,
+                        winning_chunks.into(),
+                    ),
+                })
+            })
+            .collect()
+    }))
+}
+
 struct SectorAuditingDetails {
     sector_id: SectorId,
     sector_slot_challenge: SectorSlotChallenge,
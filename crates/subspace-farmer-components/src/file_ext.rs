@@ -9,11 +9,13 @@ pub trait OpenOptionsExt {
     /// undesirable, only has impact on Windows, for other operating systems see [`FileExt`]
     fn advise_random_access(&mut self) -> &mut Self;
 
-    /// Advise Windows to not use buffering for this file and that file access will be random.
+    /// Advise Windows to not use buffering for this file and that file access will be random, on
+    /// Linux opens file with `O_DIRECT`.
     ///
     /// NOTE: There are major alignment requirements described here:
     /// https://learn.microsoft.com/en-us/windows/win32/fileio/file-buffering#alignment-and-file-access-requirements
-    #[cfg(windows)]
+    /// and in `open(2)` man page on Linux.
+    #[cfg(any(windows, target_os = "linux"))]
     fn advise_unbuffered(&mut self) -> &mut Self;
 
     /// Advise OS/file system that file will use sequential access and read-ahead behavior is
@@ -56,6 +58,12 @@ impl OpenOptionsExt for OpenOptions {
         )
     }
 
+    #[cfg(target_os = "linux")]
+    fn advise_unbuffered(&mut self) -> &mut Self {
+        use std::os::unix::fs::OpenOptionsExt;
+        self.custom_flags(libc::O_DIRECT)
+    }
+
     #[cfg(target_os = "linux")]
     fn advise_sequential_access(&mut self) -> &mut Self {
         // Not supported
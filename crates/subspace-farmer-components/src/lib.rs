@@ -25,6 +25,8 @@ mod segment_reconstruction;
 
 use crate::file_ext::FileExt;
 use async_trait::async_trait;
+use futures::stream::{BoxStream, FuturesUnordered};
+use futures::StreamExt;
 use parity_scale_codec::{Decode, Encode};
 use serde::{Deserialize, Serialize};
 use static_assertions::const_assert;
@@ -43,6 +45,32 @@ pub trait PieceGetter {
         &self,
         piece_index: PieceIndex,
     ) -> Result<Option<Piece>, Box<dyn Error + Send + Sync + 'static>>;
+
+    /// Get pieces by indices, pieces are yielded as soon as they are retrieved, not necessarily in
+    /// the same order as requested.
+    ///
+    /// Default implementation calls [`Self::get_piece()`] for every piece concurrently,
+    /// implementations that can retrieve multiple pieces at once more efficiently should override
+    /// it.
+    fn get_pieces<'a>(
+        &'a self,
+        piece_indices: Vec<PieceIndex>,
+    ) -> BoxStream<
+        'a,
+        (
+            PieceIndex,
+            Result<Option<Piece>, Box<dyn Error + Send + Sync + 'static>>,
+        ),
+    >
+    where
+        Self: Sync,
+    {
+        piece_indices
+            .into_iter()
+            .map(|piece_index| async move { (piece_index, self.get_piece(piece_index).await) })
+            .collect::<FuturesUnordered<_>>()
+            .boxed()
+    }
 }
 
 #[async_trait]
@@ -56,6 +84,19 @@ where
     ) -> Result<Option<Piece>, Box<dyn Error + Send + Sync + 'static>> {
         self.as_ref().get_piece(piece_index).await
     }
+
+    fn get_pieces<'a>(
+        &'a self,
+        piece_indices: Vec<PieceIndex>,
+    ) -> BoxStream<
+        'a,
+        (
+            PieceIndex,
+            Result<Option<Piece>, Box<dyn Error + Send + Sync + 'static>>,
+        ),
+    > {
+        self.as_ref().get_pieces(piece_indices)
+    }
 }
 
 #[async_trait]
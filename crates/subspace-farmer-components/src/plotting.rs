@@ -20,6 +20,7 @@ use futures::StreamExt;
 use parity_scale_codec::{Decode, Encode};
 use parking_lot::Mutex;
 use rayon::prelude::*;
+use std::collections::HashMap;
 use std::mem;
 use std::simd::Simd;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -28,7 +29,8 @@ use std::time::Duration;
 use subspace_core_primitives::crypto::kzg::Kzg;
 use subspace_core_primitives::crypto::{blake3_hash, blake3_hash_parallel, Scalar};
 use subspace_core_primitives::{
-    Blake3Hash, PieceIndex, PieceOffset, PosSeed, PublicKey, Record, SBucket, SectorId, SectorIndex,
+    Blake3Hash, Piece, PieceIndex, PieceOffset, PosSeed, PublicKey, Record, SBucket, SectorId,
+    SectorIndex,
 };
 use subspace_erasure_coding::ErasureCoding;
 use subspace_proof_of_space::{Table, TableGenerator};
@@ -160,7 +162,7 @@ pub async fn plot_sector<PosTable, PG>(
 ) -> Result<PlottedSector, PlottingError>
 where
     PosTable: Table,
-    PG: PieceGetter,
+    PG: PieceGetter + Sync,
 {
     let PlotSectorOptions {
         public_key,
@@ -247,7 +249,7 @@ pub async fn download_sector<PG>(
     options: DownloadSectorOptions<'_, PG>,
 ) -> Result<DownloadedSector, PlottingError>
 where
-    PG: PieceGetter,
+    PG: PieceGetter + Sync,
 {
     let DownloadSectorOptions {
         public_key,
@@ -629,17 +631,72 @@ fn record_encoding<PosTable>(
         });
 }
 
-async fn download_sector_internal<PG: PieceGetter>(
+async fn download_sector_internal<PG>(
     raw_sector: &mut RawSector,
     piece_getter: &PG,
     kzg: &Kzg,
     erasure_coding: &ErasureCoding,
     piece_indexes: &mut [Option<PieceIndex>],
-) -> Result<(), PlottingError> {
+) -> Result<(), PlottingError>
+where
+    PG: PieceGetter + Sync,
+{
     // TODO: Make configurable, likely allowing user to specify RAM usage expectations and inferring
     //  concurrency from there
     let recovery_semaphore = Semaphore::new(RECONSTRUCTION_CONCURRENCY_LIMIT);
 
+    // Request all pieces at once first, such that piece getter can retrieve multiple pieces from
+    // the same source efficiently, whatever is left is retrieved one by one below
+    // Error message for pieces that failed to be retrieved, `None` for pieces that were not found
+    let mut failed_pieces = HashMap::<PieceIndex, Option<String>>::new();
+    {
+        let mut positions_by_piece_index = HashMap::<PieceIndex, Vec<usize>>::new();
+        for (position, maybe_piece_index) in piece_indexes.iter().enumerate() {
+            // We skip pieces that we have already processed previously
+            if let Some(piece_index) = maybe_piece_index {
+                positions_by_piece_index
+                    .entry(*piece_index)
+                    .or_default()
+                    .push(position);
+            }
+        }
+
+        let mut pieces_stream =
+            piece_getter.get_pieces(positions_by_piece_index.keys().copied().collect());
+
+        while let Some((piece_index, piece_result)) = pieces_stream.next().await {
+            let piece = match piece_result {
+                Ok(Some(piece)) => piece,
+                Ok(None) => {
+                    // Will be recovered below
+                    failed_pieces.insert(piece_index, None);
+                    continue;
+                }
+                Err(error) => {
+                    debug!(%piece_index, %error, "Failed to retrieve piece, will try to recover it");
+                    // Will be recovered below
+                    failed_pieces.insert(piece_index, Some(error.to_string()));
+                    continue;
+                }
+            };
+
+            // Same piece index might be used in multiple positions of the sector
+            for position in positions_by_piece_index
+                .remove(&piece_index)
+                .unwrap_or_default()
+            {
+                write_piece(
+                    &mut raw_sector.records[position],
+                    &mut raw_sector.metadata[position],
+                    &piece,
+                );
+
+                // We have processed this piece index, clear it
+                piece_indexes[position].take();
+            }
+        }
+    }
+
     let mut pieces_receiving_futures = piece_indexes
         .iter_mut()
         .zip(raw_sector.records.iter_mut().zip(&mut raw_sector.metadata))
@@ -649,7 +706,11 @@ async fn download_sector_internal<PG: PieceGetter>(
                 return Ok(());
             };
 
-            let mut piece_result = piece_getter.get_piece(piece_index).await;
+            let mut piece_result = match failed_pieces.get(&piece_index) {
+                Some(Some(error)) => Err(error.clone().into()),
+                Some(None) => Ok(None),
+                None => piece_getter.get_piece(piece_index).await,
+            };
 
             let succeeded = piece_result
                 .as_ref()
@@ -673,23 +734,25 @@ async fn download_sector_internal<PG: PieceGetter>(
                 )
                 .await;
 
-                piece_result = recovered_piece.map(Some).map_err(Into::into);
+                match recovered_piece {
+                    Ok(piece) => {
+                        piece_result = Ok(Some(piece));
+                    }
+                    // Retrieval error is more relevant than recovery error
+                    Err(error) if piece_result.is_err() => {
+                        debug!(%piece_index, %error, "Failed to recover piece");
+                    }
+                    Err(error) => {
+                        piece_result = Err(error.into());
+                    }
+                }
             }
 
             let piece = piece_result
                 .map_err(|error| PlottingError::FailedToRetrievePiece { piece_index, error })?
                 .ok_or(PlottingError::PieceNotFound { piece_index })?;
 
-            // Fancy way to insert value in order to avoid going through stack (if naive de-referencing
-            // is used) and potentially causing stack overflow as the result
-            record
-                .as_flattened_mut()
-                .copy_from_slice(piece.record().as_flattened());
-            *metadata = RecordMetadata {
-                commitment: *piece.commitment(),
-                witness: *piece.witness(),
-                piece_checksum: blake3_hash(piece.as_ref()),
-            };
+            write_piece(record, metadata, &piece);
 
             // We have processed this piece index, clear it
             maybe_piece_index.take();
@@ -712,3 +775,16 @@ async fn download_sector_internal<PG: PieceGetter>(
 
     final_result
 }
+
+fn write_piece(record: &mut Record, metadata: &mut RecordMetadata, piece: &Piece) {
+    // Fancy way to insert value in order to avoid going through stack (if naive de-referencing is
+    // used) and potentially causing stack overflow as the result
+    record
+        .as_flattened_mut()
+        .copy_from_slice(piece.record().as_flattened());
+    *metadata = RecordMetadata {
+        commitment: *piece.commitment(),
+        witness: *piece.witness(),
+        piece_checksum: blake3_hash(piece.as_ref()),
+    };
+}
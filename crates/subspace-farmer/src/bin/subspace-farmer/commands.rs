@@ -1,9 +1,17 @@
 pub(crate) mod benchmark;
+pub(crate) mod cache_server;
 pub(crate) mod cluster;
 pub(crate) mod farm;
 mod info;
+mod migrate;
+pub(crate) mod piece_archive;
+mod resize;
 mod scrub;
-mod shared;
+pub(crate) mod shared;
+#[cfg(unix)]
+pub(crate) mod signer;
 
-pub(crate) use info::info;
+pub(crate) use info::{info, InfoFormat};
+pub(crate) use migrate::migrate;
+pub(crate) use resize::resize;
 pub(crate) use scrub::scrub;
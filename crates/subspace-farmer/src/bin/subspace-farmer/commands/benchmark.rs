@@ -2,22 +2,29 @@ use crate::{PosTable, PosTableLegacy};
 use anyhow::anyhow;
 use clap::{Parser, Subcommand};
 use criterion::{black_box, BatchSize, Criterion, Throughput};
+use parity_scale_codec::Encode;
 use parking_lot::Mutex;
 use rayon::{ThreadPool, ThreadPoolBuildError, ThreadPoolBuilder};
 use std::collections::HashSet;
 use std::fs::OpenOptions;
 use std::num::NonZeroUsize;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+use subspace_core_primitives::crypto::blake3_hash;
 use subspace_core_primitives::crypto::kzg::{embedded_kzg_settings, Kzg};
 use subspace_core_primitives::{Record, SolutionRange};
 use subspace_erasure_coding::ErasureCoding;
 use subspace_farmer::single_disk_farm::farming::rayon_files::RayonFiles;
 use subspace_farmer::single_disk_farm::farming::{PlotAudit, PlotAuditOptions};
+use subspace_farmer::single_disk_farm::identity::Identity;
 use subspace_farmer::single_disk_farm::unbuffered_io_file_windows::UnbufferedIoFileWindows;
 use subspace_farmer::single_disk_farm::{
     SingleDiskFarm, SingleDiskFarmInfo, SingleDiskFarmSummary,
 };
 use subspace_farmer::utils::{recommended_number_of_farming_threads, tokio_rayon_spawn_handler};
+use subspace_farmer_components::proving::ProvableSolutions;
 use subspace_farmer_components::reading::ReadSectorRecordChunksMode;
 use subspace_farmer_components::sector::sector_size;
 use subspace_proof_of_space::Table;
@@ -73,6 +80,43 @@ pub(crate) struct ProveOptions {
     limit_sector_count: Option<usize>,
 }
 
+#[derive(Debug, Parser)]
+pub(crate) struct SimulateOptions {
+    /// Number of slots to simulate
+    #[arg(long, default_value_t = 600)]
+    slots: u64,
+    /// Slot duration in milliseconds, synthetic slots are generated in real time with this interval
+    #[arg(long, default_value_t = 1000)]
+    slot_duration_ms: u64,
+    /// Block authoring delay in slots, solution must be proven and signed within
+    /// `slot-duration * block-authoring-delay` since the start of the slot, otherwise it is too
+    /// late to be included in a block
+    #[arg(long, default_value_t = 4)]
+    block_authoring_delay: u32,
+    /// Solution range of synthetic slots (also used as voting solution range), larger solution
+    /// range results in more solutions
+    #[arg(long)]
+    solution_range: SolutionRange,
+    /// Mode of reading chunks during proving
+    #[arg(long, default_value = "ConcurrentChunks")]
+    record_chunks_mode: ReadSectorRecordChunksMode,
+    /// Size of PER FARM thread pool used for farming (mostly for blocking I/O, but also for some
+    /// compute-intensive operations during proving), defaults to number of logical CPUs
+    /// available on UMA system and number of logical CPUs in first NUMA node on NUMA system, but
+    /// not more than 32 threads
+    #[arg(long)]
+    farming_thread_pool_size: Option<NonZeroUsize>,
+    /// Disk farms to simulate farming on, all farms are farmed concurrently just like during normal
+    /// farming, so farms that share the same disk compete for it.
+    ///
+    /// Farms must not be used by farmer at the same time or results will not be representative.
+    ///
+    /// Example:
+    ///   /path/to/directory
+    #[arg(required = true)]
+    disk_farms: Vec<PathBuf>,
+}
+
 /// Arguments for benchmark
 #[derive(Debug, Subcommand)]
 pub(crate) enum BenchmarkArgs {
@@ -80,6 +124,9 @@ pub(crate) enum BenchmarkArgs {
     Audit(AuditOptions),
     /// Proving benchmark
     Prove(ProveOptions),
+    /// Replay synthetic slots against farms and report how many proofs would have missed block
+    /// authoring deadline
+    Simulate(SimulateOptions),
 }
 
 fn create_thread_pool(
@@ -106,6 +153,7 @@ pub(crate) fn benchmark(benchmark_args: BenchmarkArgs) -> anyhow::Result<()> {
             let thread_pool = create_thread_pool(prove_options.farming_thread_pool_size)?;
             thread_pool.install(|| prove(prove_options))
         }
+        BenchmarkArgs::Simulate(simulate_options) => simulate(simulate_options),
     }
 }
 
@@ -579,3 +627,351 @@ where
 
     Ok(())
 }
+
+/// Number of histogram buckets before the deadline, the last bucket is for missed deadline
+const SIMULATION_HISTOGRAM_BUCKETS: u32 = 10;
+
+/// Farming statistics of a single farm collected during simulation
+#[derive(Debug)]
+struct FarmSimulationStats {
+    deadline: Duration,
+    audited_slots: u64,
+    skipped_slots: u64,
+    audit_time_total: Duration,
+    audit_time_max: Duration,
+    solutions: u64,
+    missed_solutions: u64,
+    failed_solutions: u64,
+    signed: bool,
+    /// Time from the start of the slot to signed solution, in fractions of the deadline
+    histogram: [u64; SIMULATION_HISTOGRAM_BUCKETS as usize + 1],
+}
+
+impl FarmSimulationStats {
+    fn new(deadline: Duration, signed: bool) -> Self {
+        Self {
+            deadline,
+            audited_slots: 0,
+            skipped_slots: 0,
+            audit_time_total: Duration::ZERO,
+            audit_time_max: Duration::ZERO,
+            solutions: 0,
+            missed_solutions: 0,
+            failed_solutions: 0,
+            signed,
+            histogram: [0; SIMULATION_HISTOGRAM_BUCKETS as usize + 1],
+        }
+    }
+
+    fn note_audit(&mut self, time: Duration) {
+        self.audited_slots += 1;
+        self.audit_time_total += time;
+        self.audit_time_max = self.audit_time_max.max(time);
+    }
+
+    fn note_solution(&mut self, time: Duration) {
+        self.solutions += 1;
+        if time >= self.deadline {
+            self.missed_solutions += 1;
+        }
+
+        let bucket = (time.as_secs_f64() / self.bucket_size().as_secs_f64()) as usize;
+        self.histogram[bucket.min(SIMULATION_HISTOGRAM_BUCKETS as usize)] += 1;
+    }
+
+    fn bucket_size(&self) -> Duration {
+        self.deadline / SIMULATION_HISTOGRAM_BUCKETS
+    }
+
+    fn print(&self) {
+        let average_audit_time = if self.audited_slots == 0 {
+            Duration::ZERO
+        } else {
+            self.audit_time_total / self.audited_slots as u32
+        };
+
+        println!(
+            "  Audited slots: {} (skipped because farm was still busy: {})",
+            self.audited_slots, self.skipped_slots
+        );
+        println!(
+            "  Audit time: average {:.3}s, max {:.3}s",
+            average_audit_time.as_secs_f64(),
+            self.audit_time_max.as_secs_f64()
+        );
+        println!(
+            "  Solutions: {}, missed deadline: {} ({:.2}%), failed to prove: {}",
+            self.solutions,
+            self.missed_solutions,
+            percentage(self.missed_solutions, self.solutions),
+            self.failed_solutions
+        );
+        if !self.signed {
+            println!("  Identity not found in farm (remote signer?), signing was not simulated");
+        }
+
+        if self.solutions == 0 {
+            return;
+        }
+
+        println!("  Time from start of the slot to signed solution:");
+        let bucket_size = self.bucket_size().as_secs_f64();
+        let max_count = self.histogram.iter().copied().max().unwrap_or_default();
+        for (bucket, &count) in self.histogram.iter().enumerate() {
+            let range = if bucket < SIMULATION_HISTOGRAM_BUCKETS as usize {
+                format!(
+                    "{:.3}s..{:.3}s",
+                    bucket as f64 * bucket_size,
+                    (bucket + 1) as f64 * bucket_size
+                )
+            } else {
+                format!(">={:.3}s (missed)", self.deadline.as_secs_f64())
+            };
+            // Bar of up to 40 characters
+            let bar = "#".repeat((count * 40).div_ceil(max_count.max(1)) as usize);
+
+            println!("    {range:>20} {count:>8} {bar}");
+        }
+    }
+}
+
+fn percentage(part: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f64 / total as f64 * 100.0
+    }
+}
+
+fn simulate(simulate_options: SimulateOptions) -> anyhow::Result<()> {
+    let SimulateOptions {
+        slots,
+        slot_duration_ms,
+        block_authoring_delay,
+        solution_range,
+        record_chunks_mode,
+        farming_thread_pool_size,
+        disk_farms,
+    } = simulate_options;
+
+    let slot_duration = Duration::from_millis(slot_duration_ms);
+    let deadline = slot_duration * block_authoring_delay;
+    if deadline.is_zero() {
+        return Err(anyhow!(
+            "Slot duration and block authoring delay must not be zero"
+        ));
+    }
+
+    let mut single_disk_farm_infos = Vec::with_capacity(disk_farms.len());
+    for disk_farm in &disk_farms {
+        let single_disk_farm_info = match SingleDiskFarm::collect_summary(disk_farm.clone()) {
+            SingleDiskFarmSummary::Found { info, directory: _ } => info,
+            SingleDiskFarmSummary::NotFound { directory } => {
+                return Err(anyhow!(
+                    "No single disk farm info found, make sure {} is a valid path to the farm and \
+                    process have permissions to access it",
+                    directory.display()
+                ));
+            }
+            SingleDiskFarmSummary::Error { directory, error } => {
+                return Err(anyhow!(
+                    "Failed to open single disk farm info, make sure {} is a valid path to the \
+                    farm and process have permissions to access it: {error}",
+                    directory.display()
+                ));
+            }
+        };
+        single_disk_farm_infos.push(single_disk_farm_info);
+    }
+
+    println!(
+        "Simulating {slots} slots of {slot_duration_ms}ms with solution range {solution_range} and \
+        deadline of {:.3}s...",
+        deadline.as_secs_f64()
+    );
+
+    let farms_stats = thread::scope(|scope| {
+        let mut slot_senders = Vec::with_capacity(disk_farms.len());
+        let mut farm_threads = Vec::with_capacity(disk_farms.len());
+
+        for (farm_index, (disk_farm, single_disk_farm_info)) in
+            disk_farms.iter().zip(&single_disk_farm_infos).enumerate()
+        {
+            // Same as in farmer, slot is skipped if farm is still busy with previous slot
+            let (slot_sender, slot_receiver) = mpsc::sync_channel(1);
+            slot_senders.push(slot_sender);
+
+            let thread_pool = create_thread_pool(farming_thread_pool_size)?;
+            let farm_thread = thread::Builder::new()
+                .name(format!("simulate-{farm_index}"))
+                .spawn_scoped(scope, move || {
+                    thread_pool.install(|| match single_disk_farm_info {
+                        SingleDiskFarmInfo::V0 { .. } => simulate_farm::<PosTableLegacy>(
+                            disk_farm,
+                            single_disk_farm_info,
+                            record_chunks_mode,
+                            deadline,
+                            slot_receiver,
+                        ),
+                        SingleDiskFarmInfo::V1 { .. } => simulate_farm::<PosTable>(
+                            disk_farm,
+                            single_disk_farm_info,
+                            record_chunks_mode,
+                            deadline,
+                            slot_receiver,
+                        ),
+                    })
+                })?;
+            farm_threads.push(farm_thread);
+        }
+
+        let mut skipped_slots = vec![0; slot_senders.len()];
+        let mut next_slot_start = Instant::now();
+        for slot_number in 0..slots {
+            let slot_info = SlotInfo {
+                slot_number,
+                global_challenge: rand::random(),
+                solution_range,
+                voting_solution_range: solution_range,
+            };
+            let slot_start = Instant::now();
+
+            for (slot_sender, skipped_slots) in slot_senders.iter().zip(&mut skipped_slots) {
+                if slot_sender.try_send((slot_info, slot_start)).is_err() {
+                    *skipped_slots += 1;
+                }
+            }
+
+            next_slot_start += slot_duration;
+            thread::sleep(next_slot_start.saturating_duration_since(Instant::now()));
+        }
+
+        // Farms will exit after processing remaining slots
+        drop(slot_senders);
+
+        farm_threads
+            .into_iter()
+            .zip(skipped_slots)
+            .map(|(farm_thread, skipped_slots)| {
+                let mut farm_stats = farm_thread
+                    .join()
+                    .map_err(|_error| anyhow!("Simulation thread panicked"))??;
+                farm_stats.skipped_slots = skipped_slots;
+
+                anyhow::Ok(farm_stats)
+            })
+            .collect::<anyhow::Result<Vec<_>>>()
+    })?;
+
+    let mut total_solutions = 0;
+    let mut total_missed_solutions = 0;
+    for (farm_index, (disk_farm, farm_stats)) in disk_farms.iter().zip(&farms_stats).enumerate() {
+        println!();
+        println!("Farm {farm_index} ({}):", disk_farm.display());
+        farm_stats.print();
+
+        total_solutions += farm_stats.solutions;
+        total_missed_solutions += farm_stats.missed_solutions;
+    }
+
+    println!();
+    println!(
+        "Total: {total_solutions} solutions, {total_missed_solutions} ({:.2}%) would have missed \
+        block authoring deadline",
+        percentage(total_missed_solutions, total_solutions)
+    );
+
+    Ok(())
+}
+
+/// Audit, prove and sign solutions for every received slot the same way farming does, collecting
+/// timings relative to the start of the slot
+fn simulate_farm<PosTable>(
+    disk_farm: &Path,
+    single_disk_farm_info: &SingleDiskFarmInfo,
+    read_sector_record_chunks_mode: ReadSectorRecordChunksMode,
+    deadline: Duration,
+    slot_receiver: mpsc::Receiver<(SlotInfo, Instant)>,
+) -> anyhow::Result<FarmSimulationStats>
+where
+    PosTable: Table,
+{
+    let kzg = Kzg::new(embedded_kzg_settings());
+    let erasure_coding = ErasureCoding::new(
+        NonZeroUsize::new(Record::NUM_S_BUCKETS.next_power_of_two().ilog2() as usize)
+            .expect("Not zero; qed"),
+    )
+    .map_err(|error| anyhow!("Failed to instantiate erasure coding: {error}"))?;
+    let table_generator = Mutex::new(PosTable::generator());
+
+    let sectors_metadata = SingleDiskFarm::read_all_sectors_metadata(disk_farm)
+        .map_err(|error| anyhow!("Failed to read sectors metadata: {error}"))?;
+    // Farm might not have identity if remote signer is used
+    let identity =
+        Identity::open(disk_farm).map_err(|error| anyhow!("Failed to open identity: {error}"))?;
+
+    #[cfg(windows)]
+    let plot = RayonFiles::open_with(
+        &disk_farm.join(SingleDiskFarm::PLOT_FILE),
+        UnbufferedIoFileWindows::open,
+    )
+    .map_err(|error| anyhow!("Failed to open plot: {error}"))?;
+    #[cfg(not(windows))]
+    let plot = RayonFiles::open(&disk_farm.join(SingleDiskFarm::PLOT_FILE))
+        .map_err(|error| anyhow!("Failed to open plot: {error}"))?;
+    let plot_audit = PlotAudit::new(&plot);
+
+    let mut farm_stats = FarmSimulationStats::new(deadline, identity.is_some());
+
+    for (slot_info, slot_start) in slot_receiver {
+        let mut sectors_solutions = plot_audit
+            .audit(PlotAuditOptions::<PosTable> {
+                public_key: single_disk_farm_info.public_key(),
+                reward_address: single_disk_farm_info.public_key(),
+                slot_info,
+                sectors_metadata: &sectors_metadata,
+                kzg: &kzg,
+                erasure_coding: &erasure_coding,
+                sectors_being_modified: &HashSet::default(),
+                read_sector_record_chunks_mode,
+                table_generator: &table_generator,
+            })
+            .map_err(|error| anyhow!("Failed to audit: {error}"))?;
+
+        sectors_solutions.sort_by(|a, b| {
+            let a_solution_distance = a.1.best_solution_distance().unwrap_or(SolutionRange::MAX);
+            let b_solution_distance = b.1.best_solution_distance().unwrap_or(SolutionRange::MAX);
+
+            a_solution_distance.cmp(&b_solution_distance)
+        });
+
+        farm_stats.note_audit(slot_start.elapsed());
+
+        'solutions_processing: for (_sector_index, sector_solutions) in sectors_solutions {
+            for maybe_solution in sector_solutions {
+                let solution = match maybe_solution {
+                    Ok(solution) => solution,
+                    Err(_error) => {
+                        farm_stats.failed_solutions += 1;
+                        continue;
+                    }
+                };
+
+                if let Some(identity) = &identity {
+                    let hash = blake3_hash(&solution.encode());
+                    black_box(identity.sign_reward_hash(&hash));
+                }
+
+                let time = slot_start.elapsed();
+                farm_stats.note_solution(time);
+
+                if time >= deadline {
+                    // Farmer doesn't try to prove remaining solutions after deadline either
+                    break 'solutions_processing;
+                }
+            }
+        }
+    }
+
+    Ok(farm_stats)
+}
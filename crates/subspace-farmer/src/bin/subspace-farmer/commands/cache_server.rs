@@ -0,0 +1,335 @@
+use crate::commands::shared::network::{configure_network, NetworkArgs};
+use crate::commands::shared::{derive_libp2p_keypair, DiskCache};
+use crate::utils::shutdown_signal;
+use anyhow::anyhow;
+use async_lock::RwLock as AsyncRwLock;
+use backoff::ExponentialBackoff;
+use bytesize::ByteSize;
+use clap::{Parser, ValueHint};
+use futures::{select, FutureExt};
+use prometheus_client::registry::Registry;
+use std::fs;
+use std::net::SocketAddr;
+use std::num::NonZeroUsize;
+use std::pin::pin;
+use std::sync::Arc;
+use std::time::Duration;
+use subspace_core_primitives::crypto::kzg::{embedded_kzg_settings, Kzg};
+use subspace_farmer::farm::plotted_pieces::PlottedPieces;
+use subspace_farmer::farm::{PieceCache, PieceCacheTier};
+use subspace_farmer::farmer_cache::FarmerCache;
+use subspace_farmer::farmer_piece_getter::piece_source::FarmerPieceSource;
+use subspace_farmer::farmer_piece_getter::piece_validator::SegmentCommitmentPieceValidator;
+use subspace_farmer::farmer_piece_getter::{DsnCacheRetryPolicy, FarmerPieceGetter};
+use subspace_farmer::node_client::caching_proxy_node_client::CachingProxyNodeClient;
+use subspace_farmer::node_client::rpc_node_client::RpcNodeClient;
+use subspace_farmer::node_client::NodeClient;
+use subspace_farmer::single_disk_farm::identity::Identity;
+use subspace_farmer::utils::{run_future_in_dedicated_thread, AsyncJoinOnDrop};
+use subspace_metrics::{start_prometheus_metrics_server, RegistryAdapter};
+use subspace_networking::utils::piece_provider::PieceProvider;
+use tracing::{error, info, warn};
+
+/// Get piece retry attempts number.
+const PIECE_GETTER_MAX_RETRIES: u16 = 7;
+/// Defines initial duration between get_piece calls.
+const GET_PIECE_INITIAL_INTERVAL: Duration = Duration::from_secs(5);
+/// Defines max duration between get_piece calls.
+const GET_PIECE_MAX_INTERVAL: Duration = Duration::from_secs(40);
+/// Interval between printing number of segments stored in cache
+const STORED_SEGMENTS_PRINT_INTERVAL: Duration = Duration::from_mins(10);
+
+type CacheIndex = u8;
+/// Cache server has no farms, but networking and piece getter still expect plotted pieces
+type FarmIndex = u8;
+
+/// Arguments for cache server
+#[derive(Debug, Parser)]
+pub(crate) struct CacheServerArgs {
+    /// One or more caches located at specified path, each with its own allocated space.
+    ///
+    /// Format for each cache is coma-separated list of strings like this:
+    ///
+    ///   path=/path/to/directory,size=5T
+    ///
+    /// `size` is max allocated size in human-readable format (e.g. 10GB, 2TiB) or just bytes that
+    /// cache will make sure to not exceed (and will pre-allocated all the space on startup to
+    /// ensure it will not run out of space in runtime).
+    ///
+    /// Optional `tier` can be set to `fast` (SSD) or `slow` (HDD, default), pieces that are
+    /// requested often are moved to fast caches.
+    ///
+    /// Pieces closest to the peer ID of the cache server are stored when caches are not large
+    /// enough, allocate at least the size of the whole archived history to cache all pieces.
+    disk_caches: Vec<DiskCache>,
+    /// Run temporary cache server with specified cache size in human-readable format (e.g. 10GB,
+    /// 2TiB) or just bytes (e.g. 4096), this will create a temporary directory that will be deleted
+    /// at the end of the process.
+    #[arg(long, conflicts_with = "disk_caches")]
+    tmp: Option<ByteSize>,
+    /// WebSocket RPC URL of the Subspace node to connect to
+    #[arg(long, value_hint = ValueHint::Url, default_value = "ws://127.0.0.1:9944")]
+    node_rpc_url: String,
+    /// Sets some flags that are convenient during development, currently `--allow-private-ips`
+    #[arg(long)]
+    dev: bool,
+    /// Network parameters
+    #[clap(flatten)]
+    network_args: NetworkArgs,
+    /// Defines endpoints for the prometheus metrics server. It doesn't start without at least
+    /// one specified endpoint. Format: 127.0.0.1:8080
+    #[arg(long, aliases = ["metrics-endpoint", "metrics-endpoints"])]
+    prometheus_listen_on: Vec<SocketAddr>,
+    /// Piece getter concurrency.
+    ///
+    /// Increase will result in higher memory usage.
+    #[arg(long, default_value = "128")]
+    piece_getter_concurrency: NonZeroUsize,
+    /// Source of pieces, can be specified multiple times, see `farm --help` for supported values.
+    ///
+    /// All built-in sources are used by default.
+    #[arg(long = "piece-source")]
+    piece_sources: Vec<FarmerPieceSource>,
+}
+
+/// Start standalone cache server that syncs pieces of archived history into local caches and
+/// serves them to other peers over DSN without doing any plotting or farming.
+pub(crate) async fn cache_server(cache_server_args: CacheServerArgs) -> anyhow::Result<()> {
+    let signal = shutdown_signal();
+
+    let CacheServerArgs {
+        mut disk_caches,
+        tmp,
+        node_rpc_url,
+        dev,
+        mut network_args,
+        prometheus_listen_on,
+        piece_getter_concurrency,
+        piece_sources,
+    } = cache_server_args;
+
+    // Override flags with `--dev`
+    network_args.allow_private_ips = network_args.allow_private_ips || dev;
+
+    let _tmp_directory = if let Some(cache_size) = tmp {
+        let tmp_directory = tempfile::Builder::new()
+            .prefix("subspace-cache-server-")
+            .tempdir()
+            .map_err(|error| anyhow!("Failed to create temporary directory: {error}"))?;
+
+        disk_caches = vec![DiskCache {
+            directory: tmp_directory.as_ref().to_path_buf(),
+            allocated_space: cache_size.as_u64(),
+            tier: PieceCacheTier::default(),
+        }];
+
+        Some(tmp_directory)
+    } else {
+        if disk_caches.is_empty() {
+            return Err(anyhow!("There must be at least one disk cache provided"));
+        }
+
+        for cache in &disk_caches {
+            if !cache.directory.exists() {
+                if let Err(error) = fs::create_dir(&cache.directory) {
+                    return Err(anyhow!(
+                        "Directory {} doesn't exist and can't be created: {}",
+                        cache.directory.display(),
+                        error
+                    ));
+                }
+            }
+        }
+        None
+    };
+
+    if CacheIndex::try_from(disk_caches.len()).is_err() {
+        return Err(anyhow!(
+            "Too many disk caches provided, at most {} are supported",
+            CacheIndex::MAX
+        ));
+    }
+
+    info!(url = %node_rpc_url, "Connecting to node RPC");
+    let node_client = RpcNodeClient::new(&node_rpc_url)
+        .await
+        .map_err(|error| anyhow!("Failed to connect to node RPC: {error}"))?;
+
+    let farmer_app_info = node_client
+        .farmer_app_info()
+        .await
+        .map_err(|error| anyhow!("Failed to get farmer app info: {error}"))?;
+
+    let first_cache_directory = &disk_caches
+        .first()
+        .expect("Disk cache collection is not empty as checked above; qed")
+        .directory;
+
+    let identity = Identity::open_or_create(first_cache_directory)
+        .map_err(|error| anyhow!("Failed to open or create identity: {error}"))?;
+    let keypair = derive_libp2p_keypair(identity.secret_key());
+    let peer_id = keypair.public().to_peer_id();
+
+    let mut registry = Registry::with_prefix("subspace_farmer");
+    let should_start_prometheus_server = !prometheus_listen_on.is_empty();
+
+    let piece_caches = disk_caches
+        .iter()
+        .map(|disk_cache| disk_cache.open(Some(&mut registry)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let history_size = farmer_app_info.protocol_info.history_size;
+    let total_capacity = piece_caches
+        .iter()
+        .map(|piece_cache| u64::from(piece_cache.max_num_elements()))
+        .sum::<u64>();
+    if total_capacity < history_size.in_pieces().get() {
+        warn!(
+            %total_capacity,
+            history_size_in_pieces = %history_size.in_pieces(),
+            "Caches are not large enough to store the whole archived history, only pieces \
+            closest to peer ID of this cache server will be stored"
+        );
+    }
+
+    let plotted_pieces = Arc::new(AsyncRwLock::new(PlottedPieces::<FarmIndex>::default()));
+
+    let (farmer_cache, farmer_cache_worker) =
+        FarmerCache::<CacheIndex>::new(node_client.clone(), peer_id, Some(&mut registry));
+
+    let node_client = CachingProxyNodeClient::new(node_client)
+        .await
+        .map_err(|error| anyhow!("Failed to create caching proxy node client: {error}"))?;
+
+    let (node, mut node_runner) = {
+        if network_args.bootstrap_nodes.is_empty() {
+            network_args
+                .bootstrap_nodes
+                .clone_from(&farmer_app_info.dsn_bootstrap_nodes);
+        }
+
+        configure_network(
+            hex::encode(farmer_app_info.genesis_hash),
+            first_cache_directory,
+            keypair,
+            network_args,
+            Arc::downgrade(&plotted_pieces),
+            node_client.clone(),
+            farmer_cache.clone(),
+            should_start_prometheus_server.then_some(&mut registry),
+        )
+        .map_err(|error| anyhow!("Failed to configure networking: {error}"))?
+    };
+
+    let kzg = Kzg::new(embedded_kzg_settings());
+    let validator = Some(SegmentCommitmentPieceValidator::new(
+        node.clone(),
+        node_client.clone(),
+        kzg,
+    ));
+    let piece_provider = PieceProvider::new(node.clone(), validator);
+
+    let piece_getter = FarmerPieceGetter::new(
+        piece_provider,
+        farmer_cache.clone(),
+        node_client.clone(),
+        plotted_pieces,
+        DsnCacheRetryPolicy {
+            max_retries: PIECE_GETTER_MAX_RETRIES,
+            backoff: ExponentialBackoff {
+                initial_interval: GET_PIECE_INITIAL_INTERVAL,
+                max_interval: GET_PIECE_MAX_INTERVAL,
+                // Try until we get a valid piece
+                max_elapsed_time: None,
+                multiplier: 1.75,
+                ..ExponentialBackoff::default()
+            },
+        },
+        piece_getter_concurrency,
+        piece_sources,
+        Some(&mut registry),
+    );
+
+    let farmer_cache_worker_fut = run_future_in_dedicated_thread(
+        {
+            let future = farmer_cache_worker.run(piece_getter.downgrade());
+
+            move || future
+        },
+        "cache-server-cache-worker".to_string(),
+    )?;
+
+    farmer_cache
+        .replace_backing_caches(
+            piece_caches
+                .into_iter()
+                .map(|piece_cache| Arc::new(piece_cache) as Arc<_>)
+                .collect(),
+            Vec::new(),
+        )
+        .await;
+
+    let _prometheus_worker = if should_start_prometheus_server {
+        let prometheus_task = start_prometheus_metrics_server(
+            prometheus_listen_on,
+            RegistryAdapter::PrometheusClient(registry),
+        )?;
+
+        let join_handle = tokio::spawn(prometheus_task);
+        Some(AsyncJoinOnDrop::new(join_handle, true))
+    } else {
+        None
+    };
+
+    let stored_segments_fut = async move {
+        loop {
+            tokio::time::sleep(STORED_SEGMENTS_PRINT_INTERVAL).await;
+
+            match node_client.farmer_app_info().await {
+                Ok(farmer_app_info) => {
+                    info!(
+                        stored_segments = %farmer_cache.stored_segments(),
+                        archived_segments = %farmer_app_info.protocol_info.history_size.get(),
+                        "Cache server status"
+                    );
+                }
+                Err(error) => {
+                    error!(%error, "Failed to get farmer app info");
+                }
+            }
+        }
+    };
+
+    let networking_fut = run_future_in_dedicated_thread(
+        move || async move { node_runner.run().await },
+        "cache-server-networking".to_string(),
+    )?;
+
+    // This defines order in which things are dropped
+    let networking_fut = networking_fut;
+    let farmer_cache_worker_fut = farmer_cache_worker_fut;
+
+    let networking_fut = pin!(networking_fut);
+    let farmer_cache_worker_fut = pin!(farmer_cache_worker_fut);
+    let stored_segments_fut = pin!(stored_segments_fut);
+
+    select! {
+        // Signal future
+        _ = signal.fuse() => {},
+
+        // Networking future
+        _ = networking_fut.fuse() => {
+            info!("Node runner exited.")
+        },
+
+        // Piece cache worker future
+        _ = farmer_cache_worker_fut.fuse() => {
+            info!("Farmer cache worker exited.")
+        },
+
+        // Stored segments future
+        _ = stored_segments_fut.fuse() => {},
+    }
+
+    anyhow::Ok(())
+}
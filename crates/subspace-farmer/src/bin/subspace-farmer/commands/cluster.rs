@@ -2,11 +2,13 @@ mod cache;
 mod controller;
 mod farmer;
 mod plotter;
+mod status;
 
 use crate::commands::cluster::cache::{cache, CacheArgs};
 use crate::commands::cluster::controller::{controller, ControllerArgs};
 use crate::commands::cluster::farmer::{farmer, FarmerArgs};
 use crate::commands::cluster::plotter::{plotter, PlotterArgs};
+use crate::commands::cluster::status::{status, StatusArgs};
 use crate::utils::shutdown_signal;
 use anyhow::anyhow;
 use async_nats::ServerAddr;
@@ -16,12 +18,19 @@ use futures::stream::FuturesUnordered;
 use futures::{select, FutureExt, StreamExt};
 use prometheus_client::registry::Registry;
 use std::env::current_exe;
-use std::mem;
-use std::net::SocketAddr;
+use std::future::Future;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::{fs, mem};
+use subspace_farmer::cluster::broker::{BrokerClient, BrokerServer};
 use subspace_farmer::cluster::nats_client::NatsClient;
 use subspace_farmer::utils::AsyncJoinOnDrop;
 use subspace_metrics::{start_prometheus_metrics_server, RegistryAdapter};
 use subspace_proof_of_space::Table;
+use tokio::net::TcpListener;
+use tracing::info;
 
 /// Arguments for cluster
 #[derive(Debug, Parser)]
@@ -50,8 +59,35 @@ struct SharedArgs {
     ///
     /// NOTE: NATS must be configured for message sizes of 2MiB or larger (1MiB is the default),
     /// which can be done by starting NATS server with config file containing `max_payload = 2MB`.
-    #[arg(long, alias = "nats-server", required = true)]
+    #[arg(
+        long,
+        alias = "nats-server",
+        required_unless_present_any = ["broker", "broker_listen_on"]
+    )]
     nats_servers: Vec<ServerAddr>,
+    /// Address of the built-in broker (`host:port`) to use instead of NATS, typically hosted by the
+    /// controller with `--broker-listen-on`.
+    ///
+    /// NOTE: Built-in broker doesn't do any authentication or encryption, it must only be used in
+    /// trusted networks.
+    #[arg(long, conflicts_with_all = ["nats_servers", "broker_listen_on"])]
+    broker: Option<String>,
+    /// Host built-in broker in this process on specified address and use it instead of NATS, other
+    /// cluster components can connect to it with `--broker`. Format: 0.0.0.0:4223
+    #[arg(long, conflicts_with = "nats_servers")]
+    broker_listen_on: Option<SocketAddr>,
+    /// Path to a file with secret shared by all cluster components. When specified, all cluster
+    /// messages are authenticated and unauthenticated or replayed messages are rejected, which is
+    /// necessary when running cluster components across untrusted networks.
+    ///
+    /// NOTE: Clocks of all cluster components must be synchronized (within a minute) for
+    /// authentication to work.
+    #[arg(long)]
+    cluster_secret_file: Option<PathBuf>,
+    /// Encrypt stream responses (plotted sectors sent by plotters, pieces, etc.) with a key derived
+    /// from cluster secret.
+    #[arg(long, requires = "cluster_secret_file")]
+    encrypt_streams: bool,
     /// Defines endpoints for the prometheus metrics server. It doesn't start without at least
     /// one specified endpoint. Format: 127.0.0.1:8080
     #[arg(long, aliases = ["metrics-endpoint", "metrics-endpoints"])]
@@ -69,6 +105,8 @@ enum ClusterSubcommand {
     Plotter(PlotterArgs),
     /// Farming cluster cache
     Cache(CacheArgs),
+    /// Print status of live cluster components
+    Status(StatusArgs),
 }
 
 impl ClusterSubcommand {
@@ -78,6 +116,7 @@ impl ClusterSubcommand {
             ClusterSubcommand::Farmer(args) => mem::take(&mut args.additional_components),
             ClusterSubcommand::Plotter(args) => mem::take(&mut args.additional_components),
             ClusterSubcommand::Cache(args) => mem::take(&mut args.additional_components),
+            ClusterSubcommand::Status(_args) => Vec::new(),
         }
     }
 }
@@ -97,23 +136,69 @@ where
     } = cluster_args;
     let SharedArgs {
         nats_servers,
+        broker,
+        broker_listen_on,
+        cluster_secret_file,
+        encrypt_streams,
         prometheus_listen_on,
     } = shared_args;
     let ClusterSubcommands { mut subcommand } = subcommands;
 
-    let nats_client = NatsClient::new(
-        nats_servers,
-        ExponentialBackoff {
-            max_elapsed_time: None,
-            ..ExponentialBackoff::default()
-        },
-    )
-    .await
-    .map_err(|error| anyhow!("Failed to connect to NATS server: {error}"))?;
-    let mut registry = Registry::with_prefix("subspace_farmer");
-
     let mut tasks = FuturesUnordered::new();
 
+    let request_retry_backoff_policy = ExponentialBackoff {
+        max_elapsed_time: None,
+        ..ExponentialBackoff::default()
+    };
+    let nats_client = if let Some(broker_listen_on) = broker_listen_on {
+        let listener = TcpListener::bind(broker_listen_on)
+            .await
+            .map_err(|error| anyhow!("Failed to listen on {broker_listen_on}: {error}"))?;
+        info!("Built-in broker is listening on {broker_listen_on}");
+
+        let join_handle = tokio::spawn(BrokerServer::default().run(listener));
+        tasks.push(
+            Box::pin(async move { Ok(AsyncJoinOnDrop::new(join_handle, true).await??) })
+                as Pin<Box<dyn Future<Output = anyhow::Result<()>>>>,
+        );
+
+        let mut local_address = broker_listen_on;
+        if local_address.ip().is_unspecified() {
+            local_address.set_ip(match local_address {
+                SocketAddr::V4(_) => Ipv4Addr::LOCALHOST.into(),
+                SocketAddr::V6(_) => Ipv6Addr::LOCALHOST.into(),
+            });
+        }
+
+        connect_to_broker(local_address.to_string(), request_retry_backoff_policy).await?
+    } else if let Some(broker) = broker {
+        connect_to_broker(broker, request_retry_backoff_policy).await?
+    } else {
+        NatsClient::new(nats_servers, request_retry_backoff_policy)
+            .await
+            .map_err(|error| anyhow!("Failed to connect to NATS server: {error}"))?
+    };
+    let nats_client = if let Some(cluster_secret_file) = cluster_secret_file {
+        let cluster_secret = fs::read(&cluster_secret_file).map_err(|error| {
+            anyhow!(
+                "Failed to read cluster secret file {}: {error}",
+                cluster_secret_file.display()
+            )
+        })?;
+        let cluster_secret = cluster_secret.trim_ascii();
+        if cluster_secret.is_empty() {
+            return Err(anyhow!(
+                "Cluster secret file {} is empty",
+                cluster_secret_file.display()
+            ));
+        }
+
+        nats_client.with_authentication(cluster_secret, encrypt_streams)
+    } else {
+        nats_client
+    };
+    let mut registry = Registry::with_prefix("subspace_farmer");
+
     loop {
         let nats_client = nats_client.clone();
         let additional_components = subcommand.extract_additional_components();
@@ -132,6 +217,7 @@ where
             ClusterSubcommand::Cache(cache_args) => {
                 cache(nats_client, &mut registry, cache_args).await?
             }
+            ClusterSubcommand::Status(status_args) => status(nats_client, status_args).await?,
         });
 
         if additional_components.is_empty() {
@@ -174,3 +260,14 @@ where
         },
     }
 }
+
+async fn connect_to_broker(
+    address: String,
+    request_retry_backoff_policy: ExponentialBackoff,
+) -> anyhow::Result<NatsClient> {
+    let broker_client = BrokerClient::connect(address.clone())
+        .await
+        .map_err(|error| anyhow!("Failed to connect to broker at {address}: {error}"))?;
+
+    NatsClient::from_transport(Arc::new(broker_client), request_retry_backoff_policy)
+}
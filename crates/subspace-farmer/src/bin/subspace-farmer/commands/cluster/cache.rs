@@ -1,3 +1,4 @@
+use crate::commands::shared::DiskCache;
 use anyhow::anyhow;
 use bytesize::ByteSize;
 use clap::Parser;
@@ -7,80 +8,16 @@ use prometheus_client::registry::Registry;
 use std::fs;
 use std::future::Future;
 use std::num::NonZeroUsize;
-use std::path::PathBuf;
 use std::pin::Pin;
-use std::str::FromStr;
 use std::time::Duration;
 use subspace_farmer::cluster::cache::cache_service;
 use subspace_farmer::cluster::nats_client::NatsClient;
-use subspace_farmer::disk_piece_cache::DiskPieceCache;
+use subspace_farmer::farm::PieceCacheTier;
 use subspace_farmer::utils::AsyncJoinOnDrop;
 
 /// Interval between cache self-identification broadcast messages
 pub(super) const CACHE_IDENTIFICATION_BROADCAST_INTERVAL: Duration = Duration::from_secs(30);
 
-#[derive(Debug, Clone)]
-struct DiskCache {
-    /// Path to directory where cache is stored
-    directory: PathBuf,
-    /// How much space in bytes can cache use
-    allocated_space: u64,
-}
-
-impl FromStr for DiskCache {
-    type Err = String;
-
-    #[inline]
-    fn from_str(s: &str) -> anyhow::Result<Self, Self::Err> {
-        let parts = s.split(',').collect::<Vec<_>>();
-        if parts.len() != 2 {
-            return Err("Must contain 2 coma-separated components".to_string());
-        }
-
-        let mut plot_directory = None;
-        let mut allocated_space = None;
-
-        for part in parts {
-            let part = part.splitn(2, '=').collect::<Vec<_>>();
-            if part.len() != 2 {
-                return Err("Each component must contain = separating key from value".to_string());
-            }
-
-            let key = *part.first().expect("Length checked above; qed");
-            let value = *part.get(1).expect("Length checked above; qed");
-
-            match key {
-                "path" => {
-                    plot_directory.replace(PathBuf::from(value));
-                }
-                "size" => {
-                    allocated_space.replace(
-                        value
-                            .parse::<ByteSize>()
-                            .map_err(|error| {
-                                format!("Failed to parse `size` \"{value}\": {error}")
-                            })?
-                            .as_u64(),
-                    );
-                }
-                key => {
-                    return Err(format!(
-                        "Key \"{key}\" is not supported, only `path` or `size`"
-                    ));
-                }
-            }
-        }
-
-        Ok(DiskCache {
-            directory: plot_directory.ok_or(
-                "`path` key is required with path to directory where cache will be stored",
-            )?,
-            allocated_space: allocated_space
-                .ok_or("`size` key is required with allocated amount of disk space")?,
-        })
-    }
-}
-
 /// Arguments for cache
 #[derive(Debug, Parser)]
 pub(super) struct CacheArgs {
@@ -93,6 +30,9 @@ pub(super) struct CacheArgs {
     /// `size` is max allocated size in human-readable format (e.g. 10GB, 2TiB) or just bytes that
     /// cache will make sure to not exceed (and will pre-allocated all the space on startup to
     /// ensure it will not run out of space in runtime).
+    ///
+    /// Optional `tier` can be set to `fast` (SSD) or `slow` (HDD, default), pieces that are
+    /// requested often are moved to fast caches by controller.
     disk_caches: Vec<DiskCache>,
     /// Run temporary cache with specified farm size in human-readable format (e.g. 10GB, 2TiB) or
     /// just bytes (e.g. 4096), this will create a temporary directory that will be deleted at the
@@ -135,6 +75,7 @@ pub(super) async fn cache(
         disk_caches = vec![DiskCache {
             directory: tmp_directory.as_ref().to_path_buf(),
             allocated_space: plot_size.as_u64(),
+            tier: PieceCacheTier::default(),
         }];
 
         Some(tmp_directory)
@@ -159,21 +100,7 @@ pub(super) async fn cache(
 
     let caches = disk_caches
         .iter()
-        .map(|disk_cache| {
-            DiskPieceCache::open(
-                &disk_cache.directory,
-                u32::try_from(disk_cache.allocated_space / DiskPieceCache::element_size() as u64)
-                    .unwrap_or(u32::MAX),
-                None,
-                Some(registry),
-            )
-            .map_err(|error| {
-                anyhow!(
-                    "Failed to open piece cache at {}: {error}",
-                    disk_cache.directory.display()
-                )
-            })
-        })
+        .map(|disk_cache| disk_cache.open(Some(registry)))
         .collect::<Result<Vec<_>, _>>()?;
 
     let mut cache_services = (0..service_instances.get())
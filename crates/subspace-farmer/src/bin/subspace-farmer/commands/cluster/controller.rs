@@ -23,6 +23,7 @@ use subspace_farmer::cluster::controller::controller_service;
 use subspace_farmer::cluster::nats_client::NatsClient;
 use subspace_farmer::farm::plotted_pieces::PlottedPieces;
 use subspace_farmer::farmer_cache::FarmerCache;
+use subspace_farmer::farmer_piece_getter::piece_source::FarmerPieceSource;
 use subspace_farmer::farmer_piece_getter::piece_validator::SegmentCommitmentPieceValidator;
 use subspace_farmer::farmer_piece_getter::{DsnCacheRetryPolicy, FarmerPieceGetter};
 use subspace_farmer::node_client::caching_proxy_node_client::CachingProxyNodeClient;
@@ -48,6 +49,11 @@ pub(super) struct ControllerArgs {
     /// Increase will result in higher memory usage.
     #[arg(long, default_value = "128")]
     piece_getter_concurrency: NonZeroUsize,
+    /// Source of pieces, can be specified multiple times, see `farm --help` for supported values.
+    ///
+    /// All built-in sources are used by default.
+    #[arg(long = "piece-source")]
+    piece_sources: Vec<FarmerPieceSource>,
     /// Base path where to store P2P network identity
     #[arg(long, value_hint = ValueHint::DirPath)]
     base_path: Option<PathBuf>,
@@ -89,6 +95,7 @@ pub(super) async fn controller(
 ) -> anyhow::Result<Pin<Box<dyn Future<Output = anyhow::Result<()>>>>> {
     let ControllerArgs {
         piece_getter_concurrency,
+        piece_sources,
         base_path,
         node_rpc_url,
         cache_group,
@@ -189,6 +196,8 @@ pub(super) async fn controller(
             },
         },
         piece_getter_concurrency,
+        piece_sources,
+        Some(registry),
     );
 
     let farmer_cache_worker_fut = run_future_in_dedicated_thread(
@@ -207,6 +216,7 @@ pub(super) async fn controller(
             let piece_getter = piece_getter.clone();
             let farmer_cache = farmer_cache.clone();
             let instance = instance.clone();
+            let cache_group = cache_group.clone();
 
             AsyncJoinOnDrop::new(
                 tokio::spawn(async move {
@@ -216,6 +226,7 @@ pub(super) async fn controller(
                         &piece_getter,
                         &farmer_cache,
                         &instance,
+                        &cache_group,
                         index == 0,
                     )
                     .await
@@ -20,7 +20,7 @@ use subspace_farmer::cluster::cache::{
 };
 use subspace_farmer::cluster::controller::ClusterControllerCacheIdentifyBroadcast;
 use subspace_farmer::cluster::nats_client::NatsClient;
-use subspace_farmer::farm::{PieceCache, PieceCacheId};
+use subspace_farmer::farm::{PieceCache, PieceCacheId, PieceCacheTier};
 use subspace_farmer::farmer_cache::FarmerCache;
 use tokio::time::MissedTickBehavior;
 use tracing::{info, trace, warn};
@@ -52,6 +52,7 @@ impl KnownCaches {
         &mut self,
         cache_id: PieceCacheId,
         max_num_elements: u32,
+        tier: PieceCacheTier,
         nats_client: &NatsClient,
     ) -> bool {
         if self.known_caches.iter_mut().any(|known_cache| {
@@ -68,6 +69,7 @@ impl KnownCaches {
         let piece_cache = Arc::new(ClusterPieceCache::new(
             cache_id,
             max_num_elements,
+            tier,
             nats_client.clone(),
         ));
         self.known_caches.push(KnownCache {
@@ -160,10 +162,12 @@ pub(super) async fn maintain_caches(
                 let ClusterCacheIdentifyBroadcast {
                     cache_id,
                     max_num_elements,
+                    tier,
                 } = identify_message;
-                if known_caches.update(cache_id, max_num_elements, nats_client) {
+                if known_caches.update(cache_id, max_num_elements, tier, nats_client) {
                     info!(
                         %cache_id,
+                        %tier,
                         "New cache discovered, scheduling reinitialization"
                     );
                     scheduled_reinitialization_for.replace(
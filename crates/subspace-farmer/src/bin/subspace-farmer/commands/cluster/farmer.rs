@@ -1,5 +1,7 @@
 //! Metrics specific for single disk farm
 
+use crate::commands::shared::control_api::{ControlApi, ControlApiFarm};
+use crate::commands::shared::event_log::EventLogArgs;
 use crate::commands::shared::DiskFarm;
 use anyhow::anyhow;
 use async_lock::Mutex as AsyncMutex;
@@ -12,7 +14,9 @@ use parking_lot::Mutex;
 use prometheus_client::registry::Registry;
 use std::fs;
 use std::future::Future;
-use std::num::NonZeroUsize;
+use std::net::SocketAddr;
+use std::num::{NonZeroU32, NonZeroUsize};
+use std::path::PathBuf;
 use std::pin::{pin, Pin};
 use std::sync::Arc;
 use std::time::Duration;
@@ -23,9 +27,15 @@ use subspace_farmer::cluster::controller::ClusterNodeClient;
 use subspace_farmer::cluster::farmer::farmer_service;
 use subspace_farmer::cluster::nats_client::NatsClient;
 use subspace_farmer::cluster::plotter::ClusterPlotter;
-use subspace_farmer::farm::Farm;
+use subspace_farmer::farm::{Farm, PieceCacheTier};
 use subspace_farmer::node_client::caching_proxy_node_client::CachingProxyNodeClient;
 use subspace_farmer::node_client::NodeClient;
+use subspace_farmer::plotter::scheduler::{
+    PlottingScheduler, PlottingSchedulerOptions, TimeWindow,
+};
+#[cfg(unix)]
+use subspace_farmer::single_disk_farm::remote_signer::RemoteSigner;
+use subspace_farmer::single_disk_farm::reward_address::RewardAddressRotation;
 use subspace_farmer::single_disk_farm::{
     SingleDiskFarm, SingleDiskFarmError, SingleDiskFarmOptions,
 };
@@ -59,11 +69,28 @@ pub(super) struct FarmerArgs {
     /// farmer will make sure to not exceed (and will pre-allocated all the space on startup to
     /// ensure it will not run out of space in runtime). Optionally, `record-chunks-mode` can be
     /// set to `ConcurrentChunks` or `WholeSector` in order to avoid internal benchmarking during
-    /// startup.
+    /// startup. Optionally, `plotting-weight` can be set to a positive integer (1 by default), farms
+    /// share plotting capacity proportionally to their weights.
+    ///
+    /// Farm can also have its own reward addresses with `reward-address` (can be specified
+    /// multiple times) and `reward-address-rotation` that override global `--reward-address` and
+    /// `--reward-address-rotation`.
+    ///
+    /// On Linux `io-uring-queue-depth` can be set to a positive integer to read from the plot with
+    /// io_uring during auditing and piece reading, which allows deeper I/O queues on HDDs without
+    /// a large farming thread pool.
     disk_farms: Vec<DiskFarm>,
-    /// Address for farming rewards
+    /// Address for farming rewards, can be specified multiple times to rotate between addresses.
+    ///
+    /// Required unless every farm has its own `reward-address` specified.
     #[arg(long, value_parser = parse_ss58_reward_address)]
-    reward_address: PublicKey,
+    reward_address: Vec<PublicKey>,
+    /// How multiple reward addresses are rotated: `solution` to switch to the next address after
+    /// every solution (default) or `slots:<number>` to switch every specified number of slots.
+    ///
+    /// Can be overridden with `reward-address-rotation` in farm options.
+    #[arg(long)]
+    reward_address_rotation: Option<RewardAddressRotation>,
     /// Run temporary farmer with specified farm size in human-readable format (e.g. 10GB, 2TiB) or
     /// just bytes (e.g. 4096), this will create a temporary directory that will be deleted at the
     /// end of the process.
@@ -93,6 +120,30 @@ pub(super) struct FarmerArgs {
     /// not more than 32 threads
     #[arg(long)]
     farming_thread_pool_size: Option<NonZeroUsize>,
+    /// Only replot expired sectors within specified daily time window in UTC, initial plotting is
+    /// not restricted. Format: 00:00-06:00, window can wrap around midnight (e.g. 22:00-06:00).
+    #[arg(long)]
+    replotting_window: Option<TimeWindow>,
+    /// Delay in seconds between checking integrity of consecutive plotted sectors in the
+    /// background, corrupted sectors are replotted automatically. Checking reads the whole sector,
+    /// so smaller values increase disk load. `0` disables background integrity check.
+    #[arg(long, default_value_t = 60)]
+    integrity_check_interval: u64,
+    /// Path to Unix socket of remote signer (see `signer` command) that holds farm identities.
+    ///
+    /// Farms created with remote signer only store public key, existing farms can be switched to
+    /// remote signer by moving their `identity.bin` files into remote signer.
+    #[arg(long)]
+    remote_signer: Option<PathBuf>,
+    /// Structured event log parameters
+    #[clap(flatten)]
+    event_log_args: EventLogArgs,
+    /// Defines endpoint for the local control API (JSON-RPC over both HTTP and WebSocket) that
+    /// allows to inspect farms, subscribe to their events and pause/resume plotting at runtime.
+    /// It doesn't start unless endpoint is specified. API has no authentication and must not be
+    /// exposed publicly. Format: 127.0.0.1:9955
+    #[arg(long)]
+    control_api_listen_on: Option<SocketAddr>,
     /// Disable farm locking, for example if file system doesn't support it
     #[arg(long)]
     disable_farm_locking: bool,
@@ -129,11 +180,17 @@ where
     let FarmerArgs {
         mut disk_farms,
         reward_address,
+        reward_address_rotation,
         tmp,
         max_pieces_in_sector,
         no_info,
         sector_encoding_concurrency,
         farming_thread_pool_size,
+        replotting_window,
+        integrity_check_interval,
+        remote_signer,
+        event_log_args,
+        control_api_listen_on,
         disable_farm_locking,
         create,
         exit_on_farm_error,
@@ -151,6 +208,12 @@ where
             directory: tmp_directory.as_ref().to_path_buf(),
             allocated_space: plot_size.as_u64(),
             read_sector_record_chunks_mode: Some(ReadSectorRecordChunksMode::ConcurrentChunks),
+            plotting_weight: NonZeroU32::MIN,
+            reward_addresses: Vec::new(),
+            reward_address_rotation: None,
+            cache_tier: PieceCacheTier::default(),
+            #[cfg(target_os = "linux")]
+            io_uring_queue_depth: None,
         }];
 
         Some(tmp_directory)
@@ -173,6 +236,23 @@ where
         None
     };
 
+    let event_log = event_log_args.start()?;
+
+    #[cfg(unix)]
+    let remote_signer = remote_signer.map(RemoteSigner::new);
+    #[cfg(not(unix))]
+    if remote_signer.is_some() {
+        return Err(anyhow!(
+            "Remote signer is only supported on Unix-like operating systems"
+        ));
+    }
+    let remote_signer_used = remote_signer.is_some();
+
+    let reward_address_policies = disk_farms
+        .iter()
+        .map(|disk_farm| disk_farm.reward_address_policy(&reward_address, reward_address_rotation))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
     let node_client = CachingProxyNodeClient::new(ClusterNodeClient::new(nats_client.clone()))
         .await
         .map_err(|error| anyhow!("Failed to create caching proxy node client: {error}"))?;
@@ -230,8 +310,9 @@ where
         },
         true,
     ));
+    let plotting_scheduler = PlottingScheduler::new(PlottingSchedulerOptions { replotting_window });
 
-    let farms = {
+    let (control_api_farms, farms) = {
         let node_client = node_client.clone();
         let info_mutex = &AsyncMutex::new(());
         let faster_read_sector_record_chunks_mode_barrier =
@@ -242,15 +323,21 @@ where
         let mut farms = Vec::with_capacity(disk_farms.len());
         let mut farms_stream = disk_farms
             .into_iter()
+            .zip(reward_address_policies)
             .enumerate()
-            .map(|(farm_index, disk_farm)| {
+            .map(|(farm_index, (disk_farm, reward_addresses))| {
                 let farmer_app_info = farmer_app_info.clone();
                 let node_client = node_client.clone();
                 let kzg = kzg.clone();
                 let erasure_coding = erasure_coding.clone();
-                let plotter_legacy = Arc::clone(&plotter_legacy);
-                let plotter = Arc::clone(&plotter);
+                let plotting_scheduler_farm =
+                    plotting_scheduler.register_farm(disk_farm.plotting_weight);
+                let plotter_legacy =
+                    Arc::new(plotting_scheduler_farm.wrap(Arc::clone(&plotter_legacy)));
+                let plotter = Arc::new(plotting_scheduler_farm.wrap(Arc::clone(&plotter)));
                 let global_mutex = Arc::clone(&global_mutex);
+                #[cfg(unix)]
+                let remote_signer = remote_signer.clone();
                 let faster_read_sector_record_chunks_mode_barrier =
                     Arc::clone(&faster_read_sector_record_chunks_mode_barrier);
                 let faster_read_sector_record_chunks_mode_concurrency =
@@ -264,13 +351,14 @@ where
                             allocated_space: disk_farm.allocated_space,
                             max_pieces_in_sector,
                             node_client,
-                            reward_address,
+                            reward_addresses,
                             plotter_legacy,
                             plotter,
                             kzg,
                             erasure_coding,
                             // Cache is provided by dedicated caches in farming cluster
                             cache_percentage: 0,
+                            piece_cache_tier: disk_farm.cache_tier,
                             farming_thread_pool_size,
                             plotting_delay: None,
                             global_mutex,
@@ -281,6 +369,12 @@ where
                             faster_read_sector_record_chunks_mode_concurrency,
                             registry: Some(registry),
                             create,
+                            integrity_check_interval: (integrity_check_interval > 0)
+                                .then(|| Duration::from_secs(integrity_check_interval)),
+                            #[cfg(target_os = "linux")]
+                            io_uring_queue_depth: disk_farm.io_uring_queue_depth,
+                            #[cfg(unix)]
+                            remote_signer,
                         },
                         farm_index,
                     );
@@ -325,7 +419,19 @@ where
                         info!("  Directory: {}", disk_farm.directory.display());
                     }
 
-                    (farm_index, Ok(Box::new(farm) as Box<dyn Farm>))
+                    let control_api_farm = ControlApiFarm {
+                        id: *farm.id(),
+                        directory: disk_farm.directory,
+                        total_sectors_count: farm.total_sectors_count(),
+                        plotted_sectors: Arc::new(farm.plotted_sectors()),
+                        plotting_pause_handle: farm.plotting_pause_handle(),
+                        remote_signer: remote_signer_used,
+                    };
+
+                    (
+                        farm_index,
+                        Ok((control_api_farm, Box::new(farm) as Box<dyn Farm>)),
+                    )
                 }
                 .instrument(info_span!("", %farm_index))
             })
@@ -347,9 +453,17 @@ where
         farms
             .into_iter()
             .map(|(_farm_index, farm)| farm)
-            .collect::<Vec<_>>()
+            .unzip::<_, _, Vec<_>, Vec<_>>()
     };
 
+    // Farmer cache is not a part of cluster farmer, hence no cache sync progress
+    let control_api = control_api_listen_on.map(|listen_on| {
+        (
+            listen_on,
+            ControlApi::new(plotting_scheduler, control_api_farms),
+        )
+    });
+
     let mut farmer_services = (0..service_instances.get())
         .map(|index| {
             AsyncJoinOnDrop::new(
@@ -375,9 +489,29 @@ where
 
     let mut farms_stream = (0u8..)
         .zip(farms)
-        .map(|(farm_index, farm)| farm.run().map(move |result| (farm_index, result)))
+        .map(|(farm_index, farm)| {
+            if let Some((_listen_on, control_api)) = &control_api {
+                control_api.subscribe(farm_index, &farm);
+            }
+
+            if let Some(event_log) = &event_log {
+                event_log.subscribe(farm_index, &farm);
+            }
+
+            farm.run().map(move |result| (farm_index, result))
+        })
         .collect::<FuturesUnordered<_>>();
 
+    let control_api_server = match control_api {
+        Some((listen_on, control_api)) => Some(
+            control_api
+                .start(listen_on)
+                .await
+                .map_err(|error| anyhow!("Failed to start control API server: {error}"))?,
+        ),
+        None => None,
+    };
+
     let mut farm_errors = Vec::new();
 
     let farm_fut = run_future_in_dedicated_thread(
@@ -435,6 +569,7 @@ where
             },
         }
 
+        drop(control_api_server);
         drop(tmp_directory);
 
         Ok(())
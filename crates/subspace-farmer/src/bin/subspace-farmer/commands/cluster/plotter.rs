@@ -1,10 +1,12 @@
 use crate::commands::shared::PlottingThreadPriority;
 use anyhow::anyhow;
+use bytesize::ByteSize;
 use clap::Parser;
 use futures::{select, FutureExt};
 use prometheus_client::registry::Registry;
 use std::future::Future;
 use std::num::NonZeroUsize;
+use std::path::PathBuf;
 use std::pin::Pin;
 use std::sync::Arc;
 use subspace_core_primitives::crypto::kzg::{embedded_kzg_settings, Kzg};
@@ -76,6 +78,16 @@ pub(super) struct PlotterArgs {
     /// "min", "max" or "default".
     #[arg(long, default_value_t = PlottingThreadPriority::Min)]
     plotting_thread_priority: PlottingThreadPriority,
+    /// Directory where plotted sectors are written while they are transferred to farmers once
+    /// `--max-held-sectors-memory` is reached, such that interrupted transfers can be resumed.
+    /// Without it plotter pauses plotting until sectors held in memory are received by farmers.
+    #[arg(long)]
+    sector_spill_directory: Option<PathBuf>,
+    /// Max amount of memory used for plotted sectors held while they are transferred to farmers,
+    /// in human-readable format (e.g. 10GB, 2TiB) or just bytes (e.g. 4096). Applies separately to
+    /// legacy and modern plotting.
+    #[arg(long, default_value = "4GiB")]
+    max_held_sectors_memory: ByteSize,
     /// Additional cluster components
     #[clap(raw = true)]
     pub(super) additional_components: Vec<String>,
@@ -98,6 +110,8 @@ where
         plotting_thread_pool_size,
         plotting_cpu_cores,
         plotting_thread_priority,
+        sector_spill_directory,
+        max_held_sectors_memory,
         additional_components: _,
     } = plotter_args;
 
@@ -144,15 +158,9 @@ where
         "Preparing plotting thread pools"
     );
 
-    let replotting_thread_pool_core_indices = plotting_thread_pool_core_indices
-        .clone()
-        .into_iter()
-        .map(|mut cpu_core_set| {
-            // We'll not use replotting threads at all, so just limit them to 1 core so we don't
-            // have too many threads hanging unnecessarily
-            cpu_core_set.truncate(1);
-            cpu_core_set
-        });
+    // Replotting requests are received from farmers with lower priority already, so dedicated
+    // plotter replots sectors using the same CPU cores as for initial plotting
+    let replotting_thread_pool_core_indices = plotting_thread_pool_core_indices.clone();
     let plotting_thread_pool_manager = create_plotting_thread_pool_manager(
         plotting_thread_pool_core_indices
             .into_iter()
@@ -183,11 +191,14 @@ where
     ));
 
     Ok(Box::pin(async move {
+        let sector_spill_directory = sector_spill_directory.as_deref();
+        let max_held_sectors_memory = max_held_sectors_memory.as_u64() as usize;
+
         select! {
-            result = plotter_service(&nats_client, &legacy_cpu_plotter, false).fuse() => {
+            result = plotter_service(&nats_client, &legacy_cpu_plotter, false, sector_spill_directory, max_held_sectors_memory).fuse() => {
                 result.map_err(|error| anyhow!("Plotter service failed: {error}"))
             }
-            result = plotter_service(&nats_client, &modern_cpu_plotter, true).fuse() => {
+            result = plotter_service(&nats_client, &modern_cpu_plotter, true, sector_spill_directory, max_held_sectors_memory).fuse() => {
                 result.map_err(|error| anyhow!("Plotter service failed: {error}"))
             }
         }
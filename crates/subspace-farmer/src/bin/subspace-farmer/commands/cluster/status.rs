@@ -0,0 +1,571 @@
+use anyhow::anyhow;
+use clap::Parser;
+use futures::stream::{FuturesUnordered, SelectAll};
+use futures::{select, FutureExt, StreamExt};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::{Duration, Instant};
+use subspace_core_primitives::SectorIndex;
+use subspace_farmer::cluster::cache::{ClusterCacheIdentifyBroadcast, ClusterPieceCache};
+use subspace_farmer::cluster::controller::{
+    ClusterControllerCacheIdentifyBroadcast, ClusterControllerFarmerIdentifyBroadcast,
+    ClusterControllerIdentifyBroadcast, ClusterControllerIdentifyRequestBroadcast,
+    ClusterControllerPlotterIdentifyBroadcast,
+};
+use subspace_farmer::cluster::farmer::{ClusterFarm, ClusterFarmerIdentifyFarmBroadcast};
+use subspace_farmer::cluster::nats_client::NatsClient;
+use subspace_farmer::cluster::plotter::ClusterPlotterIdentifyBroadcast;
+use subspace_farmer::farm::{Farm, FarmId, PieceCache, PieceCacheId, PieceCacheTier};
+use tracing::warn;
+use ulid::Ulid;
+
+/// Arguments for status
+#[derive(Debug, Parser)]
+pub(super) struct StatusArgs {
+    /// Cache group to query in addition to cache groups of controllers that are online, can be
+    /// specified multiple times
+    #[arg(long = "cache-group")]
+    cache_groups: Vec<String>,
+    /// How long to wait for components to identify themselves, in seconds
+    #[arg(long, default_value = "5")]
+    wait: u64,
+    /// Timeout for retrieving plotted sectors of each farm and contents of each cache, in seconds
+    #[arg(long, default_value = "60")]
+    details_timeout: u64,
+    /// Print JSON instead of human-readable text
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ControllerReport {
+    instance: String,
+    cache_group: String,
+    /// Time between identification request and response
+    latency_ms: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FarmReport {
+    farm_id: String,
+    total_sectors_count: SectorIndex,
+    /// Only known if plotted sectors were retrieved successfully
+    plotted_sectors_count: Option<SectorIndex>,
+    healthy: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    /// Time between identification request and response
+    latency_ms: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CacheReport {
+    cache_id: String,
+    cache_group: String,
+    tier: PieceCacheTier,
+    capacity: u32,
+    /// Only known if cache contents were retrieved successfully
+    used: Option<u32>,
+    healthy: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    /// Time between identification request and response
+    latency_ms: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PlotterReport {
+    plotter_id: String,
+    modern: bool,
+    has_free_capacity: bool,
+    /// Number of plot sector requests waiting in the queue
+    queue_depth: u32,
+    /// Time between identification request and response
+    latency_ms: u64,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ClusterReport {
+    controllers: Vec<ControllerReport>,
+    farms: Vec<FarmReport>,
+    caches: Vec<CacheReport>,
+    plotters: Vec<PlotterReport>,
+}
+
+#[derive(Debug)]
+struct IdentifiedFarm {
+    total_sectors_count: SectorIndex,
+    latency: Duration,
+}
+
+#[derive(Debug)]
+struct IdentifiedCache {
+    cache_group: String,
+    max_num_elements: u32,
+    tier: PieceCacheTier,
+    latency: Duration,
+}
+
+/// Components that identified themselves
+#[derive(Debug, Default)]
+struct IdentifiedComponents {
+    controllers: HashMap<String, (String, Duration)>,
+    farms: HashMap<FarmId, IdentifiedFarm>,
+    caches: HashMap<PieceCacheId, IdentifiedCache>,
+    plotters: HashMap<String, (ClusterPlotterIdentifyBroadcast, Duration)>,
+}
+
+pub(super) async fn status(
+    nats_client: NatsClient,
+    status_args: StatusArgs,
+) -> anyhow::Result<Pin<Box<dyn Future<Output = anyhow::Result<()>>>>> {
+    let StatusArgs {
+        cache_groups,
+        wait,
+        details_timeout,
+        json,
+    } = status_args;
+
+    Ok(Box::pin(async move {
+        let identified_components =
+            identify_components(&nats_client, cache_groups, Duration::from_secs(wait)).await?;
+        let report = collect_report(
+            &nats_client,
+            identified_components,
+            Duration::from_secs(details_timeout),
+        )
+        .await;
+
+        if json {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        } else {
+            print_report(&report);
+        }
+
+        Ok(())
+    }))
+}
+
+async fn identify_components(
+    nats_client: &NatsClient,
+    cache_groups: Vec<String>,
+    wait: Duration,
+) -> anyhow::Result<IdentifiedComponents> {
+    // Instance name is irrelevant for identification requests, but must be unique
+    let instance = format!("status-{}", Ulid::new());
+
+    let mut controller_subscription = nats_client
+        .subscribe_to_broadcasts::<ClusterControllerIdentifyBroadcast>(None, None)
+        .await
+        .map_err(|error| anyhow!("Failed to subscribe to controller identification: {error}"))?
+        .fuse();
+    let mut farm_subscription = nats_client
+        .subscribe_to_broadcasts::<ClusterFarmerIdentifyFarmBroadcast>(None, None)
+        .await
+        .map_err(|error| anyhow!("Failed to subscribe to farm identification: {error}"))?
+        .fuse();
+    let mut plotter_subscription = nats_client
+        .subscribe_to_broadcasts::<ClusterPlotterIdentifyBroadcast>(None, None)
+        .await
+        .map_err(|error| anyhow!("Failed to subscribe to plotter identification: {error}"))?
+        .fuse();
+    let mut cache_subscriptions = SelectAll::new();
+    // Identification requests are broadcasts without correlation with responses, so latency of each
+    // response is measured against the most recent request of the corresponding kind, which is the
+    // request that response was triggered by
+    let mut cache_groups_requested_at = HashMap::new();
+
+    let mut identified_components = IdentifiedComponents::default();
+
+    let mut deadline = Box::pin(tokio::time::sleep(wait).fuse());
+    // Farmers and caches ignore identification requests that are too frequent, so request is sent
+    // one more time in case the first one was ignored
+    let mut repeat = Box::pin(tokio::time::sleep(wait / 2).fuse());
+
+    let controllers_requested_at = Instant::now();
+    nats_client
+        .broadcast(&ClusterControllerIdentifyRequestBroadcast, &instance)
+        .await
+        .map_err(|error| anyhow!("Failed to request controller identification: {error}"))?;
+    let plotters_requested_at = Instant::now();
+    nats_client
+        .broadcast(&ClusterControllerPlotterIdentifyBroadcast, &instance)
+        .await
+        .map_err(|error| anyhow!("Failed to request plotter identification: {error}"))?;
+    let mut farms_requested_at = request_farm_identification(nats_client, &instance).await?;
+    for cache_group in cache_groups {
+        query_cache_group(
+            nats_client,
+            cache_group,
+            &mut cache_groups_requested_at,
+            &mut cache_subscriptions,
+        )
+        .await?;
+    }
+
+    loop {
+        select! {
+            maybe_controller = controller_subscription.next() => {
+                let Some(ClusterControllerIdentifyBroadcast { instance, cache_group }) =
+                    maybe_controller
+                else {
+                    return Err(anyhow!("Controller identification subscription ended"));
+                };
+
+                let latency = controllers_requested_at.elapsed();
+                query_cache_group(
+                    nats_client,
+                    cache_group.clone(),
+                    &mut cache_groups_requested_at,
+                    &mut cache_subscriptions,
+                )
+                .await?;
+                identified_components
+                    .controllers
+                    .entry(instance)
+                    .or_insert((cache_group, latency));
+            }
+            maybe_farm = farm_subscription.next() => {
+                let Some(farm) = maybe_farm else {
+                    return Err(anyhow!("Farm identification subscription ended"));
+                };
+
+                identified_components
+                    .farms
+                    .entry(farm.farm_id)
+                    .or_insert_with(|| IdentifiedFarm {
+                        total_sectors_count: farm.total_sectors_count,
+                        latency: farms_requested_at.elapsed(),
+                    });
+            }
+            maybe_plotter = plotter_subscription.next() => {
+                let Some(plotter) = maybe_plotter else {
+                    return Err(anyhow!("Plotter identification subscription ended"));
+                };
+
+                let latency = plotters_requested_at.elapsed();
+                identified_components
+                    .plotters
+                    .entry(plotter.plotter_id.clone())
+                    .or_insert((plotter, latency));
+            }
+            maybe_cache = cache_subscriptions.next() => {
+                let Some((cache_group, cache)) = maybe_cache else {
+                    continue;
+                };
+                let ClusterCacheIdentifyBroadcast {
+                    cache_id,
+                    max_num_elements,
+                    tier,
+                } = cache;
+                let Some(requested_at) = cache_groups_requested_at.get(&cache_group) else {
+                    continue;
+                };
+                let latency = requested_at.elapsed();
+
+                identified_components
+                    .caches
+                    .entry(cache_id)
+                    .or_insert_with(|| IdentifiedCache {
+                        cache_group,
+                        max_num_elements,
+                        tier,
+                        latency,
+                    });
+            }
+            _ = repeat => {
+                farms_requested_at = request_farm_identification(nats_client, &instance).await?;
+                for (cache_group, requested_at) in &mut cache_groups_requested_at {
+                    *requested_at = request_cache_identification(nats_client, cache_group).await?;
+                }
+            }
+            _ = deadline => {
+                break;
+            }
+        }
+    }
+
+    Ok(identified_components)
+}
+
+async fn request_farm_identification(
+    nats_client: &NatsClient,
+    instance: &str,
+) -> anyhow::Result<Instant> {
+    let requested_at = Instant::now();
+    nats_client
+        .broadcast(&ClusterControllerFarmerIdentifyBroadcast, instance)
+        .await
+        .map_err(|error| anyhow!("Failed to request farm identification: {error}"))?;
+
+    Ok(requested_at)
+}
+
+async fn request_cache_identification(
+    nats_client: &NatsClient,
+    cache_group: &str,
+) -> anyhow::Result<Instant> {
+    let requested_at = Instant::now();
+    nats_client
+        .broadcast(&ClusterControllerCacheIdentifyBroadcast, cache_group)
+        .await
+        .map_err(|error| {
+            anyhow!("Failed to request cache identification in cache group {cache_group}: {error}")
+        })?;
+
+    Ok(requested_at)
+}
+
+/// Subscribe to cache identification in cache group and request identification, does nothing if
+/// cache group was already queried
+async fn query_cache_group(
+    nats_client: &NatsClient,
+    cache_group: String,
+    cache_groups_requested_at: &mut HashMap<String, Instant>,
+    cache_subscriptions: &mut SelectAll<
+        Pin<Box<dyn futures::Stream<Item = (String, ClusterCacheIdentifyBroadcast)> + Send>>,
+    >,
+) -> anyhow::Result<()> {
+    if cache_groups_requested_at.contains_key(&cache_group) {
+        return Ok(());
+    }
+
+    let subscription = nats_client
+        .subscribe_to_broadcasts::<ClusterCacheIdentifyBroadcast>(Some(&cache_group), None)
+        .await
+        .map_err(|error| {
+            anyhow!(
+                "Failed to subscribe to cache identification in cache group {cache_group}: {error}"
+            )
+        })?;
+    cache_subscriptions.push(Box::pin(subscription.map({
+        let cache_group = cache_group.clone();
+
+        move |cache| (cache_group.clone(), cache)
+    })));
+
+    let requested_at = request_cache_identification(nats_client, &cache_group).await?;
+    cache_groups_requested_at.insert(cache_group, requested_at);
+
+    Ok(())
+}
+
+async fn collect_report(
+    nats_client: &NatsClient,
+    identified_components: IdentifiedComponents,
+    details_timeout: Duration,
+) -> ClusterReport {
+    let IdentifiedComponents {
+        controllers,
+        farms,
+        caches,
+        plotters,
+    } = identified_components;
+
+    let mut report = ClusterReport {
+        controllers: controllers
+            .into_iter()
+            .map(|(instance, (cache_group, latency))| ControllerReport {
+                instance,
+                cache_group,
+                latency_ms: latency.as_millis() as u64,
+            })
+            .collect(),
+        plotters: plotters
+            .into_values()
+            .map(|(plotter, latency)| PlotterReport {
+                plotter_id: plotter.plotter_id,
+                modern: plotter.modern,
+                has_free_capacity: plotter.has_free_capacity,
+                queue_depth: plotter.queue_depth,
+                latency_ms: latency.as_millis() as u64,
+            })
+            .collect(),
+        ..ClusterReport::default()
+    };
+
+    let farm_reports = farms
+        .into_iter()
+        .map(|(farm_id, farm)| async move {
+            let result = tokio::time::timeout(
+                details_timeout,
+                count_plotted_sectors(nats_client, farm_id, farm.total_sectors_count),
+            )
+            .await
+            .unwrap_or_else(|_elapsed| Err("Timed out retrieving plotted sectors".to_string()));
+
+            if let Err(error) = &result {
+                warn!(%farm_id, %error, "Failed to retrieve plotted sectors");
+            }
+
+            FarmReport {
+                farm_id: farm_id.to_string(),
+                total_sectors_count: farm.total_sectors_count,
+                plotted_sectors_count: result.as_ref().ok().copied(),
+                healthy: result.is_ok(),
+                error: result.err(),
+                latency_ms: farm.latency.as_millis() as u64,
+            }
+        })
+        .collect::<FuturesUnordered<_>>()
+        .collect::<Vec<_>>();
+    let cache_reports = caches
+        .into_iter()
+        .map(|(cache_id, cache)| async move {
+            let result = tokio::time::timeout(
+                details_timeout,
+                count_used_cache_elements(
+                    nats_client,
+                    cache_id,
+                    cache.max_num_elements,
+                    cache.tier,
+                ),
+            )
+            .await
+            .unwrap_or_else(|_elapsed| Err("Timed out retrieving cache contents".to_string()));
+
+            if let Err(error) = &result {
+                warn!(%cache_id, %error, "Failed to retrieve cache contents");
+            }
+
+            CacheReport {
+                cache_id: cache_id.to_string(),
+                cache_group: cache.cache_group,
+                tier: cache.tier,
+                capacity: cache.max_num_elements,
+                used: result.as_ref().ok().copied(),
+                healthy: result.is_ok(),
+                error: result.err(),
+                latency_ms: cache.latency.as_millis() as u64,
+            }
+        })
+        .collect::<FuturesUnordered<_>>()
+        .collect::<Vec<_>>();
+
+    (report.farms, report.caches) = futures::join!(farm_reports, cache_reports);
+
+    report
+        .controllers
+        .sort_by(|a, b| a.instance.cmp(&b.instance));
+    report.farms.sort_by(|a, b| a.farm_id.cmp(&b.farm_id));
+    report
+        .caches
+        .sort_by(|a, b| (&a.cache_group, &a.cache_id).cmp(&(&b.cache_group, &b.cache_id)));
+    report
+        .plotters
+        .sort_by(|a, b| a.plotter_id.cmp(&b.plotter_id));
+
+    report
+}
+
+async fn count_plotted_sectors(
+    nats_client: &NatsClient,
+    farm_id: FarmId,
+    total_sectors_count: SectorIndex,
+) -> Result<SectorIndex, String> {
+    let farm = ClusterFarm::new(farm_id, total_sectors_count, nats_client.clone())
+        .await
+        .map_err(|error| error.to_string())?;
+    let plotted_sectors = farm.plotted_sectors();
+    let mut plotted_sectors = plotted_sectors
+        .get()
+        .await
+        .map_err(|error| error.to_string())?;
+
+    let mut plotted_sectors_count: SectorIndex = 0;
+    while let Some(result) = plotted_sectors.next().await {
+        result.map_err(|error| error.to_string())?;
+        plotted_sectors_count += 1;
+    }
+
+    Ok(plotted_sectors_count)
+}
+
+async fn count_used_cache_elements(
+    nats_client: &NatsClient,
+    cache_id: PieceCacheId,
+    max_num_elements: u32,
+    tier: PieceCacheTier,
+) -> Result<u32, String> {
+    let cache = ClusterPieceCache::new(cache_id, max_num_elements, tier, nats_client.clone());
+    let mut contents = cache.contents().await.map_err(|error| error.to_string())?;
+
+    let mut used = 0;
+    while let Some(result) = contents.next().await {
+        let (_offset, maybe_piece_index) = result.map_err(|error| error.to_string())?;
+        if maybe_piece_index.is_some() {
+            used += 1;
+        }
+    }
+
+    Ok(used)
+}
+
+fn print_report(report: &ClusterReport) {
+    println!("Controllers ({}):", report.controllers.len());
+    for controller in &report.controllers {
+        println!(
+            "  {} (cache group {}): latency {} ms",
+            controller.instance, controller.cache_group, controller.latency_ms
+        );
+    }
+
+    println!("Farms ({}):", report.farms.len());
+    for farm in &report.farms {
+        let plotted = match farm.plotted_sectors_count {
+            Some(plotted_sectors_count) => plotted_sectors_count.to_string(),
+            None => "?".to_string(),
+        };
+        let health = match &farm.error {
+            Some(error) => format!("unhealthy ({error})"),
+            None => "healthy".to_string(),
+        };
+        println!(
+            "  {}: {plotted}/{} sectors plotted, {health}, latency {} ms",
+            farm.farm_id, farm.total_sectors_count, farm.latency_ms
+        );
+    }
+
+    println!("Caches ({}):", report.caches.len());
+    for cache in &report.caches {
+        let used = match cache.used {
+            Some(used) => format!(
+                "{used}/{} pieces ({:.2}%)",
+                cache.capacity,
+                f64::from(used) / f64::from(cache.capacity.max(1)) * 100.0
+            ),
+            None => format!("?/{} pieces", cache.capacity),
+        };
+        let health = match &cache.error {
+            Some(error) => format!("unhealthy ({error})"),
+            None => "healthy".to_string(),
+        };
+        println!(
+            "  {} (cache group {}, {} tier): {used}, {health}, latency {} ms",
+            cache.cache_id, cache.cache_group, cache.tier, cache.latency_ms
+        );
+    }
+
+    println!("Plotters ({}):", report.plotters.len());
+    for plotter in &report.plotters {
+        println!(
+            "  {} ({}): {}, {} queued, latency {} ms",
+            plotter.plotter_id,
+            if plotter.modern { "modern" } else { "legacy" },
+            if plotter.has_free_capacity {
+                "has free capacity"
+            } else {
+                "fully occupied"
+            },
+            plotter.queue_depth,
+            plotter.latency_ms
+        );
+    }
+}
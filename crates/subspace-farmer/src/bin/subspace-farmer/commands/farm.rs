@@ -1,5 +1,9 @@
+use crate::commands::shared::control_api::{ControlApi, ControlApiFarm};
+use crate::commands::shared::event_log::EventLogArgs;
 use crate::commands::shared::network::{configure_network, NetworkArgs};
-use crate::commands::shared::{derive_libp2p_keypair, DiskFarm, PlottingThreadPriority};
+use crate::commands::shared::{
+    cache_percentage_parser, derive_libp2p_keypair, DiskFarm, PlottingThreadPriority,
+};
 use crate::utils::shutdown_signal;
 use anyhow::anyhow;
 use async_lock::{Mutex as AsyncMutex, RwLock as AsyncRwLock};
@@ -13,24 +17,31 @@ use parking_lot::Mutex;
 use prometheus_client::registry::Registry;
 use std::fs;
 use std::net::SocketAddr;
-use std::num::{NonZeroU8, NonZeroUsize};
+use std::num::{NonZeroU32, NonZeroU8, NonZeroUsize};
+use std::path::PathBuf;
 use std::pin::pin;
-use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
 use subspace_core_primitives::crypto::kzg::{embedded_kzg_settings, Kzg};
 use subspace_core_primitives::{PublicKey, Record};
 use subspace_erasure_coding::ErasureCoding;
 use subspace_farmer::farm::plotted_pieces::PlottedPieces;
-use subspace_farmer::farm::{PlottedSectors, SectorPlottingDetails, SectorUpdate};
+use subspace_farmer::farm::{PieceCacheTier, PlottedSectors, SectorPlottingDetails, SectorUpdate};
 use subspace_farmer::farmer_cache::FarmerCache;
+use subspace_farmer::farmer_piece_getter::piece_source::FarmerPieceSource;
 use subspace_farmer::farmer_piece_getter::piece_validator::SegmentCommitmentPieceValidator;
 use subspace_farmer::farmer_piece_getter::{DsnCacheRetryPolicy, FarmerPieceGetter};
 use subspace_farmer::node_client::caching_proxy_node_client::CachingProxyNodeClient;
 use subspace_farmer::node_client::rpc_node_client::RpcNodeClient;
 use subspace_farmer::node_client::NodeClient;
 use subspace_farmer::plotter::cpu::CpuPlotter;
+use subspace_farmer::plotter::scheduler::{
+    PlottingScheduler, PlottingSchedulerOptions, TimeWindow,
+};
 use subspace_farmer::single_disk_farm::identity::Identity;
+#[cfg(unix)]
+use subspace_farmer::single_disk_farm::remote_signer::RemoteSigner;
+use subspace_farmer::single_disk_farm::reward_address::RewardAddressRotation;
 use subspace_farmer::single_disk_farm::{
     SingleDiskFarm, SingleDiskFarmError, SingleDiskFarmOptions,
 };
@@ -77,14 +88,34 @@ pub(crate) struct FarmingArgs {
     /// farmer will make sure to not exceed (and will pre-allocated all the space on startup to
     /// ensure it will not run out of space in runtime). Optionally, `record-chunks-mode` can be
     /// set to `ConcurrentChunks` or `WholeSector` in order to avoid internal benchmarking during
-    /// startup.
+    /// startup. Optionally, `plotting-weight` can be set to a positive integer (1 by default), farms
+    /// share plotting capacity proportionally to their weights.
+    ///
+    /// Farm can also have its own reward addresses with `reward-address` (can be specified
+    /// multiple times) and `reward-address-rotation` that override global `--reward-address` and
+    /// `--reward-address-rotation`.
+    ///
+    /// `cache-tier` can be set to `fast` (SSD) or `slow` (HDD, default), pieces that are requested
+    /// often are moved to piece caches of farms in fast tier.
+    ///
+    /// On Linux `io-uring-queue-depth` can be set to a positive integer to read from the plot with
+    /// io_uring during auditing and piece reading, which allows deeper I/O queues on HDDs without
+    /// a large farming thread pool.
     disk_farms: Vec<DiskFarm>,
     /// WebSocket RPC URL of the Subspace node to connect to
     #[arg(long, value_hint = ValueHint::Url, default_value = "ws://127.0.0.1:9944")]
     node_rpc_url: String,
-    /// Address for farming rewards
+    /// Address for farming rewards, can be specified multiple times to rotate between addresses.
+    ///
+    /// Required unless every farm has its own `reward-address` specified.
     #[arg(long, value_parser = parse_ss58_reward_address)]
-    reward_address: PublicKey,
+    reward_address: Vec<PublicKey>,
+    /// How multiple reward addresses are rotated: `solution` to switch to the next address after
+    /// every solution (default) or `slots:<number>` to switch every specified number of slots.
+    ///
+    /// Can be overridden with `reward-address-rotation` in farm options.
+    #[arg(long)]
+    reward_address_rotation: Option<RewardAddressRotation>,
     /// Percentage of allocated space dedicated for caching purposes, 99% max
     #[arg(long, default_value = "1", value_parser = cache_percentage_parser)]
     cache_percentage: NonZeroU8,
@@ -115,11 +146,28 @@ pub(crate) struct FarmingArgs {
     /// one specified endpoint. Format: 127.0.0.1:8080
     #[arg(long, aliases = ["metrics-endpoint", "metrics-endpoints"])]
     prometheus_listen_on: Vec<SocketAddr>,
+    /// Defines endpoint for the local control API (JSON-RPC over both HTTP and WebSocket) that
+    /// allows to inspect farms, subscribe to their events and pause/resume plotting at runtime.
+    /// It doesn't start unless endpoint is specified. API has no authentication and must not be
+    /// exposed publicly. Format: 127.0.0.1:9955
+    #[arg(long)]
+    control_api_listen_on: Option<SocketAddr>,
     /// Piece getter concurrency.
     ///
     /// Increase will result in higher memory usage.
     #[arg(long, default_value = "128")]
     piece_getter_concurrency: NonZeroUsize,
+    /// Source of pieces used for plotting and piece cache sync, can be specified multiple times.
+    ///
+    /// Supported sources are `farmer-cache`, `dsn-cache`, `node`, `local-plots`,
+    /// `archival-storage`, `http=<url>` (HTTP mirror that responds to `GET <url>/<piece-index>`
+    /// with raw piece bytes) and `directory=<path>` (directory with pieces stored in
+    /// `<piece-index>.piece` files). Sources are reordered at runtime by observed latency and
+    /// success rate, `local-plots` and `archival-storage` are only used after all other sources.
+    ///
+    /// All built-in sources are used by default.
+    #[arg(long = "piece-source")]
+    piece_sources: Vec<FarmerPieceSource>,
     /// Defines how many sectors farmer will download concurrently, allows to limit memory usage of
     /// the plotting process, defaults to `--sector-encoding-concurrency` + 1 to download future
     /// sector ahead of time.
@@ -193,6 +241,10 @@ pub(crate) struct FarmingArgs {
     /// "min", "max" or "default".
     #[arg(long, default_value_t = PlottingThreadPriority::Min)]
     plotting_thread_priority: PlottingThreadPriority,
+    /// Only replot expired sectors within specified daily time window in UTC, initial plotting is
+    /// not restricted. Format: 00:00-06:00, window can wrap around midnight (e.g. 22:00-06:00).
+    #[arg(long)]
+    replotting_window: Option<TimeWindow>,
     /// Enable plot cache.
     ///
     /// Plot cache uses unplotted space as additional cache improving plotting speeds, especially
@@ -202,6 +254,20 @@ pub(crate) struct FarmingArgs {
     /// enabled by default regardless of farm size.
     #[arg(long)]
     plot_cache: Option<bool>,
+    /// Delay in seconds between checking integrity of consecutive plotted sectors in the
+    /// background, corrupted sectors are replotted automatically. Checking reads the whole sector,
+    /// so smaller values increase disk load. `0` disables background integrity check.
+    #[arg(long, default_value_t = 60)]
+    integrity_check_interval: u64,
+    /// Path to Unix socket of remote signer (see `signer` command) that holds farm identities.
+    ///
+    /// Farms created with remote signer only store public key, existing farms can be switched to
+    /// remote signer by moving their `identity.bin` files into remote signer.
+    #[arg(long)]
+    remote_signer: Option<PathBuf>,
+    /// Structured event log parameters
+    #[clap(flatten)]
+    event_log_args: EventLogArgs,
     /// Disable farm locking, for example if file system doesn't support it
     #[arg(long)]
     disable_farm_locking: bool,
@@ -217,16 +283,6 @@ pub(crate) struct FarmingArgs {
     exit_on_farm_error: bool,
 }
 
-fn cache_percentage_parser(s: &str) -> anyhow::Result<NonZeroU8> {
-    let cache_percentage = NonZeroU8::from_str(s)?;
-
-    if cache_percentage.get() > 99 {
-        return Err(anyhow!("Cache percentage can't exceed 99"));
-    }
-
-    Ok(cache_percentage)
-}
-
 /// Start farming by using multiple replica plot in specified path and connecting to WebSocket
 /// server at specified address.
 pub(crate) async fn farm<PosTableLegacy, PosTable>(farming_args: FarmingArgs) -> anyhow::Result<()>
@@ -239,6 +295,7 @@ where
     let FarmingArgs {
         node_rpc_url,
         reward_address,
+        reward_address_rotation,
         max_pieces_in_sector,
         mut network_args,
         cache_percentage,
@@ -247,7 +304,9 @@ where
         tmp,
         mut disk_farms,
         prometheus_listen_on,
+        control_api_listen_on,
         piece_getter_concurrency,
+        piece_sources,
         sector_downloading_concurrency,
         sector_encoding_concurrency,
         record_encoding_concurrency,
@@ -257,7 +316,11 @@ where
         replotting_thread_pool_size,
         replotting_cpu_cores,
         plotting_thread_priority,
+        replotting_window,
         plot_cache,
+        integrity_check_interval,
+        remote_signer,
+        event_log_args,
         disable_farm_locking,
         create,
         exit_on_farm_error,
@@ -285,6 +348,12 @@ where
             directory: tmp_directory.as_ref().to_path_buf(),
             allocated_space: plot_size.as_u64(),
             read_sector_record_chunks_mode: Some(ReadSectorRecordChunksMode::ConcurrentChunks),
+            plotting_weight: NonZeroU32::MIN,
+            reward_addresses: Vec::new(),
+            reward_address_rotation: None,
+            cache_tier: PieceCacheTier::default(),
+            #[cfg(target_os = "linux")]
+            io_uring_queue_depth: None,
         }];
 
         Some(tmp_directory)
@@ -307,6 +376,23 @@ where
         None
     };
 
+    let event_log = event_log_args.start()?;
+
+    #[cfg(unix)]
+    let remote_signer = remote_signer.map(RemoteSigner::new);
+    #[cfg(not(unix))]
+    if remote_signer.is_some() {
+        return Err(anyhow!(
+            "Remote signer is only supported on Unix-like operating systems"
+        ));
+    }
+    let remote_signer_used = remote_signer.is_some();
+
+    let reward_address_policies = disk_farms
+        .iter()
+        .map(|disk_farm| disk_farm.reward_address_policy(&reward_address, reward_address_rotation))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
     let plotted_pieces = Arc::new(AsyncRwLock::new(PlottedPieces::default()));
 
     info!(url = %node_rpc_url, "Connecting to node RPC");
@@ -324,7 +410,19 @@ where
         .expect("Disk farm collection is not be empty as checked above; qed")
         .directory;
 
-    let identity = if create {
+    // With remote signer farms don't have identities locally, so a dedicated one is used for
+    // networking instead
+    let network_identity_directory;
+    let first_farm_directory = if remote_signer.is_some() {
+        network_identity_directory = first_farm_directory.join("network");
+        fs::create_dir_all(&network_identity_directory)
+            .map_err(|error| anyhow!("Failed to create directory for network identity: {error}"))?;
+        &network_identity_directory
+    } else {
+        first_farm_directory
+    };
+
+    let identity = if create || remote_signer.is_some() {
         Identity::open_or_create(first_farm_directory)
             .map_err(|error| anyhow!("Failed to open or create identity: {error}"))?
     } else {
@@ -400,6 +498,8 @@ where
             },
         },
         piece_getter_concurrency,
+        piece_sources,
+        Some(&mut registry),
     );
 
     let farmer_cache_worker_fut = run_future_in_dedicated_thread(
@@ -521,6 +621,13 @@ where
         Some(&mut registry),
     ));
 
+    let plotting_scheduler = PlottingScheduler::new(PlottingSchedulerOptions { replotting_window });
+
+    let farm_directories = disk_farms
+        .iter()
+        .map(|disk_farm| disk_farm.directory.clone())
+        .collect::<Vec<_>>();
+
     let (farms, plotting_delay_senders) = {
         let info_mutex = &AsyncMutex::new(());
         let faster_read_sector_record_chunks_mode_barrier =
@@ -537,13 +644,20 @@ where
             .zip(plotting_delay_receivers)
             .enumerate()
             .map(|(farm_index, (disk_farm, plotting_delay_receiver))| {
+                let reward_addresses = Arc::clone(&reward_address_policies[farm_index]);
                 let node_client = node_client.clone();
                 let farmer_app_info = farmer_app_info.clone();
                 let kzg = kzg.clone();
                 let erasure_coding = erasure_coding.clone();
-                let plotter_legacy = Arc::clone(&legacy_cpu_plotter);
-                let plotter = Arc::clone(&modern_cpu_plotter);
+                let plotting_scheduler_farm =
+                    plotting_scheduler.register_farm(disk_farm.plotting_weight);
+                let plotter_legacy =
+                    Arc::new(plotting_scheduler_farm.wrap(Arc::clone(&legacy_cpu_plotter)));
+                let plotter =
+                    Arc::new(plotting_scheduler_farm.wrap(Arc::clone(&modern_cpu_plotter)));
                 let global_mutex = Arc::clone(&global_mutex);
+                #[cfg(unix)]
+                let remote_signer = remote_signer.clone();
                 let faster_read_sector_record_chunks_mode_barrier =
                     Arc::clone(&faster_read_sector_record_chunks_mode_barrier);
                 let faster_read_sector_record_chunks_mode_concurrency =
@@ -557,12 +671,13 @@ where
                             allocated_space: disk_farm.allocated_space,
                             max_pieces_in_sector,
                             node_client,
-                            reward_address,
+                            reward_addresses,
                             plotter_legacy,
                             plotter,
                             kzg,
                             erasure_coding,
                             cache_percentage: cache_percentage.get(),
+                            piece_cache_tier: disk_farm.cache_tier,
                             farming_thread_pool_size,
                             plotting_delay: Some(plotting_delay_receiver),
                             global_mutex,
@@ -573,6 +688,12 @@ where
                             faster_read_sector_record_chunks_mode_concurrency,
                             registry: Some(registry),
                             create,
+                            integrity_check_interval: (integrity_check_interval > 0)
+                                .then(|| Duration::from_secs(integrity_check_interval)),
+                            #[cfg(target_os = "linux")]
+                            io_uring_queue_depth: disk_farm.io_uring_queue_depth,
+                            #[cfg(unix)]
+                            remote_signer,
                         },
                         farm_index,
                     );
@@ -644,6 +765,32 @@ where
         (farms, plotting_delay_senders)
     };
 
+    let control_api = control_api_listen_on.map(|listen_on| {
+        let mut control_api = ControlApi::new(
+            plotting_scheduler.clone(),
+            farms
+                .iter()
+                .zip(farm_directories)
+                .map(|(farm, directory)| ControlApiFarm {
+                    id: *farm.id(),
+                    directory,
+                    total_sectors_count: farm.total_sectors_count(),
+                    plotted_sectors: Arc::new(farm.plotted_sectors()),
+                    plotting_pause_handle: farm.plotting_pause_handle(),
+                    remote_signer: remote_signer_used,
+                }),
+        );
+
+        let cache_sync_progress = control_api.cache_sync_progress();
+        farmer_cache
+            .on_sync_progress(Arc::new(move |progress| {
+                *cache_sync_progress.lock() = *progress;
+            }))
+            .detach();
+
+        (listen_on, control_api)
+    });
+
     {
         let handler_id = Arc::new(Mutex::new(None));
         // Wait for piece cache to read already cached contents before starting plotting to improve
@@ -742,6 +889,14 @@ where
             }))
             .detach();
 
+            if let Some((_listen_on, control_api)) = &control_api {
+                control_api.subscribe(farm_index, &farm);
+            }
+
+            if let Some(event_log) = &event_log {
+                event_log.subscribe(farm_index, &farm);
+            }
+
             farm.run().map(move |result| (farm_index, result))
         })
         .collect::<FuturesUnordered<_>>();
@@ -762,6 +917,16 @@ where
         None
     };
 
+    let _control_api_server = match control_api {
+        Some((listen_on, control_api)) => Some(
+            control_api
+                .start(listen_on)
+                .await
+                .map_err(|error| anyhow!("Failed to start control API server: {error}"))?,
+        ),
+        None => None,
+    };
+
     let mut farm_errors = Vec::new();
 
     let farm_fut = run_future_in_dedicated_thread(
@@ -1,12 +1,425 @@
 use crate::commands::shared::print_disk_farm_info;
+use anyhow::anyhow;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::collections::HashMap;
 use std::path::PathBuf;
+use subspace_core_primitives::{HistorySize, PublicKey, SectorId, SegmentCommitment, SegmentIndex};
+use subspace_farmer::disk_piece_cache::DiskPieceCache;
+use subspace_farmer::node_client::rpc_node_client::RpcNodeClient;
+use subspace_farmer::node_client::NodeClient;
+use subspace_farmer::single_disk_farm::{SingleDiskFarm, SingleDiskFarmSummary};
+use subspace_farmer_components::sector::SectorMetadataChecksummed;
+use subspace_rpc_primitives::MAX_SEGMENT_HEADERS_PER_REQUEST;
+use tracing::info;
 
-pub(crate) fn info(disk_farms: Vec<PathBuf>) {
+/// Output format of `info` command
+#[derive(Debug, Copy, Clone, ValueEnum)]
+pub(crate) enum InfoFormat {
+    /// Human-readable text
+    Text,
+    /// JSON array with one object per farm
+    Json,
+    /// CSV with one row per sector
+    Csv,
+}
+
+#[derive(Debug, Copy, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+enum FarmStatus {
+    Found,
+    NotFound,
+    Error,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FarmReport {
+    farm_index: usize,
+    directory: String,
+    status: FarmStatus,
+    /// Errors that happened while collecting the report
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    genesis_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    public_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    allocated_space: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    piece_cache: Option<PieceCacheReport>,
+    sectors: Vec<SectorReport>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PieceCacheReport {
+    capacity: u32,
+    used: u32,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SectorReport {
+    sector_index: u16,
+    pieces_in_sector: u16,
+    history_size: u64,
+    /// Segment index at which sector expires, only known when node RPC URL is provided and
+    /// blockchain history is long enough for expiration to be determined
+    expires_at: Option<u64>,
+    /// Whether sector is already expired, only known when node RPC URL is provided
+    expired: Option<bool>,
+}
+
+/// Estimates sector expiration using protocol info and segment headers from the node
+struct ExpirationEstimator {
+    node_client: RpcNodeClient,
+    min_sector_lifetime: HistorySize,
+    last_segment_index: SegmentIndex,
+    segment_commitments: HashMap<SegmentIndex, Option<SegmentCommitment>>,
+}
+
+impl ExpirationEstimator {
+    async fn new(node_rpc_url: &str) -> anyhow::Result<Self> {
+        info!(url = %node_rpc_url, "Connecting to node RPC");
+        let node_client = RpcNodeClient::new(node_rpc_url)
+            .await
+            .map_err(|error| anyhow!("Failed to connect to node RPC: {error}"))?;
+
+        let farmer_app_info = node_client
+            .farmer_app_info()
+            .await
+            .map_err(|error| anyhow!("Failed to get farmer app info: {error}"))?;
+
+        let last_segment_index = node_client
+            .last_segment_headers(1)
+            .await
+            .map_err(|error| anyhow!("Failed to get last segment header: {error}"))?
+            .into_iter()
+            .next()
+            .flatten()
+            .map(|segment_header| segment_header.segment_index())
+            .unwrap_or_default();
+
+        Ok(Self {
+            node_client,
+            min_sector_lifetime: farmer_app_info.protocol_info.min_sector_lifetime,
+            last_segment_index,
+            segment_commitments: HashMap::new(),
+        })
+    }
+
+    /// Fill expiration details of sectors, segment headers necessary for that are fetched from
+    /// the node in batches and cached across farms
+    async fn estimate(
+        &mut self,
+        public_key: &PublicKey,
+        sectors_metadata: &[SectorMetadataChecksummed],
+        sectors: &mut [SectorReport],
+    ) -> anyhow::Result<()> {
+        let expiration_check_segment_indices = sectors_metadata
+            .iter()
+            .map(|sector_metadata| {
+                sector_metadata
+                    .history_size
+                    .sector_expiration_check(self.min_sector_lifetime)
+                    .map(|expiration_check_history_size| {
+                        expiration_check_history_size.segment_index()
+                    })
+            })
+            .collect::<Vec<_>>();
+
+        let mut missing_segment_indices = expiration_check_segment_indices
+            .iter()
+            .flatten()
+            .filter(|segment_index| !self.segment_commitments.contains_key(segment_index))
+            .copied()
+            .collect::<Vec<_>>();
+        missing_segment_indices.sort_unstable();
+        missing_segment_indices.dedup();
+
+        for segment_indices in missing_segment_indices.chunks(MAX_SEGMENT_HEADERS_PER_REQUEST) {
+            let segment_headers = self
+                .node_client
+                .segment_headers(segment_indices.to_vec())
+                .await
+                .map_err(|error| anyhow!("Failed to get segment headers: {error}"))?;
+
+            for (segment_index, maybe_segment_header) in segment_indices.iter().zip(segment_headers)
+            {
+                self.segment_commitments.insert(
+                    *segment_index,
+                    maybe_segment_header.map(|segment_header| segment_header.segment_commitment()),
+                );
+            }
+        }
+
+        let public_key_hash = public_key.hash();
+        for ((sector_metadata, maybe_expiration_check_segment_index), sector) in sectors_metadata
+            .iter()
+            .zip(expiration_check_segment_indices)
+            .zip(sectors)
+        {
+            let Some(Some(sector_expiration_check_segment_commitment)) =
+                maybe_expiration_check_segment_index.and_then(|expiration_check_segment_index| {
+                    self.segment_commitments
+                        .get(&expiration_check_segment_index)
+                        .copied()
+                })
+            else {
+                // Expiration can't be determined yet, which also means it didn't expire yet
+                sector.expired = Some(false);
+                continue;
+            };
+
+            let expires_at = SectorId::new(public_key_hash, sector_metadata.sector_index)
+                .derive_expiration_history_size(
+                    sector_metadata.history_size,
+                    &sector_expiration_check_segment_commitment,
+                    self.min_sector_lifetime,
+                )
+                .map(|expiration_history_size| expiration_history_size.segment_index());
+
+            sector.expires_at = expires_at.map(u64::from);
+            sector.expired = Some(
+                expires_at
+                    .map(|expires_at| expires_at <= self.last_segment_index)
+                    .unwrap_or_default(),
+            );
+        }
+
+        Ok(())
+    }
+}
+
+pub(crate) async fn info(
+    disk_farms: Vec<PathBuf>,
+    format: InfoFormat,
+    node_rpc_url: Option<String>,
+) -> anyhow::Result<()> {
+    let mut expiration_estimator = match &node_rpc_url {
+        Some(node_rpc_url) => Some(ExpirationEstimator::new(node_rpc_url).await?),
+        None => None,
+    };
+
+    match format {
+        InfoFormat::Text => {
+            for (farm_index, disk_farm) in disk_farms.into_iter().enumerate() {
+                if farm_index > 0 {
+                    println!();
+                }
+
+                print_disk_farm_info(disk_farm.clone(), farm_index);
+
+                if let Some(expiration_estimator) = &mut expiration_estimator {
+                    let report =
+                        collect_report(disk_farm, farm_index, Some(expiration_estimator)).await?;
+                    print_expiration_summary(&report);
+                }
+            }
+        }
+        InfoFormat::Json => {
+            let reports = collect_reports(disk_farms, expiration_estimator.as_mut()).await?;
+            println!("{}", serde_json::to_string_pretty(&reports)?);
+        }
+        InfoFormat::Csv => {
+            let reports = collect_reports(disk_farms, expiration_estimator.as_mut()).await?;
+            print_csv(&reports);
+        }
+    }
+
+    Ok(())
+}
+
+async fn collect_reports(
+    disk_farms: Vec<PathBuf>,
+    mut expiration_estimator: Option<&mut ExpirationEstimator>,
+) -> anyhow::Result<Vec<FarmReport>> {
+    let mut reports = Vec::with_capacity(disk_farms.len());
     for (farm_index, disk_farm) in disk_farms.into_iter().enumerate() {
-        if farm_index > 0 {
-            println!();
+        reports.push(
+            collect_report(disk_farm, farm_index, expiration_estimator.as_deref_mut()).await?,
+        );
+    }
+
+    Ok(reports)
+}
+
+async fn collect_report(
+    directory: PathBuf,
+    farm_index: usize,
+    expiration_estimator: Option<&mut ExpirationEstimator>,
+) -> anyhow::Result<FarmReport> {
+    let mut report = FarmReport {
+        farm_index,
+        directory: directory.display().to_string(),
+        status: FarmStatus::NotFound,
+        errors: Vec::new(),
+        id: None,
+        genesis_hash: None,
+        public_key: None,
+        allocated_space: None,
+        piece_cache: None,
+        sectors: Vec::new(),
+    };
+
+    let info = match SingleDiskFarm::collect_summary(directory.clone()) {
+        SingleDiskFarmSummary::Found { info, .. } => info,
+        SingleDiskFarmSummary::NotFound { .. } => {
+            return Ok(report);
+        }
+        SingleDiskFarmSummary::Error { error, .. } => {
+            report.status = FarmStatus::Error;
+            report
+                .errors
+                .push(format!("Failed to open farm info: {error}"));
+            return Ok(report);
+        }
+    };
+
+    report.status = FarmStatus::Found;
+    report.id.replace(info.id().to_string());
+    report
+        .genesis_hash
+        .replace(format!("0x{}", hex::encode(info.genesis_hash())));
+    report
+        .public_key
+        .replace(format!("0x{}", hex::encode(info.public_key())));
+    report.allocated_space.replace(info.allocated_space());
+
+    match DiskPieceCache::occupancy(&directory) {
+        Ok(maybe_occupancy) => {
+            report.piece_cache = maybe_occupancy.map(|occupancy| PieceCacheReport {
+                capacity: occupancy.capacity,
+                used: occupancy.used,
+            });
+        }
+        Err(error) => {
+            report
+                .errors
+                .push(format!("Failed to read piece cache: {error}"));
+        }
+    }
+
+    let sectors_metadata = match SingleDiskFarm::read_all_sectors_metadata(&directory) {
+        Ok(sectors_metadata) => sectors_metadata,
+        Err(error) => {
+            report
+                .errors
+                .push(format!("Failed to read sectors metadata: {error}"));
+            return Ok(report);
+        }
+    };
+
+    report.sectors = sectors_metadata
+        .iter()
+        .map(|sector_metadata| SectorReport {
+            sector_index: sector_metadata.sector_index,
+            pieces_in_sector: sector_metadata.pieces_in_sector,
+            history_size: sector_metadata.history_size.get(),
+            expires_at: None,
+            expired: None,
+        })
+        .collect();
+
+    if let Some(expiration_estimator) = expiration_estimator {
+        expiration_estimator
+            .estimate(info.public_key(), &sectors_metadata, &mut report.sectors)
+            .await?;
+    }
+
+    Ok(report)
+}
+
+/// Print summary of sector expiration in addition to human-readable farm info
+fn print_expiration_summary(report: &FarmReport) {
+    if !matches!(report.status, FarmStatus::Found) {
+        return;
+    }
+
+    for error in &report.errors {
+        println!("  {error}");
+    }
+
+    let expired_sectors = report
+        .sectors
+        .iter()
+        .filter(|sector| sector.expired.unwrap_or_default())
+        .count();
+    println!("  Plotted sectors: {}", report.sectors.len());
+    println!("  Expired sectors: {expired_sectors}");
+}
+
+/// Print reports as CSV with one row per sector, farms without sectors are printed as a single
+/// row with empty sector columns
+fn print_csv(reports: &[FarmReport]) {
+    println!(
+        "farm_index,directory,status,id,genesis_hash,public_key,allocated_space,\
+        piece_cache_capacity,piece_cache_used,sector_index,pieces_in_sector,history_size,\
+        expires_at,expired"
+    );
+
+    for report in reports {
+        let farm_columns = [
+            report.farm_index.to_string(),
+            csv_escape(&report.directory),
+            match report.status {
+                FarmStatus::Found => "found",
+                FarmStatus::NotFound => "notFound",
+                FarmStatus::Error => "error",
+            }
+            .to_string(),
+            report.id.clone().unwrap_or_default(),
+            report.genesis_hash.clone().unwrap_or_default(),
+            report.public_key.clone().unwrap_or_default(),
+            optional_to_string(report.allocated_space),
+            optional_to_string(
+                report
+                    .piece_cache
+                    .as_ref()
+                    .map(|piece_cache| piece_cache.capacity),
+            ),
+            optional_to_string(
+                report
+                    .piece_cache
+                    .as_ref()
+                    .map(|piece_cache| piece_cache.used),
+            ),
+        ]
+        .join(",");
+
+        if report.sectors.is_empty() {
+            println!("{farm_columns},,,,,");
+            continue;
+        }
+
+        for sector in &report.sectors {
+            println!(
+                "{farm_columns},{},{},{},{},{}",
+                sector.sector_index,
+                sector.pieces_in_sector,
+                sector.history_size,
+                optional_to_string(sector.expires_at),
+                optional_to_string(sector.expired),
+            );
         }
+    }
+}
+
+fn optional_to_string<T>(value: Option<T>) -> String
+where
+    T: ToString,
+{
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
 
-        print_disk_farm_info(disk_farm, farm_index);
+fn csv_escape(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
     }
 }
@@ -0,0 +1,25 @@
+use std::path::Path;
+use subspace_farmer::single_disk_farm::SingleDiskFarm;
+use tracing::info;
+
+pub(crate) fn migrate(
+    from: &Path,
+    to: &Path,
+    allow_source_in_use: bool,
+    disable_farm_locking: bool,
+) -> anyhow::Result<()> {
+    info!(
+        from = %from.display(),
+        to = %to.display(),
+        "Start migrating farm"
+    );
+
+    SingleDiskFarm::migrate(from, to, allow_source_in_use, disable_farm_locking)?;
+
+    info!(
+        to = %to.display(),
+        "Farm migrated successfully, make sure to use new path when starting farmer"
+    );
+
+    Ok(())
+}
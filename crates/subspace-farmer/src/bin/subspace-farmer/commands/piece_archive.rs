@@ -0,0 +1,428 @@
+use crate::commands::shared::DiskCache;
+use anyhow::anyhow;
+use clap::{Parser, Subcommand, ValueHint};
+use futures::StreamExt;
+use std::collections::HashSet;
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, BufWriter};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use subspace_core_primitives::crypto::kzg::{embedded_kzg_settings, Kzg};
+use subspace_core_primitives::{Piece, PieceIndex, PieceOffset, Record, SectorId, SegmentIndex};
+use subspace_erasure_coding::ErasureCoding;
+use subspace_farmer::disk_piece_cache::DiskPieceCache;
+use subspace_farmer::farm::PieceCache;
+use subspace_farmer::node_client::rpc_node_client::RpcNodeClient;
+use subspace_farmer::node_client::NodeClient;
+use subspace_farmer::piece_archive::{
+    import_piece_archive, PieceArchiveHeader, PieceArchiveReader, PieceArchiveWriter,
+};
+use subspace_farmer::single_disk_farm::{SingleDiskFarm, SingleDiskFarmInfo};
+use subspace_farmer_components::reading::{read_piece, ReadSectorRecordChunksMode};
+use subspace_farmer_components::sector::sector_size;
+use subspace_farmer_components::{FarmerProtocolInfo, ReadAt, ReadAtSync};
+use subspace_proof_of_space::Table;
+use subspace_rpc_primitives::MAX_SEGMENT_HEADERS_PER_REQUEST;
+use tokio::task;
+use tracing::{debug, info, warn};
+
+/// Arguments for piece archive export
+#[derive(Debug, Parser)]
+pub(crate) struct ExportArgs {
+    /// One or more farms or piece caches located at specified path to export pieces from.
+    ///
+    /// Both piece cache and plot of the farm are used, directories that only contain piece cache
+    /// (like those used by `cluster cache` or `cache-server`) are supported as well. Farms and
+    /// caches should not be in use during export.
+    ///
+    /// Example:
+    ///   /path/to/directory
+    sources: Vec<PathBuf>,
+    /// Path to piece archive file that will be created
+    #[arg(long)]
+    output: PathBuf,
+    /// Index of the first segment to export
+    #[arg(long)]
+    from_segment: u64,
+    /// Index of the last segment to export (inclusive)
+    #[arg(long)]
+    to_segment: u64,
+    /// WebSocket RPC URL of the Subspace node, used to retrieve segment headers
+    #[arg(long, value_hint = ValueHint::Url, default_value = "ws://127.0.0.1:9944")]
+    node_rpc_url: String,
+}
+
+/// Arguments for piece archive import
+#[derive(Debug, Parser)]
+pub(crate) struct ImportArgs {
+    /// Path to piece archive file
+    archive: PathBuf,
+    /// Piece cache to seed, it must be empty.
+    ///
+    /// Format is the same as for `cache-server` command:
+    ///
+    ///   path=/path/to/directory,size=5T
+    #[arg(long)]
+    disk_cache: DiskCache,
+    /// WebSocket RPC URL of the Subspace node, used to verify segment headers in the archive
+    #[arg(long, value_hint = ValueHint::Url, default_value = "ws://127.0.0.1:9944")]
+    node_rpc_url: String,
+}
+
+/// Piece archive commands
+#[derive(Debug, Subcommand)]
+pub(crate) enum PieceArchiveArgs {
+    /// Export pieces of specified segments from farms and piece caches into piece archive
+    Export(ExportArgs),
+    /// Seed fresh piece cache with verified pieces from piece archive
+    Import(ImportArgs),
+}
+
+pub(crate) async fn piece_archive<PosTableLegacy, PosTable>(
+    piece_archive_args: PieceArchiveArgs,
+) -> anyhow::Result<()>
+where
+    PosTableLegacy: Table,
+    PosTable: Table,
+{
+    match piece_archive_args {
+        PieceArchiveArgs::Export(export_args) => {
+            export::<PosTableLegacy, PosTable>(export_args).await
+        }
+        PieceArchiveArgs::Import(import_args) => import(import_args).await,
+    }
+}
+
+/// Writes verified pieces into archive and keeps track of pieces that are not exported yet
+struct PieceExporter<'a> {
+    writer: PieceArchiveWriter<BufWriter<File>>,
+    header: &'a PieceArchiveHeader,
+    kzg: &'a Kzg,
+    remaining: HashSet<PieceIndex>,
+}
+
+impl PieceExporter<'_> {
+    fn export_piece(&mut self, piece_index: PieceIndex, piece: &Piece) -> anyhow::Result<()> {
+        if !self.header.is_piece_valid(self.kzg, piece_index, piece) {
+            warn!(%piece_index, "Skipping invalid piece");
+            return Ok(());
+        }
+
+        self.writer.write_piece(piece_index, piece)?;
+        self.remaining.remove(&piece_index);
+
+        Ok(())
+    }
+}
+
+async fn export<PosTableLegacy, PosTable>(export_args: ExportArgs) -> anyhow::Result<()>
+where
+    PosTableLegacy: Table,
+    PosTable: Table,
+{
+    let ExportArgs {
+        sources,
+        output,
+        from_segment,
+        to_segment,
+        node_rpc_url,
+    } = export_args;
+
+    if sources.is_empty() {
+        return Err(anyhow!("There must be at least one farm or cache provided"));
+    }
+    if from_segment > to_segment {
+        return Err(anyhow!(
+            "First segment {from_segment} must not be after last segment {to_segment}"
+        ));
+    }
+
+    let node_client = RpcNodeClient::new(&node_rpc_url)
+        .await
+        .map_err(|error| anyhow!("Failed to connect to node RPC: {error}"))?;
+    let farmer_app_info = node_client
+        .farmer_app_info()
+        .await
+        .map_err(|error| anyhow!("Failed to get farmer app info: {error}"))?;
+
+    let segment_indices = (from_segment..=to_segment)
+        .map(SegmentIndex::from)
+        .collect::<Vec<_>>();
+    let mut segment_headers = Vec::with_capacity(segment_indices.len());
+    for segment_indices in segment_indices.chunks(MAX_SEGMENT_HEADERS_PER_REQUEST) {
+        let maybe_segment_headers = node_client
+            .segment_headers(segment_indices.to_vec())
+            .await
+            .map_err(|error| anyhow!("Failed to get segment headers: {error}"))?;
+
+        for (segment_index, maybe_segment_header) in
+            segment_indices.iter().zip(maybe_segment_headers)
+        {
+            segment_headers.push(
+                maybe_segment_header
+                    .ok_or_else(|| anyhow!("Segment {segment_index} is not archived yet"))?,
+            );
+        }
+    }
+
+    let header = PieceArchiveHeader {
+        genesis_hash: farmer_app_info.genesis_hash,
+        segment_headers,
+    };
+    let kzg = Kzg::new(embedded_kzg_settings());
+    let erasure_coding = ErasureCoding::new(
+        NonZeroUsize::new(Record::NUM_S_BUCKETS.next_power_of_two().ilog2() as usize)
+            .expect("Not zero; qed"),
+    )
+    .map_err(|error| anyhow!("Failed to instantiate erasure coding: {error}"))?;
+
+    let file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&output)
+        .map_err(|error| anyhow!("Failed to create {}: {error}", output.display()))?;
+    let mut exporter = PieceExporter {
+        writer: PieceArchiveWriter::new(BufWriter::new(file), &header)?,
+        header: &header,
+        kzg: &kzg,
+        remaining: segment_indices
+            .iter()
+            .flat_map(SegmentIndex::segment_piece_indexes)
+            .collect(),
+    };
+    let total_pieces = exporter.remaining.len();
+
+    info!(
+        %from_segment,
+        %to_segment,
+        output = %output.display(),
+        "Exporting pieces from piece caches"
+    );
+    for directory in &sources {
+        export_from_piece_cache(directory, &mut exporter).await?;
+    }
+
+    for directory in &sources {
+        if exporter.remaining.is_empty() {
+            break;
+        }
+
+        let Some(single_disk_farm_info) = SingleDiskFarmInfo::load_from(directory)? else {
+            continue;
+        };
+        if single_disk_farm_info.genesis_hash() != &header.genesis_hash {
+            warn!(
+                directory = %directory.display(),
+                "Farm was created for a different chain, skipping"
+            );
+            continue;
+        }
+
+        info!(
+            directory = %directory.display(),
+            remaining = %exporter.remaining.len(),
+            "Exporting remaining pieces from plot"
+        );
+        match single_disk_farm_info {
+            SingleDiskFarmInfo::V0 { .. } => {
+                export_from_plot::<PosTableLegacy>(
+                    directory,
+                    &single_disk_farm_info,
+                    &farmer_app_info.protocol_info,
+                    &erasure_coding,
+                    &mut exporter,
+                )
+                .await?;
+            }
+            SingleDiskFarmInfo::V1 { .. } => {
+                export_from_plot::<PosTable>(
+                    directory,
+                    &single_disk_farm_info,
+                    &farmer_app_info.protocol_info,
+                    &erasure_coding,
+                    &mut exporter,
+                )
+                .await?;
+            }
+        }
+    }
+
+    let missing_pieces = exporter.remaining.len();
+    exporter.writer.finish()?;
+
+    if missing_pieces == 0 {
+        info!(%total_pieces, "All pieces exported successfully");
+    } else {
+        warn!(
+            exported_pieces = %(total_pieces - missing_pieces),
+            %missing_pieces,
+            "Some pieces were not found in provided farms and caches"
+        );
+    }
+
+    Ok(())
+}
+
+async fn export_from_piece_cache(
+    directory: &Path,
+    exporter: &mut PieceExporter<'_>,
+) -> anyhow::Result<()> {
+    // Capacity is derived from existing file, such that it is not resized when opened
+    let Some(occupancy) = DiskPieceCache::occupancy(directory)? else {
+        debug!(directory = %directory.display(), "No piece cache found");
+        return Ok(());
+    };
+    let piece_cache = DiskPieceCache::open(directory, occupancy.capacity, None, None)?;
+
+    let mut offsets = Vec::new();
+    {
+        let mut contents = piece_cache.contents().await?;
+        while let Some(result) = contents.next().await {
+            let (offset, maybe_piece_index) = result?;
+            if let Some(piece_index) = maybe_piece_index
+                && exporter.remaining.contains(&piece_index)
+            {
+                offsets.push(offset);
+            }
+        }
+    }
+
+    for offset in offsets {
+        if let Some((piece_index, piece)) = piece_cache.read_piece(offset).await? {
+            exporter.export_piece(piece_index, &piece)?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn export_from_plot<PosTable>(
+    directory: &Path,
+    single_disk_farm_info: &SingleDiskFarmInfo,
+    farmer_protocol_info: &FarmerProtocolInfo,
+    erasure_coding: &ErasureCoding,
+    exporter: &mut PieceExporter<'_>,
+) -> anyhow::Result<()>
+where
+    PosTable: Table,
+{
+    let sectors_metadata = SingleDiskFarm::read_all_sectors_metadata(directory)
+        .map_err(|error| anyhow!("Failed to read sectors metadata: {error}"))?;
+    let plot_file = OpenOptions::new()
+        .read(true)
+        .open(directory.join(SingleDiskFarm::PLOT_FILE))
+        .map_err(|error| anyhow!("Failed to open plot: {error}"))?;
+
+    let public_key_hash = single_disk_farm_info.public_key().hash();
+    let sector_size = sector_size(single_disk_farm_info.pieces_in_sector()) as u64;
+    let mut table_generator = PosTable::generator();
+
+    for sector_metadata in &sectors_metadata {
+        let sector_index = sector_metadata.sector_index;
+        let sector_id = SectorId::new(public_key_hash, sector_index);
+        let sector = plot_file.offset(u64::from(sector_index) * sector_size);
+
+        for piece_offset in
+            (PieceOffset::ZERO..).take(usize::from(sector_metadata.pieces_in_sector))
+        {
+            let piece_index = sector_id.derive_piece_index(
+                piece_offset,
+                sector_metadata.history_size,
+                farmer_protocol_info.max_pieces_in_sector,
+                farmer_protocol_info.recent_segments,
+                farmer_protocol_info.recent_history_fraction,
+            );
+            if !exporter.remaining.contains(&piece_index) {
+                continue;
+            }
+
+            match read_piece::<PosTable, _, _>(
+                piece_offset,
+                &sector_id,
+                sector_metadata,
+                &ReadAt::from_sync(&sector),
+                erasure_coding,
+                ReadSectorRecordChunksMode::ConcurrentChunks,
+                &mut table_generator,
+            )
+            .await
+            {
+                Ok(piece) => {
+                    exporter.export_piece(piece_index, &piece)?;
+                }
+                Err(error) => {
+                    warn!(
+                        %sector_index,
+                        %piece_offset,
+                        %piece_index,
+                        %error,
+                        "Failed to read piece from plot, skipping"
+                    );
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn import(import_args: ImportArgs) -> anyhow::Result<()> {
+    let ImportArgs {
+        archive,
+        disk_cache,
+        node_rpc_url,
+    } = import_args;
+
+    let file = File::open(&archive)
+        .map_err(|error| anyhow!("Failed to open {}: {error}", archive.display()))?;
+    let mut reader = PieceArchiveReader::new(BufReader::new(file))?;
+
+    let node_client = RpcNodeClient::new(&node_rpc_url)
+        .await
+        .map_err(|error| anyhow!("Failed to connect to node RPC: {error}"))?;
+    reader.header().verify_with_node(&node_client).await?;
+
+    if !disk_cache.directory.exists() {
+        if let Err(error) = fs::create_dir(&disk_cache.directory) {
+            return Err(anyhow!(
+                "Directory {} doesn't exist and can't be created: {}",
+                disk_cache.directory.display(),
+                error
+            ));
+        }
+    }
+    // Check before opening, opening cache with a different size would resize it
+    if let Some(occupancy) = DiskPieceCache::occupancy(&disk_cache.directory)?
+        && occupancy.used > 0
+    {
+        return Err(anyhow!(
+            "Piece cache at {} is not empty, only fresh piece cache can be seeded",
+            disk_cache.directory.display()
+        ));
+    }
+    let piece_cache = disk_cache.open(None)?;
+
+    info!(
+        archive = %archive.display(),
+        segments = %reader.header().segment_headers.len(),
+        "Importing pieces from piece archive"
+    );
+
+    let kzg = Kzg::new(embedded_kzg_settings());
+    let summary =
+        task::spawn_blocking(move || import_piece_archive(&mut reader, &kzg, &piece_cache))
+            .await??;
+
+    info!(
+        imported = %summary.imported,
+        invalid = %summary.invalid,
+        duplicate = %summary.duplicate,
+        "Piece archive import finished"
+    );
+    if summary.cache_full {
+        warn!("Piece cache is full, not all pieces from piece archive were imported");
+    }
+
+    Ok(())
+}
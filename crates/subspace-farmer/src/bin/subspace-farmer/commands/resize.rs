@@ -0,0 +1,56 @@
+use crate::commands::shared::DiskFarm;
+use rayon::prelude::*;
+use subspace_farmer::single_disk_farm::{SingleDiskFarm, SingleDiskFarmError};
+use tracing::{error, info, info_span};
+
+pub(crate) fn resize(disk_farms: &[DiskFarm], cache_percentage: u8, disable_farm_locking: bool) {
+    disk_farms
+        .into_par_iter()
+        .enumerate()
+        .for_each(|(farm_index, disk_farm)| {
+            let span = info_span!("", %farm_index);
+            let _span_guard = span.enter();
+            info!(
+                path = %disk_farm.directory.display(),
+                size = %bytesize::to_string(disk_farm.allocated_space, true),
+                "Start resizing farm"
+            );
+
+            match SingleDiskFarm::resize(
+                &disk_farm.directory,
+                disk_farm.allocated_space,
+                cache_percentage,
+                disable_farm_locking,
+            ) {
+                Ok(()) => {
+                    info!(
+                        path = %disk_farm.directory.display(),
+                        "Farm resized successfully"
+                    );
+                }
+                Err(SingleDiskFarmError::InsufficientAllocatedSpace {
+                    min_space,
+                    allocated_space,
+                }) => {
+                    error!(
+                        path = %disk_farm.directory.display(),
+                        "Allocated space {} ({}) is not enough, minimum is ~{} (~{}, {} bytes to be \
+                        exact)",
+                        bytesize::to_string(allocated_space, true),
+                        bytesize::to_string(allocated_space, false),
+                        bytesize::to_string(min_space, true),
+                        bytesize::to_string(min_space, false),
+                        min_space
+                    );
+                }
+                Err(error) => {
+                    error!(
+                        path = %disk_farm.directory.display(),
+                        %error,
+                        "Failed to resize farm, resizing will be finished next time farm is \
+                        resized or started"
+                    );
+                }
+            }
+        });
+}
@@ -8,6 +8,7 @@ pub(crate) fn scrub(
     disable_farm_locking: bool,
     target: ScrubTarget,
     dry_run: bool,
+    remote_signer: bool,
 ) {
     disk_farms
         .into_par_iter()
@@ -20,7 +21,13 @@ pub(crate) fn scrub(
                 "Start scrubbing farm"
             );
 
-            match SingleDiskFarm::scrub(directory, disable_farm_locking, target, dry_run) {
+            match SingleDiskFarm::scrub(
+                directory,
+                disable_farm_locking,
+                target,
+                dry_run,
+                remote_signer,
+            ) {
                 Ok(()) => {
                     info!(
                         path = %directory.display(),
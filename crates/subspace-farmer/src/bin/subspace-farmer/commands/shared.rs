@@ -1,11 +1,25 @@
+pub(super) mod control_api;
+pub(super) mod event_log;
+pub(super) mod events;
 pub(super) mod network;
 
+use anyhow::anyhow;
 use bytesize::ByteSize;
 use clap::Parser;
+use prometheus_client::registry::Registry;
 use std::fmt;
+use std::num::{NonZeroU32, NonZeroU8};
 use std::path::PathBuf;
 use std::str::FromStr;
+use std::sync::Arc;
+use subspace_core_primitives::PublicKey;
+use subspace_farmer::disk_piece_cache::DiskPieceCache;
+use subspace_farmer::farm::PieceCacheTier;
+use subspace_farmer::single_disk_farm::reward_address::{
+    RewardAddressPolicy, RewardAddressRotation, RotatingRewardAddresses,
+};
 use subspace_farmer::single_disk_farm::{SingleDiskFarm, SingleDiskFarmSummary};
+use subspace_farmer::utils::ss58::parse_ss58_reward_address;
 use subspace_farmer_components::reading::ReadSectorRecordChunksMode;
 use subspace_networking::libp2p::identity::{ed25519, Keypair};
 use thread_priority::ThreadPriority;
@@ -66,6 +80,53 @@ pub(in super::super) struct DiskFarm {
     pub(in super::super) allocated_space: u64,
     /// Which mode to use for reading of sector record chunks
     pub(in super::super) read_sector_record_chunks_mode: Option<ReadSectorRecordChunksMode>,
+    /// Weight of the farm when sharing plotting capacity with other farms
+    pub(in super::super) plotting_weight: NonZeroU32,
+    /// Reward addresses specific to this farm, global reward addresses are used if empty
+    pub(in super::super) reward_addresses: Vec<PublicKey>,
+    /// Rotation of reward addresses specific to this farm
+    pub(in super::super) reward_address_rotation: Option<RewardAddressRotation>,
+    /// Performance tier of the piece cache of this farm
+    pub(in super::super) cache_tier: PieceCacheTier,
+    /// Queue depth of io_uring used for reading from the plot, blocking reads are used if not set
+    #[cfg(target_os = "linux")]
+    pub(in super::super) io_uring_queue_depth: Option<NonZeroU32>,
+}
+
+impl DiskFarm {
+    /// Reward address policy of this farm, falls back to global reward addresses and rotation if
+    /// farm doesn't have its own.
+    ///
+    /// Multiple addresses are rotated after every solution unless rotation is specified
+    /// explicitly.
+    pub(in super::super) fn reward_address_policy(
+        &self,
+        global_reward_addresses: &[PublicKey],
+        global_reward_address_rotation: Option<RewardAddressRotation>,
+    ) -> anyhow::Result<Arc<dyn RewardAddressPolicy>> {
+        let reward_addresses = if self.reward_addresses.is_empty() {
+            global_reward_addresses
+        } else {
+            &self.reward_addresses
+        };
+        let rotation = self
+            .reward_address_rotation
+            .or(global_reward_address_rotation)
+            .unwrap_or(RewardAddressRotation::EverySolution);
+
+        match reward_addresses {
+            [] => Err(anyhow!(
+                "Farm {} doesn't have reward address, specify `--reward-address` or \
+                `reward-address` in farm options",
+                self.directory.display()
+            )),
+            [reward_address] => Ok(Arc::new(*reward_address)),
+            reward_addresses => Ok(Arc::new(
+                RotatingRewardAddresses::new(reward_addresses.to_vec(), rotation)
+                    .expect("Not empty, checked above; qed"),
+            )),
+        }
+    }
 }
 
 impl FromStr for DiskFarm {
@@ -81,6 +142,12 @@ impl FromStr for DiskFarm {
         let mut plot_directory = None;
         let mut allocated_space = None;
         let mut read_sector_record_chunks_mode = None;
+        let mut plotting_weight = None;
+        let mut reward_addresses = Vec::new();
+        let mut reward_address_rotation = None;
+        let mut cache_tier = None;
+        #[cfg(target_os = "linux")]
+        let mut io_uring_queue_depth = None;
 
         for part in parts {
             let part = part.splitn(2, '=').collect::<Vec<_>>();
@@ -114,10 +181,43 @@ impl FromStr for DiskFarm {
                             })?,
                     );
                 }
+                "plotting-weight" => {
+                    plotting_weight.replace(value.parse::<NonZeroU32>().map_err(|error| {
+                        format!("Failed to parse `plotting-weight` \"{value}\": {error}")
+                    })?);
+                }
+                "reward-address" => {
+                    reward_addresses.push(parse_ss58_reward_address(value).map_err(|error| {
+                        format!("Failed to parse `reward-address` \"{value}\": {error}")
+                    })?);
+                }
+                "reward-address-rotation" => {
+                    reward_address_rotation.replace(
+                        value.parse::<RewardAddressRotation>().map_err(|error| {
+                            format!(
+                                "Failed to parse `reward-address-rotation` \"{value}\": \
+                                    {error}"
+                            )
+                        })?,
+                    );
+                }
+                "cache-tier" => {
+                    cache_tier.replace(value.parse::<PieceCacheTier>().map_err(|error| {
+                        format!("Failed to parse `cache-tier` \"{value}\": {error}")
+                    })?);
+                }
+                #[cfg(target_os = "linux")]
+                "io-uring-queue-depth" => {
+                    io_uring_queue_depth.replace(value.parse::<NonZeroU32>().map_err(|error| {
+                        format!("Failed to parse `io-uring-queue-depth` \"{value}\": {error}")
+                    })?);
+                }
                 key => {
                     return Err(format!(
-                        "Key \"{key}\" is not supported, only `path`, `size` or \
-                        `record-chunks-mode` are allowed"
+                        "Key \"{key}\" is not supported, only `path`, `size`, \
+                        `record-chunks-mode`, `plotting-weight`, `reward-address`, \
+                        `reward-address-rotation`, `cache-tier` or `io-uring-queue-depth` (Linux \
+                        only) are allowed"
                     ));
                 }
             }
@@ -129,10 +229,122 @@ impl FromStr for DiskFarm {
             allocated_space: allocated_space
                 .ok_or("`size` key is required with allocated amount of disk space")?,
             read_sector_record_chunks_mode,
+            plotting_weight: plotting_weight.unwrap_or(NonZeroU32::MIN),
+            reward_addresses,
+            reward_address_rotation,
+            cache_tier: cache_tier.unwrap_or_default(),
+            #[cfg(target_os = "linux")]
+            io_uring_queue_depth,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(in super::super) struct DiskCache {
+    /// Path to directory where cache is stored
+    pub(in super::super) directory: PathBuf,
+    /// How much space in bytes can cache use
+    pub(in super::super) allocated_space: u64,
+    /// Performance tier of the cache
+    pub(in super::super) tier: PieceCacheTier,
+}
+
+impl DiskCache {
+    /// Open piece cache in this directory, creating it if necessary
+    pub(in super::super) fn open(
+        &self,
+        registry: Option<&mut Registry>,
+    ) -> anyhow::Result<DiskPieceCache> {
+        DiskPieceCache::open(
+            &self.directory,
+            u32::try_from(self.allocated_space / DiskPieceCache::element_size() as u64)
+                .unwrap_or(u32::MAX),
+            None,
+            registry,
+        )
+        .map(|piece_cache| piece_cache.with_tier(self.tier))
+        .map_err(|error| {
+            anyhow!(
+                "Failed to open piece cache at {}: {error}",
+                self.directory.display()
+            )
         })
     }
 }
 
+impl FromStr for DiskCache {
+    type Err = String;
+
+    #[inline]
+    fn from_str(s: &str) -> anyhow::Result<Self, Self::Err> {
+        let parts = s.split(',').collect::<Vec<_>>();
+        if parts.len() < 2 {
+            return Err("Must contain 2 or more coma-separated components".to_string());
+        }
+
+        let mut plot_directory = None;
+        let mut allocated_space = None;
+        let mut tier = None;
+
+        for part in parts {
+            let part = part.splitn(2, '=').collect::<Vec<_>>();
+            if part.len() != 2 {
+                return Err("Each component must contain = separating key from value".to_string());
+            }
+
+            let key = *part.first().expect("Length checked above; qed");
+            let value = *part.get(1).expect("Length checked above; qed");
+
+            match key {
+                "path" => {
+                    plot_directory.replace(PathBuf::from(value));
+                }
+                "size" => {
+                    allocated_space.replace(
+                        value
+                            .parse::<ByteSize>()
+                            .map_err(|error| {
+                                format!("Failed to parse `size` \"{value}\": {error}")
+                            })?
+                            .as_u64(),
+                    );
+                }
+                "tier" => {
+                    tier.replace(
+                        value.parse::<PieceCacheTier>().map_err(|error| {
+                            format!("Failed to parse `tier` \"{value}\": {error}")
+                        })?,
+                    );
+                }
+                key => {
+                    return Err(format!(
+                        "Key \"{key}\" is not supported, only `path`, `size` or `tier`"
+                    ));
+                }
+            }
+        }
+
+        Ok(DiskCache {
+            directory: plot_directory.ok_or(
+                "`path` key is required with path to directory where cache will be stored",
+            )?,
+            allocated_space: allocated_space
+                .ok_or("`size` key is required with allocated amount of disk space")?,
+            tier: tier.unwrap_or_default(),
+        })
+    }
+}
+
+pub(in super::super) fn cache_percentage_parser(s: &str) -> anyhow::Result<NonZeroU8> {
+    let cache_percentage = NonZeroU8::from_str(s)?;
+
+    if cache_percentage.get() > 99 {
+        return Err(anyhow!("Cache percentage can't exceed 99"));
+    }
+
+    Ok(cache_percentage)
+}
+
 pub(in super::super) fn derive_libp2p_keypair(schnorrkel_sk: &schnorrkel::SecretKey) -> Keypair {
     let mut secret_bytes = Zeroizing::new(schnorrkel_sk.to_ed25519_bytes());
 
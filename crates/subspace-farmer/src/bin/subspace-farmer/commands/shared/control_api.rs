@@ -0,0 +1,382 @@
+use crate::commands::shared::events::{FarmingNotificationEvent, SectorUpdateEvent};
+use futures::{select, FutureExt};
+use jsonrpsee::core::{async_trait, RpcResult};
+use jsonrpsee::proc_macros::rpc;
+use jsonrpsee::server::{Server, ServerHandle};
+use jsonrpsee::types::error::{INTERNAL_ERROR_CODE, INVALID_PARAMS_CODE};
+use jsonrpsee::types::ErrorObject;
+use jsonrpsee::{PendingSubscriptionSink, SubscriptionMessage};
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use subspace_core_primitives::SectorIndex;
+use subspace_farmer::farm::{Farm, FarmId, PlottedSectors};
+use subspace_farmer::plotter::scheduler::PlottingScheduler;
+use subspace_farmer::single_disk_farm::{PlottingPauseHandle, ScrubTarget, SingleDiskFarm};
+use tokio::sync::broadcast;
+use tracing::{debug, error, info, info_span, warn};
+
+/// Capacity of event channels, subscribers that fall behind more than this will miss some events
+const EVENTS_CHANNEL_CAPACITY: usize = 1000;
+
+/// Farm as exposed by control API
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FarmDetails {
+    farm_index: u8,
+    id: String,
+    directory: String,
+    total_sectors_count: SectorIndex,
+    plotting_paused: bool,
+    scrubbing: bool,
+}
+
+/// Farm that is controlled by control API
+pub(in super::super) struct ControlApiFarm {
+    pub(in super::super) id: FarmId,
+    pub(in super::super) directory: PathBuf,
+    pub(in super::super) total_sectors_count: SectorIndex,
+    pub(in super::super) plotted_sectors: Arc<dyn PlottedSectors>,
+    pub(in super::super) plotting_pause_handle: PlottingPauseHandle,
+    /// Farm identity is held by remote signer
+    pub(in super::super) remote_signer: bool,
+}
+
+struct ControlledFarm {
+    farm: ControlApiFarm,
+    scrubbing: Arc<AtomicBool>,
+}
+
+/// Local control API of the farmer, exposed over both HTTP and WebSocket
+#[rpc(server)]
+trait ControlApi {
+    /// List of farms with their current state
+    #[method(name = "farmer_farms")]
+    fn farms(&self) -> RpcResult<Vec<FarmDetails>>;
+
+    /// Number of sectors plotted in a farm so far
+    #[method(name = "farmer_plottedSectorsCount")]
+    async fn plotted_sectors_count(&self, farm_index: u8) -> RpcResult<SectorIndex>;
+
+    /// Pause plotting and replotting of a farm, sectors that are already being plotted will
+    /// finish and farming is not affected
+    #[method(name = "farmer_pausePlotting")]
+    fn pause_plotting(&self, farm_index: u8) -> RpcResult<()>;
+
+    /// Resume previously paused plotting of a farm
+    #[method(name = "farmer_resumePlotting")]
+    fn resume_plotting(&self, farm_index: u8) -> RpcResult<()>;
+
+    /// Pause plotting and replotting of all farms at once, this is independent of pausing of
+    /// individual farms
+    #[method(name = "farmer_pauseAllPlotting")]
+    fn pause_all_plotting(&self) -> RpcResult<()>;
+
+    /// Resume previously paused plotting of all farms
+    #[method(name = "farmer_resumeAllPlotting")]
+    fn resume_all_plotting(&self) -> RpcResult<()>;
+
+    /// Whether plotting of all farms is paused
+    #[method(name = "farmer_allPlottingPaused")]
+    fn all_plotting_paused(&self) -> RpcResult<bool>;
+
+    /// Start checking farm for corruption in the background, results are printed in logs.
+    ///
+    /// Farm is in use, so errors are only reported and not corrected, stop the farmer and use
+    /// `scrub` command to correct them. Target is one of `all`, `metadata`, `plot` or `cache`.
+    #[method(name = "farmer_scrub")]
+    fn scrub(&self, farm_index: u8, target: Option<String>) -> RpcResult<()>;
+
+    /// Progress of farmer cache synchronization in %
+    #[method(name = "farmer_cacheSyncProgress")]
+    fn cache_sync_progress(&self) -> RpcResult<f32>;
+
+    /// Sector updates of all farms
+    #[subscription(
+        name = "farmer_subscribeSectorUpdates" => "farmer_sector_update",
+        unsubscribe = "farmer_unsubscribeSectorUpdates",
+        item = SectorUpdateEvent,
+    )]
+    fn subscribe_sector_updates(&self);
+
+    /// Farming notifications of all farms
+    #[subscription(
+        name = "farmer_subscribeFarmingNotifications" => "farmer_farming_notification",
+        unsubscribe = "farmer_unsubscribeFarmingNotifications",
+        item = FarmingNotificationEvent,
+    )]
+    fn subscribe_farming_notifications(&self);
+}
+
+/// Control API implementation, events are fed into it by subscribing to farms before the server
+/// is started
+pub(in super::super) struct ControlApi {
+    plotting_scheduler: PlottingScheduler,
+    farms: Vec<ControlledFarm>,
+    sector_update_sender: broadcast::Sender<SectorUpdateEvent>,
+    farming_notification_sender: broadcast::Sender<FarmingNotificationEvent>,
+    /// Only present when farmer cache is part of this process
+    cache_sync_progress: Option<Arc<Mutex<f32>>>,
+}
+
+impl ControlApi {
+    pub(in super::super) fn new<I>(plotting_scheduler: PlottingScheduler, farms: I) -> Self
+    where
+        I: IntoIterator<Item = ControlApiFarm>,
+    {
+        Self {
+            plotting_scheduler,
+            farms: farms
+                .into_iter()
+                .map(|farm| ControlledFarm {
+                    farm,
+                    scrubbing: Arc::default(),
+                })
+                .collect(),
+            sector_update_sender: broadcast::channel(EVENTS_CHANNEL_CAPACITY).0,
+            farming_notification_sender: broadcast::channel(EVENTS_CHANNEL_CAPACITY).0,
+            cache_sync_progress: None,
+        }
+    }
+
+    /// Subscribe to sector updates and farming notifications of a farm
+    pub(in super::super) fn subscribe<F>(&self, farm_index: u8, farm: &F)
+    where
+        F: Farm + ?Sized,
+    {
+        farm.on_sector_update(Arc::new({
+            let sector_update_sender = self.sector_update_sender.clone();
+
+            move |(sector_index, sector_update)| {
+                // Avoid conversion if nobody is subscribed
+                if sector_update_sender.receiver_count() > 0 {
+                    // Doesn't matter if all receivers are gone in the meantime
+                    let _ = sector_update_sender.send(SectorUpdateEvent::new(
+                        farm_index,
+                        *sector_index,
+                        sector_update,
+                    ));
+                }
+            }
+        }))
+        .detach();
+
+        farm.on_farming_notification(Arc::new({
+            let farming_notification_sender = self.farming_notification_sender.clone();
+
+            move |farming_notification| {
+                // Avoid conversion if nobody is subscribed
+                if farming_notification_sender.receiver_count() > 0 {
+                    // Doesn't matter if all receivers are gone in the meantime
+                    let _ = farming_notification_sender.send(FarmingNotificationEvent::new(
+                        farm_index,
+                        farming_notification,
+                    ));
+                }
+            }
+        }))
+        .detach();
+    }
+
+    /// Shared storage for farmer cache sync progress, must be called for cache sync progress to
+    /// be available through API
+    pub(in super::super) fn cache_sync_progress(&mut self) -> Arc<Mutex<f32>> {
+        Arc::clone(self.cache_sync_progress.get_or_insert_with(Arc::default))
+    }
+
+    /// Start control API server on specified address
+    pub(in super::super) async fn start(
+        self,
+        listen_on: SocketAddr,
+    ) -> anyhow::Result<ServerHandle> {
+        let server = Server::builder().build(listen_on).await?;
+        info!(address = %server.local_addr()?, "Started control API server");
+
+        Ok(server.start(self.into_rpc()))
+    }
+
+    fn farm(&self, farm_index: u8) -> RpcResult<&ControlledFarm> {
+        self.farms.get(usize::from(farm_index)).ok_or_else(|| {
+            ErrorObject::owned(
+                INVALID_PARAMS_CODE,
+                format!("Farm {farm_index} doesn't exist"),
+                None::<()>,
+            )
+        })
+    }
+}
+
+#[async_trait]
+impl ControlApiServer for ControlApi {
+    fn farms(&self) -> RpcResult<Vec<FarmDetails>> {
+        Ok((0u8..)
+            .zip(self.farms.iter())
+            .map(|(farm_index, controlled_farm)| FarmDetails {
+                farm_index,
+                id: controlled_farm.farm.id.to_string(),
+                directory: controlled_farm.farm.directory.display().to_string(),
+                total_sectors_count: controlled_farm.farm.total_sectors_count,
+                plotting_paused: controlled_farm.farm.plotting_pause_handle.is_paused(),
+                scrubbing: controlled_farm.scrubbing.load(Ordering::Acquire),
+            })
+            .collect())
+    }
+
+    async fn plotted_sectors_count(&self, farm_index: u8) -> RpcResult<SectorIndex> {
+        self.farm(farm_index)?
+            .farm
+            .plotted_sectors
+            .count()
+            .await
+            .map_err(|error| {
+                ErrorObject::owned(
+                    INTERNAL_ERROR_CODE,
+                    format!("Failed to get plotted sectors count: {error}"),
+                    None::<()>,
+                )
+            })
+    }
+
+    fn pause_plotting(&self, farm_index: u8) -> RpcResult<()> {
+        self.farm(farm_index)?.farm.plotting_pause_handle.pause();
+        info!(%farm_index, "Plotting paused through control API");
+
+        Ok(())
+    }
+
+    fn resume_plotting(&self, farm_index: u8) -> RpcResult<()> {
+        self.farm(farm_index)?.farm.plotting_pause_handle.resume();
+        info!(%farm_index, "Plotting resumed through control API");
+
+        Ok(())
+    }
+
+    fn pause_all_plotting(&self) -> RpcResult<()> {
+        self.plotting_scheduler.pause();
+        info!("Plotting of all farms paused through control API");
+
+        Ok(())
+    }
+
+    fn resume_all_plotting(&self) -> RpcResult<()> {
+        self.plotting_scheduler.resume();
+        info!("Plotting of all farms resumed through control API");
+
+        Ok(())
+    }
+
+    fn all_plotting_paused(&self) -> RpcResult<bool> {
+        Ok(self.plotting_scheduler.is_paused())
+    }
+
+    fn scrub(&self, farm_index: u8, target: Option<String>) -> RpcResult<()> {
+        let controlled_farm = self.farm(farm_index)?;
+        let target = match target {
+            Some(target) => target
+                .parse::<ScrubTarget>()
+                .map_err(|error| ErrorObject::owned(INVALID_PARAMS_CODE, error, None::<()>))?,
+            None => ScrubTarget::All,
+        };
+
+        if controlled_farm.scrubbing.swap(true, Ordering::AcqRel) {
+            return Err(ErrorObject::owned(
+                INVALID_PARAMS_CODE,
+                format!("Farm {farm_index} is already being scrubbed"),
+                None::<()>,
+            ));
+        }
+
+        let directory = controlled_farm.farm.directory.clone();
+        let remote_signer = controlled_farm.farm.remote_signer;
+        let scrubbing = Arc::clone(&controlled_farm.scrubbing);
+        let span = info_span!("", %farm_index);
+
+        tokio::task::spawn_blocking(move || {
+            let _span_guard = span.enter();
+            info!(path = %directory.display(), %target, "Start scrubbing farm");
+
+            // Farm is locked by this farmer, hence locking is disabled, while dry run makes sure
+            // nothing is modified while farm is in use
+            match SingleDiskFarm::scrub(&directory, true, target, true, remote_signer) {
+                Ok(()) => {
+                    info!(path = %directory.display(), "Farm checked successfully");
+                }
+                Err(error) => {
+                    error!(path = %directory.display(), %error, "Failed to scrub farm");
+                }
+            }
+
+            scrubbing.store(false, Ordering::Release);
+        });
+
+        Ok(())
+    }
+
+    fn cache_sync_progress(&self) -> RpcResult<f32> {
+        match &self.cache_sync_progress {
+            Some(cache_sync_progress) => Ok(*cache_sync_progress.lock()),
+            None => Err(ErrorObject::owned(
+                INVALID_PARAMS_CODE,
+                "Farmer cache is not managed by this farmer",
+                None::<()>,
+            )),
+        }
+    }
+
+    fn subscribe_sector_updates(&self, pending: PendingSubscriptionSink) {
+        tokio::spawn(forward_events(
+            pending,
+            self.sector_update_sender.subscribe(),
+        ));
+    }
+
+    fn subscribe_farming_notifications(&self, pending: PendingSubscriptionSink) {
+        tokio::spawn(forward_events(
+            pending,
+            self.farming_notification_sender.subscribe(),
+        ));
+    }
+}
+
+/// Forward events from broadcast channel to subscriber until either of them is closed
+async fn forward_events<T>(pending: PendingSubscriptionSink, mut receiver: broadcast::Receiver<T>)
+where
+    T: Serialize + Clone,
+{
+    let Ok(sink) = pending.accept().await else {
+        return;
+    };
+
+    loop {
+        let event = select! {
+            result = receiver.recv().fuse() => match result {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    debug!(%skipped, "Control API subscriber is too slow, skipped some events");
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => {
+                    break;
+                }
+            },
+            _ = sink.closed().fuse() => {
+                break;
+            }
+        };
+
+        let message = match SubscriptionMessage::from_json(&event) {
+            Ok(message) => message,
+            Err(error) => {
+                warn!(%error, "Failed to serialize control API event");
+                continue;
+            }
+        };
+
+        if sink.send(message).await.is_err() {
+            break;
+        }
+    }
+}
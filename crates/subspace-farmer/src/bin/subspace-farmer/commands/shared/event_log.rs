@@ -0,0 +1,435 @@
+#[cfg(test)]
+mod tests;
+
+use crate::commands::shared::events::{FarmingNotificationEvent, SectorUpdateEvent, SolutionEvent};
+use anyhow::anyhow;
+use bytesize::ByteSize;
+use clap::Parser;
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::Arc;
+#[cfg(unix)]
+use std::time::Instant;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::{fs, io, thread};
+use subspace_farmer::farm::{Farm, FarmId};
+use tracing::{debug, warn};
+
+/// Number of events that can be buffered before they start being dropped
+const EVENT_LOG_BUFFER: usize = 10_000;
+/// How often to flush buffered writes if there are no new events
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+/// Delay before reconnecting to Unix socket after failure, events are dropped in the meantime
+#[cfg(unix)]
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Arguments for structured event log
+#[derive(Debug, Parser)]
+pub(in super::super) struct EventLogArgs {
+    /// Write every sector update, farming notification and solution of every farm as
+    /// newline-delimited JSON for later analysis (for example of missed proofs).
+    ///
+    /// Format: `file:/path/to/events.ndjson` to write into a file that is rotated according to
+    /// `--event-log-max-file-size` and `--event-log-max-files` or `unix:/path/to/socket` to stream
+    /// events into Unix socket of a log collector that is listening on it.
+    #[arg(long)]
+    event_log: Option<EventLogDestination>,
+    /// Max size of event log file before it is rotated
+    #[arg(long, default_value = "100MiB")]
+    event_log_max_file_size: ByteSize,
+    /// Number of rotated event log files to keep in addition to the current one
+    #[arg(long, default_value_t = 5)]
+    event_log_max_files: usize,
+}
+
+impl EventLogArgs {
+    /// Start event log if it was requested
+    pub(in super::super) fn start(self) -> anyhow::Result<Option<EventLog>> {
+        let Self {
+            event_log,
+            event_log_max_file_size,
+            event_log_max_files,
+        } = self;
+
+        let Some(destination) = event_log else {
+            return Ok(None);
+        };
+
+        let writer = match destination {
+            EventLogDestination::File(path) => EventLogWriter::File(
+                RotatingFile::open(path, event_log_max_file_size.as_u64(), event_log_max_files)
+                    .map_err(|error| anyhow!("Failed to open event log file: {error}"))?,
+            ),
+            #[cfg(unix)]
+            EventLogDestination::UnixSocket(path) => EventLogWriter::UnixSocket {
+                path,
+                stream: None,
+                last_connection_attempt: None,
+            },
+        };
+
+        EventLog::new(writer).map(Some)
+    }
+}
+
+/// Where event log is written to
+#[derive(Debug, Clone)]
+pub(in super::super) enum EventLogDestination {
+    /// Rotating file
+    File(PathBuf),
+    /// Unix socket
+    #[cfg(unix)]
+    UnixSocket(PathBuf),
+}
+
+impl FromStr for EventLogDestination {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(path) = s.strip_prefix("file:") {
+            return Ok(Self::File(PathBuf::from(path)));
+        }
+
+        if let Some(path) = s.strip_prefix("unix:") {
+            #[cfg(unix)]
+            return Ok(Self::UnixSocket(PathBuf::from(path)));
+            #[cfg(not(unix))]
+            return Err(format!(
+                "Unix socket \"{path}\" is only supported on Unix-like operating systems"
+            ));
+        }
+
+        Err(format!(
+            "Unsupported event log destination \"{s}\", only `file:<path>` or `unix:<path>` are \
+            allowed"
+        ))
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+enum EventKind {
+    Sector,
+    Farming,
+    Solution,
+    Dropped,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct EventLogEntry<'a, T> {
+    /// Milliseconds since Unix epoch
+    timestamp: u64,
+    kind: EventKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    farm_id: Option<&'a FarmId>,
+    #[serde(flatten)]
+    event: T,
+}
+
+#[derive(Debug, Serialize)]
+struct DroppedEvents {
+    count: u64,
+}
+
+fn timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// Structured event log that writes events from farms as newline-delimited JSON.
+///
+/// Serialization happens in event handlers, while writing is done in a dedicated thread, so slow
+/// destination never blocks farming. If destination can't keep up, events are dropped and number
+/// of dropped events is written into the log once it catches up.
+#[derive(Debug, Clone)]
+pub(in super::super) struct EventLog {
+    sender: SyncSender<Vec<u8>>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl EventLog {
+    fn new(writer: EventLogWriter) -> anyhow::Result<Self> {
+        let (sender, receiver) = sync_channel(EVENT_LOG_BUFFER);
+        let dropped = Arc::new(AtomicU64::new(0));
+
+        thread::Builder::new()
+            .name("event-log".to_string())
+            .spawn({
+                let dropped = Arc::clone(&dropped);
+
+                move || run_writer(writer, receiver, &dropped)
+            })
+            .map_err(|error| anyhow!("Failed to spawn event log thread: {error}"))?;
+
+        Ok(Self { sender, dropped })
+    }
+
+    /// Subscribe to events of the farm, handlers stay subscribed for the lifetime of the farm
+    pub(in super::super) fn subscribe<F>(&self, farm_index: u8, farm: &F)
+    where
+        F: Farm + ?Sized,
+    {
+        let farm_id = *farm.id();
+
+        farm.on_sector_update(Arc::new({
+            let event_log = self.clone();
+
+            move |(sector_index, sector_update)| {
+                event_log.write(
+                    EventKind::Sector,
+                    Some(&farm_id),
+                    SectorUpdateEvent::new(farm_index, *sector_index, sector_update),
+                );
+            }
+        }))
+        .detach();
+
+        farm.on_farming_notification(Arc::new({
+            let event_log = self.clone();
+
+            move |farming_notification| {
+                event_log.write(
+                    EventKind::Farming,
+                    Some(&farm_id),
+                    FarmingNotificationEvent::new(farm_index, farming_notification),
+                );
+            }
+        }))
+        .detach();
+
+        farm.on_solution(Arc::new({
+            let event_log = self.clone();
+
+            move |solution_response| {
+                event_log.write(
+                    EventKind::Solution,
+                    Some(&farm_id),
+                    SolutionEvent::new(farm_index, solution_response),
+                );
+            }
+        }))
+        .detach();
+    }
+
+    fn write<T>(&self, kind: EventKind, farm_id: Option<&FarmId>, event: T)
+    where
+        T: Serialize,
+    {
+        let entry = EventLogEntry {
+            timestamp: timestamp(),
+            kind,
+            farm_id,
+            event,
+        };
+        let mut line = match serde_json::to_vec(&entry) {
+            Ok(line) => line,
+            Err(error) => {
+                warn!(%error, "Failed to serialize event log entry");
+                return;
+            }
+        };
+        line.push(b'\n');
+
+        match self.sender.try_send(line) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_line)) => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(TrySendError::Disconnected(_line)) => {
+                // Writer thread exited, error was already logged there
+            }
+        }
+    }
+}
+
+fn run_writer(mut writer: EventLogWriter, receiver: Receiver<Vec<u8>>, dropped: &AtomicU64) {
+    loop {
+        let line = match receiver.recv_timeout(FLUSH_INTERVAL) {
+            Ok(line) => line,
+            Err(RecvTimeoutError::Timeout) => {
+                if let Err(error) = writer.flush() {
+                    debug!(%error, "Failed to flush event log");
+                }
+                continue;
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                break;
+            }
+        };
+
+        let dropped_count = dropped.swap(0, Ordering::Relaxed);
+        if dropped_count > 0 {
+            warn!(%dropped_count, "Event log can't keep up, some events were dropped");
+
+            let entry = EventLogEntry {
+                timestamp: timestamp(),
+                kind: EventKind::Dropped,
+                farm_id: None,
+                event: DroppedEvents {
+                    count: dropped_count,
+                },
+            };
+            if let Ok(mut dropped_line) = serde_json::to_vec(&entry) {
+                dropped_line.push(b'\n');
+                if let Err(error) = writer.write_line(&dropped_line) {
+                    warn!(%error, "Failed to write event log");
+                }
+            }
+        }
+
+        if let Err(error) = writer.write_line(&line) {
+            warn!(%error, "Failed to write event log");
+        }
+    }
+
+    let _ = writer.flush();
+}
+
+enum EventLogWriter {
+    File(RotatingFile),
+    #[cfg(unix)]
+    UnixSocket {
+        path: PathBuf,
+        stream: Option<BufWriter<UnixStream>>,
+        last_connection_attempt: Option<Instant>,
+    },
+}
+
+impl EventLogWriter {
+    fn write_line(&mut self, line: &[u8]) -> io::Result<()> {
+        match self {
+            Self::File(file) => file.write_line(line),
+            #[cfg(unix)]
+            Self::UnixSocket {
+                path,
+                stream,
+                last_connection_attempt,
+            } => {
+                if stream.is_none() {
+                    // Avoid reconnecting on every event while collector is down
+                    if last_connection_attempt
+                        .is_some_and(|instant| instant.elapsed() < RECONNECT_INTERVAL)
+                    {
+                        return Ok(());
+                    }
+                    last_connection_attempt.replace(Instant::now());
+
+                    stream.replace(BufWriter::new(UnixStream::connect(path.as_path())?));
+                    debug!(path = %path.display(), "Connected to event log socket");
+                }
+
+                let result = stream
+                    .as_mut()
+                    .expect("Stream was just set; qed")
+                    .write_all(line);
+                if result.is_err() {
+                    // Connection is in unknown state after error
+                    stream.take();
+                }
+                result
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::File(file) => file.file.flush(),
+            #[cfg(unix)]
+            Self::UnixSocket { stream, .. } => {
+                let Some(writer) = stream.as_mut() else {
+                    return Ok(());
+                };
+
+                let result = writer.flush();
+                if result.is_err() {
+                    stream.take();
+                }
+                result
+            }
+        }
+    }
+}
+
+/// File that is rotated once it reaches max size, rotated files get `.1`, `.2`, etc. suffix with
+/// `.1` being the most recent one
+struct RotatingFile {
+    path: PathBuf,
+    file: BufWriter<File>,
+    size: u64,
+    max_size: u64,
+    max_files: usize,
+}
+
+impl RotatingFile {
+    fn open(path: PathBuf, max_size: u64, max_files: usize) -> io::Result<Self> {
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            fs::create_dir_all(parent)?;
+        }
+
+        let file = Self::open_file(&path)?;
+        let size = file.metadata()?.len();
+
+        Ok(Self {
+            path,
+            file: BufWriter::new(file),
+            size,
+            max_size,
+            max_files,
+        })
+    }
+
+    fn open_file(path: &Path) -> io::Result<File> {
+        OpenOptions::new().create(true).append(true).open(path)
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{index}"));
+        PathBuf::from(path)
+    }
+
+    fn write_line(&mut self, line: &[u8]) -> io::Result<()> {
+        if self.size > 0 && self.size + line.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+
+        self.file.write_all(line)?;
+        self.size += line.len() as u64;
+
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+
+        if self.max_files == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            // Oldest file is overwritten by the next one
+            for index in (1..self.max_files).rev() {
+                let from = self.rotated_path(index);
+                if from.exists() {
+                    fs::rename(from, self.rotated_path(index + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated_path(1))?;
+        }
+
+        self.file = BufWriter::new(Self::open_file(&self.path)?);
+        self.size = 0;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,85 @@
+use crate::commands::shared::event_log::{EventKind, EventLog, EventLogWriter, RotatingFile};
+use crate::commands::shared::events::SectorUpdateEvent;
+use serde_json::json;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use std::{fs, thread};
+use subspace_core_primitives::SegmentIndex;
+use subspace_farmer::farm::{FarmId, SectorExpirationDetails, SectorUpdate};
+use tempfile::tempdir;
+
+/// Wait for writer thread to write specified number of complete lines
+fn read_lines(path: &Path, count: usize) -> String {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        let contents = fs::read_to_string(path).unwrap_or_default();
+        if contents.matches('\n').count() >= count || Instant::now() > deadline {
+            return contents;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn ndjson_line_format() {
+    let directory = tempdir().unwrap();
+    let path = directory.path().join("events.ndjson");
+    let event_log = EventLog::new(EventLogWriter::File(
+        RotatingFile::open(path.clone(), u64::MAX, 0).unwrap(),
+    ))
+    .unwrap();
+    let farm_id = FarmId::new();
+
+    event_log.write(
+        EventKind::Sector,
+        Some(&farm_id),
+        SectorUpdateEvent::new(
+            1,
+            2,
+            &SectorUpdate::Expiration(SectorExpirationDetails::Determined {
+                expires_at: SegmentIndex::new(3),
+            }),
+        ),
+    );
+    event_log.write(
+        EventKind::Sector,
+        Some(&farm_id),
+        SectorUpdateEvent::new(
+            1,
+            4,
+            &SectorUpdate::Expiration(SectorExpirationDetails::Expired),
+        ),
+    );
+    // Writer thread flushes and exits once the last sender is dropped
+    drop(event_log);
+
+    let contents = read_lines(&path, 2);
+    assert!(contents.ends_with('\n'));
+    let lines = contents.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2);
+
+    let expected_lines = [
+        json!({
+            "kind": "sector",
+            "farmId": farm_id,
+            "farmIndex": 1,
+            "sectorIndex": 2,
+            "event": "expirationDetermined",
+            "expiresAt": 3,
+        }),
+        json!({
+            "kind": "sector",
+            "farmId": farm_id,
+            "farmIndex": 1,
+            "sectorIndex": 4,
+            "event": "expired",
+        }),
+    ];
+    for (line, expected_line) in lines.into_iter().zip(expected_lines) {
+        let mut line = serde_json::from_str::<serde_json::Value>(line).unwrap();
+        let timestamp = line.as_object_mut().unwrap().remove("timestamp").unwrap();
+        assert!(timestamp.as_u64().unwrap() > 0);
+        assert_eq!(line, expected_line);
+    }
+}
@@ -0,0 +1,215 @@
+use serde::Serialize;
+use subspace_core_primitives::{SectorIndex, SlotNumber};
+use subspace_farmer::farm::{
+    FarmingNotification, ProvingResult, SectorExpirationDetails, SectorPlottingDetails,
+    SectorUpdate,
+};
+use subspace_rpc_primitives::SolutionResponse;
+
+/// Sector update of a specific farm
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(in super::super) struct SectorUpdateEvent {
+    pub(in super::super) farm_index: u8,
+    pub(in super::super) sector_index: SectorIndex,
+    #[serde(flatten)]
+    pub(in super::super) details: SectorUpdateEventDetails,
+}
+
+impl SectorUpdateEvent {
+    pub(in super::super) fn new(
+        farm_index: u8,
+        sector_index: SectorIndex,
+        sector_update: &SectorUpdate,
+    ) -> Self {
+        Self {
+            farm_index,
+            sector_index,
+            details: SectorUpdateEventDetails::from(sector_update),
+        }
+    }
+}
+
+/// Sector update details, durations are in seconds
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "camelCase")]
+pub(in super::super) enum SectorUpdateEventDetails {
+    #[serde(rename_all = "camelCase")]
+    PlottingStarting {
+        progress: f32,
+        replotting: bool,
+        last_queued: bool,
+    },
+    PlottingDownloading,
+    PlottingDownloaded {
+        time: f64,
+    },
+    PlottingEncoding,
+    PlottingEncoded {
+        time: f64,
+    },
+    PlottingWriting,
+    PlottingWritten {
+        time: f64,
+    },
+    #[serde(rename_all = "camelCase")]
+    PlottingFinished {
+        pieces: usize,
+        replotted: bool,
+        time: f64,
+    },
+    PlottingError {
+        error: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    ExpirationDetermined {
+        expires_at: u64,
+    },
+    AboutToExpire,
+    Expired,
+    Corrupted {
+        corruption: String,
+    },
+}
+
+impl From<&SectorUpdate> for SectorUpdateEventDetails {
+    fn from(sector_update: &SectorUpdate) -> Self {
+        match sector_update {
+            SectorUpdate::Plotting(plotting_details) => match plotting_details {
+                SectorPlottingDetails::Starting {
+                    progress,
+                    replotting,
+                    last_queued,
+                } => Self::PlottingStarting {
+                    progress: *progress,
+                    replotting: *replotting,
+                    last_queued: *last_queued,
+                },
+                SectorPlottingDetails::Downloading => Self::PlottingDownloading,
+                SectorPlottingDetails::Downloaded(time) => Self::PlottingDownloaded {
+                    time: time.as_secs_f64(),
+                },
+                SectorPlottingDetails::Encoding => Self::PlottingEncoding,
+                SectorPlottingDetails::Encoded(time) => Self::PlottingEncoded {
+                    time: time.as_secs_f64(),
+                },
+                SectorPlottingDetails::Writing => Self::PlottingWriting,
+                SectorPlottingDetails::Written(time) => Self::PlottingWritten {
+                    time: time.as_secs_f64(),
+                },
+                SectorPlottingDetails::Finished {
+                    plotted_sector,
+                    old_plotted_sector,
+                    time,
+                } => Self::PlottingFinished {
+                    pieces: plotted_sector.piece_indexes.len(),
+                    replotted: old_plotted_sector.is_some(),
+                    time: time.as_secs_f64(),
+                },
+                SectorPlottingDetails::Error(error) => Self::PlottingError {
+                    error: error.clone(),
+                },
+            },
+            SectorUpdate::Expiration(expiration_details) => match expiration_details {
+                SectorExpirationDetails::Determined { expires_at } => Self::ExpirationDetermined {
+                    expires_at: u64::from(*expires_at),
+                },
+                SectorExpirationDetails::AboutToExpire => Self::AboutToExpire,
+                SectorExpirationDetails::Expired => Self::Expired,
+            },
+            SectorUpdate::Corrupted(corruption_details) => Self::Corrupted {
+                corruption: corruption_details.to_string(),
+            },
+        }
+    }
+}
+
+/// Farming notification of a specific farm
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(in super::super) struct FarmingNotificationEvent {
+    pub(in super::super) farm_index: u8,
+    #[serde(flatten)]
+    pub(in super::super) details: FarmingNotificationEventDetails,
+}
+
+impl FarmingNotificationEvent {
+    pub(in super::super) fn new(
+        farm_index: u8,
+        farming_notification: &FarmingNotification,
+    ) -> Self {
+        Self {
+            farm_index,
+            details: FarmingNotificationEventDetails::from(farming_notification),
+        }
+    }
+}
+
+/// Farming notification details, durations are in seconds
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "camelCase")]
+pub(in super::super) enum FarmingNotificationEventDetails {
+    #[serde(rename_all = "camelCase")]
+    Auditing {
+        sectors_count: SectorIndex,
+        time: f64,
+    },
+    Proving {
+        result: String,
+        time: f64,
+    },
+    NonFatalError {
+        error: String,
+    },
+}
+
+impl From<&FarmingNotification> for FarmingNotificationEventDetails {
+    fn from(farming_notification: &FarmingNotification) -> Self {
+        match farming_notification {
+            FarmingNotification::Auditing(auditing_details) => Self::Auditing {
+                sectors_count: auditing_details.sectors_count,
+                time: auditing_details.time.as_secs_f64(),
+            },
+            FarmingNotification::Proving(proving_details) => Self::Proving {
+                result: match proving_details.result {
+                    ProvingResult::Success => "success",
+                    ProvingResult::Timeout => "timeout",
+                    ProvingResult::Rejected => "rejected",
+                    ProvingResult::Failed => "failed",
+                }
+                .to_string(),
+                time: proving_details.time.as_secs_f64(),
+            },
+            FarmingNotification::NonFatalError(error) => Self::NonFatalError {
+                error: error.to_string(),
+            },
+        }
+    }
+}
+
+/// Solution found by a specific farm
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(in super::super) struct SolutionEvent {
+    pub(in super::super) farm_index: u8,
+    pub(in super::super) slot_number: SlotNumber,
+    pub(in super::super) sector_index: SectorIndex,
+    pub(in super::super) piece_offset: u16,
+    pub(in super::super) public_key: String,
+    pub(in super::super) reward_address: String,
+}
+
+impl SolutionEvent {
+    pub(in super::super) fn new(farm_index: u8, solution_response: &SolutionResponse) -> Self {
+        let solution = &solution_response.solution;
+
+        Self {
+            farm_index,
+            slot_number: solution_response.slot_number,
+            sector_index: solution.sector_index,
+            piece_offset: u16::from(solution.piece_offset),
+            public_key: solution.public_key.to_string(),
+            reward_address: solution.reward_address.to_string(),
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use async_lock::RwLock as AsyncRwLock;
 use clap::Parser;
+use futures::future::join_all;
 use prometheus_client::registry::Registry;
 use std::collections::HashSet;
 use std::fmt;
@@ -7,19 +8,23 @@ use std::hash::Hash;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::path::Path;
 use std::sync::{Arc, Weak};
+use subspace_core_primitives::{Piece, PieceIndex};
 use subspace_farmer::farm::plotted_pieces::PlottedPieces;
 use subspace_farmer::farmer_cache::FarmerCache;
 use subspace_farmer::node_client::NodeClientExt;
 use subspace_farmer::KNOWN_PEERS_CACHE_SIZE;
 use subspace_networking::libp2p::identity::Keypair;
+use subspace_networking::libp2p::mdns::Config as MdnsConfig;
 use subspace_networking::libp2p::multiaddr::Protocol;
 use subspace_networking::libp2p::Multiaddr;
 use subspace_networking::utils::multihash::ToMultihash;
 use subspace_networking::utils::strip_peer_id;
 use subspace_networking::{
     construct, Config, KademliaMode, KnownPeersManager, KnownPeersManagerConfig, Node, NodeRunner,
-    PieceByIndexRequest, PieceByIndexRequestHandler, PieceByIndexResponse,
+    PeerReputationConfig, PieceByIndexRequest, PieceByIndexRequestHandler, PieceByIndexResponse,
+    PiecesByIndexRequestHandler, PiecesByIndexResponse,
     SegmentHeaderBySegmentIndexesRequestHandler, SegmentHeaderRequest, SegmentHeaderResponse,
+    MAX_PIECES_PER_REQUEST,
 };
 use subspace_rpc_primitives::MAX_SEGMENT_HEADERS_PER_REQUEST;
 use tracing::{debug, error, info, Instrument};
@@ -35,8 +40,12 @@ pub(in super::super) struct NetworkArgs {
     /// Multiaddrs of bootstrap nodes to connect to on startup, multiple are supported
     #[arg(long)]
     pub(in super::super) bootstrap_nodes: Vec<Multiaddr>,
-    /// Multiaddr to listen on for subspace networking, for instance `/ip4/0.0.0.0/tcp/0`,
-    /// multiple are supported.
+    /// Multiaddr to listen on for subspace networking, for instance `/ip4/0.0.0.0/tcp/0` for TCP
+    /// or `/ip4/0.0.0.0/udp/0/quic-v1` for QUIC, multiple are supported. QUIC transport is only
+    /// enabled when listening on QUIC address.
+    ///
+    /// Farmers behind NAT can additionally listen through circuit relay server with
+    /// `<relay server address>/p2p/<relay server peer ID>/p2p-circuit` to serve pieces to others.
     #[arg(long, default_values_t = [
         Multiaddr::from(IpAddr::V4(Ipv4Addr::UNSPECIFIED))
             .with(Protocol::Tcp(30533)),
@@ -66,6 +75,12 @@ pub(in super::super) struct NetworkArgs {
     /// Known external addresses
     #[arg(long, alias = "external-address")]
     pub(in super::super) external_addresses: Vec<Multiaddr>,
+    /// Discover other farmers and nodes on local network using mDNS, such that they are
+    /// connected immediately and preferred when requesting pieces.
+    ///
+    /// Useful for farms with many farmers and nodes on the same local network.
+    #[arg(long, default_value_t = false)]
+    pub(in super::super) mdns: bool,
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -83,6 +98,7 @@ pub(in super::super) fn configure_network<FarmIndex, CacheIndex, NC>(
         pending_in_connections,
         pending_out_connections,
         external_addresses,
+        mdns,
     }: NetworkArgs,
     weak_plotted_pieces: Weak<AsyncRwLock<PlottedPieces<FarmIndex>>>,
     node_client: NC,
@@ -114,44 +130,57 @@ where
         farmer_cache.clone(),
         prometheus_metrics_registry,
     );
-    let config = Config {
+    let mut config = Config {
         reserved_peers,
         listen_on,
         allow_non_global_addresses_in_dht: allow_private_ips,
         known_peers_registry,
+        peer_reputation: PeerReputationConfig {
+            path: Some(base_path.join("peer_reputation.bin").into_boxed_path()),
+            ..PeerReputationConfig::default()
+        },
         request_response_protocols: vec![
-            PieceByIndexRequestHandler::create(move |_, &PieceByIndexRequest { piece_index }| {
-                debug!(?piece_index, "Piece request received. Trying cache...");
+            PieceByIndexRequestHandler::create({
+                let weak_plotted_pieces = weak_plotted_pieces.clone();
+                let farmer_cache = farmer_cache.clone();
+
+                move |_, &PieceByIndexRequest { piece_index }| {
+                    debug!(?piece_index, "Piece request received. Trying cache...");
+
+                    let weak_plotted_pieces = weak_plotted_pieces.clone();
+                    let farmer_cache = farmer_cache.clone();
+
+                    async move {
+                        let piece =
+                            read_piece(piece_index, &farmer_cache, &weak_plotted_pieces).await;
+
+                        Some(PieceByIndexResponse { piece })
+                    }
+                    .in_current_span()
+                }
+            }),
+            PiecesByIndexRequestHandler::create(move |_, request| {
+                let piece_indices = request.piece_indices.clone();
+                debug!(?piece_indices, "Pieces request received");
 
                 let weak_plotted_pieces = weak_plotted_pieces.clone();
                 let farmer_cache = farmer_cache.clone();
 
                 async move {
-                    let piece_from_cache = farmer_cache.get_piece(piece_index.to_multihash()).await;
-
-                    if let Some(piece) = piece_from_cache {
-                        Some(PieceByIndexResponse { piece: Some(piece) })
-                    } else {
+                    if piece_indices.len() > MAX_PIECES_PER_REQUEST {
                         debug!(
-                            ?piece_index,
-                            "No piece in the cache. Trying archival storage..."
+                            num_pieces = piece_indices.len(),
+                            "Pieces request exceeds the limit"
                         );
+                        return None;
+                    }
 
-                        let read_piece_fut = match weak_plotted_pieces.upgrade() {
-                            Some(plotted_pieces) => plotted_pieces
-                                .try_read()?
-                                .read_piece(piece_index)?
-                                .in_current_span(),
-                            None => {
-                                debug!("A readers and pieces are already dropped");
-                                return None;
-                            }
-                        };
-
-                        let piece = read_piece_fut.await;
+                    let maybe_pieces = join_all(piece_indices.into_iter().map(|piece_index| {
+                        read_piece(piece_index, &farmer_cache, &weak_plotted_pieces)
+                    }))
+                    .await;
 
-                        Some(PieceByIndexResponse { piece })
-                    }
+                    Some(PiecesByIndexResponse::from_pieces(maybe_pieces))
                 }
                 .in_current_span()
             }),
@@ -224,8 +253,10 @@ where
         bootstrap_addresses: bootstrap_nodes,
         kademlia_mode: KademliaMode::Dynamic,
         external_addresses,
+        mdns: mdns.then(MdnsConfig::default),
         ..default_config
     };
+    config.enable_quic_if_listening();
 
     construct(config)
         .map(|(node, node_runner)| {
@@ -246,3 +277,41 @@ where
         })
         .map_err(Into::into)
 }
+
+/// Read piece from farmer cache or, if not found there, from local plots
+async fn read_piece<FarmIndex, CacheIndex>(
+    piece_index: PieceIndex,
+    farmer_cache: &FarmerCache<CacheIndex>,
+    weak_plotted_pieces: &Weak<AsyncRwLock<PlottedPieces<FarmIndex>>>,
+) -> Option<Piece>
+where
+    FarmIndex: Hash + Eq + Copy + fmt::Debug + Send + Sync + 'static,
+    usize: From<FarmIndex>,
+    CacheIndex: Hash + Eq + Copy + fmt::Debug + fmt::Display + Send + Sync + 'static,
+    usize: From<CacheIndex>,
+    CacheIndex: TryFrom<usize>,
+{
+    let piece_from_cache = farmer_cache.get_piece(piece_index.to_multihash()).await;
+
+    if piece_from_cache.is_some() {
+        return piece_from_cache;
+    }
+
+    debug!(
+        ?piece_index,
+        "No piece in the cache. Trying archival storage..."
+    );
+
+    let read_piece_fut = match weak_plotted_pieces.upgrade() {
+        Some(plotted_pieces) => plotted_pieces
+            .try_read()?
+            .read_piece(piece_index)?
+            .in_current_span(),
+        None => {
+            debug!("A readers and pieces are already dropped");
+            return None;
+        }
+    };
+
+    read_piece_fut.await
+}
@@ -0,0 +1,79 @@
+use crate::utils::shutdown_signal;
+use anyhow::anyhow;
+use clap::Parser;
+use futures::{select, FutureExt};
+use std::fs;
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::path::PathBuf;
+use subspace_farmer::single_disk_farm::remote_signer::RemoteSignerServer;
+use tokio::net::UnixListener;
+use tracing::info;
+
+/// Arguments for remote signer
+#[derive(Debug, Parser)]
+pub(crate) struct SignerArgs {
+    /// Path to Unix socket to listen on, farmer connects to it with `--remote-signer`
+    #[arg(long)]
+    socket: PathBuf,
+    /// Directory where identities are stored, each in its own subdirectory.
+    ///
+    /// Identities of new farms are created here automatically. To move identity of existing farm
+    /// into remote signer, move `identity.bin` file from the farm into a new subdirectory of this
+    /// directory.
+    #[arg(long)]
+    identities_directory: PathBuf,
+}
+
+/// Start remote signer that holds farm identities and signs reward hashes on behalf of farms
+pub(crate) async fn signer(signer_args: SignerArgs) -> anyhow::Result<()> {
+    let signal = shutdown_signal();
+
+    let SignerArgs {
+        socket,
+        identities_directory,
+    } = signer_args;
+
+    let server = RemoteSignerServer::open(identities_directory.clone())
+        .map_err(|error| anyhow!("Failed to open identities: {error}"))?;
+
+    info!(
+        path = %identities_directory.display(),
+        identities = %server.public_keys().len(),
+        "Opened identities"
+    );
+    for public_key in server.public_keys() {
+        info!(%public_key, "Found identity");
+    }
+
+    // Socket file might be left after previous run
+    if let Ok(metadata) = fs::symlink_metadata(&socket) {
+        if !metadata.file_type().is_socket() {
+            return Err(anyhow!(
+                "File {} already exists and is not a socket",
+                socket.display()
+            ));
+        }
+        fs::remove_file(&socket)?;
+    }
+
+    let listener = UnixListener::bind(&socket)
+        .map_err(|error| anyhow!("Failed to listen on {}: {error}", socket.display()))?;
+    // Only the same user is allowed to request signatures
+    fs::set_permissions(&socket, fs::Permissions::from_mode(0o600))?;
+
+    info!(path = %socket.display(), "Remote signer is listening");
+
+    select! {
+        // Signal future
+        _ = signal.fuse() => {},
+
+        // Server future
+        result = server.run(listener).fuse() => {
+            result?;
+        },
+    }
+
+    let _ = fs::remove_file(&socket);
+
+    anyhow::Ok(())
+}
@@ -10,8 +10,11 @@
 mod commands;
 mod utils;
 
+use crate::commands::shared::{cache_percentage_parser, DiskFarm};
+use crate::commands::InfoFormat;
 use clap::Parser;
 use std::fs;
+use std::num::NonZeroU8;
 use std::path::PathBuf;
 use subspace_farmer::single_disk_farm::{ScrubTarget, SingleDiskFarm};
 use subspace_proof_of_space::chia::ChiaTable;
@@ -35,9 +38,18 @@ enum Command {
     Farm(commands::farm::FarmingArgs),
     /// Farming cluster
     Cluster(commands::cluster::ClusterArgs),
+    /// Start standalone piece cache server, syncs pieces of archived history and serves them over
+    /// DSN without plotting or farming
+    CacheServer(commands::cache_server::CacheServerArgs),
+    /// Export pieces of archived history into portable piece archive or seed piece cache from it
+    #[clap(subcommand)]
+    PieceArchive(commands::piece_archive::PieceArchiveArgs),
     /// Run various benchmarks
     #[clap(subcommand)]
     Benchmark(commands::benchmark::BenchmarkArgs),
+    /// Start remote signer that holds farm identities outside of farms
+    #[cfg(unix)]
+    Signer(commands::signer::SignerArgs),
     /// Print information about farm and its content
     Info {
         /// One or more farm located at specified path.
@@ -45,6 +57,15 @@ enum Command {
         /// Example:
         ///   /path/to/directory
         disk_farms: Vec<PathBuf>,
+        /// Output format
+        ///
+        /// `json` prints per-sector details and piece cache occupancy of each farm, `csv` prints
+        /// one row per sector
+        #[arg(long, value_enum, default_value_t = InfoFormat::Text)]
+        format: InfoFormat,
+        /// WebSocket RPC URL of the Subspace node, used to estimate sector expiration
+        #[arg(long)]
+        node_rpc_url: Option<String>,
     },
     /// Checks the farm for corruption and repairs errors (caused by disk errors or something else)
     Scrub {
@@ -64,6 +85,46 @@ enum Command {
         /// Check for errors, but do not attempt to correct them
         #[arg(long)]
         dry_run: bool,
+        /// Farms use remote signer (see `signer` command), so their identity files are not
+        /// expected to exist
+        #[arg(long)]
+        remote_signer: bool,
+    },
+    /// Grows or shrinks existing farm without wiping it
+    Resize {
+        /// One or more farm located at specified path, each with its new allocated space.
+        ///
+        /// Format is the same as for `farm` command:
+        ///
+        ///   path=/path/to/directory,size=5T
+        ///
+        /// Shrinking drops sectors with the highest indices, growing makes space for new sectors
+        /// that will be plotted on next start.
+        disk_farms: Vec<DiskFarm>,
+        /// Percentage of allocated space dedicated for caching purposes, must be the same as used
+        /// with `farm` command
+        #[arg(long, default_value = "1", value_parser = cache_percentage_parser)]
+        cache_percentage: NonZeroU8,
+        /// Disable farm locking, for example if file system doesn't support it
+        #[arg(long)]
+        disable_farm_locking: bool,
+    },
+    /// Copies farm into another directory (typically on a different disk) with verification
+    Migrate {
+        /// Path to directory of existing farm
+        from: PathBuf,
+        /// Path to directory where farm will be copied to, interrupted migration is resumed when
+        /// the same target is used again
+        to: PathBuf,
+        /// Allow source farm to be in use during migration.
+        ///
+        /// Sectors replotted while being copied are copied again, run migration once more after
+        /// stopping source farm to catch up with all remaining changes.
+        #[arg(long)]
+        allow_source_in_use: bool,
+        /// Disable farm locking, for example if file system doesn't support it
+        #[arg(long)]
+        disable_farm_locking: bool,
     },
     /// Wipes the farm
     Wipe {
@@ -105,14 +166,29 @@ async fn main() -> anyhow::Result<()> {
         Command::Cluster(cluster_args) => {
             commands::cluster::cluster::<PosTableLegacy, PosTable>(cluster_args).await?;
         }
+        Command::CacheServer(cache_server_args) => {
+            commands::cache_server::cache_server(cache_server_args).await?;
+        }
+        Command::PieceArchive(piece_archive_args) => {
+            commands::piece_archive::piece_archive::<PosTableLegacy, PosTable>(piece_archive_args)
+                .await?;
+        }
         Command::Benchmark(benchmark_args) => {
             commands::benchmark::benchmark(benchmark_args)?;
         }
-        Command::Info { disk_farms } => {
+        #[cfg(unix)]
+        Command::Signer(signer_args) => {
+            commands::signer::signer(signer_args).await?;
+        }
+        Command::Info {
+            disk_farms,
+            format,
+            node_rpc_url,
+        } => {
             if disk_farms.is_empty() {
                 info!("No farm was specified, so there is nothing to do");
             } else {
-                commands::info(disk_farms);
+                commands::info(disk_farms, format, node_rpc_url).await?;
             }
         }
         Command::Scrub {
@@ -120,13 +196,39 @@ async fn main() -> anyhow::Result<()> {
             disable_farm_locking,
             target,
             dry_run,
+            remote_signer,
         } => {
             if disk_farms.is_empty() {
                 info!("No farm was specified, so there is nothing to do");
             } else {
-                commands::scrub(&disk_farms, disable_farm_locking, target, dry_run);
+                commands::scrub(
+                    &disk_farms,
+                    disable_farm_locking,
+                    target,
+                    dry_run,
+                    remote_signer,
+                );
             }
         }
+        Command::Resize {
+            disk_farms,
+            cache_percentage,
+            disable_farm_locking,
+        } => {
+            if disk_farms.is_empty() {
+                info!("No farm was specified, so there is nothing to do");
+            } else {
+                commands::resize(&disk_farms, cache_percentage.get(), disable_farm_locking);
+            }
+        }
+        Command::Migrate {
+            from,
+            to,
+            allow_source_in_use,
+            disable_farm_locking,
+        } => {
+            commands::migrate(&from, &to, allow_source_in_use, disable_farm_locking)?;
+        }
         Command::Wipe { disk_farms } => {
             for disk_farm in &disk_farms {
                 if !disk_farm.exists() {
@@ -139,6 +241,10 @@ async fn main() -> anyhow::Result<()> {
                     info!("Wiping known addresses");
                     let _ = fs::remove_file(disk_farm.join("known_addresses.bin"));
                 }
+                if disk_farm.join("network").exists() {
+                    info!("Wiping network identity");
+                    let _ = fs::remove_dir_all(disk_farm.join("network"));
+                }
 
                 SingleDiskFarm::wipe(disk_farm)?;
             }
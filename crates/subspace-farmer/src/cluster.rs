@@ -49,9 +49,19 @@
 //! There could be any number of caches in the cluster, but each cache instance belongs to one of
 //! the controllers. So if multiple controllers are present in the cluster, you'll want at least one
 //! cache connected to each as well for optimal performance.
+//!
+//! ### Transport
+//!
+//! Components communicate with each other using NATS-style messaging. NATS server is used by
+//! default, but for smaller deployments built-in broker can be used instead, see [`broker`] module.
+//! Messages can be authenticated (and stream responses encrypted) with a shared secret for
+//! deployments across untrusted networks, see [`auth`] module.
 
+pub mod auth;
+pub mod broker;
 pub mod cache;
 pub mod controller;
 pub mod farmer;
 pub mod nats_client;
 pub mod plotter;
+pub mod transport;
@@ -0,0 +1,387 @@
+//! Authentication and encryption of cluster messages
+//!
+//! [`AuthenticatedTransport`] wraps another [`ClusterTransport`] and authenticates every message
+//! (requests, responses, notifications, broadcasts and stream responses) with a key derived from
+//! the secret shared by all cluster components. Each payload is wrapped in an envelope with
+//! timestamp, random nonce and keyed BLAKE3 MAC over the subject, reply subject and the rest of the
+//! envelope.
+//!
+//! Messages without valid MAC (including messages from components that don't know the secret) are
+//! dropped. Messages are only accepted with timestamps within [`MAX_CLOCK_SKEW`] of local time,
+//! hence clocks of all cluster components must be synchronized, and nonces of messages recently
+//! received by any subscription of the transport are remembered to reject replays.
+//!
+//! Replay protection is local to each transport instance (each cluster component) and recipient is
+//! not part of the MAC, since messages sent to queue groups don't have a single known recipient.
+//! As a result, a message captured on the wire can still be delivered within [`MAX_CLOCK_SKEW`] to
+//! a different component that didn't receive it yet, most notably to another member of the same
+//! queue group (for example another plotter or farm). Messages addressed to a particular component
+//! include its identifier in the subject, which is covered by the MAC, and can't be redirected.
+//!
+//! Payloads of stream responses (most notably plotted sectors sent by plotters) can additionally be
+//! encrypted with ChaCha20-Poly1305.
+
+#[cfg(test)]
+mod tests;
+
+use crate::cluster::nats_client::STREAM_RESPONSE_SUBJECT_PREFIX;
+use crate::cluster::transport::{
+    ClusterTransport, TransportError, TransportMessage, TransportSubscription,
+};
+use async_trait::async_trait;
+use bytes::Bytes;
+use chacha20poly1305::aead::Aead;
+use chacha20poly1305::{ChaCha20Poly1305, Key, KeyInit, Nonce};
+use futures::{future, StreamExt};
+use parity_scale_codec::{Decode, Encode};
+use parking_lot::Mutex;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+use tracing::warn;
+
+/// Max difference between timestamp of the message and local time for message to be accepted
+pub const MAX_CLOCK_SKEW: Duration = Duration::from_mins(1);
+const MAC_KEY_CONTEXT: &str = "subspace-farmer 2024-08-01 cluster message authentication";
+const ENCRYPTION_KEY_CONTEXT: &str = "subspace-farmer 2024-08-01 cluster message encryption";
+/// Upper bound of the envelope size in addition to the payload (including encryption tag)
+const ENVELOPE_OVERHEAD: usize = 128;
+
+type MessageNonce = [u8; 12];
+
+/// Errors happening when opening authenticated message
+#[derive(Debug, Error)]
+enum AuthenticationError {
+    /// Message is not a valid envelope
+    #[error("Failed to decode envelope: {0}")]
+    Decoding(#[from] parity_scale_codec::Error),
+    /// MAC doesn't match
+    #[error("Invalid MAC")]
+    InvalidMac,
+    /// Message timestamp is too far from local time
+    #[error("Timestamp {timestamp} is too far from local time {now}")]
+    ClockSkew {
+        /// Message timestamp
+        timestamp: u64,
+        /// Local time
+        now: u64,
+    },
+    /// Message with the same nonce was received recently
+    #[error("Message was replayed")]
+    Replay,
+    /// Failed to decrypt payload
+    #[error("Failed to decrypt payload")]
+    Decryption,
+}
+
+#[derive(Debug, Encode, Decode)]
+struct Envelope {
+    /// Milliseconds since Unix epoch
+    timestamp: u64,
+    nonce: MessageNonce,
+    encrypted: bool,
+    payload: Vec<u8>,
+    mac: [u8; 32],
+}
+
+/// Receiver of messages: subscription or a single request waiting for response
+#[derive(Debug, Copy, Clone)]
+struct Receiver {
+    id: u64,
+    created_at: Instant,
+}
+
+#[derive(Debug)]
+struct ReceivedNonce {
+    /// When message with this nonce was received for the first time
+    received_at: Instant,
+    /// Receivers that received message with this nonce
+    receivers: HashSet<u64>,
+}
+
+/// Remembers nonces of messages recently received by all receivers of the transport
+#[derive(Debug, Default)]
+struct ReplayProtection {
+    nonces: HashMap<MessageNonce, ReceivedNonce>,
+    received: VecDeque<(Instant, MessageNonce)>,
+}
+
+impl ReplayProtection {
+    /// Returns `false` if message with the same nonce was received recently by the same receiver or
+    /// before receiver was created.
+    ///
+    /// The same message is legitimately delivered once to every matching subscription, but
+    /// subscriptions created after the message was received must not accept it again.
+    fn check(&mut self, nonce: MessageNonce, receiver: Receiver) -> bool {
+        let now = Instant::now();
+        // Messages with the same nonce received before this are too old to pass timestamp check
+        while let Some(&(received_at, old_nonce)) = self.received.front()
+            && now.duration_since(received_at) > MAX_CLOCK_SKEW * 2
+        {
+            self.nonces.remove(&old_nonce);
+            self.received.pop_front();
+        }
+
+        match self.nonces.entry(nonce) {
+            Entry::Occupied(mut entry) => {
+                let received_nonce = entry.get_mut();
+                if receiver.created_at > received_nonce.received_at {
+                    return false;
+                }
+                received_nonce.receivers.insert(receiver.id)
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(ReceivedNonce {
+                    received_at: now,
+                    receivers: HashSet::from([receiver.id]),
+                });
+                self.received.push_back((now, nonce));
+                true
+            }
+        }
+    }
+}
+
+struct Inner {
+    transport: Arc<dyn ClusterTransport>,
+    mac_key: [u8; 32],
+    cipher: ChaCha20Poly1305,
+    encrypt_stream_responses: bool,
+    /// Shared by all receivers, such that messages can't be replayed to a different subscription
+    replay_protection: Mutex<ReplayProtection>,
+    next_receiver_id: AtomicU64,
+}
+
+impl fmt::Debug for Inner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Inner")
+            .field("transport", &self.transport)
+            .field("encrypt_stream_responses", &self.encrypt_stream_responses)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Inner {
+    fn new_receiver(&self) -> Receiver {
+        Receiver {
+            id: self.next_receiver_id.fetch_add(1, Ordering::Relaxed),
+            created_at: Instant::now(),
+        }
+    }
+
+    fn mac(
+        &self,
+        subject: &str,
+        reply: Option<&str>,
+        timestamp: u64,
+        nonce: &MessageNonce,
+        encrypted: bool,
+        payload: &[u8],
+    ) -> blake3::Hash {
+        let mut hasher = blake3::Hasher::new_keyed(&self.mac_key);
+        (subject, reply, timestamp, nonce, encrypted).using_encoded(|bytes| {
+            hasher.update(bytes);
+        });
+        hasher.update(payload);
+        hasher.finalize()
+    }
+
+    fn seal(
+        &self,
+        subject: &str,
+        reply: Option<&str>,
+        payload: Bytes,
+    ) -> Result<Bytes, TransportError> {
+        let timestamp = unix_timestamp_millis();
+        let nonce = rand::random::<MessageNonce>();
+        let encrypted =
+            self.encrypt_stream_responses && subject.starts_with(STREAM_RESPONSE_SUBJECT_PREFIX);
+        let payload = if encrypted {
+            self.cipher
+                .encrypt(Nonce::from_slice(&nonce), payload.as_ref())
+                .map_err(|_error| TransportError::Other("Failed to encrypt payload".to_string()))?
+        } else {
+            payload.to_vec()
+        };
+        let mac = self.mac(subject, reply, timestamp, &nonce, encrypted, &payload);
+
+        Ok(Envelope {
+            timestamp,
+            nonce,
+            encrypted,
+            payload,
+            mac: mac.into(),
+        }
+        .encode()
+        .into())
+    }
+
+    fn open(
+        &self,
+        message: TransportMessage,
+        receiver: Receiver,
+    ) -> Result<TransportMessage, AuthenticationError> {
+        let Envelope {
+            timestamp,
+            nonce,
+            encrypted,
+            payload,
+            mac,
+        } = Envelope::decode(&mut message.payload.as_ref())?;
+
+        // Comparison of `blake3::Hash` is constant-time
+        if self.mac(
+            &message.subject,
+            message.reply.as_deref(),
+            timestamp,
+            &nonce,
+            encrypted,
+            &payload,
+        ) != blake3::Hash::from(mac)
+        {
+            return Err(AuthenticationError::InvalidMac);
+        }
+
+        let now = unix_timestamp_millis();
+        if now.abs_diff(timestamp) > MAX_CLOCK_SKEW.as_millis() as u64 {
+            return Err(AuthenticationError::ClockSkew { timestamp, now });
+        }
+
+        if !self.replay_protection.lock().check(nonce, receiver) {
+            return Err(AuthenticationError::Replay);
+        }
+
+        let payload = if encrypted {
+            self.cipher
+                .decrypt(Nonce::from_slice(&nonce), payload.as_slice())
+                .map_err(|_error| AuthenticationError::Decryption)?
+        } else {
+            payload
+        };
+
+        Ok(TransportMessage {
+            subject: message.subject,
+            reply: message.reply,
+            payload: payload.into(),
+        })
+    }
+}
+
+/// Transport wrapper that authenticates all messages and optionally encrypts stream responses, see
+/// module-level documentation for details
+#[derive(Debug, Clone)]
+pub struct AuthenticatedTransport {
+    inner: Arc<Inner>,
+}
+
+#[async_trait]
+impl ClusterTransport for AuthenticatedTransport {
+    fn max_payload(&self) -> usize {
+        self.inner
+            .transport
+            .max_payload()
+            .saturating_sub(ENVELOPE_OVERHEAD)
+    }
+
+    async fn publish(
+        &self,
+        subject: String,
+        message_id: Option<String>,
+        payload: Bytes,
+    ) -> Result<(), TransportError> {
+        let payload = self.inner.seal(&subject, None, payload)?;
+        self.inner
+            .transport
+            .publish(subject, message_id, payload)
+            .await
+    }
+
+    fn new_inbox(&self) -> String {
+        self.inner.transport.new_inbox()
+    }
+
+    async fn request_with_inbox(
+        &self,
+        subject: String,
+        inbox: String,
+        payload: Bytes,
+    ) -> Result<TransportMessage, TransportError> {
+        let payload = self.inner.seal(&subject, Some(&inbox), payload)?;
+        let receiver = self.inner.new_receiver();
+        let response = self
+            .inner
+            .transport
+            .request_with_inbox(subject, inbox, payload)
+            .await?;
+
+        self.inner
+            .open(response, receiver)
+            .map_err(|error| TransportError::Authentication(error.to_string()))
+    }
+
+    async fn subscribe(
+        &self,
+        subject: String,
+        queue_group: Option<String>,
+    ) -> Result<TransportSubscription, TransportError> {
+        let receiver = self.inner.new_receiver();
+        let subscription = self
+            .inner
+            .transport
+            .subscribe(subject.clone(), queue_group.clone())
+            .await?;
+
+        let inner = Arc::clone(&self.inner);
+        let messages = subscription.filter_map(move |message| {
+            let message_subject = message.subject.clone();
+            let result = match inner.open(message, receiver) {
+                Ok(message) => Some(message),
+                Err(error) => {
+                    warn!(subject = %message_subject, %error, "Rejected cluster message");
+                    None
+                }
+            };
+
+            future::ready(result)
+        });
+
+        Ok(TransportSubscription::new(subject, queue_group, messages))
+    }
+}
+
+impl AuthenticatedTransport {
+    /// Wrap provided transport, all cluster components must use the same `secret`.
+    ///
+    /// With `encrypt_stream_responses` payloads of stream responses sent by this instance will be
+    /// encrypted, encrypted payloads are decrypted regardless of this option.
+    pub fn new(
+        transport: Arc<dyn ClusterTransport>,
+        secret: &[u8],
+        encrypt_stream_responses: bool,
+    ) -> Self {
+        let mac_key = blake3::derive_key(MAC_KEY_CONTEXT, secret);
+        let encryption_key = blake3::derive_key(ENCRYPTION_KEY_CONTEXT, secret);
+
+        Self {
+            inner: Arc::new(Inner {
+                transport,
+                mac_key,
+                cipher: ChaCha20Poly1305::new(Key::from_slice(&encryption_key)),
+                encrypt_stream_responses,
+                replay_protection: Mutex::default(),
+                next_receiver_id: AtomicU64::new(0),
+            }),
+        }
+    }
+}
+
+fn unix_timestamp_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
@@ -0,0 +1,150 @@
+use crate::cluster::auth::AuthenticatedTransport;
+use crate::cluster::broker::{BrokerClient, BrokerServer};
+use crate::cluster::nats_client::STREAM_RESPONSE_SUBJECT_PREFIX;
+use crate::cluster::transport::{ClusterTransport, TransportError};
+use futures::StreamExt;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+
+const SUBJECT: &str = "subspace.test.auth";
+
+async fn connect_all(secrets: &[Option<&[u8]>]) -> Vec<Arc<dyn ClusterTransport>> {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    tokio::spawn(BrokerServer::default().run(listener));
+
+    let mut transports = Vec::new();
+    for secret in secrets {
+        let broker_client: Arc<dyn ClusterTransport> =
+            Arc::new(BrokerClient::connect(address.clone()).await.unwrap());
+        transports.push(match secret {
+            Some(secret) => Arc::new(AuthenticatedTransport::new(broker_client, secret, true)),
+            None => broker_client,
+        });
+    }
+    transports
+}
+
+#[tokio::test]
+async fn authentication() {
+    let transports = connect_all(&[Some(b"secret"), Some(b"secret"), Some(b"other"), None]).await;
+    let [authenticated, receiver, wrong_secret, raw] = transports.as_slice() else {
+        unreachable!();
+    };
+
+    let mut subscription = receiver.subscribe(SUBJECT.to_string(), None).await.unwrap();
+    let mut raw_subscription = raw.subscribe(SUBJECT.to_string(), None).await.unwrap();
+
+    // Messages without or with wrong authentication are rejected
+    raw.publish(SUBJECT.to_string(), None, vec![1].into())
+        .await
+        .unwrap();
+    wrong_secret
+        .publish(SUBJECT.to_string(), None, vec![2].into())
+        .await
+        .unwrap();
+    authenticated
+        .publish(SUBJECT.to_string(), None, vec![3].into())
+        .await
+        .unwrap();
+    assert_eq!(subscription.next().await.unwrap().payload.as_ref(), &[3]);
+
+    // Replayed messages are rejected, including by subscriptions created after original message
+    // was received
+    let mut new_subscription = receiver.subscribe(SUBJECT.to_string(), None).await.unwrap();
+    for _ in 0..3 {
+        let message = raw_subscription.next().await.unwrap();
+        raw.publish(SUBJECT.to_string(), None, message.payload)
+            .await
+            .unwrap();
+    }
+    for subscription in [&mut subscription, &mut new_subscription] {
+        assert!(
+            tokio::time::timeout(Duration::from_millis(100), subscription.next())
+                .await
+                .is_err()
+        );
+    }
+
+    // Requests without responders still fail the same way
+    assert!(matches!(
+        authenticated
+            .request("subspace.test.request".to_string(), vec![].into())
+            .await,
+        Err(TransportError::NoResponders)
+    ));
+}
+
+#[tokio::test]
+async fn tampered_reply_subject() {
+    let transports = connect_all(&[Some(b"secret"), Some(b"secret"), None]).await;
+    let [requester, receiver, raw] = transports.as_slice() else {
+        unreachable!();
+    };
+
+    let mut raw_subscription = raw.subscribe(SUBJECT.to_string(), None).await.unwrap();
+    tokio::spawn({
+        let requester = Arc::clone(requester);
+
+        async move { requester.request(SUBJECT.to_string(), vec![1].into()).await }
+    });
+    let request = raw_subscription.next().await.unwrap();
+    let reply = request.reply.unwrap();
+    drop(raw_subscription);
+
+    // Receiver only subscribes now, so it has never seen the original request
+    let mut subscription = receiver.subscribe(SUBJECT.to_string(), None).await.unwrap();
+
+    // Request redirected to a different reply subject is rejected
+    tokio::spawn({
+        let raw = Arc::clone(raw);
+        let payload = request.payload.clone();
+
+        async move {
+            raw.request_with_inbox(SUBJECT.to_string(), raw.new_inbox(), payload)
+                .await
+        }
+    });
+    assert!(
+        tokio::time::timeout(Duration::from_millis(100), subscription.next())
+            .await
+            .is_err()
+    );
+
+    // The same request with original reply subject is accepted
+    tokio::spawn({
+        let raw = Arc::clone(raw);
+
+        async move {
+            raw.request_with_inbox(SUBJECT.to_string(), reply, request.payload)
+                .await
+        }
+    });
+    let message = subscription.next().await.unwrap();
+    assert_eq!(message.payload.as_ref(), &[1]);
+}
+
+#[tokio::test]
+async fn encryption() {
+    let transports = connect_all(&[Some(b"secret"), Some(b"secret"), None]).await;
+    let [sender, receiver, raw] = transports.as_slice() else {
+        unreachable!();
+    };
+
+    let subject = format!("{STREAM_RESPONSE_SUBJECT_PREFIX}test");
+    let mut subscription = receiver.subscribe(subject.clone(), None).await.unwrap();
+    let mut raw_subscription = raw.subscribe(subject.clone(), None).await.unwrap();
+
+    let payload = vec![42; 1024];
+    sender
+        .publish(subject, None, payload.clone().into())
+        .await
+        .unwrap();
+
+    assert_eq!(subscription.next().await.unwrap().payload.as_ref(), payload);
+    let raw_payload = raw_subscription.next().await.unwrap().payload;
+    assert!(!raw_payload
+        .windows(payload.len())
+        .any(|window| window == payload));
+}
@@ -0,0 +1,736 @@
+//! Built-in message broker
+//!
+//! Broker implements a subset of NATS semantics (subjects with wildcards, queue groups and
+//! request/response) over plain TCP, such that small clusters can be deployed without running a
+//! separate NATS server. [`BrokerServer`] is typically hosted by the controller, while
+//! [`BrokerClient`] implements [`ClusterTransport`] and can be used with
+//! [`NatsClient::from_transport()`](crate::cluster::nats_client::NatsClient::from_transport) by all
+//! cluster components.
+//!
+//! Protocol is a sequence of SCALE-encoded messages, each prefixed with its length as
+//! little-endian `u32`.
+//!
+//! NOTE: Broker doesn't do any authentication or encryption, it must only be exposed to trusted
+//! networks.
+
+#[cfg(test)]
+mod tests;
+
+use crate::cluster::transport::{
+    ClusterTransport, TransportError, TransportMessage, TransportSubscription,
+};
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::channel::{mpsc, oneshot};
+use futures::{select, FutureExt, Stream, StreamExt};
+use parity_scale_codec::{Decode, Encode};
+use parking_lot::Mutex;
+use rand::prelude::*;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::tcp::OwnedReadHalf;
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, info, trace, warn};
+use ulid::Ulid;
+
+/// Max payload size supported by broker
+const MAX_PAYLOAD: usize = 4 * 1024 * 1024;
+/// Max size of a single message, accounts for subjects in addition to payload
+const MAX_MESSAGE_SIZE: u32 = MAX_PAYLOAD as u32 + 64 * 1024;
+/// Timeout for a single request, matches timeout used with NATS
+const REQUEST_TIMEOUT: Duration = Duration::from_mins(5);
+/// Delay between attempts to reconnect to the broker
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+/// Number of recent message IDs broker remembers for de-duplication purposes
+const RECENT_MESSAGE_IDS: usize = 1024;
+/// Subscription ID reserved for responses to requests made by the client
+const INBOX_SUBSCRIPTION_ID: u64 = 0;
+
+#[derive(Debug, Encode, Decode)]
+enum ClientMessage {
+    /// Subscribe to subject, replaces existing subscription with the same ID
+    Subscribe {
+        id: u64,
+        subject: String,
+        queue_group: Option<String>,
+    },
+    Unsubscribe {
+        id: u64,
+    },
+    Publish {
+        subject: String,
+        reply: Option<String>,
+        message_id: Option<String>,
+        payload: Vec<u8>,
+    },
+}
+
+#[derive(Debug, Encode, Decode)]
+enum ServerMessage {
+    Message {
+        subscription_id: u64,
+        subject: String,
+        reply: Option<String>,
+        payload: Vec<u8>,
+    },
+    /// There were no subscribers for a request with specified reply subject
+    NoResponders { reply: String },
+    /// Subscription with specified ID is active, messages published after this will be delivered
+    Subscribed { id: u64 },
+}
+
+async fn write_message<S, T>(stream: &mut S, message: &T) -> io::Result<()>
+where
+    S: AsyncWrite + Unpin,
+    T: Encode,
+{
+    let encoded = message.encode();
+    stream.write_u32_le(encoded.len() as u32).await?;
+    stream.write_all(&encoded).await?;
+    stream.flush().await
+}
+
+async fn read_message<S, T>(stream: &mut S) -> io::Result<T>
+where
+    S: AsyncRead + Unpin,
+    T: Decode,
+{
+    let size = stream.read_u32_le().await?;
+    if size > MAX_MESSAGE_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Message of {size} bytes is too large"),
+        ));
+    }
+
+    let mut buffer = vec![0; size as usize];
+    stream.read_exact(&mut buffer).await?;
+
+    T::decode(&mut buffer.as_slice())
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
+/// Check whether subject matches subscription subject, which may contain `*` to match a single
+/// token and `>` (as the last token) to match one or more tokens
+fn subject_matches(subscription_subject: &str, subject: &str) -> bool {
+    let mut subscription_tokens = subscription_subject.split('.');
+    let mut tokens = subject.split('.');
+
+    loop {
+        match (subscription_tokens.next(), tokens.next()) {
+            (Some(">"), Some(_)) => {
+                return true;
+            }
+            (Some(subscription_token), Some(token)) => {
+                if subscription_token != "*" && subscription_token != token {
+                    return false;
+                }
+            }
+            (None, None) => {
+                return true;
+            }
+            _ => {
+                return false;
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct ServerSubscription {
+    subject: String,
+    queue_group: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct ServerState {
+    connections: HashMap<u64, mpsc::UnboundedSender<ServerMessage>>,
+    /// Subscriptions by connection ID and subscription ID
+    subscriptions: HashMap<(u64, u64), ServerSubscription>,
+    recent_message_ids: HashSet<String>,
+    recent_message_ids_order: VecDeque<String>,
+}
+
+impl ServerState {
+    /// Returns `false` if message with the same ID was published recently
+    fn remember_message_id(&mut self, message_id: String) -> bool {
+        if self.recent_message_ids.contains(&message_id) {
+            return false;
+        }
+
+        if self.recent_message_ids_order.len() == RECENT_MESSAGE_IDS
+            && let Some(old_message_id) = self.recent_message_ids_order.pop_front()
+        {
+            self.recent_message_ids.remove(&old_message_id);
+        }
+        self.recent_message_ids_order.push_back(message_id.clone());
+        self.recent_message_ids.insert(message_id);
+
+        true
+    }
+
+    fn publish(
+        &self,
+        publisher_connection_id: u64,
+        subject: String,
+        reply: Option<String>,
+        payload: Vec<u8>,
+    ) {
+        let mut recipients = Vec::new();
+        let mut queue_groups = HashMap::<(&str, &str), Vec<(u64, u64)>>::new();
+
+        for (&recipient, subscription) in &self.subscriptions {
+            if !subject_matches(&subscription.subject, &subject) {
+                continue;
+            }
+
+            match &subscription.queue_group {
+                Some(queue_group) => {
+                    queue_groups
+                        .entry((&subscription.subject, queue_group))
+                        .or_default()
+                        .push(recipient);
+                }
+                None => {
+                    recipients.push(recipient);
+                }
+            }
+        }
+
+        // Only one member of each queue group receives the message
+        recipients.extend(
+            queue_groups
+                .into_values()
+                .filter_map(|members| members.choose(&mut thread_rng()).copied()),
+        );
+
+        if recipients.is_empty() {
+            if let Some(reply) = reply
+                && let Some(message_sender) = self.connections.get(&publisher_connection_id)
+            {
+                // Connection might be closing, in which case it doesn't care about response anyway
+                let _ = message_sender.unbounded_send(ServerMessage::NoResponders { reply });
+            } else {
+                trace!(%subject, "No subscribers for message");
+            }
+            return;
+        }
+
+        for (connection_id, subscription_id) in recipients {
+            if let Some(message_sender) = self.connections.get(&connection_id) {
+                // Connection might be closing, in which case message is lost the same way as if
+                // connection was closed a bit earlier
+                let _ = message_sender.unbounded_send(ServerMessage::Message {
+                    subscription_id,
+                    subject: subject.clone(),
+                    reply: reply.clone(),
+                    payload: payload.clone(),
+                });
+            }
+        }
+    }
+}
+
+/// Broker server that routes messages between connected [`BrokerClient`]s
+#[derive(Debug, Default)]
+pub struct BrokerServer {
+    state: Mutex<ServerState>,
+    next_connection_id: AtomicU64,
+}
+
+impl BrokerServer {
+    /// Serve connections on provided listener, returns only in case of error
+    pub async fn run(self, listener: TcpListener) -> io::Result<()> {
+        let server = Arc::new(self);
+
+        loop {
+            let (stream, address) = listener.accept().await?;
+            debug!(%address, "Accepted broker connection");
+
+            let connection_id = server.next_connection_id.fetch_add(1, Ordering::Relaxed);
+
+            tokio::spawn({
+                let server = Arc::clone(&server);
+
+                async move {
+                    if let Err(error) = server.serve_connection(connection_id, stream).await {
+                        debug!(%address, %error, "Broker connection closed with error");
+                    } else {
+                        debug!(%address, "Broker connection closed");
+                    }
+
+                    let mut state = server.state.lock();
+                    state.connections.remove(&connection_id);
+                    state
+                        .subscriptions
+                        .retain(|&(subscriber_connection_id, _), _| {
+                            subscriber_connection_id != connection_id
+                        });
+                }
+            });
+        }
+    }
+
+    async fn serve_connection(&self, connection_id: u64, stream: TcpStream) -> io::Result<()> {
+        stream.set_nodelay(true)?;
+        let (mut reader, mut writer) = stream.into_split();
+
+        // Unbounded in order to never block message routing on slow connections
+        let (message_sender, mut message_receiver) = mpsc::unbounded();
+        self.state
+            .lock()
+            .connections
+            .insert(connection_id, message_sender);
+
+        let writing = async move {
+            while let Some(message) = message_receiver.next().await {
+                write_message(&mut writer, &message).await?;
+            }
+
+            Ok::<_, io::Error>(())
+        };
+        let reading = async move {
+            loop {
+                let message = match read_message::<_, ClientMessage>(&mut reader).await {
+                    Ok(message) => message,
+                    Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => {
+                        // Connection closed by client
+                        return Ok(());
+                    }
+                    Err(error) => {
+                        return Err(error);
+                    }
+                };
+
+                self.process_message(connection_id, message);
+            }
+        };
+
+        select! {
+            result = writing.fuse() => result,
+            result = reading.fuse() => result,
+        }
+    }
+
+    fn process_message(&self, connection_id: u64, message: ClientMessage) {
+        let mut state = self.state.lock();
+
+        match message {
+            ClientMessage::Subscribe {
+                id,
+                subject,
+                queue_group,
+            } => {
+                state.subscriptions.insert(
+                    (connection_id, id),
+                    ServerSubscription {
+                        subject,
+                        queue_group,
+                    },
+                );
+                if let Some(message_sender) = state.connections.get(&connection_id) {
+                    // Connection might be closing, in which case it doesn't care about confirmation
+                    // anyway
+                    let _ = message_sender.unbounded_send(ServerMessage::Subscribed { id });
+                }
+            }
+            ClientMessage::Unsubscribe { id } => {
+                state.subscriptions.remove(&(connection_id, id));
+            }
+            ClientMessage::Publish {
+                subject,
+                reply,
+                message_id,
+                payload,
+            } => {
+                if let Some(message_id) = message_id
+                    && !state.remember_message_id(message_id)
+                {
+                    trace!(%subject, "Ignoring duplicated message");
+                    return;
+                }
+
+                state.publish(connection_id, subject, reply, payload);
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct ClientSubscription {
+    subject: String,
+    queue_group: Option<String>,
+    message_sender: mpsc::UnboundedSender<TransportMessage>,
+}
+
+#[derive(Debug, Default)]
+struct ClientState {
+    subscriptions: HashMap<u64, ClientSubscription>,
+    /// Requests waiting for response by reply subject
+    pending_requests: HashMap<String, oneshot::Sender<Result<TransportMessage, TransportError>>>,
+    /// Subscriptions waiting for confirmation from the broker by subscription ID
+    pending_subscriptions: HashMap<u64, oneshot::Sender<()>>,
+}
+
+/// Client of the built-in broker.
+///
+/// Connection is re-established automatically if broker was restarted, existing subscriptions are
+/// restored after reconnection. Messages published while connection is down are lost.
+#[derive(Debug, Clone)]
+pub struct BrokerClient {
+    state: Arc<Mutex<ClientState>>,
+    outgoing_sender: mpsc::UnboundedSender<ClientMessage>,
+    inbox_prefix: String,
+    next_subscription_id: Arc<AtomicU64>,
+}
+
+#[async_trait]
+impl ClusterTransport for BrokerClient {
+    fn max_payload(&self) -> usize {
+        MAX_PAYLOAD
+    }
+
+    async fn publish(
+        &self,
+        subject: String,
+        message_id: Option<String>,
+        payload: Bytes,
+    ) -> Result<(), TransportError> {
+        self.publish_internal(subject, None, message_id, payload)
+    }
+
+    fn new_inbox(&self) -> String {
+        format!("{}.{}", self.inbox_prefix, Ulid::new())
+    }
+
+    async fn request_with_inbox(
+        &self,
+        subject: String,
+        reply: String,
+        payload: Bytes,
+    ) -> Result<TransportMessage, TransportError> {
+        let (response_sender, response_receiver) = oneshot::channel();
+        self.state
+            .lock()
+            .pending_requests
+            .insert(reply.clone(), response_sender);
+
+        if let Err(error) = self.publish_internal(subject, Some(reply.clone()), None, payload) {
+            self.state.lock().pending_requests.remove(&reply);
+            return Err(error);
+        }
+
+        match tokio::time::timeout(REQUEST_TIMEOUT, response_receiver).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(_cancelled)) => Err(TransportError::ConnectionClosed),
+            Err(_elapsed) => {
+                self.state.lock().pending_requests.remove(&reply);
+                Err(TransportError::TimedOut)
+            }
+        }
+    }
+
+    async fn subscribe(
+        &self,
+        subject: String,
+        queue_group: Option<String>,
+    ) -> Result<TransportSubscription, TransportError> {
+        let id = self.next_subscription_id.fetch_add(1, Ordering::Relaxed);
+        let (message_sender, message_receiver) = mpsc::unbounded();
+        let (subscribed_sender, subscribed_receiver) = oneshot::channel();
+
+        {
+            let mut state = self.state.lock();
+            state.subscriptions.insert(
+                id,
+                ClientSubscription {
+                    subject: subject.clone(),
+                    queue_group: queue_group.clone(),
+                    message_sender,
+                },
+            );
+            state.pending_subscriptions.insert(id, subscribed_sender);
+        }
+        // Unsubscribes if dropped before confirmation is received
+        let subscription = BrokerSubscription {
+            id,
+            state: Arc::clone(&self.state),
+            outgoing_sender: self.outgoing_sender.clone(),
+            message_receiver,
+        };
+
+        if self
+            .outgoing_sender
+            .unbounded_send(ClientMessage::Subscribe {
+                id,
+                subject: subject.clone(),
+                queue_group: queue_group.clone(),
+            })
+            .is_err()
+        {
+            return Err(TransportError::ConnectionClosed);
+        }
+
+        // Subscription is restored and confirmed after reconnection in case connection is broken
+        // right now
+        match tokio::time::timeout(REQUEST_TIMEOUT, subscribed_receiver).await {
+            Ok(Ok(())) => {}
+            Ok(Err(_cancelled)) => {
+                return Err(TransportError::ConnectionClosed);
+            }
+            Err(_elapsed) => {
+                return Err(TransportError::TimedOut);
+            }
+        }
+
+        Ok(TransportSubscription::new(
+            subject,
+            queue_group,
+            subscription,
+        ))
+    }
+}
+
+impl BrokerClient {
+    /// Connect to the broker at specified address (`host:port`).
+    ///
+    /// Returns error if initial connection fails, after that connection is maintained in the
+    /// background for as long as client or any of its subscriptions exist.
+    pub async fn connect(address: String) -> io::Result<Self> {
+        let stream = TcpStream::connect(&address).await?;
+
+        let state = Arc::<Mutex<ClientState>>::default();
+        let inbox_prefix = format!("_INBOX.{}", Ulid::new());
+        let (outgoing_sender, outgoing_receiver) = mpsc::unbounded();
+
+        tokio::spawn(maintain_connection(
+            address,
+            stream,
+            inbox_prefix.clone(),
+            Arc::clone(&state),
+            outgoing_receiver,
+        ));
+
+        Ok(Self {
+            state,
+            outgoing_sender,
+            inbox_prefix,
+            next_subscription_id: Arc::new(AtomicU64::new(INBOX_SUBSCRIPTION_ID + 1)),
+        })
+    }
+
+    fn publish_internal(
+        &self,
+        subject: String,
+        reply: Option<String>,
+        message_id: Option<String>,
+        payload: Bytes,
+    ) -> Result<(), TransportError> {
+        if payload.len() > MAX_PAYLOAD {
+            return Err(TransportError::PayloadTooLarge {
+                size: payload.len(),
+                max_payload: MAX_PAYLOAD,
+            });
+        }
+
+        self.outgoing_sender
+            .unbounded_send(ClientMessage::Publish {
+                subject,
+                reply,
+                message_id,
+                payload: payload.into(),
+            })
+            .map_err(|_error| TransportError::ConnectionClosed)
+    }
+}
+
+/// Subscription that unsubscribes from the broker when dropped
+struct BrokerSubscription {
+    id: u64,
+    state: Arc<Mutex<ClientState>>,
+    outgoing_sender: mpsc::UnboundedSender<ClientMessage>,
+    message_receiver: mpsc::UnboundedReceiver<TransportMessage>,
+}
+
+impl Drop for BrokerSubscription {
+    fn drop(&mut self) {
+        {
+            let mut state = self.state.lock();
+            state.subscriptions.remove(&self.id);
+            state.pending_subscriptions.remove(&self.id);
+        }
+        // Connection might be gone already, in which case there is nothing to unsubscribe from
+        let _ = self
+            .outgoing_sender
+            .unbounded_send(ClientMessage::Unsubscribe { id: self.id });
+    }
+}
+
+impl Stream for BrokerSubscription {
+    type Item = TransportMessage;
+
+    #[inline]
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.message_receiver.poll_next_unpin(cx)
+    }
+}
+
+/// Runs connection and reconnects when it breaks, returns when all clients and subscriptions are
+/// dropped
+async fn maintain_connection(
+    address: String,
+    mut stream: TcpStream,
+    inbox_prefix: String,
+    state: Arc<Mutex<ClientState>>,
+    mut outgoing_receiver: mpsc::UnboundedReceiver<ClientMessage>,
+) {
+    loop {
+        match run_connection(stream, &inbox_prefix, &state, &mut outgoing_receiver).await {
+            Ok(()) => {
+                return;
+            }
+            Err(error) => {
+                warn!(%address, %error, "Connection to broker lost, reconnecting");
+            }
+        }
+
+        // Responses to requests in flight will never arrive
+        for (_reply, response_sender) in state.lock().pending_requests.drain() {
+            // Doesn't matter if requester still cares about it
+            let _ = response_sender.send(Err(TransportError::ConnectionClosed));
+        }
+
+        stream = loop {
+            tokio::time::sleep(RECONNECT_DELAY).await;
+
+            // Subscriptions are restored from state after reconnection, so only publications are
+            // actually lost here
+            loop {
+                match outgoing_receiver.try_next() {
+                    Ok(Some(_message)) => {
+                        trace!("Dropping message while disconnected from broker");
+                    }
+                    Ok(None) => {
+                        return;
+                    }
+                    Err(_empty) => {
+                        break;
+                    }
+                }
+            }
+
+            match TcpStream::connect(&address).await {
+                Ok(stream) => {
+                    break stream;
+                }
+                Err(error) => {
+                    debug!(%address, %error, "Failed to reconnect to broker");
+                }
+            }
+        };
+
+        info!(%address, "Reconnected to broker");
+    }
+}
+
+/// Returns `Ok(())` when all clients and subscriptions are dropped
+async fn run_connection(
+    stream: TcpStream,
+    inbox_prefix: &str,
+    state: &Mutex<ClientState>,
+    outgoing_receiver: &mut mpsc::UnboundedReceiver<ClientMessage>,
+) -> io::Result<()> {
+    stream.set_nodelay(true)?;
+    let (mut reader, mut writer) = stream.into_split();
+
+    write_message(
+        &mut writer,
+        &ClientMessage::Subscribe {
+            id: INBOX_SUBSCRIPTION_ID,
+            subject: format!("{inbox_prefix}.>"),
+            queue_group: None,
+        },
+    )
+    .await?;
+
+    // Restore existing subscriptions (relevant after reconnection)
+    let subscribe_messages = state
+        .lock()
+        .subscriptions
+        .iter()
+        .map(|(&id, subscription)| ClientMessage::Subscribe {
+            id,
+            subject: subscription.subject.clone(),
+            queue_group: subscription.queue_group.clone(),
+        })
+        .collect::<Vec<_>>();
+    for message in &subscribe_messages {
+        write_message(&mut writer, message).await?;
+    }
+
+    let writing = async move {
+        while let Some(message) = outgoing_receiver.next().await {
+            write_message(&mut writer, &message).await?;
+        }
+
+        Ok::<_, io::Error>(())
+    };
+
+    select! {
+        result = writing.fuse() => result,
+        result = read_server_messages(&mut reader, state).fuse() => result,
+    }
+}
+
+async fn read_server_messages(
+    reader: &mut OwnedReadHalf,
+    state: &Mutex<ClientState>,
+) -> io::Result<()> {
+    loop {
+        let message = read_message::<_, ServerMessage>(reader).await?;
+        let mut state = state.lock();
+
+        match message {
+            ServerMessage::Message {
+                subscription_id,
+                subject,
+                reply,
+                payload,
+            } => {
+                let message = TransportMessage {
+                    subject,
+                    reply,
+                    payload: payload.into(),
+                };
+
+                if subscription_id == INBOX_SUBSCRIPTION_ID {
+                    if let Some(response_sender) = state.pending_requests.remove(&message.subject) {
+                        // Doesn't matter if requester still cares about it
+                        let _ = response_sender.send(Ok(message));
+                    }
+                } else if let Some(subscription) = state.subscriptions.get(&subscription_id) {
+                    // Subscription might be in the process of being dropped
+                    let _ = subscription.message_sender.unbounded_send(message);
+                }
+            }
+            ServerMessage::NoResponders { reply } => {
+                if let Some(response_sender) = state.pending_requests.remove(&reply) {
+                    // Doesn't matter if requester still cares about it
+                    let _ = response_sender.send(Err(TransportError::NoResponders));
+                }
+            }
+            ServerMessage::Subscribed { id } => {
+                if let Some(subscribed_sender) = state.pending_subscriptions.remove(&id) {
+                    // Doesn't matter if subscriber still cares about it
+                    let _ = subscribed_sender.send(());
+                }
+            }
+        }
+    }
+}
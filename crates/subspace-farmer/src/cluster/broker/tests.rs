@@ -0,0 +1,227 @@
+use crate::cluster::broker::{subject_matches, BrokerClient, BrokerServer};
+use crate::cluster::nats_client::{GenericRequest, NatsClient};
+use crate::cluster::transport::{ClusterTransport, TransportError};
+use backoff::ExponentialBackoff;
+use futures::StreamExt;
+use parity_scale_codec::{Decode, Encode};
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use ulid::Ulid;
+
+/// Environment variable with address of NATS server, tests run against NATS in addition to the
+/// built-in broker when it is set
+const NATS_SERVER_ENV: &str = "SUBSPACE_TEST_NATS_SERVER";
+/// How long to wait for a message that is not expected to arrive
+const NO_MESSAGE_TIMEOUT: Duration = Duration::from_millis(100);
+
+#[derive(Debug)]
+enum TestTransport {
+    Broker { address: String },
+    Nats { address: String },
+}
+
+impl TestTransport {
+    /// Built-in broker and NATS (if configured with [`NATS_SERVER_ENV`])
+    async fn all() -> Vec<Self> {
+        let mut transports = vec![Self::Broker {
+            address: start_broker().await,
+        }];
+        if let Ok(address) = env::var(NATS_SERVER_ENV) {
+            transports.push(Self::Nats { address });
+        }
+        transports
+    }
+
+    async fn connect(&self) -> Arc<dyn ClusterTransport> {
+        match self {
+            Self::Broker { address } => {
+                Arc::new(BrokerClient::connect(address.clone()).await.unwrap())
+            }
+            Self::Nats { address } => Arc::new(async_nats::connect(address).await.unwrap()),
+        }
+    }
+}
+
+async fn start_broker() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    tokio::spawn(BrokerServer::default().run(listener));
+    address
+}
+
+#[test]
+fn subjects() {
+    assert!(subject_matches("a.b.c", "a.b.c"));
+    assert!(!subject_matches("a.b.c", "a.b"));
+    assert!(!subject_matches("a.b", "a.b.c"));
+    assert!(subject_matches("a.*.c", "a.b.c"));
+    assert!(!subject_matches("a.*", "a.b.c"));
+    assert!(subject_matches("a.>", "a.b.c"));
+    assert!(subject_matches("a.>", "a.b"));
+    assert!(!subject_matches("a.>", "a"));
+}
+
+#[tokio::test]
+async fn publish_subscribe() {
+    for transport in TestTransport::all().await {
+        publish_subscribe_with(&transport).await;
+    }
+}
+
+async fn publish_subscribe_with(transport: &TestTransport) {
+    // Unique prefix such that concurrently running tests sharing NATS server don't interfere
+    let prefix = format!("subspace-{}", Ulid::new());
+    let client_1 = transport.connect().await;
+    let client_2 = transport.connect().await;
+
+    let mut subscription_1 = client_1
+        .subscribe(format!("{prefix}.test.*"), None)
+        .await
+        .unwrap();
+    let mut subscription_2 = client_2
+        .subscribe(format!("{prefix}.>"), None)
+        .await
+        .unwrap();
+    // Queue group members share messages
+    let mut queue_subscriptions = Vec::new();
+    for client in [&client_1, &client_2] {
+        queue_subscriptions.push(
+            client
+                .subscribe(format!("{prefix}.test.a"), Some("group".to_string()))
+                .await
+                .unwrap(),
+        );
+    }
+
+    client_1
+        .publish(format!("{prefix}.test.a"), None, vec![1].into())
+        .await
+        .unwrap();
+
+    for subscription in [&mut subscription_1, &mut subscription_2] {
+        let message = subscription.next().await.unwrap();
+        assert_eq!(message.subject, format!("{prefix}.test.a"), "{transport:?}");
+        assert_eq!(message.payload.as_ref(), &[1], "{transport:?}");
+    }
+
+    let mut queue_messages = 0;
+    for subscription in &mut queue_subscriptions {
+        if tokio::time::timeout(NO_MESSAGE_TIMEOUT, subscription.next())
+            .await
+            .is_ok()
+        {
+            queue_messages += 1;
+        }
+    }
+    assert_eq!(queue_messages, 1, "{transport:?}");
+
+    // Dropping one subscription doesn't affect other subscriptions to the same subject
+    drop(subscription_1);
+    client_2
+        .publish(format!("{prefix}.test.b"), None, vec![2].into())
+        .await
+        .unwrap();
+    assert_eq!(
+        subscription_2.next().await.unwrap().payload.as_ref(),
+        &[2],
+        "{transport:?}"
+    );
+}
+
+/// NATS only de-duplicates messages with JetStream, so this is specific to the built-in broker
+#[tokio::test]
+async fn message_deduplication() {
+    let address = start_broker().await;
+    let client = BrokerClient::connect(address).await.unwrap();
+
+    let mut subscription = client
+        .subscribe("subspace.test.b".to_string(), None)
+        .await
+        .unwrap();
+
+    // Messages with the same ID are de-duplicated
+    for _ in 0..2 {
+        client
+            .publish(
+                "subspace.test.b".to_string(),
+                Some("message-id".to_string()),
+                vec![2].into(),
+            )
+            .await
+            .unwrap();
+    }
+    assert_eq!(subscription.next().await.unwrap().payload.as_ref(), &[2]);
+    assert!(
+        tokio::time::timeout(NO_MESSAGE_TIMEOUT, subscription.next())
+            .await
+            .is_err()
+    );
+}
+
+#[derive(Debug, Encode, Decode)]
+struct TestRequest(u32);
+
+impl GenericRequest for TestRequest {
+    const SUBJECT: &'static str = "subspace.test.*.request";
+    type Response = u32;
+}
+
+#[tokio::test]
+async fn request_response() {
+    for transport in TestTransport::all().await {
+        request_response_with(&transport).await;
+    }
+}
+
+async fn request_response_with(transport: &TestTransport) {
+    // Unique instance such that concurrently running tests sharing NATS server don't interfere
+    let instance = Ulid::new().to_string();
+    let client = transport.connect().await;
+
+    assert!(
+        matches!(
+            client
+                .request(format!("subspace.test.{instance}.request"), vec![].into())
+                .await,
+            Err(TransportError::NoResponders)
+        ),
+        "{transport:?}"
+    );
+
+    let responder_client =
+        NatsClient::from_transport(transport.connect().await, ExponentialBackoff::default())
+            .unwrap();
+    tokio::spawn({
+        let instance = instance.clone();
+
+        async move {
+            responder_client
+                .request_responder(
+                    Some(instance.as_str()),
+                    None,
+                    |TestRequest(value)| async move { Some(value + 1) },
+                )
+                .await
+        }
+    });
+
+    let nats_client = NatsClient::from_transport(
+        client,
+        ExponentialBackoff {
+            max_elapsed_time: None,
+            ..ExponentialBackoff::default()
+        },
+    )
+    .unwrap();
+    // Retries until responder is subscribed
+    assert_eq!(
+        nats_client
+            .request(&TestRequest(1), Some(instance.as_str()))
+            .await
+            .unwrap(),
+        2,
+        "{transport:?}"
+    );
+}
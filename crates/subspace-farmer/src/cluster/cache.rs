@@ -11,7 +11,7 @@ use crate::cluster::controller::ClusterControllerCacheIdentifyBroadcast;
 use crate::cluster::nats_client::{
     GenericBroadcast, GenericRequest, GenericStreamRequest, NatsClient, StreamRequest,
 };
-use crate::farm::{FarmError, PieceCache, PieceCacheId, PieceCacheOffset};
+use crate::farm::{FarmError, PieceCache, PieceCacheId, PieceCacheOffset, PieceCacheTier};
 use anyhow::anyhow;
 use async_trait::async_trait;
 use futures::stream::FuturesUnordered;
@@ -36,6 +36,8 @@ pub struct ClusterCacheIdentifyBroadcast {
     pub cache_id: PieceCacheId,
     /// Max number of elements in this cache
     pub max_num_elements: u32,
+    /// Performance tier of this cache
+    pub tier: PieceCacheTier,
 }
 
 impl GenericBroadcast for ClusterCacheIdentifyBroadcast {
@@ -93,6 +95,7 @@ pub struct ClusterPieceCache {
     cache_id: PieceCacheId,
     cache_id_string: String,
     max_num_elements: u32,
+    tier: PieceCacheTier,
     nats_client: NatsClient,
 }
 
@@ -107,6 +110,11 @@ impl PieceCache for ClusterPieceCache {
         self.max_num_elements
     }
 
+    #[inline]
+    fn tier(&self) -> PieceCacheTier {
+        self.tier
+    }
+
     async fn contents(
         &self,
     ) -> Result<
@@ -179,12 +187,14 @@ impl ClusterPieceCache {
     pub fn new(
         cache_id: PieceCacheId,
         max_num_elements: u32,
+        tier: PieceCacheTier,
         nats_client: NatsClient,
     ) -> ClusterPieceCache {
         Self {
             cache_id,
             cache_id_string: cache_id.to_string(),
             max_num_elements,
+            tier,
             nats_client,
         }
     }
@@ -215,7 +225,12 @@ where
             let cache_id = *cache.id();
 
             if primary_instance {
-                info!(%cache_id, max_num_elements = %cache.max_num_elements(), "Created cache");
+                info!(
+                    %cache_id,
+                    max_num_elements = %cache.max_num_elements(),
+                    tier = %cache.tier(),
+                    "Created cache"
+                );
             }
 
             CacheDetails {
@@ -336,6 +351,7 @@ async fn send_identify_broadcast<C>(
                     &ClusterCacheIdentifyBroadcast {
                         cache_id: cache.cache_id,
                         max_num_elements: cache.cache.max_num_elements(),
+                        tier: cache.cache.tier(),
                     },
                     cache_group,
                 )
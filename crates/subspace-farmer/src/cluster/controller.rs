@@ -15,7 +15,6 @@ use crate::farmer_cache::FarmerCache;
 use crate::node_client::{Error as NodeClientError, NodeClient};
 use anyhow::anyhow;
 use async_lock::Semaphore;
-use async_nats::HeaderValue;
 use async_trait::async_trait;
 use futures::{select, FutureExt, Stream, StreamExt};
 use parity_scale_codec::{Decode, Encode};
@@ -48,6 +47,36 @@ impl GenericBroadcast for ClusterControllerCacheIdentifyBroadcast {
     const SUBJECT: &'static str = "subspace.controller.*.cache-identify";
 }
 
+/// Broadcast sent by controllers requesting plotters to identify themselves
+#[derive(Debug, Copy, Clone, Encode, Decode)]
+pub struct ClusterControllerPlotterIdentifyBroadcast;
+
+impl GenericBroadcast for ClusterControllerPlotterIdentifyBroadcast {
+    const SUBJECT: &'static str = "subspace.controller.plotter-identify";
+}
+
+/// Broadcast requesting controllers to identify themselves
+#[derive(Debug, Copy, Clone, Encode, Decode)]
+pub struct ClusterControllerIdentifyRequestBroadcast;
+
+impl GenericBroadcast for ClusterControllerIdentifyRequestBroadcast {
+    const SUBJECT: &'static str = "subspace.controller.identify-request";
+}
+
+/// Broadcast with identification details by controllers
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct ClusterControllerIdentifyBroadcast {
+    /// Controller instance
+    pub instance: String,
+    /// Cache group managed by controller
+    pub cache_group: String,
+}
+
+impl GenericBroadcast for ClusterControllerIdentifyBroadcast {
+    /// `*` here stands for controller instance
+    const SUBJECT: &'static str = "subspace.controller.*.identify";
+}
+
 /// Broadcast with slot info sent by controllers
 #[derive(Debug, Clone, Encode, Decode)]
 struct ClusterControllerSlotInfoBroadcast {
@@ -58,12 +87,10 @@ struct ClusterControllerSlotInfoBroadcast {
 impl GenericBroadcast for ClusterControllerSlotInfoBroadcast {
     const SUBJECT: &'static str = "subspace.controller.slot-info";
 
-    fn deterministic_message_id(&self) -> Option<HeaderValue> {
+    fn deterministic_message_id(&self) -> Option<String> {
         // TODO: Depending on answer in `https://github.com/nats-io/nats.docs/issues/663` this might
         //  be simplified to just a slot number
-        Some(HeaderValue::from(
-            format!("slot-info-{}", self.slot_info.slot_number).as_str(),
-        ))
+        Some(format!("slot-info-{}", self.slot_info.slot_number))
     }
 }
 
@@ -86,15 +113,12 @@ struct ClusterControllerArchivedSegmentHeaderBroadcast {
 impl GenericBroadcast for ClusterControllerArchivedSegmentHeaderBroadcast {
     const SUBJECT: &'static str = "subspace.controller.archived-segment-header";
 
-    fn deterministic_message_id(&self) -> Option<HeaderValue> {
+    fn deterministic_message_id(&self) -> Option<String> {
         // TODO: Depending on answer in `https://github.com/nats-io/nats.docs/issues/663` this might
         //  be simplified to just a segment index
-        Some(HeaderValue::from(
-            format!(
-                "archived-segment-{}",
-                self.archived_segment_header.segment_index()
-            )
-            .as_str(),
+        Some(format!(
+            "archived-segment-{}",
+            self.archived_segment_header.segment_index()
         ))
     }
 }
@@ -437,6 +461,7 @@ pub async fn controller_service<NC, PG>(
     piece_getter: &PG,
     farmer_cache: &FarmerCache<ClusterCacheIndex>,
     instance: &str,
+    cache_group: &str,
     primary_instance: bool,
 ) -> anyhow::Result<()>
 where
@@ -445,6 +470,9 @@ where
 {
     if primary_instance {
         select! {
+            result = identify_responder(nats_client, instance, cache_group).fuse() => {
+                result
+            },
             result = slot_info_broadcaster(nats_client, node_client, instance).fuse() => {
                 result
             },
@@ -491,6 +519,39 @@ where
     }
 }
 
+/// Listen for controller identification broadcast and publish identification broadcast in response
+async fn identify_responder(
+    nats_client: &NatsClient,
+    instance: &str,
+    cache_group: &str,
+) -> anyhow::Result<()> {
+    let mut subscription = nats_client
+        .subscribe_to_broadcasts::<ClusterControllerIdentifyRequestBroadcast>(None, None)
+        .await
+        .map_err(|error| {
+            anyhow!("Failed to subscribe to controller identify broadcast requests: {error}")
+        })?;
+
+    while let Some(message) = subscription.next().await {
+        trace!(?message, "Controller received identify broadcast message");
+
+        if let Err(error) = nats_client
+            .broadcast(
+                &ClusterControllerIdentifyBroadcast {
+                    instance: instance.to_string(),
+                    cache_group: cache_group.to_string(),
+                },
+                instance,
+            )
+            .await
+        {
+            warn!(%error, "Failed to send controller identify broadcast");
+        }
+    }
+
+    Ok(())
+}
+
 async fn slot_info_broadcaster<NC>(
     nats_client: &NatsClient,
     node_client: &NC,
@@ -1,7 +1,9 @@
 //! NATS client
 //!
-//! [`NatsClient`] provided here is a wrapper around [`Client`] that provides convenient methods
-//! using domain-specific traits.
+//! [`NatsClient`] provided here is a wrapper around [`ClusterTransport`] that provides convenient
+//! methods using domain-specific traits. NATS [`Client`] is the primary transport, but the same
+//! NATS-style messaging can also be done through built-in broker, see
+//! [`broker`](crate::cluster::broker) module.
 //!
 //! Before reading code, make sure to familiarize yourself with NATS documentation, especially with
 //! [subjects](https://docs.nats.io/nats-concepts/subjects) and
@@ -13,14 +15,17 @@
 //! * notifications (typically targeting a particular instance of an app) and corresponding subscriptions (for example solution notification)
 //! * broadcasts and corresponding subscriptions (for example slot info broadcast)
 
+use crate::cluster::auth::AuthenticatedTransport;
+use crate::cluster::transport::{
+    ClusterTransport, TransportError, TransportMessage, TransportSubscription,
+};
 use crate::utils::AsyncJoinOnDrop;
 use anyhow::anyhow;
-use async_nats::{
-    Client, ConnectOptions, HeaderMap, HeaderValue, Message, PublishError, RequestError,
-    RequestErrorKind, Subject, SubscribeError, Subscriber, ToServerAddrs,
-};
+use async_nats::{Client, ConnectOptions, HeaderMap, RequestErrorKind, ToServerAddrs};
+use async_trait::async_trait;
 use backoff::backoff::Backoff;
 use backoff::ExponentialBackoff;
+use bytes::Bytes;
 use derive_more::{Deref, DerefMut};
 use futures::channel::mpsc;
 use futures::stream::FuturesUnordered;
@@ -44,6 +49,8 @@ const ACKNOWLEDGEMENT_TIMEOUT: Duration = Duration::from_mins(1);
 /// Requests should time out eventually, but we should set a larger timeout to allow for spikes in
 /// load to be absorbed gracefully
 const REQUEST_TIMEOUT: Duration = Duration::from_mins(5);
+/// Prefix of subjects stream responses are sent to
+pub(super) const STREAM_RESPONSE_SUBJECT_PREFIX: &str = "stream-response.";
 
 /// Generic request with associated response.
 ///
@@ -156,20 +163,31 @@ where
     pub fn new(request: Request) -> Self {
         Self {
             request,
-            response_subject: format!("stream-response.{}", Ulid::new()),
+            response_subject: format!("{STREAM_RESPONSE_SUBJECT_PREFIX}{}", Ulid::new()),
         }
     }
 }
 
+/// Request error
+#[derive(Debug, Error)]
+pub enum RequestError {
+    /// Transport error
+    #[error("Transport error: {0}")]
+    Transport(#[from] TransportError),
+    /// Failed to decode response
+    #[error("Failed to decode response: {0}")]
+    Decoding(#[from] parity_scale_codec::Error),
+}
+
 /// Stream request error
 #[derive(Debug, Error)]
 pub enum StreamRequestError {
     /// Subscribe error
     #[error("Subscribe error: {0}")]
-    Subscribe(#[from] SubscribeError),
+    Subscribe(TransportError),
     /// Publish error
     #[error("Publish error: {0}")]
-    Publish(#[from] PublishError),
+    Publish(TransportError),
 }
 
 /// Wrapper around subscription that transforms [`GenericStreamResponses<Response>`] messages into a
@@ -180,7 +198,7 @@ pub struct StreamResponseSubscriber<Response> {
     #[pin]
     #[deref]
     #[deref_mut]
-    subscriber: Subscriber,
+    subscriber: TransportSubscription,
     response_subject: String,
     buffered_responses: Option<GenericStreamResponses<Response>>,
     next_index: u32,
@@ -270,7 +288,11 @@ where
 }
 
 impl<Response> StreamResponseSubscriber<Response> {
-    fn new(subscriber: Subscriber, response_subject: String, nats_client: NatsClient) -> Self {
+    fn new(
+        subscriber: TransportSubscription,
+        response_subject: String,
+        nats_client: NatsClient,
+    ) -> Self {
         let (acknowledgement_sender, mut acknowledgement_receiver) =
             mpsc::unbounded::<(String, u32)>();
 
@@ -287,7 +309,9 @@ impl<Response> StreamResponseSubscriber<Response> {
                         "Sending stream response acknowledgement"
                     );
                     if let Err(error) = nats_client
-                        .publish(subject.clone(), index.to_le_bytes().to_vec().into())
+                        .inner
+                        .transport
+                        .publish(subject.clone(), None, index.to_le_bytes().to_vec().into())
                         .await
                     {
                         warn!(
@@ -336,7 +360,7 @@ pub trait GenericBroadcast: Encode + Decode + fmt::Debug + Send + Sync + 'static
 
     /// Deterministic message ID that is used for de-duplicating messages broadcast by different
     /// instances
-    fn deterministic_message_id(&self) -> Option<HeaderValue> {
+    fn deterministic_message_id(&self) -> Option<String> {
         None
     }
 }
@@ -348,7 +372,7 @@ pub struct SubscriberWrapper<Message> {
     #[pin]
     #[deref]
     #[deref_mut]
-    subscriber: Subscriber,
+    subscriber: TransportSubscription,
     _phantom: PhantomData<Message>,
 }
 
@@ -381,25 +405,92 @@ where
 
 #[derive(Debug)]
 struct Inner {
-    client: Client,
+    transport: Arc<dyn ClusterTransport>,
     request_retry_backoff_policy: ExponentialBackoff,
     approximate_max_message_size: usize,
     max_message_size: usize,
 }
 
-/// NATS client wrapper that can be used to interact with other Subspace-specific clients
-#[derive(Debug, Clone)]
-pub struct NatsClient {
-    inner: Arc<Inner>,
-}
+#[async_trait]
+impl ClusterTransport for Client {
+    fn max_payload(&self) -> usize {
+        self.server_info().max_payload
+    }
 
-impl Deref for NatsClient {
-    type Target = Client;
+    async fn publish(
+        &self,
+        subject: String,
+        message_id: Option<String>,
+        payload: Bytes,
+    ) -> Result<(), TransportError> {
+        let mut headers = HeaderMap::new();
+        if let Some(message_id) = message_id {
+            headers.insert("Nats-Msg-Id", message_id.as_str());
+        }
 
-    #[inline]
-    fn deref(&self) -> &Self::Target {
-        &self.inner.client
+        self.publish_with_headers(subject, headers, payload)
+            .await
+            .map_err(|error| TransportError::Other(error.to_string()))
+    }
+
+    fn new_inbox(&self) -> String {
+        Client::new_inbox(self)
+    }
+
+    async fn request_with_inbox(
+        &self,
+        subject: String,
+        inbox: String,
+        payload: Bytes,
+    ) -> Result<TransportMessage, TransportError> {
+        let request = async_nats::Request::new().inbox(inbox).payload(payload);
+        match self.send_request(subject, request).await {
+            Ok(message) => Ok(TransportMessage {
+                subject: message.subject.to_string(),
+                reply: message.reply.map(|reply| reply.to_string()),
+                payload: message.payload,
+            }),
+            Err(error) => Err(match error.kind() {
+                RequestErrorKind::TimedOut => TransportError::TimedOut,
+                RequestErrorKind::NoResponders => TransportError::NoResponders,
+                RequestErrorKind::Other => TransportError::Other(error.to_string()),
+            }),
+        }
     }
+
+    async fn subscribe(
+        &self,
+        subject: String,
+        queue_group: Option<String>,
+    ) -> Result<TransportSubscription, TransportError> {
+        let subscriber = if let Some(queue_group) = queue_group.clone() {
+            self.queue_subscribe(subject.clone(), queue_group).await
+        } else {
+            Client::subscribe(self, subject.clone()).await
+        }
+        .map_err(|error| TransportError::Other(error.to_string()))?;
+        // Server processes messages in order, so once flushed subscription is active
+        self.flush()
+            .await
+            .map_err(|error| TransportError::Other(error.to_string()))?;
+
+        Ok(TransportSubscription::new(
+            subject,
+            queue_group,
+            subscriber.map(|message| TransportMessage {
+                subject: message.subject.to_string(),
+                reply: message.reply.map(|reply| reply.to_string()),
+                payload: message.payload,
+            }),
+        ))
+    }
+}
+
+/// Client wrapper that can be used to interact with other Subspace-specific clients over NATS or
+/// other [`ClusterTransport`]
+#[derive(Debug, Clone)]
+pub struct NatsClient {
+    inner: Arc<Inner>,
 }
 
 impl NatsClient {
@@ -433,8 +524,35 @@ impl NatsClient {
             .into());
         }
 
+        Self::from_transport(Arc::new(client), request_retry_backoff_policy)
+            .map_err(|error| error.to_string().into())
+    }
+
+    /// Create new client from arbitrary transport
+    pub fn from_transport(
+        transport: Arc<dyn ClusterTransport>,
+        request_retry_backoff_policy: ExponentialBackoff,
+    ) -> anyhow::Result<Self> {
+        let max_payload = transport.max_payload();
+        if max_payload < EXPECTED_MESSAGE_SIZE {
+            return Err(anyhow!(
+                "Max payload {max_payload} is smaller than expected {EXPECTED_MESSAGE_SIZE}"
+            ));
+        }
+
+        Ok(Self::from_transport_unchecked(
+            transport,
+            request_retry_backoff_policy,
+        ))
+    }
+
+    fn from_transport_unchecked(
+        transport: Arc<dyn ClusterTransport>,
+        request_retry_backoff_policy: ExponentialBackoff,
+    ) -> Self {
+        let max_payload = transport.max_payload();
         let inner = Inner {
-            client,
+            transport,
             request_retry_backoff_policy,
             // Allow up to 90%, the rest will be wrapper data structures, etc.
             approximate_max_message_size: max_payload * 9 / 10,
@@ -442,9 +560,25 @@ impl NatsClient {
             max_message_size: max_payload,
         };
 
-        Ok(Self {
+        Self {
             inner: Arc::new(inner),
-        })
+        }
+    }
+
+    /// Create a new client on top of the same transport that authenticates all messages with
+    /// shared secret and optionally encrypts stream responses, see
+    /// [`auth`](crate::cluster::auth) module for details
+    pub fn with_authentication(&self, secret: &[u8], encrypt_stream_responses: bool) -> Self {
+        // Underlying transport was already checked, envelope overhead is small enough to not
+        // matter for expected message sizes
+        Self::from_transport_unchecked(
+            Arc::new(AuthenticatedTransport::new(
+                Arc::clone(&self.inner.transport),
+                secret,
+                encrypt_stream_responses,
+            )),
+            self.inner.request_retry_backoff_policy.clone(),
+        )
     }
 
     /// Approximate max message size (a few more bytes will not hurt), the actual limit is expected
@@ -467,7 +601,7 @@ impl NatsClient {
         let message = loop {
             match self
                 .inner
-                .client
+                .transport
                 .request(subject.clone(), request.encode().into())
                 .await
             {
@@ -475,12 +609,12 @@ impl NatsClient {
                     break message;
                 }
                 Err(error) => {
-                    match error.kind() {
-                        RequestErrorKind::TimedOut | RequestErrorKind::NoResponders => {
+                    match error {
+                        TransportError::TimedOut | TransportError::NoResponders => {
                             // Continue with retries
                         }
-                        RequestErrorKind::Other => {
-                            return Err(error);
+                        _ => {
+                            return Err(error.into());
                         }
                     }
 
@@ -502,7 +636,7 @@ impl NatsClient {
                         tokio::time::sleep(delay).await;
                         continue;
                     } else {
-                        return Err(error);
+                        return Err(error.into());
                     }
                 }
             }
@@ -518,7 +652,7 @@ impl NatsClient {
                     "Response decoding failed"
                 );
 
-                RequestErrorKind::Other
+                error
             })?;
 
         Ok(response)
@@ -555,20 +689,17 @@ impl NatsClient {
         ]);
 
         let subject = subject_with_instance(Request::SUBJECT, instance);
-        let subscription = if let Some(queue_group) = queue_group {
-            self.inner
-                .client
-                .queue_subscribe(subject, queue_group)
-                .await
-        } else {
-            self.inner.client.subscribe(subject).await
-        }
-        .map_err(|error| {
-            anyhow!(
-                "Failed to subscribe to {} requests for {instance:?}: {error}",
-                type_name::<Request>(),
-            )
-        })?;
+        let subscription = self
+            .inner
+            .transport
+            .subscribe(subject, queue_group)
+            .await
+            .map_err(|error| {
+                anyhow!(
+                    "Failed to subscribe to {} requests for {instance:?}: {error}",
+                    type_name::<Request>(),
+                )
+            })?;
 
         debug!(
             request_type = %type_name::<Request>(),
@@ -599,7 +730,7 @@ impl NatsClient {
         Ok(())
     }
 
-    async fn process_request<Request, F, OP>(&self, message: Message, process: OP)
+    async fn process_request<Request, F, OP>(&self, message: TransportMessage, process: OP)
     where
         Request: GenericRequest,
         F: Future<Output = Option<Request::Response>> + Send,
@@ -644,7 +775,11 @@ impl NatsClient {
         }
 
         if let Some(response) = process(request).await
-            && let Err(error) = self.publish(reply_subject, response.encode().into()).await
+            && let Err(error) = self
+                .inner
+                .transport
+                .publish(reply_subject, None, response.encode().into())
+                .await
         {
             warn!(
                 request_type = %type_name::<Request>(),
@@ -667,9 +802,10 @@ impl NatsClient {
 
         let subscriber = self
             .inner
-            .client
-            .subscribe(stream_request.response_subject.clone())
-            .await?;
+            .transport
+            .subscribe(stream_request.response_subject.clone(), None)
+            .await
+            .map_err(StreamRequestError::Subscribe)?;
 
         let stream_request_subject = subject_with_instance(Request::SUBJECT, instance);
         debug!(
@@ -680,9 +816,10 @@ impl NatsClient {
         );
 
         self.inner
-            .client
-            .publish(stream_request_subject, stream_request.encode().into())
-            .await?;
+            .transport
+            .publish(stream_request_subject, None, stream_request.encode().into())
+            .await
+            .map_err(StreamRequestError::Publish)?;
 
         Ok(StreamResponseSubscriber::new(
             subscriber,
@@ -707,8 +844,11 @@ impl NatsClient {
             Some(first_element) => first_element,
             None => {
                 if let Err(error) = self
+                    .inner
+                    .transport
                     .publish(
                         response_subject.clone(),
+                        None,
                         Response::<Request>::Last {
                             index: 0,
                             responses: VecDeque::new(),
@@ -735,7 +875,12 @@ impl NatsClient {
             self.approximate_max_message_size() / first_element.encoded_size();
 
         let ack_subject = format!("stream-response-ack.{}", Ulid::new());
-        let mut ack_subscription = match self.subscribe(ack_subject.clone()).await {
+        let mut ack_subscription = match self
+            .inner
+            .transport
+            .subscribe(ack_subject.clone(), None)
+            .await
+        {
             Ok(ack_subscription) => ack_subscription,
             Err(error) => {
                 warn!(
@@ -828,7 +973,9 @@ impl NatsClient {
                 );
 
                 if let Err(error) = self
-                    .publish(response_subject.clone(), encoded_response.into())
+                    .inner
+                    .transport
+                    .publish(response_subject.clone(), None, encoded_response.into())
                     .await
                 {
                     warn!(
@@ -937,14 +1084,15 @@ impl NatsClient {
         &self,
         notification: &Notification,
         instance: Option<&str>,
-    ) -> Result<(), PublishError>
+    ) -> Result<(), TransportError>
     where
         Notification: GenericNotification,
     {
         self.inner
-            .client
+            .transport
             .publish(
                 subject_with_instance(Notification::SUBJECT, instance),
+                None,
                 notification.encode().into(),
             )
             .await
@@ -955,21 +1103,15 @@ impl NatsClient {
         &self,
         message: &Broadcast,
         instance: &str,
-    ) -> Result<(), PublishError>
+    ) -> Result<(), TransportError>
     where
         Broadcast: GenericBroadcast,
     {
         self.inner
-            .client
-            .publish_with_headers(
+            .transport
+            .publish(
                 Broadcast::SUBJECT.replace('*', instance),
-                {
-                    let mut headers = HeaderMap::new();
-                    if let Some(message_id) = message.deterministic_message_id() {
-                        headers.insert("Nats-Msg-Id", message_id);
-                    }
-                    headers
-                },
+                message.deterministic_message_id(),
                 message.encode().into(),
             )
             .await
@@ -981,7 +1123,7 @@ impl NatsClient {
         &self,
         instance: Option<&str>,
         queue_group: Option<String>,
-    ) -> Result<SubscriberWrapper<StreamRequest<Request>>, SubscribeError>
+    ) -> Result<SubscriberWrapper<StreamRequest<Request>>, TransportError>
     where
         Request: GenericStreamRequest,
     {
@@ -995,7 +1137,7 @@ impl NatsClient {
         &self,
         instance: Option<&str>,
         queue_group: Option<String>,
-    ) -> Result<SubscriberWrapper<Notification>, SubscribeError>
+    ) -> Result<SubscriberWrapper<Notification>, TransportError>
     where
         Notification: GenericNotification,
     {
@@ -1009,7 +1151,7 @@ impl NatsClient {
         &self,
         instance: Option<&str>,
         queue_group: Option<String>,
-    ) -> Result<SubscriberWrapper<Broadcast>, SubscribeError>
+    ) -> Result<SubscriberWrapper<Broadcast>, TransportError>
     where
         Broadcast: GenericBroadcast,
     {
@@ -1017,6 +1159,21 @@ impl NatsClient {
             .await
     }
 
+    /// Subscription to raw messages for cases not covered by higher-level abstractions,
+    /// unsubscribes when dropped
+    pub async fn subscribe_raw(
+        &self,
+        subject: String,
+        queue_group: Option<String>,
+    ) -> Result<TransportSubscription, TransportError> {
+        self.inner.transport.subscribe(subject, queue_group).await
+    }
+
+    /// Publish raw payload for cases not covered by higher-level abstractions
+    pub async fn publish_raw(&self, subject: String, payload: Bytes) -> Result<(), TransportError> {
+        self.inner.transport.publish(subject, None, payload).await
+    }
+
     /// Simple subscription that will produce decoded messages, while skipping messages that fail to
     /// decode
     async fn simple_subscribe<Message>(
@@ -1024,21 +1181,15 @@ impl NatsClient {
         subject: &'static str,
         instance: Option<&str>,
         queue_group: Option<String>,
-    ) -> Result<SubscriberWrapper<Message>, SubscribeError>
+    ) -> Result<SubscriberWrapper<Message>, TransportError>
     where
         Message: Decode,
     {
-        let subscriber = if let Some(queue_group) = queue_group {
-            self.inner
-                .client
-                .queue_subscribe(subject_with_instance(subject, instance), queue_group)
-                .await?
-        } else {
-            self.inner
-                .client
-                .subscribe(subject_with_instance(subject, instance))
-                .await?
-        };
+        let subscriber = self
+            .inner
+            .transport
+            .subscribe(subject_with_instance(subject, instance), queue_group)
+            .await?;
         debug!(
             %subject,
             message_type = %type_name::<Message>(),
@@ -1053,10 +1204,10 @@ impl NatsClient {
     }
 }
 
-fn subject_with_instance(subject: &'static str, instance: Option<&str>) -> Subject {
+fn subject_with_instance(subject: &'static str, instance: Option<&str>) -> String {
     if let Some(instance) = instance {
-        Subject::from(subject.replace('*', instance))
+        subject.replace('*', instance)
     } else {
-        Subject::from_static(subject)
+        subject.to_string()
     }
 }
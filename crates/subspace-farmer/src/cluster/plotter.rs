@@ -5,11 +5,23 @@
 //! This module exposes some data structures for NATS communication, custom plotter
 //! implementation designed to work with cluster plotter and a service function to drive the backend
 //! part of the plotter.
+//!
+//! Requests from farmers are queued by plotters, see [`plotter_service`] for details.
+//!
+//! Plotted sectors are held by plotter until farmer confirms that the whole sector was received,
+//! such that interrupted transfer can be resumed instead of plotting the sector again.
 
+mod held_sectors;
+mod queue;
+
+use crate::cluster::controller::ClusterControllerPlotterIdentifyBroadcast;
 use crate::cluster::nats_client::{
-    GenericRequest, GenericStreamRequest, NatsClient, StreamRequest,
+    GenericBroadcast, GenericNotification, GenericRequest, GenericStreamRequest, NatsClient,
+    StreamRequest,
 };
-use crate::plotter::{Plotter, SectorPlottingProgress};
+use crate::cluster::plotter::held_sectors::{HeldSectors, HELD_SECTOR_TIMEOUT};
+use crate::cluster::plotter::queue::PlottingQueue;
+use crate::plotter::{Plotter, PlottingPriority, SectorPlottingProgress};
 use crate::utils::AsyncJoinOnDrop;
 use anyhow::anyhow;
 use async_trait::async_trait;
@@ -17,13 +29,14 @@ use backoff::backoff::Backoff;
 use backoff::ExponentialBackoff;
 use derive_more::Display;
 use event_listener_primitives::{Bag, HandlerId};
-use futures::channel::mpsc;
+use futures::channel::{mpsc, oneshot};
 use futures::stream::FuturesUnordered;
-use futures::{select, stream, FutureExt, Sink, SinkExt, StreamExt};
+use futures::{select, stream, FutureExt, Sink, SinkExt, Stream, StreamExt};
 use parity_scale_codec::{Decode, Encode};
 use std::error::Error;
 use std::future::{pending, Future};
 use std::num::NonZeroUsize;
+use std::path::Path;
 use std::pin::{pin, Pin};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
@@ -40,6 +53,10 @@ const FREE_CAPACITY_CHECK_INTERVAL: Duration = Duration::from_secs(1);
 const PING_INTERVAL: Duration = Duration::from_secs(10);
 /// Timeout after which plotter that doesn't send pings is assumed to be down
 const PING_TIMEOUT: Duration = Duration::from_mins(1);
+/// How many times farmer will try to resume interrupted sector transfer before giving up
+const SECTOR_TRANSFER_RESUME_ATTEMPTS: usize = 5;
+/// Delay between attempts to resume interrupted sector transfer
+const SECTOR_TRANSFER_RESUME_DELAY: Duration = Duration::from_secs(5);
 
 /// Type alias used for event handlers
 pub type HandlerFn3<A, B, C> = Arc<dyn Fn(&A, &B, &C) + Send + Sync + 'static>;
@@ -60,6 +77,24 @@ impl ClusterPlotterId {
     }
 }
 
+/// Broadcast with identification details by plotters
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct ClusterPlotterIdentifyBroadcast {
+    /// Plotter ID
+    pub plotter_id: String,
+    /// Whether plotter is plotting modern sectors (legacy otherwise)
+    pub modern: bool,
+    /// Whether plotter has free capacity to plot more sectors right now
+    pub has_free_capacity: bool,
+    /// Number of plot sector requests waiting in the queue
+    pub queue_depth: u32,
+}
+
+impl GenericBroadcast for ClusterPlotterIdentifyBroadcast {
+    /// `*` here stands for plotter ID
+    const SUBJECT: &'static str = "subspace.plotter.*.identify";
+}
+
 /// Request for free plotter instance
 #[derive(Debug, Clone, Encode, Decode)]
 struct ClusterPlotterFreeInstanceRequest;
@@ -90,6 +125,8 @@ enum ClusterSectorPlottingProgress {
         plotted_sector: PlottedSector,
         /// How much time it took to plot a sector
         time: Duration,
+        /// Details of sector transfer, can be used to resume interrupted transfer
+        transfer: ClusterSectorTransfer,
     },
     /// Sector chunk after finished plotting
     SectorChunk(Result<Vec<u8>, String>),
@@ -98,22 +135,74 @@ enum ClusterSectorPlottingProgress {
         /// Error message
         error: String,
     },
+    /// Request is waiting in the queue, sent periodically and serves as a ping
+    Queued {
+        /// Number of requests in the queue (including this one)
+        queue_depth: u32,
+    },
+    /// Request was handed over to another plotter with free capacity and must be sent to it
+    /// directly
+    Redirect {
+        /// Plotter to send request to
+        plotter_id: String,
+    },
 }
 
-/// Request to plot sector from plotter
+/// Request to plot sector from plotter.
+///
+/// Subject is versioned, such that plotters and farmers that encode this request differently don't
+/// misinterpret each other's requests.
 #[derive(Debug, Clone, Encode, Decode)]
 struct ClusterPlotterPlotSectorRequest {
     public_key: PublicKey,
     sector_index: SectorIndex,
     farmer_protocol_info: FarmerProtocolInfo,
     pieces_in_sector: u16,
+    priority: PlottingPriority,
+    /// Request was redirected to this plotter and must not be redirected again
+    redirected: bool,
 }
 
 impl GenericStreamRequest for ClusterPlotterPlotSectorRequest {
-    const SUBJECT: &'static str = "subspace.plotter.*.plot-sector";
+    const SUBJECT: &'static str = "subspace.plotter.*.plot-sector-v2";
     type Response = ClusterSectorPlottingProgress;
 }
 
+/// Details of plotted sector transfer from plotter to farmer
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct ClusterSectorTransfer {
+    /// Plotter that holds plotted sector
+    pub plotter_id: String,
+    /// Transfer ID
+    pub transfer_id: String,
+    /// Size of the sector in bytes
+    pub sector_size: u64,
+    /// BLAKE3 checksum of the whole sector
+    pub checksum: [u8; 32],
+}
+
+/// Request to resume interrupted sector transfer from specified offset
+#[derive(Debug, Clone, Encode, Decode)]
+struct ClusterPlotterResumeSectorTransferRequest {
+    transfer_id: String,
+    offset: u64,
+}
+
+impl GenericStreamRequest for ClusterPlotterResumeSectorTransferRequest {
+    const SUBJECT: &'static str = "subspace.plotter.*.resume-sector-transfer";
+    type Response = Result<Vec<u8>, String>;
+}
+
+/// Notification that sector was received by farmer and plotter no longer needs to hold it
+#[derive(Debug, Clone, Encode, Decode)]
+struct ClusterPlotterReleaseSectorNotification {
+    transfer_id: String,
+}
+
+impl GenericNotification for ClusterPlotterReleaseSectorNotification {
+    const SUBJECT: &'static str = "subspace.plotter.*.release-sector";
+}
+
 #[derive(Default, Debug)]
 struct Handlers {
     plotting_progress: Handler3<PublicKey, SectorIndex, SectorPlottingProgress>,
@@ -156,7 +245,7 @@ impl Plotter for ClusterPlotter {
         sector_index: SectorIndex,
         farmer_protocol_info: FarmerProtocolInfo,
         pieces_in_sector: u16,
-        _replotting: bool,
+        priority: PlottingPriority,
         mut progress_sender: mpsc::Sender<SectorPlottingProgress>,
     ) {
         let start = Instant::now();
@@ -197,6 +286,7 @@ impl Plotter for ClusterPlotter {
             sector_index,
             farmer_protocol_info,
             pieces_in_sector,
+            priority,
             progress_sender,
         )
         .await
@@ -208,7 +298,7 @@ impl Plotter for ClusterPlotter {
         sector_index: SectorIndex,
         farmer_protocol_info: FarmerProtocolInfo,
         pieces_in_sector: u16,
-        _replotting: bool,
+        priority: PlottingPriority,
         progress_sender: mpsc::Sender<SectorPlottingProgress>,
     ) -> bool {
         let start = Instant::now();
@@ -226,6 +316,7 @@ impl Plotter for ClusterPlotter {
             sector_index,
             farmer_protocol_info,
             pieces_in_sector,
+            priority,
             progress_sender,
         )
         .await;
@@ -300,12 +391,13 @@ impl ClusterPlotter {
         sector_index: SectorIndex,
         farmer_protocol_info: FarmerProtocolInfo,
         pieces_in_sector: u16,
+        priority: PlottingPriority,
         mut progress_sender: PS,
     ) where
         PS: Sink<SectorPlottingProgress> + Unpin + Send + 'static,
         PS::Error: Error,
     {
-        trace!("Starting plotting, getting plotting permit");
+        trace!(?priority, "Starting plotting");
 
         let progress_updater = ProgressUpdater {
             public_key,
@@ -316,57 +408,31 @@ impl ClusterPlotter {
         let mut retry_backoff_policy = self.retry_backoff_policy.clone();
         retry_backoff_policy.reset();
 
-        // Try to get plotter instance here first as a backpressure measure
-        let modern = self.modern;
-        let free_plotter_instance_fut = get_free_plotter_instance(
-            &self.nats_client,
-            &progress_updater,
-            &mut progress_sender,
-            &mut retry_backoff_policy,
-            modern,
-        );
-        let mut maybe_free_instance = free_plotter_instance_fut.await;
-        if maybe_free_instance.is_none() {
-            return;
-        }
-
-        trace!("Got plotting permit #1");
-
+        let mut request = ClusterPlotterPlotSectorRequest {
+            public_key,
+            sector_index,
+            farmer_protocol_info,
+            pieces_in_sector,
+            priority,
+            redirected: false,
+        };
+        // Request is sent to any plotter of the same kind first, where it is queued and might be
+        // redirected to another plotter later
+        let any_instance = if self.modern { "modern" } else { "legacy" };
         let nats_client = self.nats_client.clone();
+        // Sender is dropped once plotting has started (or failed to start), which is used as a
+        // backpressure measure below
+        let (scheduled_sender, scheduled_receiver) = oneshot::channel::<()>();
 
         let plotting_fut = async move {
-            'outer: loop {
-                // Take free instance that was found earlier if available or try to find a new one
-                let free_instance = match maybe_free_instance.take() {
-                    Some(free_instance) => free_instance,
-                    None => {
-                        let free_plotter_instance_fut = get_free_plotter_instance(
-                            &nats_client,
-                            &progress_updater,
-                            &mut progress_sender,
-                            &mut retry_backoff_policy,
-                            modern,
-                        );
-                        let Some(free_instance) = free_plotter_instance_fut.await else {
-                            break;
-                        };
-                        trace!("Got plotting permit #2");
-                        free_instance
-                    }
-                };
+            let mut maybe_scheduled_sender = Some(scheduled_sender);
+            let mut instance = any_instance.to_string();
 
+            'outer: loop {
                 let response_stream_result = nats_client
-                    .stream_request(
-                        ClusterPlotterPlotSectorRequest {
-                            public_key,
-                            sector_index,
-                            farmer_protocol_info,
-                            pieces_in_sector,
-                        },
-                        Some(&free_instance),
-                    )
+                    .stream_request(request.clone(), Some(&instance))
                     .await;
-                trace!("Subscribed to plotting notifications");
+                trace!(%instance, "Subscribed to plotting notifications");
 
                 let mut response_stream = match response_stream_result {
                     Ok(response_stream) => response_stream,
@@ -384,25 +450,40 @@ impl ClusterPlotter {
                     }
                 };
 
-                let (mut sector_sender, sector_receiver) = mpsc::channel(1);
-                let mut maybe_sector_receiver = Some(sector_receiver);
+                let mut maybe_sector_transfer = None;
                 loop {
                     match tokio::time::timeout(PING_TIMEOUT, response_stream.next()).await {
                         Ok(Some(response)) => {
+                            if !matches!(
+                                response,
+                                ClusterSectorPlottingProgress::Occupied
+                                    | ClusterSectorPlottingProgress::Queued { .. }
+                                    | ClusterSectorPlottingProgress::Redirect { .. }
+                            ) {
+                                // Request left the queue and plotting has started
+                                maybe_scheduled_sender.take();
+                            }
+
                             match process_response_notification(
-                                &start,
-                                &free_instance,
+                                &instance,
                                 &progress_updater,
                                 &mut progress_sender,
                                 &mut retry_backoff_policy,
                                 response,
-                                &mut sector_sender,
-                                &mut maybe_sector_receiver,
+                                &mut maybe_sector_transfer,
                             )
                             .await
                             {
                                 ResponseProcessingResult::Retry => {
                                     debug!("Retrying");
+                                    instance = any_instance.to_string();
+                                    request.redirected = false;
+                                    continue 'outer;
+                                }
+                                ResponseProcessingResult::Redirect { plotter_id } => {
+                                    debug!(%plotter_id, "Redirected to another plotter");
+                                    instance = plotter_id;
+                                    request.redirected = true;
                                     continue 'outer;
                                 }
                                 ResponseProcessingResult::Abort => {
@@ -420,6 +501,11 @@ impl ClusterPlotter {
                             break;
                         }
                         Err(_error) => {
+                            if maybe_sector_transfer.is_some() {
+                                debug!("Timed out receiving sector from plotter");
+                                break;
+                            }
+
                             progress_updater
                                 .update_progress_and_events(
                                     &mut progress_sender,
@@ -433,6 +519,17 @@ impl ClusterPlotter {
                     }
                 }
 
+                if let Some(sector_transfer) = maybe_sector_transfer {
+                    complete_sector_transfer(
+                        &nats_client,
+                        &start,
+                        &progress_updater,
+                        &mut progress_sender,
+                        sector_transfer,
+                    )
+                    .await;
+                }
+
                 break;
             }
 
@@ -451,84 +548,37 @@ impl ClusterPlotter {
             self.handlers
                 .plotting_progress
                 .call_simple(&public_key, &sector_index, &progress);
+
+            return;
         }
-    }
-}
 
-// Try to get free plotter instance and return `None` if it is not possible
-async fn get_free_plotter_instance<PS>(
-    nats_client: &NatsClient,
-    progress_updater: &ProgressUpdater,
-    progress_sender: &mut PS,
-    retry_backoff_policy: &mut ExponentialBackoff,
-    modern: bool,
-) -> Option<String>
-where
-    PS: Sink<SectorPlottingProgress> + Unpin + Send + 'static,
-    PS::Error: Error,
-{
-    loop {
-        match nats_client
-            .request(
-                &ClusterPlotterFreeInstanceRequest,
-                Some(if modern { "modern" } else { "legacy" }),
-            )
-            .await
-        {
-            Ok(Some(free_instance)) => {
-                return Some(free_instance);
-            }
-            Ok(None) => {
-                if let Some(delay) = retry_backoff_policy.next_backoff() {
-                    debug!("Instance was occupied, retrying #1");
-
-                    tokio::time::sleep(delay).await;
-                    continue;
-                } else {
-                    progress_updater
-                        .update_progress_and_events(
-                            progress_sender,
-                            SectorPlottingProgress::Error {
-                                error: "Instance was occupied, exiting #1".to_string(),
-                            },
-                        )
-                        .await;
-                    return None;
-                }
-            }
-            // TODO: Handle different kinds of errors differently, not all of them are
-            //  fatal
-            Err(error) => {
-                progress_updater
-                    .update_progress_and_events(
-                        progress_sender,
-                        SectorPlottingProgress::Error {
-                            error: format!("Failed to get free plotter instance: {error}"),
-                        },
-                    )
-                    .await;
-                return None;
-            }
-        };
+        // Wait for request to leave the queue of the plotter before returning
+        let _ = scheduled_receiver.await;
+        trace!("Plotting started");
     }
 }
 
 enum ResponseProcessingResult {
     Retry,
+    Redirect { plotter_id: String },
     Abort,
     Continue,
 }
 
-#[allow(clippy::too_many_arguments)]
+/// Sector that is being received from plotter
+struct SectorTransfer {
+    plotted_sector: PlottedSector,
+    transfer: ClusterSectorTransfer,
+    sector: Vec<u8>,
+}
+
 async fn process_response_notification<PS>(
-    start: &Instant,
-    free_instance: &str,
+    instance: &str,
     progress_updater: &ProgressUpdater,
     progress_sender: &mut PS,
     retry_backoff_policy: &mut ExponentialBackoff,
     response: ClusterSectorPlottingProgress,
-    sector_sender: &mut mpsc::Sender<Result<Vec<u8>, String>>,
-    maybe_sector_receiver: &mut Option<mpsc::Receiver<Result<Vec<u8>, String>>>,
+    maybe_sector_transfer: &mut Option<SectorTransfer>,
 ) -> ResponseProcessingResult
 where
     PS: Sink<SectorPlottingProgress> + Unpin + Send + 'static,
@@ -542,7 +592,7 @@ where
 
     match response {
         ClusterSectorPlottingProgress::Occupied => {
-            debug!(%free_instance, "Instance was occupied, retrying #2");
+            debug!(%instance, "Instance was occupied, retrying #2");
 
             if let Some(delay) = retry_backoff_policy.next_backoff() {
                 debug!("Instance was occupied, retrying #2");
@@ -557,6 +607,12 @@ where
         ClusterSectorPlottingProgress::Ping => {
             // Expected
         }
+        ClusterSectorPlottingProgress::Queued { queue_depth } => {
+            trace!(%instance, %queue_depth, "Waiting in plotter queue");
+        }
+        ClusterSectorPlottingProgress::Redirect { plotter_id } => {
+            return ResponseProcessingResult::Redirect { plotter_id };
+        }
         ClusterSectorPlottingProgress::Downloading => {
             if !progress_updater
                 .update_progress_and_events(progress_sender, SectorPlottingProgress::Downloading)
@@ -595,8 +651,9 @@ where
         ClusterSectorPlottingProgress::Finished {
             plotted_sector,
             time: _,
+            transfer,
         } => {
-            let Some(sector_receiver) = maybe_sector_receiver.take() else {
+            if maybe_sector_transfer.is_some() {
                 debug!("Unexpected duplicated sector plotting progress Finished");
 
                 progress_updater
@@ -609,30 +666,44 @@ where
                     )
                     .await;
                 return ResponseProcessingResult::Abort;
-            };
+            }
 
-            let progress = SectorPlottingProgress::Finished {
+            // Sector is collected and only handed over to the farm once the whole sector was
+            // received and its checksum was verified
+            *maybe_sector_transfer = Some(SectorTransfer {
                 plotted_sector,
-                // Use local time instead of reported by remote plotter
-                time: start.elapsed(),
-                sector: Box::pin(sector_receiver),
-            };
-            if !progress_updater
-                .update_progress_and_events(progress_sender, progress)
-                .await
-            {
-                return ResponseProcessingResult::Abort;
-            }
+                sector: Vec::with_capacity(transfer.sector_size as usize),
+                transfer,
+            });
 
             return ResponseProcessingResult::Continue;
         }
-        // This variant must be sent after Finished and it handled above
+        // This variant must be sent after Finished
         ClusterSectorPlottingProgress::SectorChunk(maybe_sector_chunk) => {
-            if let Err(error) = sector_sender.send(maybe_sector_chunk).await {
-                warn!(%error, "Failed to send sector chunk");
-                return ResponseProcessingResult::Abort;
-            }
-            return ResponseProcessingResult::Continue;
+            let error = match (maybe_sector_transfer.as_mut(), maybe_sector_chunk) {
+                (Some(sector_transfer), Ok(sector_chunk)) => {
+                    if sector_transfer.sector.len() + sector_chunk.len()
+                        > sector_transfer.transfer.sector_size as usize
+                    {
+                        "Received more sector bytes than expected".to_string()
+                    } else {
+                        sector_transfer.sector.extend_from_slice(&sector_chunk);
+                        return ResponseProcessingResult::Continue;
+                    }
+                }
+                (Some(_sector_transfer), Err(error)) => error,
+                (None, _) => {
+                    "Unexpected sector chunk before sector plotting progress Finished".to_string()
+                }
+            };
+
+            progress_updater
+                .update_progress_and_events(
+                    progress_sender,
+                    SectorPlottingProgress::Error { error },
+                )
+                .await;
+            return ResponseProcessingResult::Abort;
         }
         ClusterSectorPlottingProgress::Error { error } => {
             if !progress_updater
@@ -650,6 +721,122 @@ where
     ResponseProcessingResult::Continue
 }
 
+/// Resume interrupted sector transfer if necessary, verify sector checksum and hand sector over to
+/// the farm
+async fn complete_sector_transfer<PS>(
+    nats_client: &NatsClient,
+    start: &Instant,
+    progress_updater: &ProgressUpdater,
+    progress_sender: &mut PS,
+    sector_transfer: SectorTransfer,
+) where
+    PS: Sink<SectorPlottingProgress> + Unpin + Send + 'static,
+    PS::Error: Error,
+{
+    let SectorTransfer {
+        plotted_sector,
+        transfer,
+        mut sector,
+    } = sector_transfer;
+    let transfer_id = &transfer.transfer_id;
+
+    let mut attempt = 0;
+    loop {
+        if sector.len() as u64 == transfer.sector_size {
+            if blake3::hash(&sector).as_bytes() == &transfer.checksum {
+                break;
+            }
+
+            warn!(%transfer_id, "Sector checksum mismatch, restarting transfer");
+            sector.clear();
+        }
+
+        attempt += 1;
+        if attempt > SECTOR_TRANSFER_RESUME_ATTEMPTS {
+            progress_updater
+                .update_progress_and_events(
+                    progress_sender,
+                    SectorPlottingProgress::Error {
+                        error: format!(
+                            "Failed to receive sector after {SECTOR_TRANSFER_RESUME_ATTEMPTS} \
+                            attempts to resume transfer"
+                        ),
+                    },
+                )
+                .await;
+            return;
+        }
+
+        debug!(%transfer_id, offset = %sector.len(), %attempt, "Resuming sector transfer");
+
+        if let Err(error) = resume_sector_transfer(nats_client, &transfer, &mut sector).await {
+            debug!(%transfer_id, %error, "Failed to resume sector transfer");
+            tokio::time::sleep(SECTOR_TRANSFER_RESUME_DELAY).await;
+        }
+    }
+
+    if let Err(error) = nats_client
+        .notification(
+            &ClusterPlotterReleaseSectorNotification {
+                transfer_id: transfer_id.clone(),
+            },
+            Some(&transfer.plotter_id),
+        )
+        .await
+    {
+        // Not critical, plotter will remove held sector eventually anyway
+        debug!(%transfer_id, %error, "Failed to send release sector notification");
+    }
+
+    let progress = SectorPlottingProgress::Finished {
+        plotted_sector,
+        // Use local time instead of reported by remote plotter
+        time: start.elapsed(),
+        sector: Box::pin(stream::iter([Ok(sector)])),
+    };
+    progress_updater
+        .update_progress_and_events(progress_sender, progress)
+        .await;
+}
+
+/// Receive the rest of the sector from plotter, starting from the current length of the sector
+async fn resume_sector_transfer(
+    nats_client: &NatsClient,
+    transfer: &ClusterSectorTransfer,
+    sector: &mut Vec<u8>,
+) -> Result<(), String> {
+    let mut response_stream = nats_client
+        .stream_request(
+            ClusterPlotterResumeSectorTransferRequest {
+                transfer_id: transfer.transfer_id.clone(),
+                offset: sector.len() as u64,
+            },
+            Some(&transfer.plotter_id),
+        )
+        .await
+        .map_err(|error| format!("Failed make stream request: {error}"))?;
+
+    loop {
+        match tokio::time::timeout(PING_TIMEOUT, response_stream.next()).await {
+            Ok(Some(Ok(sector_chunk))) => {
+                if sector.len() + sector_chunk.len() > transfer.sector_size as usize {
+                    return Err("Received more sector bytes than expected".to_string());
+                }
+                sector.extend_from_slice(&sector_chunk);
+            }
+            Ok(Some(Err(error))) => {
+                return Err(error);
+            }
+            Ok(None) => {
+                return Ok(());
+            }
+            Err(_error) => {
+                return Err("Timed out receiving sector chunk from plotter".to_string());
+            }
+        }
+    }
+}
+
 struct ProgressUpdater {
     public_key: PublicKey,
     sector_index: SectorIndex,
@@ -683,49 +870,150 @@ impl ProgressUpdater {
     }
 }
 
+/// Plot sector request waiting in the queue of plotter service
+struct QueuedPlotSectorRequest {
+    request: ClusterPlotterPlotSectorRequest,
+    progress_sender: mpsc::Sender<SectorPlottingProgress>,
+    dequeued_sender: oneshot::Sender<Dequeued>,
+}
+
+/// What happened to plot sector request once it left the queue
+enum Dequeued {
+    /// Plotting has started on this plotter
+    Plotting,
+    /// Request was handed over to another plotter with free capacity
+    Redirected { plotter_id: String },
+}
+
 /// Create plotter service that will be processing incoming requests.
 ///
+/// Plot sector requests are sent by farmers to any plotter of the same kind, which queues them and
+/// plots sectors as capacity becomes available. Requests are served according to priority first
+/// (initial plotting, then replotting of expiring sectors, then any other replotting) and farms
+/// take turns within the same priority, such that one large farm can't starve others. While plotter
+/// is fully occupied, queued requests are handed over to other plotters that have free capacity.
+/// Requests are removed from the queue when farmer that sent them disappears.
+///
+/// Plotted sectors are held by plotter until farmer confirms that the whole sector was received,
+/// such that farmer can resume interrupted transfer from the last received chunk. Up to
+/// `max_held_sectors_memory` bytes of sectors are held in memory, the rest is written to
+/// `sector_spill_directory` if specified. Without spill directory plotter doesn't take new work
+/// while memory limit is reached.
+///
 /// Implementation is using concurrency with multiple tokio tasks, but can be started multiple times
 /// per controller instance in order to parallelize more work across threads if needed.
 pub async fn plotter_service<P>(
     nats_client: &NatsClient,
     plotter: &P,
     modern: bool,
+    sector_spill_directory: Option<&Path>,
+    max_held_sectors_memory: usize,
 ) -> anyhow::Result<()>
 where
     P: Plotter + Sync,
 {
     let plotter_id = ClusterPlotterId::new();
+    let queue = PlottingQueue::default();
+    let held_sectors =
+        HeldSectors::new(sector_spill_directory, &plotter_id, max_held_sectors_memory)
+            .map_err(|error| anyhow!("Failed to prepare sector spill directory: {error}"))?;
 
     select! {
-        result = free_instance_responder(&plotter_id, nats_client, plotter, modern).fuse() => {
+        result = identify_responder(&plotter_id, nats_client, plotter, &queue, &held_sectors, modern).fuse() => {
+            result
+        }
+        result = free_instance_responder(&plotter_id, nats_client, plotter, &queue, &held_sectors, modern).fuse() => {
             result
         }
-        result = plot_sector_responder(&plotter_id, nats_client, plotter).fuse() => {
+        result = plot_sector_responder(&plotter_id, nats_client, &queue, &held_sectors, modern).fuse() => {
             result
         }
+        result = resume_sector_transfer_responder(&plotter_id, nats_client, &held_sectors).fuse() => {
+            result
+        }
+        result = release_sector_responder(&plotter_id, nats_client, &held_sectors).fuse() => {
+            result
+        }
+        _ = held_sectors_cleanup(&held_sectors).fuse() => {
+            unreachable!("Cleanup loop never ends");
+        }
+        result = queue_processor(plotter, &queue, &held_sectors).fuse() => {
+            result
+        }
+        result = queue_redirector(&plotter_id, nats_client, plotter, &queue, &held_sectors, modern).fuse() => {
+            result
+        }
+    }
+}
+
+/// Listen for plotter identification broadcast from controller and publish identification
+/// broadcast in response
+async fn identify_responder<P>(
+    plotter_id: &ClusterPlotterId,
+    nats_client: &NatsClient,
+    plotter: &P,
+    queue: &PlottingQueue<QueuedPlotSectorRequest>,
+    held_sectors: &HeldSectors,
+    modern: bool,
+) -> anyhow::Result<()>
+where
+    P: Plotter + Sync,
+{
+    let plotter_id_string = plotter_id.to_string();
+    let mut subscription = nats_client
+        .subscribe_to_broadcasts::<ClusterControllerPlotterIdentifyBroadcast>(None, None)
+        .await
+        .map_err(|error| {
+            anyhow!("Failed to subscribe to plotter identify broadcast requests: {error}")
+        })?;
+
+    while let Some(message) = subscription.next().await {
+        trace!(?message, "Plotter received identify broadcast message");
+
+        if let Err(error) = nats_client
+            .broadcast(
+                &ClusterPlotterIdentifyBroadcast {
+                    plotter_id: plotter_id_string.clone(),
+                    modern,
+                    has_free_capacity: has_free_capacity(plotter, held_sectors).await,
+                    queue_depth: queue.len() as u32,
+                },
+                &plotter_id_string,
+            )
+            .await
+        {
+            warn!(%error, "Failed to send plotter identify broadcast");
+        }
     }
+
+    Ok(())
 }
 
 async fn free_instance_responder<P>(
     plotter_id: &ClusterPlotterId,
     nats_client: &NatsClient,
     plotter: &P,
+    queue: &PlottingQueue<QueuedPlotSectorRequest>,
+    held_sectors: &HeldSectors,
     modern: bool,
 ) -> anyhow::Result<()>
 where
     P: Plotter + Sync,
 {
+    // Plotter with queued requests will use free capacity for them first
+    let check_free_capacity =
+        || async { queue.is_empty() && has_free_capacity(plotter, held_sectors).await };
+
     loop {
-        while !plotter.has_free_capacity().await.unwrap_or_default() {
+        while !check_free_capacity().await {
             tokio::time::sleep(FREE_CAPACITY_CHECK_INTERVAL).await;
         }
 
         let mut subscription = nats_client
-            .queue_subscribe(
+            .subscribe_raw(
                 ClusterPlotterFreeInstanceRequest::SUBJECT
                     .replace('*', if modern { "modern" } else { "legacy" }),
-                "subspace.plotter".to_string(),
+                Some("subspace.plotter".to_string()),
             )
             .await
             .map_err(|error| anyhow!("Failed to subscribe to free instance requests: {error}"))?;
@@ -738,46 +1026,59 @@ where
 
             debug!(%reply_subject, "Free instance request");
 
-            let has_free_capacity = plotter.has_free_capacity().await.unwrap_or_default();
+            let has_free_capacity = check_free_capacity().await;
             let response: <ClusterPlotterFreeInstanceRequest as GenericRequest>::Response =
                 has_free_capacity.then(|| plotter_id.to_string());
 
             if let Err(error) = nats_client
-                .publish(reply_subject, response.encode().into())
+                .publish_raw(reply_subject, response.encode().into())
                 .await
             {
                 warn!(%error, "Failed to send free instance response");
             }
 
             if !has_free_capacity {
-                subscription.unsubscribe().await.map_err(|error| {
-                    anyhow!("Failed to unsubscribe from free instance requests: {error}")
-                })?;
+                // Unsubscribe (by dropping subscription) until there is free capacity again
+                break;
             }
         }
     }
 }
 
-async fn plot_sector_responder<P>(
+async fn plot_sector_responder(
     plotter_id: &ClusterPlotterId,
     nats_client: &NatsClient,
-    plotter: &P,
-) -> anyhow::Result<()>
-where
-    P: Plotter + Sync,
-{
+    queue: &PlottingQueue<QueuedPlotSectorRequest>,
+    held_sectors: &HeldSectors,
+    modern: bool,
+) -> anyhow::Result<()> {
     let plotter_id_string = plotter_id.to_string();
 
     // Initialize with pending future so it never ends
     let mut processing = FuturesUnordered::from_iter([
         Box::pin(pending()) as Pin<Box<dyn Future<Output = ()> + Send>>
     ]);
-    let subscription = nats_client
-        .subscribe_to_stream_requests(Some(&plotter_id_string), Some(plotter_id_string.clone()))
+    // Requests sent to any plotter of the same kind
+    let shared_subscription = nats_client
+        .subscribe_to_stream_requests(
+            Some(if modern { "modern" } else { "legacy" }),
+            Some("subspace.plotter".to_string()),
+        )
         .await
         .map_err(|error| anyhow!("Failed to subscribe to plot sector requests: {}", error))?;
-    debug!(?subscription, "Plot sector subscription");
-    let mut subscription = subscription.fuse();
+    debug!(?shared_subscription, "Plot sector subscription");
+    // Requests redirected to this plotter specifically
+    let direct_subscription = nats_client
+        .subscribe_to_stream_requests(Some(&plotter_id_string), Some(plotter_id_string.clone()))
+        .await
+        .map_err(|error| {
+            anyhow!(
+                "Failed to subscribe to redirected plot sector requests: {}",
+                error
+            )
+        })?;
+    debug!(?direct_subscription, "Redirected plot sector subscription");
+    let mut subscription = stream::select(shared_subscription, direct_subscription).fuse();
 
     loop {
         select! {
@@ -788,8 +1089,10 @@ where
 
                 // Create background task for concurrent processing
                 processing.push(Box::pin(process_plot_sector_request(
+                    &plotter_id_string,
                     nats_client,
-                    plotter,
+                    queue,
+                    held_sectors,
                     message,
                 )));
             }
@@ -802,54 +1105,308 @@ where
     Ok(())
 }
 
-async fn process_plot_sector_request<P>(
+async fn resume_sector_transfer_responder(
+    plotter_id: &ClusterPlotterId,
+    nats_client: &NatsClient,
+    held_sectors: &HeldSectors,
+) -> anyhow::Result<()> {
+    let plotter_id_string = plotter_id.to_string();
+
+    // Initialize with pending future so it never ends
+    let mut processing = FuturesUnordered::from_iter([
+        Box::pin(pending()) as Pin<Box<dyn Future<Output = ()> + Send>>
+    ]);
+    let mut subscription = nats_client
+        .subscribe_to_stream_requests::<ClusterPlotterResumeSectorTransferRequest>(
+            Some(&plotter_id_string),
+            Some(plotter_id_string.clone()),
+        )
+        .await
+        .map_err(|error| {
+            anyhow!(
+                "Failed to subscribe to resume sector transfer requests: {}",
+                error
+            )
+        })?
+        .fuse();
+    debug!(?subscription, "Resume sector transfer subscription");
+
+    loop {
+        select! {
+            maybe_message = subscription.next() => {
+                let Some(message) = maybe_message else {
+                    break;
+                };
+
+                let StreamRequest {
+                    request: ClusterPlotterResumeSectorTransferRequest { transfer_id, offset },
+                    response_subject,
+                } = message;
+
+                debug!(%transfer_id, %offset, "Resume sector transfer request");
+
+                // Create background task for concurrent processing
+                processing.push(Box::pin(
+                    nats_client.stream_response::<ClusterPlotterResumeSectorTransferRequest, _>(
+                        response_subject,
+                        held_sector_chunks(
+                            held_sectors.clone(),
+                            transfer_id,
+                            offset as usize,
+                            nats_client.approximate_max_message_size(),
+                        ),
+                    ),
+                ));
+            }
+            _ = processing.next() => {
+                // Nothing to do here
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn release_sector_responder(
+    plotter_id: &ClusterPlotterId,
     nats_client: &NatsClient,
+    held_sectors: &HeldSectors,
+) -> anyhow::Result<()> {
+    let plotter_id_string = plotter_id.to_string();
+
+    let mut subscription = nats_client
+        .subscribe_to_notifications::<ClusterPlotterReleaseSectorNotification>(
+            Some(&plotter_id_string),
+            None,
+        )
+        .await
+        .map_err(|error| anyhow!("Failed to subscribe to release sector notifications: {error}"))?;
+
+    while let Some(ClusterPlotterReleaseSectorNotification { transfer_id }) =
+        subscription.next().await
+    {
+        held_sectors.release(&transfer_id);
+    }
+
+    Ok(())
+}
+
+/// Periodically remove held sectors that farmers didn't release
+async fn held_sectors_cleanup(held_sectors: &HeldSectors) {
+    let mut cleanup_interval = tokio::time::interval(HELD_SECTOR_TIMEOUT / 10);
+    cleanup_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        cleanup_interval.tick().await;
+        held_sectors.remove_expired();
+    }
+}
+
+/// Stream of held sector chunks starting at specified offset, ends with an error if sector is not
+/// held (anymore) or can't be read
+fn held_sector_chunks(
+    held_sectors: HeldSectors,
+    transfer_id: String,
+    offset: usize,
+    chunk_size: usize,
+) -> impl Stream<Item = Result<Vec<u8>, String>> + Unpin + Send {
+    Box::pin(stream::unfold(Some(offset), move |maybe_offset| {
+        let held_sectors = held_sectors.clone();
+        let transfer_id = transfer_id.clone();
+
+        async move {
+            let offset = maybe_offset?;
+            let Some(size) = held_sectors.size(&transfer_id) else {
+                return Some((Err(format!("Sector {transfer_id} is not held")), None));
+            };
+            if offset == size {
+                return None;
+            }
+
+            match held_sectors.read(&transfer_id, offset, chunk_size).await {
+                Some(Ok(sector_chunk)) => {
+                    let next_offset = offset + sector_chunk.len();
+                    Some((Ok(sector_chunk), Some(next_offset)))
+                }
+                Some(Err(error)) => Some((
+                    Err(format!("Failed to read held sector {transfer_id}: {error}")),
+                    None,
+                )),
+                None => Some((Err(format!("Sector {transfer_id} is not held")), None)),
+            }
+        }
+    }))
+}
+
+/// Whether plotter can take more work, which also requires space for holding plotted sectors
+async fn has_free_capacity<P>(plotter: &P, held_sectors: &HeldSectors) -> bool
+where
+    P: Plotter + Sync,
+{
+    held_sectors.has_free_capacity() && plotter.has_free_capacity().await.unwrap_or_default()
+}
+
+/// Take requests from the queue and plot corresponding sectors as capacity becomes available
+async fn queue_processor<P>(
     plotter: &P,
-    request: StreamRequest<ClusterPlotterPlotSectorRequest>,
-) where
-    P: Plotter,
+    queue: &PlottingQueue<QueuedPlotSectorRequest>,
+    held_sectors: &HeldSectors,
+) -> anyhow::Result<()>
+where
+    P: Plotter + Sync,
 {
-    let StreamRequest {
-        request:
-            ClusterPlotterPlotSectorRequest {
-                public_key,
-                sector_index,
-                farmer_protocol_info,
-                pieces_in_sector,
-            },
-        response_subject,
-    } = request;
+    loop {
+        queue.wait_for_entries().await;
 
-    // Wrapper future just for instrumentation below
-    let inner_fut = async {
-        info!("Plot sector request");
+        // Request is only taken from the queue once it can be started, such that requests with
+        // higher priority that arrive in the meantime are served first
+        while !has_free_capacity(plotter, held_sectors).await {
+            tokio::time::sleep(FREE_CAPACITY_CHECK_INTERVAL).await;
+        }
 
-        let (progress_sender, mut progress_receiver) = mpsc::channel(1);
+        let Some(QueuedPlotSectorRequest {
+            request,
+            progress_sender,
+            dequeued_sender,
+        }) = queue.try_pop()
+        else {
+            // Requests were cancelled or redirected in the meantime
+            continue;
+        };
+
+        if dequeued_sender.send(Dequeued::Plotting).is_err() {
+            // Farmer is gone
+            continue;
+        }
 
-        if !plotter
-            .try_plot_sector(
+        let ClusterPlotterPlotSectorRequest {
+            public_key,
+            sector_index,
+            farmer_protocol_info,
+            pieces_in_sector,
+            priority,
+            redirected: _,
+        } = request;
+
+        plotter
+            .plot_sector(
                 public_key,
                 sector_index,
                 farmer_protocol_info,
                 pieces_in_sector,
-                false,
+                priority,
                 progress_sender,
             )
+            .await;
+    }
+}
+
+/// Hand over queued requests to other plotters with free capacity while this plotter is fully
+/// occupied, requests that were redirected to this plotter already are not redirected again
+async fn queue_redirector<P>(
+    plotter_id: &ClusterPlotterId,
+    nats_client: &NatsClient,
+    plotter: &P,
+    queue: &PlottingQueue<QueuedPlotSectorRequest>,
+    held_sectors: &HeldSectors,
+    modern: bool,
+) -> anyhow::Result<()>
+where
+    P: Plotter + Sync,
+{
+    let plotter_id_string = plotter_id.to_string();
+    let can_redirect =
+        |queued_request: &QueuedPlotSectorRequest| !queued_request.request.redirected;
+
+    loop {
+        queue.wait_for_entries().await;
+
+        if has_free_capacity(plotter, held_sectors).await || !queue.contains_matching(can_redirect)
+        {
+            // Queue will be processed locally
+            tokio::time::sleep(FREE_CAPACITY_CHECK_INTERVAL).await;
+            continue;
+        }
+
+        // Retries until some plotter with free capacity responds
+        let free_instance = match nats_client
+            .request(
+                &ClusterPlotterFreeInstanceRequest,
+                Some(if modern { "modern" } else { "legacy" }),
+            )
             .await
         {
-            debug!("Plotter is currently occupied and can't plot more sectors");
+            Ok(Some(free_instance)) => free_instance,
+            Ok(None) => {
+                tokio::time::sleep(FREE_CAPACITY_CHECK_INTERVAL).await;
+                continue;
+            }
+            Err(error) => {
+                debug!(%error, "Failed to find free plotter instance");
+                tokio::time::sleep(FREE_CAPACITY_CHECK_INTERVAL).await;
+                continue;
+            }
+        };
 
-            nats_client
-                .stream_response::<ClusterPlotterPlotSectorRequest, _>(
-                    response_subject,
-                    pin!(stream::once(async move {
-                        ClusterSectorPlottingProgress::Occupied
-                    })),
-                )
-                .await;
-            return;
+        if free_instance == plotter_id_string {
+            // This plotter got free capacity in the meantime
+            continue;
         }
 
+        let Some(QueuedPlotSectorRequest {
+            request,
+            dequeued_sender,
+            ..
+        }) = queue.try_pop_matching(can_redirect)
+        else {
+            continue;
+        };
+
+        debug!(
+            public_key = %request.public_key,
+            sector_index = %request.sector_index,
+            %free_instance,
+            "Redirecting queued plot sector request"
+        );
+        // Nothing to do if farmer is gone already
+        let _ = dequeued_sender.send(Dequeued::Redirected {
+            plotter_id: free_instance,
+        });
+    }
+}
+
+async fn process_plot_sector_request(
+    plotter_id: &str,
+    nats_client: &NatsClient,
+    queue: &PlottingQueue<QueuedPlotSectorRequest>,
+    held_sectors: &HeldSectors,
+    request: StreamRequest<ClusterPlotterPlotSectorRequest>,
+) {
+    let StreamRequest {
+        request,
+        response_subject,
+    } = request;
+    let public_key = request.public_key;
+    let sector_index = request.sector_index;
+    let priority = request.priority;
+
+    // Wrapper future just for instrumentation below
+    let inner_fut = async {
+        info!(?priority, "Plot sector request");
+
+        let (progress_sender, mut progress_receiver) = mpsc::channel(1);
+        let (dequeued_sender, dequeued_receiver) = oneshot::channel();
+        // Request is removed from the queue when handle is dropped
+        let _queue_entry_handle = queue.push(
+            public_key,
+            priority,
+            QueuedPlotSectorRequest {
+                request,
+                progress_sender,
+                dequeued_sender,
+            },
+        );
+
         let (mut response_proxy_sender, response_proxy_receiver) = mpsc::channel(10);
 
         let response_streaming_fut = nats_client
@@ -859,6 +1416,66 @@ async fn process_plot_sector_request<P>(
             )
             .fuse();
         let mut response_streaming_fut = pin!(response_streaming_fut);
+
+        let dequeued = {
+            // Report queue depth while waiting, which also serves as a ping
+            let queued_fut = async {
+                let mut queued_interval = tokio::time::interval(PING_INTERVAL);
+                queued_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                loop {
+                    queued_interval.tick().await;
+                    if let Err(error) = response_proxy_sender
+                        .send(ClusterSectorPlottingProgress::Queued {
+                            queue_depth: queue.len() as u32,
+                        })
+                        .await
+                    {
+                        warn!(%error, "Failed to send queued notification");
+                        return;
+                    }
+                }
+            };
+
+            select! {
+                dequeued = dequeued_receiver.fuse() => {
+                    dequeued
+                }
+                _ = queued_fut.fuse() => {
+                    return;
+                }
+                _ = response_streaming_fut => {
+                    // Stream response ends early when acknowledgements stop arriving
+                    debug!("Farmer is gone, removing plot sector request from the queue");
+                    return;
+                }
+            }
+        };
+
+        match dequeued {
+            Ok(Dequeued::Plotting) => {
+                // Continue below
+            }
+            Ok(Dequeued::Redirected { plotter_id }) => {
+                debug!(%plotter_id, "Plot sector request redirected to another plotter");
+
+                if let Err(error) = response_proxy_sender
+                    .send(ClusterSectorPlottingProgress::Redirect { plotter_id })
+                    .await
+                {
+                    warn!(%error, "Failed to send redirect notification");
+                    return;
+                }
+                response_proxy_sender.close_channel();
+                response_streaming_fut.await;
+
+                return;
+            }
+            Err(_canceled) => {
+                warn!("Plot sector request was dropped from the queue");
+                return;
+            }
+        }
+
         let progress_proxy_fut = {
             let mut response_proxy_sender = response_proxy_sender.clone();
             let approximate_max_message_size = nats_client.approximate_max_message_size();
@@ -866,6 +1483,8 @@ async fn process_plot_sector_request<P>(
             async move {
                 while let Some(progress) = progress_receiver.next().await {
                     send_publish_progress(
+                        plotter_id,
+                        held_sectors,
                         &mut response_proxy_sender,
                         progress,
                         approximate_max_message_size,
@@ -916,6 +1535,8 @@ async fn process_plot_sector_request<P>(
 }
 
 async fn send_publish_progress(
+    plotter_id: &str,
+    held_sectors: &HeldSectors,
     response_sender: &mut mpsc::Sender<ClusterSectorPlottingProgress>,
     progress: SectorPlottingProgress,
     approximate_max_message_size: usize,
@@ -931,45 +1552,78 @@ async fn send_publish_progress(
             time,
             mut sector,
         } => {
-            if let Err(error) = response_sender
-                .send(ClusterSectorPlottingProgress::Finished {
-                    plotted_sector,
-                    time,
-                })
-                .await
-            {
-                warn!(%error, "Failed to send plotting progress");
-                return;
-            }
-
+            // Collect the whole sector first, such that it can be held until farmer receives it
+            let mut sector_bytes = Vec::new();
             while let Some(maybe_sector_chunk) = sector.next().await {
                 match maybe_sector_chunk {
                     Ok(sector_chunk) => {
-                        // Slice large chunks into smaller ones before publishing
-                        for sector_chunk in sector_chunk.chunks(approximate_max_message_size) {
-                            if let Err(error) = response_sender
-                                .send(ClusterSectorPlottingProgress::SectorChunk(Ok(
-                                    sector_chunk.to_vec()
-                                )))
-                                .await
-                            {
-                                warn!(%error, "Failed to send plotting progress");
-                                return;
-                            }
-                        }
+                        sector_bytes.extend_from_slice(&sector_chunk);
                     }
                     Err(error) => {
                         if let Err(error) = response_sender
-                            .send(ClusterSectorPlottingProgress::SectorChunk(Err(error)))
+                            .send(ClusterSectorPlottingProgress::Error { error })
                             .await
                         {
                             warn!(%error, "Failed to send plotting progress");
-                            return;
                         }
+                        return;
                     }
                 }
             }
 
+            let sector_size = sector_bytes.len();
+            let checksum = *blake3::hash(&sector_bytes).as_bytes();
+            let transfer_id = match held_sectors.insert(sector_bytes).await {
+                Ok(transfer_id) => transfer_id,
+                Err(error) => {
+                    if let Err(error) = response_sender
+                        .send(ClusterSectorPlottingProgress::Error {
+                            error: format!("Failed to hold plotted sector: {error}"),
+                        })
+                        .await
+                    {
+                        warn!(%error, "Failed to send plotting progress");
+                    }
+                    return;
+                }
+            };
+
+            if let Err(error) = response_sender
+                .send(ClusterSectorPlottingProgress::Finished {
+                    plotted_sector,
+                    time,
+                    transfer: ClusterSectorTransfer {
+                        plotter_id: plotter_id.to_string(),
+                        transfer_id: transfer_id.clone(),
+                        sector_size: sector_size as u64,
+                        checksum,
+                    },
+                })
+                .await
+            {
+                warn!(%error, "Failed to send plotting progress");
+                return;
+            }
+
+            let mut sector_chunks = held_sector_chunks(
+                held_sectors.clone(),
+                transfer_id,
+                0,
+                approximate_max_message_size,
+            );
+            while let Some(maybe_sector_chunk) = sector_chunks.next().await {
+                if let Err(error) = response_sender
+                    .send(ClusterSectorPlottingProgress::SectorChunk(
+                        maybe_sector_chunk,
+                    ))
+                    .await
+                {
+                    // Farmer can still resume the transfer
+                    warn!(%error, "Failed to send plotting progress");
+                    return;
+                }
+            }
+
             response_sender.close_channel();
 
             return;
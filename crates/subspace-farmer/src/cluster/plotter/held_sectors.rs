@@ -0,0 +1,388 @@
+//! Plotted sectors held by plotter while they are transferred to farmers
+//!
+//! Plotter keeps plotted sector until farmer confirms that the whole sector was received and its
+//! checksum matches, such that interrupted transfer can be resumed from the last received chunk
+//! instead of plotting the sector again. Sectors are held in memory up to configured limit, after
+//! which they are written to spill directory if specified. Without spill directory plotter doesn't
+//! take new work while memory limit is reached. Sectors that farmer didn't release are removed
+//! after [`HELD_SECTOR_TIMEOUT`] of inactivity.
+//!
+//! Spill directory can be shared by multiple plotter instances, each instance writes sectors into
+//! its own subdirectory named after plotter ID and holds exclusive lock on `<plotter-id>.lock` file
+//! next to it. Subdirectories whose lock is not held by anyone are left by previous runs and are
+//! removed on startup.
+
+#[cfg(test)]
+mod tests;
+
+use crate::cluster::plotter::ClusterPlotterId;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::{fs, io};
+use subspace_farmer_components::file_ext::FileExt;
+use tokio::runtime::Handle;
+use tokio::task;
+use tracing::{debug, warn};
+use ulid::Ulid;
+
+/// Sector that wasn't accessed for this long is removed
+pub(super) const HELD_SECTOR_TIMEOUT: Duration = Duration::from_mins(10);
+/// Extension of files with spilled sectors
+const SPILLED_SECTOR_EXTENSION: &str = "sector";
+/// Extension of lock files of plotter instances
+const LOCK_FILE_EXTENSION: &str = "lock";
+
+#[derive(Debug)]
+enum HeldSectorData {
+    Memory(Arc<Vec<u8>>),
+    File { file: Arc<File>, path: PathBuf },
+}
+
+#[derive(Debug)]
+struct HeldSector {
+    data: HeldSectorData,
+    size: usize,
+    last_access: Instant,
+}
+
+impl Drop for HeldSector {
+    fn drop(&mut self) {
+        let HeldSectorData::File { path, .. } = &self.data else {
+            return;
+        };
+        let path = path.clone();
+        let remove_file = move || match fs::remove_file(&path) {
+            Ok(()) => {}
+            // Instance directory might have been removed already
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+            Err(error) => {
+                warn!(%error, path = %path.display(), "Failed to remove spilled sector file");
+            }
+        };
+
+        // Sectors are dropped while holding the lock, so file is removed in the background
+        if let Ok(handle) = Handle::try_current() {
+            handle.spawn_blocking(remove_file);
+        } else {
+            remove_file();
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    sectors: HashMap<String, HeldSector>,
+    /// Total size of sectors held in memory
+    memory_used: usize,
+}
+
+impl State {
+    fn remove(&mut self, transfer_id: &str) -> Option<HeldSector> {
+        let held_sector = self.sectors.remove(transfer_id)?;
+        if let HeldSectorData::Memory(_) = &held_sector.data {
+            self.memory_used -= held_sector.size;
+        }
+
+        Some(held_sector)
+    }
+}
+
+/// Spill directory of this plotter instance
+#[derive(Debug)]
+struct InstanceDirectory {
+    path: PathBuf,
+    lock_file_path: PathBuf,
+    /// Exclusive lock is held for the lifetime of the instance
+    lock_file: Option<File>,
+}
+
+impl Drop for InstanceDirectory {
+    fn drop(&mut self) {
+        if let Err(error) = fs::remove_dir_all(&self.path) {
+            warn!(%error, path = %self.path.display(), "Failed to remove sector spill directory");
+        }
+        // Lock file is closed before removal since open files can't be removed on Windows
+        drop(self.lock_file.take());
+        if let Err(error) = fs::remove_file(&self.lock_file_path) {
+            warn!(
+                %error,
+                path = %self.lock_file_path.display(),
+                "Failed to remove sector spill directory lock file"
+            );
+        }
+    }
+}
+
+impl InstanceDirectory {
+    fn create(spill_directory: &Path, plotter_id: &ClusterPlotterId) -> io::Result<Self> {
+        let path = spill_directory.join(plotter_id.to_string());
+        let lock_file_path = path.with_extension(LOCK_FILE_EXTENSION);
+
+        // Lock is acquired before directory is created, such that other instances never see
+        // directory of a live instance without a lock
+        let lock_file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&lock_file_path)?;
+        fs4::FileExt::try_lock_exclusive(&lock_file)?;
+        fs::create_dir(&path)?;
+
+        Ok(Self {
+            path,
+            lock_file_path,
+            lock_file: Some(lock_file),
+        })
+    }
+}
+
+#[derive(Debug)]
+struct Inner {
+    spill_directory: Option<InstanceDirectory>,
+    max_memory: usize,
+    state: Mutex<State>,
+}
+
+/// Plotted sectors held by plotter, see module-level documentation for details
+#[derive(Debug, Clone)]
+pub(super) struct HeldSectors {
+    inner: Arc<Inner>,
+}
+
+impl HeldSectors {
+    /// Create new instance that holds up to `max_memory` bytes of sectors in memory, and the rest
+    /// in `spill_directory` if specified.
+    ///
+    /// Sectors left in `spill_directory` by previous runs are removed, sectors of other running
+    /// instances are not touched.
+    pub(super) fn new(
+        spill_directory: Option<&Path>,
+        plotter_id: &ClusterPlotterId,
+        max_memory: usize,
+    ) -> io::Result<Self> {
+        let spill_directory = spill_directory
+            .map(|spill_directory| {
+                remove_stale_sectors(spill_directory)?;
+                InstanceDirectory::create(spill_directory, plotter_id)
+            })
+            .transpose()?;
+
+        Ok(Self {
+            inner: Arc::new(Inner {
+                spill_directory,
+                max_memory,
+                state: Mutex::default(),
+            }),
+        })
+    }
+
+    /// Whether there is space for more sectors, plotter shouldn't take new work otherwise
+    pub(super) fn has_free_capacity(&self) -> bool {
+        self.inner.spill_directory.is_some()
+            || self.inner.state.lock().memory_used < self.inner.max_memory
+    }
+
+    /// Hold sector, returns transfer ID that can be used to read it later.
+    ///
+    /// Sector is always accepted, memory limit may be exceeded without spill directory since the
+    /// sector was plotted already (see [`Self::has_free_capacity()`]).
+    pub(super) async fn insert(&self, sector: Vec<u8>) -> io::Result<String> {
+        let transfer_id = Ulid::new().to_string();
+        let size = sector.len();
+
+        let spill_directory = {
+            let mut state = self.inner.state.lock();
+
+            match &self.inner.spill_directory {
+                Some(spill_directory) if state.memory_used + size > self.inner.max_memory => {
+                    Some(&spill_directory.path)
+                }
+                _ => {
+                    // Reserve memory right away, such that concurrent insertions respect the limit
+                    state.memory_used += size;
+                    None
+                }
+            }
+        };
+
+        let data = match spill_directory {
+            Some(spill_directory) => {
+                let path =
+                    spill_directory.join(format!("{transfer_id}.{SPILLED_SECTOR_EXTENSION}"));
+                let file = task::spawn_blocking({
+                    let path = path.clone();
+
+                    move || {
+                        let file = OpenOptions::new()
+                            .read(true)
+                            .write(true)
+                            .create_new(true)
+                            .open(&path)?;
+                        if let Err(error) = file.write_all_at(&sector, 0) {
+                            let _ = fs::remove_file(&path);
+                            return Err(error);
+                        }
+
+                        Ok::<_, io::Error>(file)
+                    }
+                })
+                .await
+                .map_err(|error| {
+                    io::Error::other(format!("Failed to spawn blocking tokio task: {error}"))
+                })??;
+
+                HeldSectorData::File {
+                    file: Arc::new(file),
+                    path,
+                }
+            }
+            None => HeldSectorData::Memory(Arc::new(sector)),
+        };
+
+        self.inner.state.lock().sectors.insert(
+            transfer_id.clone(),
+            HeldSector {
+                data,
+                size,
+                last_access: Instant::now(),
+            },
+        );
+
+        Ok(transfer_id)
+    }
+
+    /// Size of held sector, `None` if sector is not held (anymore)
+    pub(super) fn size(&self, transfer_id: &str) -> Option<usize> {
+        self.inner
+            .state
+            .lock()
+            .sectors
+            .get(transfer_id)
+            .map(|held_sector| held_sector.size)
+    }
+
+    /// Read part of held sector, `None` if sector is not held (anymore)
+    pub(super) async fn read(
+        &self,
+        transfer_id: &str,
+        offset: usize,
+        len: usize,
+    ) -> Option<io::Result<Vec<u8>>> {
+        let data = {
+            let mut state = self.inner.state.lock();
+            let held_sector = state.sectors.get_mut(transfer_id)?;
+            held_sector.last_access = Instant::now();
+
+            if offset > held_sector.size {
+                return Some(Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "Offset {offset} is larger than sector size {}",
+                        held_sector.size
+                    ),
+                )));
+            }
+            let len = len.min(held_sector.size - offset);
+
+            match &held_sector.data {
+                HeldSectorData::Memory(sector) => {
+                    return Some(Ok(sector[offset..][..len].to_vec()));
+                }
+                HeldSectorData::File { file, .. } => (Arc::clone(file), len),
+            }
+        };
+
+        let (file, len) = data;
+        let result = task::spawn_blocking(move || {
+            let mut buffer = vec![0; len];
+            file.read_exact_at(&mut buffer, offset as u64)
+                .map(|()| buffer)
+        })
+        .await
+        .map_err(|error| io::Error::other(format!("Failed to spawn blocking tokio task: {error}")))
+        .and_then(|result| result);
+
+        Some(result)
+    }
+
+    /// Release sector once farmer has received it
+    pub(super) fn release(&self, transfer_id: &str) {
+        if self.inner.state.lock().remove(transfer_id).is_some() {
+            debug!(%transfer_id, "Released held sector");
+        }
+    }
+
+    /// Remove sectors that were not accessed for [`HELD_SECTOR_TIMEOUT`]
+    pub(super) fn remove_expired(&self) {
+        let mut state = self.inner.state.lock();
+        let expired = state
+            .sectors
+            .iter()
+            .filter(|(_transfer_id, held_sector)| {
+                held_sector.last_access.elapsed() >= HELD_SECTOR_TIMEOUT
+            })
+            .map(|(transfer_id, _held_sector)| transfer_id.clone())
+            .collect::<Vec<_>>();
+
+        for transfer_id in expired {
+            debug!(%transfer_id, "Removing expired held sector");
+            state.remove(&transfer_id);
+        }
+    }
+}
+
+/// Remove sectors left in spill directory by instances that are no longer running, for example
+/// after plotter crash
+fn remove_stale_sectors(spill_directory: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(spill_directory)? {
+        let lock_file_path = entry?.path();
+        if lock_file_path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            != Some(LOCK_FILE_EXTENSION)
+        {
+            continue;
+        }
+
+        let lock_file = match OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&lock_file_path)
+        {
+            Ok(lock_file) => lock_file,
+            // Another plotter instance might have removed it already
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                continue;
+            }
+            Err(error) => {
+                return Err(error);
+            }
+        };
+        if fs4::FileExt::try_lock_exclusive(&lock_file).is_err() {
+            // Instance is still running
+            continue;
+        }
+
+        let path = lock_file_path.with_extension("");
+        debug!(path = %path.display(), "Removing stale sector spill directory");
+        let result = fs::remove_dir_all(&path);
+        // Lock file is closed before removal since open files can't be removed on Windows
+        drop(lock_file);
+        for result in [result, fs::remove_file(&lock_file_path)] {
+            match result {
+                Ok(()) => {}
+                // Another plotter instance might have removed it already
+                Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+                Err(error) => {
+                    return Err(error);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
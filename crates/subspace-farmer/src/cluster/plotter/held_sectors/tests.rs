@@ -0,0 +1,162 @@
+use crate::cluster::plotter::held_sectors::HeldSectors;
+use crate::cluster::plotter::ClusterPlotterId;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use tempfile::tempdir;
+
+const SECTOR_SIZE: usize = 10_000;
+
+fn sector() -> Vec<u8> {
+    (0..=u8::MAX).cycle().take(SECTOR_SIZE).collect()
+}
+
+async fn read_and_release(held_sectors: &HeldSectors) {
+    let sector = sector();
+
+    let transfer_id = held_sectors.insert(sector.clone()).await.unwrap();
+    assert_eq!(held_sectors.size(&transfer_id), Some(sector.len()));
+
+    assert_eq!(
+        held_sectors
+            .read(&transfer_id, 1_000, 500)
+            .await
+            .unwrap()
+            .unwrap(),
+        &sector[1_000..1_500]
+    );
+    // Reads past the end are truncated
+    assert_eq!(
+        held_sectors
+            .read(&transfer_id, 9_000, 5_000)
+            .await
+            .unwrap()
+            .unwrap(),
+        &sector[9_000..]
+    );
+    assert!(held_sectors
+        .read(&transfer_id, 10_001, 1)
+        .await
+        .unwrap()
+        .is_err());
+
+    held_sectors.release(&transfer_id);
+    assert_eq!(held_sectors.size(&transfer_id), None);
+    assert!(held_sectors.read(&transfer_id, 0, 1).await.is_none());
+}
+
+/// Number of spilled sectors across all instance directories
+fn spilled_sectors(spill_directory: &Path) -> usize {
+    spill_directory
+        .read_dir()
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.is_dir())
+        .map(|path| path.read_dir().unwrap().count())
+        .sum()
+}
+
+/// Spilled sector files are removed in the background
+async fn wait_for_no_spilled_sectors(spill_directory: &Path) {
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while spilled_sectors(spill_directory) > 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("Spilled sectors must be removed");
+}
+
+#[tokio::test]
+async fn memory() {
+    read_and_release(&HeldSectors::new(None, &ClusterPlotterId::new(), usize::MAX).unwrap()).await;
+}
+
+#[tokio::test]
+async fn spill_to_disk() {
+    let spill_directory = tempdir().unwrap();
+
+    let held_sectors =
+        HeldSectors::new(Some(spill_directory.path()), &ClusterPlotterId::new(), 0).unwrap();
+    read_and_release(&held_sectors).await;
+
+    wait_for_no_spilled_sectors(spill_directory.path()).await;
+
+    // Instance directory and lock file are removed on drop
+    drop(held_sectors);
+    assert_eq!(spill_directory.path().read_dir().unwrap().count(), 0);
+}
+
+#[tokio::test]
+async fn memory_limit() {
+    // Without spill directory plotter stops taking work once limit is reached, but sectors that
+    // were plotted already are still accepted
+    let held_sectors = HeldSectors::new(None, &ClusterPlotterId::new(), SECTOR_SIZE + 1).unwrap();
+    assert!(held_sectors.has_free_capacity());
+    let transfer_id_1 = held_sectors.insert(sector()).await.unwrap();
+    assert!(held_sectors.has_free_capacity());
+    let transfer_id_2 = held_sectors.insert(sector()).await.unwrap();
+    assert!(!held_sectors.has_free_capacity());
+    held_sectors.release(&transfer_id_1);
+    assert!(held_sectors.has_free_capacity());
+    held_sectors.release(&transfer_id_2);
+
+    // With spill directory sectors that don't fit into memory are written to disk
+    let spill_directory = tempdir().unwrap();
+    let held_sectors = HeldSectors::new(
+        Some(spill_directory.path()),
+        &ClusterPlotterId::new(),
+        SECTOR_SIZE,
+    )
+    .unwrap();
+    let transfer_id_1 = held_sectors.insert(sector()).await.unwrap();
+    assert_eq!(spilled_sectors(spill_directory.path()), 0);
+    let transfer_id_2 = held_sectors.insert(sector()).await.unwrap();
+    assert_eq!(spilled_sectors(spill_directory.path()), 1);
+    assert!(held_sectors.has_free_capacity());
+    assert_eq!(
+        held_sectors
+            .read(&transfer_id_2, 0, SECTOR_SIZE)
+            .await
+            .unwrap()
+            .unwrap(),
+        sector()
+    );
+
+    held_sectors.release(&transfer_id_1);
+    held_sectors.release(&transfer_id_2);
+    wait_for_no_spilled_sectors(spill_directory.path()).await;
+}
+
+#[tokio::test]
+async fn stale_spilled_sectors_are_removed() {
+    let spill_directory = tempdir().unwrap();
+    // Left by instance that is no longer running
+    let stale_directory = spill_directory.path().join("stale");
+    fs::create_dir(&stale_directory).unwrap();
+    fs::write(stale_directory.join("stale.sector"), sector()).unwrap();
+    fs::write(spill_directory.path().join("stale.lock"), []).unwrap();
+    let unrelated_file = spill_directory.path().join("unrelated.bin");
+    fs::write(&unrelated_file, [1, 2, 3]).unwrap();
+
+    let running_held_sectors =
+        HeldSectors::new(Some(spill_directory.path()), &ClusterPlotterId::new(), 0).unwrap();
+    let transfer_id = running_held_sectors.insert(sector()).await.unwrap();
+
+    assert!(!stale_directory.exists());
+    assert!(!spill_directory.path().join("stale.lock").exists());
+    assert!(unrelated_file.exists());
+
+    // Sectors of running instance sharing spill directory are not touched
+    let _held_sectors =
+        HeldSectors::new(Some(spill_directory.path()), &ClusterPlotterId::new(), 0).unwrap();
+    assert_eq!(spilled_sectors(spill_directory.path()), 1);
+    assert_eq!(
+        running_held_sectors
+            .read(&transfer_id, 0, SECTOR_SIZE)
+            .await
+            .unwrap()
+            .unwrap(),
+        sector()
+    );
+}
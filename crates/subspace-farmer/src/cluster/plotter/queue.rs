@@ -0,0 +1,262 @@
+//! Fair queue of plotting requests
+//!
+//! Requests are grouped by priority first (see [`PlottingPriority`]) and requests of higher
+//! priority are always served first. Within the same priority farms (identified by public key)
+//! take turns in round-robin fashion, such that a farm with many queued sectors can't starve farms
+//! with fewer sectors queued.
+
+#[cfg(test)]
+mod tests;
+
+use crate::plotter::PlottingPriority;
+use parking_lot::Mutex;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use subspace_core_primitives::PublicKey;
+use tokio::sync::Notify;
+
+#[derive(Debug)]
+struct PriorityLevel<T> {
+    /// Farms in the order in which they will get their turn
+    farms: VecDeque<PublicKey>,
+    /// Queued entries of each farm in arrival order
+    entries: HashMap<PublicKey, VecDeque<(u64, T)>>,
+}
+
+impl<T> Default for PriorityLevel<T> {
+    fn default() -> Self {
+        Self {
+            farms: VecDeque::new(),
+            entries: HashMap::new(),
+        }
+    }
+}
+
+impl<T> PriorityLevel<T> {
+    /// Pop the first entry of the first farm in rotation that has entries matching `predicate`
+    fn pop_matching<P>(&mut self, predicate: &P) -> Option<T>
+    where
+        P: Fn(&T) -> bool,
+    {
+        let (farm_position, entry_position) =
+            self.farms
+                .iter()
+                .enumerate()
+                .find_map(|(farm_position, public_key)| {
+                    self.entries
+                        .get(public_key)
+                        .expect("Farm is only present in rotation when it has entries; qed")
+                        .iter()
+                        .position(|(_id, value)| predicate(value))
+                        .map(|entry_position| (farm_position, entry_position))
+                })?;
+        let public_key = self
+            .farms
+            .remove(farm_position)
+            .expect("Farm position was just found; qed");
+        let farm_entries = self
+            .entries
+            .get_mut(&public_key)
+            .expect("Farm is only present in rotation when it has entries; qed");
+        let (_id, value) = farm_entries
+            .remove(entry_position)
+            .expect("Entry position was just found; qed");
+
+        if farm_entries.is_empty() {
+            self.entries.remove(&public_key);
+        } else {
+            // Go to the end of the line
+            self.farms.push_back(public_key);
+        }
+
+        Some(value)
+    }
+
+    fn remove(&mut self, public_key: &PublicKey, id: u64) -> bool {
+        let Some(farm_entries) = self.entries.get_mut(public_key) else {
+            return false;
+        };
+        let Some(position) = farm_entries
+            .iter()
+            .position(|(entry_id, _)| *entry_id == id)
+        else {
+            return false;
+        };
+
+        farm_entries.remove(position);
+        if farm_entries.is_empty() {
+            self.entries.remove(public_key);
+            self.farms.retain(|farm| farm != public_key);
+        }
+
+        true
+    }
+}
+
+#[derive(Debug)]
+struct State<T> {
+    levels: [PriorityLevel<T>; PlottingPriority::ALL.len()],
+    len: usize,
+    next_id: u64,
+}
+
+impl<T> Default for State<T> {
+    fn default() -> Self {
+        Self {
+            levels: Default::default(),
+            len: 0,
+            next_id: 0,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Inner<T> {
+    state: Mutex<State<T>>,
+    notify: Notify,
+}
+
+/// Fair queue of plotting requests, see module-level documentation for details
+#[derive(Debug)]
+pub(super) struct PlottingQueue<T> {
+    inner: Arc<Inner<T>>,
+}
+
+impl<T> Clone for PlottingQueue<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<T> Default for PlottingQueue<T> {
+    fn default() -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                state: Mutex::default(),
+                notify: Notify::new(),
+            }),
+        }
+    }
+}
+
+impl<T> PlottingQueue<T> {
+    /// Add entry to the queue, entry is removed from the queue if returned handle is dropped before
+    /// entry was popped
+    pub(super) fn push(
+        &self,
+        public_key: PublicKey,
+        priority: PlottingPriority,
+        value: T,
+    ) -> QueueEntryHandle<T> {
+        let id = {
+            let mut state = self.inner.state.lock();
+            let id = state.next_id;
+            state.next_id += 1;
+            state.len += 1;
+
+            let level = &mut state.levels[priority_index(priority)];
+            let farm_entries = level.entries.entry(public_key).or_default();
+            if farm_entries.is_empty() {
+                level.farms.push_back(public_key);
+            }
+            farm_entries.push_back((id, value));
+
+            id
+        };
+        self.inner.notify.notify_waiters();
+
+        QueueEntryHandle {
+            queue: self.clone(),
+            public_key,
+            priority,
+            id,
+        }
+    }
+
+    /// Take the next entry from the queue if there is any
+    pub(super) fn try_pop(&self) -> Option<T> {
+        self.try_pop_matching(|_value| true)
+    }
+
+    /// Take the next entry matching `predicate` from the queue if there is any, entries that don't
+    /// match are skipped and stay in the queue
+    pub(super) fn try_pop_matching<P>(&self, predicate: P) -> Option<T>
+    where
+        P: Fn(&T) -> bool,
+    {
+        let mut state = self.inner.state.lock();
+        let value = state
+            .levels
+            .iter_mut()
+            .find_map(|level| level.pop_matching(&predicate))?;
+        state.len -= 1;
+
+        Some(value)
+    }
+
+    /// Whether queue contains entries matching `predicate`
+    pub(super) fn contains_matching<P>(&self, predicate: P) -> bool
+    where
+        P: Fn(&T) -> bool,
+    {
+        self.inner.state.lock().levels.iter().any(|level| {
+            level
+                .entries
+                .values()
+                .flatten()
+                .any(|(_id, value)| predicate(value))
+        })
+    }
+
+    /// Wait until queue has at least one entry
+    pub(super) async fn wait_for_entries(&self) {
+        loop {
+            let notified = self.inner.notify.notified();
+            if !self.is_empty() {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    /// Number of queued entries
+    pub(super) fn len(&self) -> usize {
+        self.inner.state.lock().len
+    }
+
+    /// Whether queue is empty
+    pub(super) fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn remove(&self, public_key: &PublicKey, priority: PlottingPriority, id: u64) {
+        let mut state = self.inner.state.lock();
+        if state.levels[priority_index(priority)].remove(public_key, id) {
+            state.len -= 1;
+        }
+    }
+}
+
+/// Handle of queued entry, removes entry from the queue (if it is still there) on drop
+#[derive(Debug)]
+pub(super) struct QueueEntryHandle<T> {
+    queue: PlottingQueue<T>,
+    public_key: PublicKey,
+    priority: PlottingPriority,
+    id: u64,
+}
+
+impl<T> Drop for QueueEntryHandle<T> {
+    fn drop(&mut self) {
+        self.queue.remove(&self.public_key, self.priority, self.id);
+    }
+}
+
+fn priority_index(priority: PlottingPriority) -> usize {
+    PlottingPriority::ALL
+        .iter()
+        .position(|&candidate| candidate == priority)
+        .expect("All priorities are listed; qed")
+}
@@ -0,0 +1,119 @@
+use crate::cluster::plotter::queue::PlottingQueue;
+use crate::plotter::PlottingPriority;
+use std::time::Duration;
+use subspace_core_primitives::PublicKey;
+
+#[test]
+fn priorities_and_fairness() {
+    let queue = PlottingQueue::default();
+    let large_farm = PublicKey::from([1; 32]);
+    let small_farm = PublicKey::from([2; 32]);
+
+    let mut handles = Vec::new();
+    for sector_index in 0..3 {
+        handles.push(queue.push(
+            large_farm,
+            PlottingPriority::ExpiringReplotting,
+            ("large", sector_index),
+        ));
+    }
+    handles.push(queue.push(small_farm, PlottingPriority::Replotting, ("small", 0)));
+    handles.push(queue.push(small_farm, PlottingPriority::InitialPlotting, ("small", 1)));
+    handles.push(queue.push(
+        small_farm,
+        PlottingPriority::ExpiringReplotting,
+        ("small", 2),
+    ));
+    assert_eq!(queue.len(), 6);
+
+    let popped = std::iter::from_fn(|| queue.try_pop()).collect::<Vec<_>>();
+    assert_eq!(
+        popped,
+        [
+            // Initial plotting goes first
+            ("small", 1),
+            // Farms take turns within the same priority
+            ("large", 0),
+            ("small", 2),
+            ("large", 1),
+            ("large", 2),
+            ("small", 0),
+        ]
+    );
+    assert!(queue.is_empty());
+}
+
+#[test]
+fn cancellation() {
+    let queue = PlottingQueue::default();
+    let farm = PublicKey::from([1; 32]);
+
+    let handle_0 = queue.push(farm, PlottingPriority::InitialPlotting, 0);
+    let handle_1 = queue.push(farm, PlottingPriority::InitialPlotting, 1);
+    assert_eq!(queue.len(), 2);
+
+    drop(handle_1);
+    assert_eq!(queue.len(), 1);
+    assert_eq!(queue.try_pop(), Some(0));
+
+    // Dropping handle of already popped entry does nothing
+    let _handle_2 = queue.push(farm, PlottingPriority::InitialPlotting, 2);
+    drop(handle_0);
+    assert_eq!(queue.len(), 1);
+    assert_eq!(queue.try_pop(), Some(2));
+    assert_eq!(queue.try_pop(), None);
+}
+
+#[test]
+fn pop_matching() {
+    let queue = PlottingQueue::default();
+    let farm_1 = PublicKey::from([1; 32]);
+    let farm_2 = PublicKey::from([2; 32]);
+
+    let _handles = [
+        queue.push(farm_1, PlottingPriority::InitialPlotting, (1, false)),
+        queue.push(farm_1, PlottingPriority::InitialPlotting, (2, true)),
+        queue.push(farm_2, PlottingPriority::InitialPlotting, (3, true)),
+        queue.push(farm_2, PlottingPriority::Replotting, (4, false)),
+    ];
+
+    let matches = |&(_value, matches): &(u8, bool)| matches;
+    assert!(queue.contains_matching(matches));
+    assert_eq!(queue.try_pop_matching(matches), Some((2, true)));
+    assert_eq!(queue.try_pop_matching(matches), Some((3, true)));
+    assert!(!queue.contains_matching(matches));
+    assert_eq!(queue.try_pop_matching(matches), None);
+
+    // Entries that didn't match stay in the queue in the same order
+    assert_eq!(queue.len(), 2);
+    assert_eq!(queue.try_pop(), Some((1, false)));
+    assert_eq!(queue.try_pop(), Some((4, false)));
+}
+
+#[tokio::test]
+async fn wait_for_entries() {
+    let queue = PlottingQueue::default();
+
+    assert!(
+        tokio::time::timeout(Duration::from_millis(100), queue.wait_for_entries())
+            .await
+            .is_err()
+    );
+
+    let waiting = tokio::spawn({
+        let queue = queue.clone();
+
+        async move {
+            queue.wait_for_entries().await;
+            queue.try_pop()
+        }
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let _handle = queue.push(
+        PublicKey::from([1; 32]),
+        PlottingPriority::InitialPlotting,
+        1,
+    );
+
+    assert_eq!(waiting.await.unwrap(), Some(1));
+}
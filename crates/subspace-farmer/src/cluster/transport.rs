@@ -0,0 +1,154 @@
+//! Cluster transport
+//!
+//! [`ClusterTransport`] is a minimal set of messaging primitives (publish, request and subscribe
+//! with optional queue groups) that [`NatsClient`](crate::cluster::nats_client::NatsClient) builds
+//! higher-level abstractions on top of. Subjects follow NATS conventions: tokens are separated with
+//! `.`, subscriptions can use `*` to match a single token and `>` to match one or more trailing
+//! tokens.
+//!
+//! Two implementations are provided: NATS [`Client`](async_nats::Client) and built-in
+//! [`BrokerClient`](crate::cluster::broker::BrokerClient) that doesn't require external server.
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::Stream;
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use thiserror::Error;
+
+/// Message received from cluster transport
+#[derive(Debug, Clone)]
+pub struct TransportMessage {
+    /// Subject message was published to
+    pub subject: String,
+    /// Subject where response is expected, if any
+    pub reply: Option<String>,
+    /// Message payload
+    pub payload: Bytes,
+}
+
+/// Cluster transport error
+#[derive(Debug, Error)]
+pub enum TransportError {
+    /// Request timed out
+    #[error("Request timed out")]
+    TimedOut,
+    /// There are no subscribers that can respond to request
+    #[error("No responders")]
+    NoResponders,
+    /// Connection is closed
+    #[error("Connection is closed")]
+    ConnectionClosed,
+    /// Payload is too large
+    #[error("Payload of {size} bytes exceeds max payload of {max_payload} bytes")]
+    PayloadTooLarge {
+        /// Payload size
+        size: usize,
+        /// Max payload size
+        max_payload: usize,
+    },
+    /// Message authentication failed
+    #[error("Message authentication failed: {0}")]
+    Authentication(String),
+    /// Other transport-specific error
+    #[error("Transport error: {0}")]
+    Other(String),
+}
+
+/// Subscription to messages of cluster transport, unsubscribes when dropped
+pub struct TransportSubscription {
+    subject: String,
+    queue_group: Option<String>,
+    messages: Pin<Box<dyn Stream<Item = TransportMessage> + Send>>,
+}
+
+impl fmt::Debug for TransportSubscription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TransportSubscription")
+            .field("subject", &self.subject)
+            .field("queue_group", &self.queue_group)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Stream for TransportSubscription {
+    type Item = TransportMessage;
+
+    #[inline]
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.messages.as_mut().poll_next(cx)
+    }
+}
+
+impl TransportSubscription {
+    /// Create new instance from a stream of messages
+    pub fn new<S>(subject: String, queue_group: Option<String>, messages: S) -> Self
+    where
+        S: Stream<Item = TransportMessage> + Send + 'static,
+    {
+        Self {
+            subject,
+            queue_group,
+            messages: Box::pin(messages),
+        }
+    }
+
+    /// Subject of the subscription
+    pub fn subject(&self) -> &str {
+        &self.subject
+    }
+}
+
+/// Messaging primitives used by cluster components to communicate with each other
+#[async_trait]
+pub trait ClusterTransport: fmt::Debug + Send + Sync + 'static {
+    /// Max payload size supported by transport
+    fn max_payload(&self) -> usize;
+
+    /// Publish message to subject.
+    ///
+    /// `message_id` is a deterministic message ID that transport may use for de-duplication of the
+    /// same message published by multiple senders.
+    async fn publish(
+        &self,
+        subject: String,
+        message_id: Option<String>,
+        payload: Bytes,
+    ) -> Result<(), TransportError>;
+
+    /// Create unique subject that can be used as reply subject with [`Self::request_with_inbox()`]
+    fn new_inbox(&self) -> String;
+
+    /// Publish request to subject and wait for a single response
+    async fn request(
+        &self,
+        subject: String,
+        payload: Bytes,
+    ) -> Result<TransportMessage, TransportError> {
+        self.request_with_inbox(subject, self.new_inbox(), payload)
+            .await
+    }
+
+    /// Publish request to subject with reply subject created with [`Self::new_inbox()`] and wait
+    /// for a single response
+    async fn request_with_inbox(
+        &self,
+        subject: String,
+        inbox: String,
+        payload: Bytes,
+    ) -> Result<TransportMessage, TransportError>;
+
+    /// Subscribe to messages published to subject.
+    ///
+    /// When queue group is specified, each message is delivered to just one of the subscribers in
+    /// the same queue group.
+    ///
+    /// Returns once subscription is active, such that messages published afterwards (even by other
+    /// clients) are delivered to it.
+    async fn subscribe(
+        &self,
+        subject: String,
+        queue_group: Option<String>,
+    ) -> Result<TransportSubscription, TransportError>;
+}
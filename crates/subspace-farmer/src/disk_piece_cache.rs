@@ -6,7 +6,7 @@ mod tests;
 
 use crate::disk_piece_cache::metrics::DiskPieceCacheMetrics;
 use crate::farm;
-use crate::farm::{FarmError, PieceCacheId, PieceCacheOffset};
+use crate::farm::{FarmError, PieceCacheId, PieceCacheOffset, PieceCacheTier};
 #[cfg(windows)]
 use crate::single_disk_farm::unbuffered_io_file_windows::UnbufferedIoFileWindows;
 use crate::single_disk_farm::unbuffered_io_file_windows::DISK_SECTOR_SIZE;
@@ -17,8 +17,10 @@ use futures::channel::mpsc;
 use futures::{stream, SinkExt, Stream, StreamExt};
 use parking_lot::Mutex;
 use prometheus_client::registry::Registry;
+use std::collections::HashSet;
 #[cfg(not(windows))]
-use std::fs::{File, OpenOptions};
+use std::fs::File;
+use std::fs::OpenOptions;
 use std::path::Path;
 use std::sync::Arc;
 use std::task::Poll;
@@ -73,6 +75,15 @@ struct Inner {
     metrics: Option<DiskPieceCacheMetrics>,
 }
 
+/// Occupancy of piece cache stored on disk
+#[derive(Debug, Copy, Clone)]
+pub struct DiskPieceCacheOccupancy {
+    /// Number of elements cache file can store
+    pub capacity: u32,
+    /// Number of elements that contain valid pieces
+    pub used: u32,
+}
+
 /// Dedicated piece cache stored on one disk, is used both to accelerate DSN queries and to plot
 /// faster.
 ///
@@ -80,6 +91,7 @@ struct Inner {
 #[derive(Debug, Clone)]
 pub struct DiskPieceCache {
     inner: Arc<Inner>,
+    tier: PieceCacheTier,
 }
 
 #[async_trait]
@@ -93,6 +105,11 @@ impl farm::PieceCache for DiskPieceCache {
         self.inner.max_num_elements
     }
 
+    #[inline]
+    fn tier(&self) -> PieceCacheTier {
+        self.tier
+    }
+
     async fn contents(
         &self,
     ) -> Result<
@@ -234,9 +251,16 @@ impl DiskPieceCache {
                 max_num_elements: capacity,
                 metrics,
             }),
+            tier: PieceCacheTier::default(),
         })
     }
 
+    /// Set performance tier of this cache, slow tier is used by default
+    pub fn with_tier(mut self, tier: PieceCacheTier) -> Self {
+        self.tier = tier;
+        self
+    }
+
     /// Size of a single piece cache element
     pub const fn element_size() -> u32 {
         (PieceIndex::SIZE + Piece::SIZE + mem::size_of::<Blake3Hash>()) as u32
@@ -397,9 +421,18 @@ impl DiskPieceCache {
         offset: u32,
         element: &mut [u8],
     ) -> Result<Option<PieceIndex>, DiskPieceCacheError> {
-        self.inner
-            .file
-            .read_exact_at(element, u64::from(offset) * u64::from(Self::element_size()))?;
+        Self::read_element(&self.inner.file, offset, element)
+    }
+
+    fn read_element<F>(
+        file: &F,
+        offset: u32,
+        element: &mut [u8],
+    ) -> Result<Option<PieceIndex>, DiskPieceCacheError>
+    where
+        F: FileExt,
+    {
+        file.read_exact_at(element, u64::from(offset) * u64::from(Self::element_size()))?;
 
         let (piece_index_bytes, remaining_bytes) = element.split_at(PieceIndex::SIZE);
         let (piece_bytes, expected_checksum) = remaining_bytes.split_at(Piece::SIZE);
@@ -428,6 +461,145 @@ impl DiskPieceCache {
         Ok(Some(piece_index))
     }
 
+    /// Collect occupancy of the cache stored in specified directory without opening it for
+    /// writing, returns `None` if there is no cache file.
+    ///
+    /// Same as with [`Self::contents()`] the rest of the file is not checked after a few vacant
+    /// elements in a row since cache is filled sequentially.
+    pub fn occupancy(
+        directory: &Path,
+    ) -> Result<Option<DiskPieceCacheOccupancy>, DiskPieceCacheError> {
+        let file = match OpenOptions::new()
+            .read(true)
+            .open(directory.join(Self::FILE_NAME))
+        {
+            Ok(file) => file,
+            Err(error) => {
+                return if error.kind() == io::ErrorKind::NotFound {
+                    Ok(None)
+                } else {
+                    Err(error.into())
+                };
+            }
+        };
+
+        let capacity =
+            u32::try_from(file.size()? / u64::from(Self::element_size())).unwrap_or(u32::MAX);
+        let mut element = vec![0; Self::element_size() as usize];
+        let mut used = 0;
+        let mut current_skip = 0;
+
+        for offset in 0..capacity {
+            if current_skip > CONTENTS_READ_SKIP_LIMIT {
+                break;
+            }
+
+            match Self::read_element(&file, offset, &mut element) {
+                Ok(Some(_piece_index)) => {
+                    used += 1;
+                    current_skip = 0;
+                }
+                Ok(None) | Err(DiskPieceCacheError::ChecksumMismatch) => {
+                    current_skip += 1;
+                }
+                Err(error) => {
+                    return Err(error);
+                }
+            }
+        }
+
+        Ok(Some(DiskPieceCacheOccupancy { capacity, used }))
+    }
+
+    /// Shrink cache file to specified capacity.
+    ///
+    /// Pieces stored beyond new capacity are moved into vacant elements within new capacity (as
+    /// long as there are any) instead of being discarded. Operation is idempotent and can be
+    /// restarted safely if it was interrupted.
+    pub(crate) fn shrink(directory: &Path, capacity: u32) -> Result<(), DiskPieceCacheError> {
+        let file = match OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(directory.join(Self::FILE_NAME))
+        {
+            Ok(file) => file,
+            Err(error) => {
+                return if error.kind() == io::ErrorKind::NotFound {
+                    Ok(())
+                } else {
+                    Err(error.into())
+                };
+            }
+        };
+
+        let element_size = u64::from(Self::element_size());
+        let num_elements = u32::try_from(file.size()? / element_size).unwrap_or(u32::MAX);
+
+        if num_elements > capacity {
+            let mut element = vec![0; Self::element_size() as usize];
+            let mut stored_pieces = HashSet::new();
+            let mut vacant_offsets = Vec::new();
+
+            for offset in 0..capacity {
+                match Self::read_element(&file, offset, &mut element) {
+                    Ok(Some(piece_index)) => {
+                        stored_pieces.insert(piece_index);
+                    }
+                    Ok(None) | Err(DiskPieceCacheError::ChecksumMismatch) => {
+                        vacant_offsets.push(offset);
+                    }
+                    Err(error) => {
+                        return Err(error);
+                    }
+                }
+            }
+
+            let mut vacant_offsets = vacant_offsets.into_iter();
+            let mut moved_pieces = 0_usize;
+
+            for offset in capacity..num_elements {
+                let piece_index = match Self::read_element(&file, offset, &mut element) {
+                    Ok(Some(piece_index)) => piece_index,
+                    Ok(None) | Err(DiskPieceCacheError::ChecksumMismatch) => {
+                        continue;
+                    }
+                    Err(error) => {
+                        return Err(error);
+                    }
+                };
+
+                // Piece might have been moved already before shrinking was interrupted
+                if !stored_pieces.insert(piece_index) {
+                    continue;
+                }
+
+                let Some(vacant_offset) = vacant_offsets.next() else {
+                    break;
+                };
+
+                file.write_all_at(&element, u64::from(vacant_offset) * element_size)?;
+                moved_pieces += 1;
+            }
+
+            debug!(
+                old_capacity = %num_elements,
+                new_capacity = %capacity,
+                %moved_pieces,
+                "Moved pieces before shrinking piece cache"
+            );
+        }
+
+        let expected_size = element_size * u64::from(capacity);
+        // Align cache file size for disk sector size
+        let expected_size =
+            expected_size.div_ceil(DISK_SECTOR_SIZE as u64) * DISK_SECTOR_SIZE as u64;
+        if file.size()? > expected_size {
+            file.set_len(expected_size)?;
+        }
+
+        Ok(())
+    }
+
     pub(crate) fn wipe(directory: &Path) -> io::Result<()> {
         let piece_cache = directory.join(Self::FILE_NAME);
         if !piece_cache.exists() {
@@ -141,3 +141,51 @@ fn basic() {
         );
     }
 }
+
+#[test]
+fn shrink() {
+    let path = tempdir().unwrap();
+    let pieces = (0..4_u64)
+        .map(|piece_index| {
+            let mut piece = Piece::default();
+            thread_rng().fill(piece.as_mut());
+            (PieceIndex::from(piece_index), piece)
+        })
+        .collect::<Vec<_>>();
+
+    {
+        let disk_piece_cache = DiskPieceCache::open(path.as_ref(), 4, None, None).unwrap();
+
+        // Leave offset 1 vacant, fill the rest
+        for (offset, (piece_index, piece)) in [0, 2, 3].into_iter().zip(&pieces) {
+            disk_piece_cache
+                .write_piece(PieceCacheOffset(offset), *piece_index, piece)
+                .unwrap();
+        }
+    }
+
+    DiskPieceCache::shrink(path.as_ref(), 2).unwrap();
+    // Repeated shrinking is a no-op
+    DiskPieceCache::shrink(path.as_ref(), 2).unwrap();
+
+    {
+        let disk_piece_cache = DiskPieceCache::open(path.as_ref(), 2, None, None).unwrap();
+
+        // Piece from offset 2 was moved into vacant offset 1, piece from offset 3 didn't fit
+        assert_eq!(
+            disk_piece_cache
+                .contents()
+                .filter_map(|(_offset, maybe_piece_index)| maybe_piece_index)
+                .collect::<Vec<_>>(),
+            vec![pieces[0].0, pieces[1].0]
+        );
+        assert_eq!(
+            disk_piece_cache
+                .read_piece(PieceCacheOffset(1))
+                .unwrap()
+                .unwrap()
+                .1,
+            pieces[1].1
+        );
+    }
+}
@@ -10,11 +10,12 @@
 use crate::node_client;
 use async_trait::async_trait;
 use derive_more::{Display, From};
-use futures::Stream;
+use futures::{Stream, StreamExt};
 use parity_scale_codec::{Decode, Encode, EncodeLike, Input, Output};
 use serde::{Deserialize, Serialize};
 use std::future::Future;
 use std::pin::Pin;
+use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
 use std::{fmt, io};
@@ -44,6 +45,22 @@ pub trait PlottedSectors: Send + Sync + fmt::Debug {
         Box<dyn Stream<Item = Result<PlottedSector, FarmError>> + Unpin + Send + '_>,
         FarmError,
     >;
+
+    /// Number of already plotted sectors.
+    ///
+    /// Default implementation counts sectors returned by [`Self::get()`], implementations are
+    /// encouraged to override it with something cheaper.
+    async fn count(&self) -> Result<SectorIndex, FarmError> {
+        let mut plotted_sectors = self.get().await?;
+
+        let mut count = 0;
+        while let Some(plotted_sector_result) = plotted_sectors.next().await {
+            plotted_sector_result?;
+            count += 1;
+        }
+
+        Ok(count)
+    }
 }
 
 /// An identifier for a cache, can be used for in logs, thread names, etc.
@@ -107,6 +124,44 @@ impl PieceCacheId {
 #[repr(transparent)]
 pub struct PieceCacheOffset(pub(crate) u32);
 
+/// Performance tier of [`PieceCache`].
+///
+/// Pieces that are requested often are kept in fast tier, while the rest are stored in slow tier.
+#[derive(
+    Debug, Default, Copy, Clone, Eq, PartialEq, Hash, Encode, Decode, Serialize, Deserialize,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum PieceCacheTier {
+    /// Fast cache, typically backed by SSD
+    Fast,
+    /// Slow cache, typically backed by HDD
+    #[default]
+    Slow,
+}
+
+impl fmt::Display for PieceCacheTier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Fast => f.write_str("fast"),
+            Self::Slow => f.write_str("slow"),
+        }
+    }
+}
+
+impl FromStr for PieceCacheTier {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fast" => Ok(Self::Fast),
+            "slow" => Ok(Self::Slow),
+            s => Err(format!(
+                "Unsupported piece cache tier \"{s}\", only `fast` or `slow` are allowed"
+            )),
+        }
+    }
+}
+
 /// Abstract piece cache implementation.
 ///
 /// Piece cache is a simple container that stores concatenated pieces in a flat file at specific
@@ -120,6 +175,11 @@ pub trait PieceCache: Send + Sync + fmt::Debug {
     /// Max number of elements in this cache
     fn max_num_elements(&self) -> u32;
 
+    /// Performance tier of this cache
+    fn tier(&self) -> PieceCacheTier {
+        PieceCacheTier::default()
+    }
+
     /// Contents of this piece cache.
     ///
     /// NOTE: it is possible to do concurrent reads and writes, higher level logic must ensure this
@@ -411,6 +471,39 @@ pub enum SectorExpirationDetails {
     Expired,
 }
 
+/// Details about detected sector corruption
+#[derive(Debug, Clone, Encode, Decode)]
+pub enum SectorCorruptionDetails {
+    /// Sector metadata stored on disk is corrupted or doesn't match metadata in memory
+    Metadata,
+    /// Sector contents doesn't match its checksum
+    Checksum,
+    /// Piece stored in the sector doesn't match its record metadata
+    Piece {
+        /// Piece offset within sector
+        piece_offset: PieceOffset,
+        /// String representation of an error
+        error: String,
+    },
+    /// Failed to read sector
+    Read(String),
+}
+
+impl fmt::Display for SectorCorruptionDetails {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Metadata => f.write_str("Sector metadata mismatch"),
+            Self::Checksum => f.write_str("Sector checksum mismatch"),
+            Self::Piece {
+                piece_offset,
+                error,
+            } => write!(f, "Piece at offset {piece_offset} is corrupted: {error}"),
+            Self::Read(error) => write!(f, "Failed to read sector: {error}"),
+        }
+    }
+}
+
 /// Various sector updates
 #[derive(Debug, Clone, Encode, Decode)]
 pub enum SectorUpdate {
@@ -418,6 +511,8 @@ pub enum SectorUpdate {
     Plotting(SectorPlottingDetails),
     /// Sector expiration information updated
     Expiration(SectorExpirationDetails),
+    /// Sector corruption was detected by background integrity check, sector will be replotted
+    Corrupted(SectorCorruptionDetails),
 }
 
 /// Abstract piece reader implementation
@@ -2,13 +2,20 @@
 //!
 //! Farmer cache is a container that orchestrates a bunch of piece and plot caches that together
 //! persist pieces in a way that is easy to retrieve comparing to decoding pieces from plots.
+//!
+//! Piece caches can be in fast or slow tier (see [`PieceCacheTier`]), pieces that are requested
+//! from slow tier are promoted into fast tier, while pieces in fast tier that were not requested
+//! for the longest time are demoted into slow tier to make space for them.
 
 mod metrics;
 mod piece_cache_state;
 #[cfg(test)]
 mod tests;
 
-use crate::farm::{MaybePieceStoredResult, PieceCache, PieceCacheId, PieceCacheOffset, PlotCache};
+use crate::farm::{
+    FarmError, MaybePieceStoredResult, PieceCache, PieceCacheId, PieceCacheOffset, PieceCacheTier,
+    PlotCache,
+};
 use crate::farmer_cache::metrics::FarmerCacheMetrics;
 use crate::farmer_cache::piece_cache_state::PieceCachesState;
 use crate::node_client::NodeClient;
@@ -21,7 +28,7 @@ use prometheus_client::registry::Registry;
 use rayon::prelude::*;
 use std::collections::HashMap;
 use std::hash::Hash;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 use std::{fmt, mem};
@@ -29,7 +36,7 @@ use subspace_core_primitives::{Piece, PieceIndex, SegmentHeader, SegmentIndex};
 use subspace_farmer_components::PieceGetter;
 use subspace_networking::libp2p::kad::{ProviderRecord, RecordKey};
 use subspace_networking::libp2p::PeerId;
-use subspace_networking::utils::multihash::ToMultihash;
+use subspace_networking::utils::multihash::{Multihash, MultihashCode, ToMultihash};
 use subspace_networking::{KeyWrapper, LocalRecordProvider, UniqueRecordBinaryHeap};
 use tokio::runtime::Handle;
 use tokio::sync::mpsc;
@@ -78,6 +85,7 @@ struct CacheBackend {
     backend: Arc<dyn PieceCache>,
     used_capacity: u32,
     total_capacity: u32,
+    tier: PieceCacheTier,
 }
 
 impl std::ops::Deref for CacheBackend {
@@ -90,10 +98,13 @@ impl std::ops::Deref for CacheBackend {
 
 impl CacheBackend {
     fn new(backend: Arc<dyn PieceCache>, total_capacity: u32) -> Self {
+        let tier = backend.tier();
+
         Self {
             backend,
             used_capacity: 0,
             total_capacity,
+            tier,
         }
     }
 
@@ -128,6 +139,12 @@ enum WorkerCommand {
     ForgetKey {
         key: RecordKey,
     },
+    /// Move piece that was requested from slow tier into fast tier
+    PromotePiece {
+        piece_index: PieceIndex,
+        /// Piece that was read from slow tier, worker reads it if `None`
+        piece: Option<Piece>,
+    },
 }
 
 #[derive(Debug)]
@@ -149,6 +166,7 @@ where
     plot_caches: Arc<PlotCaches>,
     handlers: Arc<Handlers>,
     worker_receiver: Option<mpsc::Receiver<WorkerCommand>>,
+    stored_segments: Arc<AtomicU64>,
     metrics: Option<Arc<FarmerCacheMetrics>>,
 }
 
@@ -277,9 +295,209 @@ where
                     }
                 }
             }
+            WorkerCommand::PromotePiece { piece_index, piece } => {
+                self.promote_piece(piece_index, piece, worker_state).await;
+            }
         }
     }
 
+    /// Move piece from slow tier into fast tier, demoting the least recently used piece from fast
+    /// tier into its place if there is no free space in fast tier
+    async fn promote_piece(
+        &self,
+        piece_index: PieceIndex,
+        maybe_piece: Option<Piece>,
+        worker_state: &mut CacheWorkerState,
+    ) {
+        let key = RecordKey::from(piece_index.to_multihash());
+
+        let (slow_offset, slow_backend, fast_offset, fast_backend, maybe_demoted_key) = {
+            let mut caches = self.piece_caches.write().await;
+            let Some(&slow_offset) = caches.get_stored_piece(&key) else {
+                // Piece was evicted in the meantime
+                return;
+            };
+            if caches.tier(slow_offset.cache_index) != Some(PieceCacheTier::Slow) {
+                // Already promoted
+                return;
+            }
+            let Some(slow_backend) = caches.get_backend(slow_offset.cache_index).cloned() else {
+                return;
+            };
+
+            let (fast_offset, maybe_demoted_key) = if let Some(fast_offset) =
+                caches.pop_free_offset_in_tier(PieceCacheTier::Fast)
+            {
+                (fast_offset, None)
+            } else if let Some((demoted_key, fast_offset)) = caches.least_recently_used_fast_piece()
+            {
+                (fast_offset, Some(demoted_key))
+            } else {
+                return;
+            };
+            let Some(fast_backend) = caches.get_backend(fast_offset.cache_index).cloned() else {
+                return;
+            };
+
+            // Pieces are not available from cache while they are being moved
+            caches.remove_stored_piece(&key);
+            if let Some(demoted_key) = &maybe_demoted_key {
+                caches.remove_stored_piece(demoted_key);
+            }
+
+            (
+                slow_offset,
+                slow_backend,
+                fast_offset,
+                fast_backend,
+                maybe_demoted_key,
+            )
+        };
+
+        let piece = match maybe_piece {
+            Some(piece) => piece,
+            None => match read_piece_at(&slow_backend, slow_offset.piece_offset).await {
+                Ok((stored_piece_index, piece)) if stored_piece_index == piece_index => piece,
+                result => {
+                    match result {
+                        Ok((stored_piece_index, _piece)) => {
+                            warn!(
+                                %piece_index,
+                                %stored_piece_index,
+                                cache_index = %slow_offset.cache_index,
+                                piece_offset = %slow_offset.piece_offset,
+                                "Unexpected piece in slow tier, might be a disk corruption"
+                            );
+                        }
+                        Err(error) => {
+                            warn!(
+                                %error,
+                                %piece_index,
+                                cache_index = %slow_offset.cache_index,
+                                piece_offset = %slow_offset.piece_offset,
+                                "Failed to read piece for promotion from slow tier, might be a \
+                                disk corruption"
+                            );
+                        }
+                    }
+
+                    // Unreadable piece in slow tier is forgotten, fast tier is left untouched
+                    let mut caches = self.piece_caches.write().await;
+                    caches.push_dangling_free_offset(slow_offset);
+                    worker_state.heap.remove(KeyWrapper(piece_index));
+                    match maybe_demoted_key {
+                        Some(demoted_key) => {
+                            caches.push_stored_piece(demoted_key, fast_offset);
+                        }
+                        None => {
+                            caches.push_dangling_free_offset(fast_offset);
+                        }
+                    }
+                    return;
+                }
+            },
+        };
+
+        let maybe_demoted_piece_result = match &maybe_demoted_key {
+            Some(_) => Some(read_piece_at(&fast_backend, fast_offset.piece_offset).await),
+            None => None,
+        };
+
+        let mut caches = self.piece_caches.write().await;
+
+        let maybe_demoted_piece = match maybe_demoted_piece_result {
+            None => None,
+            Some(Ok(demoted_piece)) => Some(demoted_piece),
+            Some(Err(error)) => {
+                warn!(
+                    %error,
+                    cache_index = %fast_offset.cache_index,
+                    piece_offset = %fast_offset.piece_offset,
+                    "Failed to read piece for demotion from fast tier, might be a disk corruption"
+                );
+
+                // Piece stays in slow tier, while unreadable piece in fast tier is forgotten
+                caches.push_stored_piece(key, slow_offset);
+                caches.push_dangling_free_offset(fast_offset);
+                if let Some(demoted_piece_index) =
+                    maybe_demoted_key.as_ref().and_then(piece_index_from_key)
+                {
+                    worker_state.heap.remove(KeyWrapper(demoted_piece_index));
+                }
+                return;
+            }
+        };
+
+        if let Err(error) = fast_backend
+            .write_piece(fast_offset.piece_offset, piece_index, &piece)
+            .await
+        {
+            error!(
+                %error,
+                cache_index = %fast_offset.cache_index,
+                %piece_index,
+                piece_offset = %fast_offset.piece_offset,
+                "Failed to write piece into fast tier"
+            );
+
+            // Promoted piece is still in slow tier, but demoted piece might be corrupted
+            caches.push_stored_piece(key, slow_offset);
+            caches.push_dangling_free_offset(fast_offset);
+            if let Some((demoted_piece_index, _demoted_piece)) = maybe_demoted_piece {
+                worker_state.heap.remove(KeyWrapper(demoted_piece_index));
+            }
+            return;
+        }
+        caches.push_stored_piece(key, fast_offset);
+        if let Some(metrics) = &self.metrics {
+            metrics.piece_promotions.inc();
+        }
+
+        let Some((demoted_piece_index, demoted_piece)) = maybe_demoted_piece else {
+            // Slot in slow tier is not used anymore
+            caches.push_dangling_free_offset(slow_offset);
+            if let Some(metrics) = &self.metrics {
+                metrics.dec_tier_capacity_used(PieceCacheTier::Slow);
+                metrics.inc_tier_capacity_used(PieceCacheTier::Fast, 1);
+            }
+            return;
+        };
+
+        if let Err(error) = slow_backend
+            .write_piece(
+                slow_offset.piece_offset,
+                demoted_piece_index,
+                &demoted_piece,
+            )
+            .await
+        {
+            error!(
+                %error,
+                cache_index = %slow_offset.cache_index,
+                piece_index = %demoted_piece_index,
+                piece_offset = %slow_offset.piece_offset,
+                "Failed to write piece into slow tier"
+            );
+
+            caches.push_dangling_free_offset(slow_offset);
+            worker_state.heap.remove(KeyWrapper(demoted_piece_index));
+            return;
+        }
+        caches.push_stored_piece(
+            RecordKey::from(demoted_piece_index.to_multihash()),
+            slow_offset,
+        );
+        if let Some(metrics) = &self.metrics {
+            metrics.piece_demotions.inc();
+        }
+
+        trace!(
+            %piece_index,
+            %demoted_piece_index,
+            "Promoted piece into fast tier"
+        );
+    }
+
     async fn initialize<PG>(
         &self,
         piece_getter: &PG,
@@ -299,6 +517,7 @@ where
         if let Some(metrics) = &self.metrics {
             metrics.piece_cache_capacity_total.set(0);
             metrics.piece_cache_capacity_used.set(0);
+            metrics.reset_tier_capacity();
         }
 
         // Build cache state of all backends
@@ -321,6 +540,7 @@ where
                     metrics
                         .piece_cache_capacity_total
                         .inc_by(total_capacity as i64);
+                    metrics.inc_tier_capacity_total(backend.tier, total_capacity as i64);
                 }
 
                 let init_fut = async move {
@@ -502,10 +722,11 @@ where
                 piece_caches_capacity_used[usize::from(offset.cache_index)] += 1;
             }
 
-            for cache_used in piece_caches_capacity_used {
+            for (backend, cache_used) in caches.backends().zip(piece_caches_capacity_used) {
                 metrics
                     .piece_cache_capacity_used
                     .inc_by(i64::from(cache_used));
+                metrics.inc_tier_capacity_used(backend.tier, i64::from(cache_used));
             }
         }
 
@@ -607,6 +828,7 @@ where
         }
 
         *self.piece_caches.write().await = caches;
+        self.update_stored_segments().await;
         self.handlers.progress.call_simple(&100.0);
         worker_state.last_segment_index = last_segment_index;
 
@@ -715,6 +937,7 @@ where
             }
 
             worker_state.last_segment_index = segment_index;
+            self.update_stored_segments().await;
         } else {
             self.acknowledge_archived_segment_processing(segment_index)
                 .await;
@@ -806,6 +1029,20 @@ where
         info!("Finished syncing piece cache to the latest history size");
 
         worker_state.last_segment_index = last_segment_index;
+        self.update_stored_segments().await;
+    }
+
+    /// Update number of segments all pieces of which are stored in piece caches
+    async fn update_stored_segments(&self) {
+        let stored_segments = self.piece_caches.read().await.stored_segments();
+
+        debug!(%stored_segments, "Updated number of stored segments");
+
+        self.stored_segments
+            .store(stored_segments, Ordering::Relaxed);
+        if let Some(metrics) = &self.metrics {
+            metrics.segments_stored.set(stored_segments as i64);
+        }
     }
 
     /// This assumes it was already checked that piece needs to be stored, no verification for this
@@ -906,6 +1143,9 @@ where
                     );
                     if let Some(metrics) = &self.metrics {
                         metrics.piece_cache_capacity_used.inc();
+                        if let Some(tier) = caches.tier(cache_index) {
+                            metrics.inc_tier_capacity_used(tier, 1);
+                        }
                     }
                     caches.push_stored_piece(record_key, offset);
                 }
@@ -914,6 +1154,26 @@ where
     }
 }
 
+/// Piece index that record key was derived from, see [`ToMultihash`]
+fn piece_index_from_key(key: &RecordKey) -> Option<PieceIndex> {
+    let multihash = Multihash::from_bytes(key.as_ref()).ok()?;
+    if multihash.code() != u64::from(MultihashCode::PieceIndex) {
+        return None;
+    }
+
+    Some(PieceIndex::from_bytes(multihash.digest().try_into().ok()?))
+}
+
+async fn read_piece_at(
+    backend: &CacheBackend,
+    piece_offset: PieceCacheOffset,
+) -> Result<(PieceIndex, Piece), FarmError> {
+    backend
+        .read_piece(piece_offset)
+        .await?
+        .ok_or_else(|| format!("Piece offset {piece_offset} is out of range").into())
+}
+
 #[derive(Debug)]
 struct PlotCaches {
     /// Additional piece caches
@@ -1006,6 +1266,7 @@ pub struct FarmerCache<CacheIndex> {
     handlers: Arc<Handlers>,
     // We do not want to increase capacity unnecessarily on clone
     worker_sender: Arc<mpsc::Sender<WorkerCommand>>,
+    stored_segments: Arc<AtomicU64>,
     metrics: Option<Arc<FarmerCacheMetrics>>,
 }
 
@@ -1035,6 +1296,7 @@ where
             caches: AsyncRwLock::default(),
             next_plot_cache: AtomicUsize::new(0),
         });
+        let stored_segments = Arc::default();
         let metrics = registry.map(|registry| Arc::new(FarmerCacheMetrics::new(registry)));
 
         let instance = Self {
@@ -1043,6 +1305,7 @@ where
             plot_caches: Arc::clone(&plot_caches),
             handlers: Arc::clone(&handlers),
             worker_sender: Arc::new(worker_sender),
+            stored_segments: Arc::clone(&stored_segments),
             metrics: metrics.clone(),
         };
         let worker = FarmerCacheWorker {
@@ -1052,6 +1315,7 @@ where
             plot_caches,
             handlers,
             worker_receiver: Some(worker_receiver),
+            stored_segments,
             metrics,
         };
 
@@ -1064,33 +1328,47 @@ where
         RecordKey: From<Key>,
     {
         let key = RecordKey::from(key);
-        let maybe_piece_found = {
+        let (maybe_piece_found, tiers) = {
             let caches = self.piece_caches.read().await;
 
-            caches.get_stored_piece(&key).and_then(|offset| {
+            let maybe_piece_found = caches.get_stored_piece(&key).and_then(|offset| {
                 let cache_index = offset.cache_index;
                 let piece_offset = offset.piece_offset;
+                caches.touch_stored_piece(&key);
                 Some((
                     piece_offset,
                     cache_index,
                     caches.get_backend(cache_index)?.clone(),
+                    caches.is_tiered(),
                 ))
-            })
+            });
+
+            (maybe_piece_found, caches.tiers())
         };
 
-        if let Some((piece_offset, cache_index, backend)) = maybe_piece_found {
+        if let Some((piece_offset, cache_index, backend, is_tiered)) = maybe_piece_found {
             match backend.read_piece(piece_offset).await {
                 Ok(maybe_piece) => {
                     return match maybe_piece {
-                        Some((_piece_index, piece)) => {
+                        Some((piece_index, piece)) => {
                             if let Some(metrics) = &self.metrics {
                                 metrics.cache_get_hit.inc();
+                                metrics.note_tier_get_hit(backend.tier);
+                                if is_tiered && backend.tier == PieceCacheTier::Slow {
+                                    metrics.note_tier_get_miss(PieceCacheTier::Fast);
+                                }
+                            }
+                            if is_tiered && backend.tier == PieceCacheTier::Slow {
+                                self.request_promotion(piece_index, Some(piece.clone()));
                             }
                             Some(piece)
                         }
                         None => {
                             if let Some(metrics) = &self.metrics {
                                 metrics.cache_get_miss.inc();
+                                for &tier in &tiers {
+                                    metrics.note_tier_get_miss(tier);
+                                }
                             }
                             None
                         }
@@ -1132,6 +1410,9 @@ where
 
         if let Some(metrics) = &self.metrics {
             metrics.cache_get_miss.inc();
+            for &tier in &tiers {
+                metrics.note_tier_get_miss(tier);
+            }
         }
         None
     }
@@ -1157,6 +1438,12 @@ where
             if let Some(metrics) = &self.metrics {
                 metrics.cache_find_hit.inc();
             }
+            caches.touch_stored_piece(&key);
+            // Caller reads piece from returned offset later and checks piece index, so piece that
+            // was moved by then is simply not found there
+            if caches.is_tiered() && backend.tier == PieceCacheTier::Slow {
+                self.request_promotion(piece_index, None);
+            }
             return Some((*backend.id(), piece_offset));
         }
 
@@ -1166,6 +1453,26 @@ where
         None
     }
 
+    /// Ask worker to move piece from slow tier into fast tier, worker will read the piece itself if
+    /// it is not provided.
+    ///
+    /// This is best-effort, request is dropped if worker is busy.
+    fn request_promotion(&self, piece_index: PieceIndex, piece: Option<Piece>) {
+        if let Err(error) = self
+            .worker_sender
+            .try_send(WorkerCommand::PromotePiece { piece_index, piece })
+        {
+            trace!(%error, %piece_index, "Failed to send PromotePiece command to worker");
+        }
+    }
+
+    /// Number of segments all pieces of which are stored in piece caches.
+    ///
+    /// Only pieces in dedicated piece caches are taken into account, plot caches are ignored.
+    pub fn stored_segments(&self) -> u64 {
+        self.stored_segments.load(Ordering::Relaxed)
+    }
+
     /// Try to store a piece in additional downloaded pieces, if there is space for them
     pub async fn maybe_store_additional_piece(&self, piece_index: PieceIndex, piece: &Piece) {
         let key = RecordKey::from(piece_index.to_multihash());
@@ -1,6 +1,8 @@
 //! Metrics for farmer cache
 
+use crate::farm::PieceCacheTier;
 use prometheus_client::metrics::counter::Counter;
+use prometheus_client::metrics::family::Family;
 use prometheus_client::metrics::gauge::Gauge;
 use prometheus_client::registry::{Registry, Unit};
 use std::sync::atomic::{AtomicI64, AtomicU64};
@@ -15,6 +17,13 @@ pub(super) struct FarmerCacheMetrics {
     pub(super) cache_find_miss: Counter<u64, AtomicU64>,
     pub(super) piece_cache_capacity_total: Gauge<i64, AtomicI64>,
     pub(super) piece_cache_capacity_used: Gauge<i64, AtomicI64>,
+    pub(super) segments_stored: Gauge<i64, AtomicI64>,
+    tier_get_hit: Family<Vec<(&'static str, String)>, Counter<u64, AtomicU64>>,
+    tier_get_miss: Family<Vec<(&'static str, String)>, Counter<u64, AtomicU64>>,
+    tier_capacity_total: Family<Vec<(&'static str, String)>, Gauge<i64, AtomicI64>>,
+    tier_capacity_used: Family<Vec<(&'static str, String)>, Gauge<i64, AtomicI64>>,
+    pub(super) piece_promotions: Counter<u64, AtomicU64>,
+    pub(super) piece_demotions: Counter<u64, AtomicU64>,
 }
 
 impl FarmerCacheMetrics {
@@ -78,6 +87,62 @@ impl FarmerCacheMetrics {
             piece_cache_capacity_used.clone(),
         );
 
+        let segments_stored = Gauge::default();
+        registry.register_with_unit(
+            "segments_stored",
+            "Segments all pieces of which are stored in piece cache",
+            Unit::Other("Segments".to_string()),
+            segments_stored.clone(),
+        );
+
+        let tier_get_hit = Family::default();
+        registry.register_with_unit(
+            "tier_get_hit",
+            "Cache get hit by piece cache tier",
+            Unit::Other("Requests".to_string()),
+            tier_get_hit.clone(),
+        );
+
+        let tier_get_miss = Family::default();
+        registry.register_with_unit(
+            "tier_get_miss",
+            "Cache get miss by piece cache tier",
+            Unit::Other("Requests".to_string()),
+            tier_get_miss.clone(),
+        );
+
+        let tier_capacity_total = Family::default();
+        registry.register_with_unit(
+            "tier_capacity_total",
+            "Piece cache capacity total by piece cache tier",
+            Unit::Other("Pieces".to_string()),
+            tier_capacity_total.clone(),
+        );
+
+        let tier_capacity_used = Family::default();
+        registry.register_with_unit(
+            "tier_capacity_used",
+            "Piece cache capacity used by piece cache tier",
+            Unit::Other("Pieces".to_string()),
+            tier_capacity_used.clone(),
+        );
+
+        let piece_promotions = Counter::default();
+        registry.register_with_unit(
+            "piece_promotions",
+            "Pieces moved from slow to fast piece cache tier",
+            Unit::Other("Pieces".to_string()),
+            piece_promotions.clone(),
+        );
+
+        let piece_demotions = Counter::default();
+        registry.register_with_unit(
+            "piece_demotions",
+            "Pieces moved from fast to slow piece cache tier",
+            Unit::Other("Pieces".to_string()),
+            piece_demotions.clone(),
+        );
+
         Self {
             cache_get_hit,
             cache_get_miss,
@@ -86,6 +151,56 @@ impl FarmerCacheMetrics {
             cache_find_miss,
             piece_cache_capacity_total,
             piece_cache_capacity_used,
+            segments_stored,
+            tier_get_hit,
+            tier_get_miss,
+            tier_capacity_total,
+            tier_capacity_used,
+            piece_promotions,
+            piece_demotions,
+        }
+    }
+
+    pub(super) fn note_tier_get_hit(&self, tier: PieceCacheTier) {
+        self.tier_get_hit
+            .get_or_create(&vec![("tier", tier.to_string())])
+            .inc();
+    }
+
+    /// Piece was not found in specified tier, this includes pieces found in slow tier only when
+    /// there is also a fast tier
+    pub(super) fn note_tier_get_miss(&self, tier: PieceCacheTier) {
+        self.tier_get_miss
+            .get_or_create(&vec![("tier", tier.to_string())])
+            .inc();
+    }
+
+    pub(super) fn reset_tier_capacity(&self) {
+        for tier in [PieceCacheTier::Fast, PieceCacheTier::Slow] {
+            self.tier_capacity_total
+                .get_or_create(&vec![("tier", tier.to_string())])
+                .set(0);
+            self.tier_capacity_used
+                .get_or_create(&vec![("tier", tier.to_string())])
+                .set(0);
         }
     }
+
+    pub(super) fn inc_tier_capacity_total(&self, tier: PieceCacheTier, capacity: i64) {
+        self.tier_capacity_total
+            .get_or_create(&vec![("tier", tier.to_string())])
+            .inc_by(capacity);
+    }
+
+    pub(super) fn inc_tier_capacity_used(&self, tier: PieceCacheTier, used: i64) {
+        self.tier_capacity_used
+            .get_or_create(&vec![("tier", tier.to_string())])
+            .inc_by(used);
+    }
+
+    pub(super) fn dec_tier_capacity_used(&self, tier: PieceCacheTier) {
+        self.tier_capacity_used
+            .get_or_create(&vec![("tier", tier.to_string())])
+            .dec();
+    }
 }
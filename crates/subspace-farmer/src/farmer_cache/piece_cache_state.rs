@@ -1,17 +1,117 @@
-use crate::farmer_cache::{CacheBackend, FarmerCacheOffset};
+use crate::farm::PieceCacheTier;
+use crate::farmer_cache::{piece_index_from_key, CacheBackend, FarmerCacheOffset};
+use parking_lot::Mutex;
 use std::collections::hash_map::Values;
-use std::collections::{HashMap, VecDeque};
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::fmt;
 use std::hash::Hash;
-use subspace_core_primitives::PieceIndex;
+use std::sync::Arc;
+use subspace_core_primitives::{ArchivedHistorySegment, PieceIndex, SegmentIndex};
 use subspace_networking::libp2p::kad::RecordKey;
 use tracing::{debug, trace};
 
-#[derive(Debug, Clone)]
+/// Order in which pieces stored in fast tier were accessed, least recently accessed pieces are
+/// demoted to slow tier first
+#[derive(Debug, Default, Clone)]
+pub(super) struct FastTierRecency {
+    next_tick: u64,
+    ticks: HashMap<RecordKey, u64>,
+    order: BTreeMap<u64, RecordKey>,
+}
+
+impl FastTierRecency {
+    /// Insert piece as the most recently accessed one
+    pub(super) fn insert(&mut self, key: RecordKey) {
+        let tick = self.next_tick;
+        self.next_tick += 1;
+
+        if let Some(old_tick) = self.ticks.insert(key.clone(), tick) {
+            self.order.remove(&old_tick);
+        }
+        self.order.insert(tick, key);
+    }
+
+    /// Mark piece as the most recently accessed one if it is stored in fast tier
+    pub(super) fn touch(&mut self, key: &RecordKey) {
+        if self.ticks.contains_key(key) {
+            self.insert(key.clone());
+        }
+    }
+
+    pub(super) fn remove(&mut self, key: &RecordKey) {
+        if let Some(tick) = self.ticks.remove(key) {
+            self.order.remove(&tick);
+        }
+    }
+
+    pub(super) fn least_recently_used(&self) -> Option<&RecordKey> {
+        self.order.values().next()
+    }
+}
+
+/// Number of stored pieces per segment, maintained incrementally as pieces are stored and removed
+#[derive(Debug, Default, Clone)]
+struct StoredSegments {
+    stored_pieces: HashMap<SegmentIndex, usize>,
+    complete_segments: u64,
+}
+
+impl StoredSegments {
+    fn insert(&mut self, key: &RecordKey) {
+        let Some(piece_index) = piece_index_from_key(key) else {
+            return;
+        };
+        let stored_pieces = self
+            .stored_pieces
+            .entry(piece_index.segment_index())
+            .or_default();
+        *stored_pieces += 1;
+        if *stored_pieces == ArchivedHistorySegment::NUM_PIECES {
+            self.complete_segments += 1;
+        }
+    }
+
+    fn remove(&mut self, key: &RecordKey) {
+        let Some(piece_index) = piece_index_from_key(key) else {
+            return;
+        };
+        let segment_index = piece_index.segment_index();
+        let Some(stored_pieces) = self.stored_pieces.get_mut(&segment_index) else {
+            return;
+        };
+        if *stored_pieces == ArchivedHistorySegment::NUM_PIECES {
+            self.complete_segments -= 1;
+        }
+        *stored_pieces -= 1;
+        if *stored_pieces == 0 {
+            self.stored_pieces.remove(&segment_index);
+        }
+    }
+}
+
+#[derive(Debug)]
 pub(super) struct PieceCachesState<CacheIndex> {
     stored_pieces: HashMap<RecordKey, FarmerCacheOffset<CacheIndex>>,
     dangling_free_offsets: VecDeque<FarmerCacheOffset<CacheIndex>>,
     backends: Vec<CacheBackend>,
+    stored_segments: StoredSegments,
+    fast_tier_recency: Arc<Mutex<FastTierRecency>>,
+}
+
+impl<CacheIndex> Clone for PieceCachesState<CacheIndex>
+where
+    CacheIndex: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            stored_pieces: self.stored_pieces.clone(),
+            dangling_free_offsets: self.dangling_free_offsets.clone(),
+            backends: self.backends.clone(),
+            stored_segments: self.stored_segments.clone(),
+            // Recency is a part of the state, clones must not share it
+            fast_tier_recency: Arc::new(Mutex::new(self.fast_tier_recency.lock().clone())),
+        }
+    }
 }
 
 impl<CacheIndex> PieceCachesState<CacheIndex>
@@ -25,10 +125,24 @@ where
         dangling_free_offsets: VecDeque<FarmerCacheOffset<CacheIndex>>,
         backends: Vec<CacheBackend>,
     ) -> Self {
+        let mut stored_segments = StoredSegments::default();
+        let mut fast_tier_recency = FastTierRecency::default();
+        for (key, offset) in &stored_pieces {
+            stored_segments.insert(key);
+            if backends
+                .get(usize::from(offset.cache_index))
+                .is_some_and(|backend| backend.tier == PieceCacheTier::Fast)
+            {
+                fast_tier_recency.insert(key.clone());
+            }
+        }
+
         Self {
             stored_pieces,
             dangling_free_offsets,
             backends,
+            stored_segments,
+            fast_tier_recency: Arc::new(Mutex::new(fast_tier_recency)),
         }
     }
 
@@ -67,6 +181,39 @@ where
         }
     }
 
+    /// Take free offset in specified tier, returns `None` if there is no free space in that tier
+    pub(super) fn pop_free_offset_in_tier(
+        &mut self,
+        tier: PieceCacheTier,
+    ) -> Option<FarmerCacheOffset<CacheIndex>> {
+        if let Some(position) = self
+            .dangling_free_offsets
+            .iter()
+            .position(|free_offset| self.tier(free_offset.cache_index) == Some(tier))
+        {
+            return self.dangling_free_offsets.remove(position);
+        }
+
+        let mut sorted_backends = self
+            .backends
+            .iter_mut()
+            .enumerate()
+            .filter(|(_cache_index, backend)| backend.tier == tier)
+            .filter_map(|(cache_index, backend)| {
+                Some((CacheIndex::try_from(cache_index).ok()?, backend))
+            })
+            .collect::<Vec<_>>();
+        sorted_backends.sort_unstable_by_key(|(_, backend)| backend.free_size());
+        sorted_backends
+            .into_iter()
+            .rev()
+            .find_map(|(cache_index, backend)| {
+                backend
+                    .next_free()
+                    .map(|free_offset| FarmerCacheOffset::new(cache_index, free_offset))
+            })
+    }
+
     pub(super) fn get_stored_piece(
         &self,
         key: &RecordKey,
@@ -83,7 +230,16 @@ where
         key: RecordKey,
         cache_offset: FarmerCacheOffset<CacheIndex>,
     ) -> Option<FarmerCacheOffset<CacheIndex>> {
-        self.stored_pieces.insert(key, cache_offset)
+        if self.tier(cache_offset.cache_index) == Some(PieceCacheTier::Fast) {
+            self.fast_tier_recency.lock().insert(key.clone());
+        } else {
+            self.fast_tier_recency.lock().remove(&key);
+        }
+        let maybe_old_offset = self.stored_pieces.insert(key.clone(), cache_offset);
+        if maybe_old_offset.is_none() {
+            self.stored_segments.insert(&key);
+        }
+        maybe_old_offset
     }
 
     pub(super) fn stored_pieces_offests(
@@ -96,16 +252,45 @@ where
         &mut self,
         key: &RecordKey,
     ) -> Option<FarmerCacheOffset<CacheIndex>> {
-        self.stored_pieces.remove(key)
+        self.fast_tier_recency.lock().remove(key);
+        let maybe_offset = self.stored_pieces.remove(key);
+        if maybe_offset.is_some() {
+            self.stored_segments.remove(key);
+        }
+        maybe_offset
+    }
+
+    /// Number of segments all pieces of which are stored
+    pub(super) fn stored_segments(&self) -> u64 {
+        self.stored_segments.complete_segments
+    }
+
+    /// Mark piece as recently accessed, which prevents it from being demoted from fast tier
+    pub(super) fn touch_stored_piece(&self, key: &RecordKey) {
+        self.fast_tier_recency.lock().touch(key);
+    }
+
+    /// Piece in fast tier that was not accessed for the longest time
+    pub(super) fn least_recently_used_fast_piece(
+        &self,
+    ) -> Option<(RecordKey, FarmerCacheOffset<CacheIndex>)> {
+        let fast_tier_recency = self.fast_tier_recency.lock();
+        let key = fast_tier_recency.least_recently_used()?;
+        let offset = self.stored_pieces.get(key)?;
+
+        Some((key.clone(), *offset))
     }
 
     pub(super) fn free_unneeded_stored_pieces(
         &mut self,
         piece_indices_to_store: &mut HashMap<RecordKey, PieceIndex>,
     ) {
+        let mut fast_tier_recency = self.fast_tier_recency.lock();
         self.stored_pieces
             .extract_if(|key, _offset| piece_indices_to_store.remove(key).is_none())
-            .for_each(|(_piece_index, offset)| {
+            .for_each(|(key, offset)| {
+                self.stored_segments.remove(&key);
+                fast_tier_recency.remove(&key);
                 // There is no need to adjust the `last_stored_offset` of the `backend` here,
                 // as the free_offset will be preferentially taken from the dangling free offsets
                 self.dangling_free_offsets.push_back(offset);
@@ -125,6 +310,28 @@ where
         self.backends.iter()
     }
 
+    pub(super) fn tier(&self, cache_index: CacheIndex) -> Option<PieceCacheTier> {
+        self.get_backend(cache_index).map(|backend| backend.tier)
+    }
+
+    /// Whether there are caches in both fast and slow tiers, such that pieces can be moved
+    /// between them
+    pub(super) fn is_tiered(&self) -> bool {
+        self.tiers().len() == 2
+    }
+
+    /// Tiers that have caches with non-zero capacity
+    pub(super) fn tiers(&self) -> Vec<PieceCacheTier> {
+        [PieceCacheTier::Fast, PieceCacheTier::Slow]
+            .into_iter()
+            .filter(|&tier| {
+                self.backends
+                    .iter()
+                    .any(|backend| backend.tier == tier && backend.total_capacity > 0)
+            })
+            .collect()
+    }
+
     pub(super) fn reuse(
         self,
     ) -> (
@@ -135,6 +342,8 @@ where
             mut stored_pieces,
             mut dangling_free_offsets,
             backends: _,
+            stored_segments: _,
+            fast_tier_recency: _,
         } = self;
 
         stored_pieces.clear();
@@ -149,6 +358,8 @@ impl<CacheIndex> Default for PieceCachesState<CacheIndex> {
             stored_pieces: HashMap::default(),
             dangling_free_offsets: VecDeque::default(),
             backends: Vec::default(),
+            stored_segments: StoredSegments::default(),
+            fast_tier_recency: Arc::default(),
         }
     }
 }
@@ -1,5 +1,7 @@
 use crate::disk_piece_cache::DiskPieceCache;
-use crate::farmer_cache::FarmerCache;
+use crate::farm::{PieceCache, PieceCacheOffset, PieceCacheTier};
+use crate::farmer_cache::piece_cache_state::FastTierRecency;
+use crate::farmer_cache::{piece_index_from_key, FarmerCache};
 use crate::node_client::{Error, NodeClient};
 use async_trait::async_trait;
 use futures::channel::{mpsc, oneshot};
@@ -17,6 +19,7 @@ use subspace_core_primitives::{
 };
 use subspace_farmer_components::{FarmerProtocolInfo, PieceGetter};
 use subspace_networking::libp2p::identity;
+use subspace_networking::libp2p::kad::RecordKey;
 use subspace_networking::utils::multihash::ToMultihash;
 use subspace_rpc_primitives::{
     FarmerAppInfo, RewardSignatureResponse, RewardSigningInfo, SlotInfo, SolutionResponse,
@@ -218,6 +221,9 @@ async fn basic() {
         // Wait for piece cache to be initialized
         receiver.await.unwrap();
 
+        // Only a couple of pieces are cached, which is not enough for a single full segment
+        assert_eq!(farmer_cache.stored_segments(), 0);
+
         // These 2 pieces are requested from node during initialization
         {
             let mut requested_pieces = pieces.lock().keys().copied().collect::<Vec<_>>();
@@ -436,3 +442,213 @@ async fn basic() {
         farmer_cache_worker_exited.await.unwrap();
     }
 }
+
+/// Pieces stored in caches with capacity of a single piece
+async fn stored_pieces(caches: [&DiskPieceCache; 2]) -> Vec<(PieceIndex, Piece)> {
+    let mut stored_pieces = Vec::new();
+    for cache in caches {
+        stored_pieces.push(
+            cache
+                .read_piece(PieceCacheOffset(0))
+                .await
+                .unwrap()
+                .unwrap(),
+        );
+    }
+    stored_pieces
+}
+
+/// Pieces are moved between caches in the background
+async fn wait_for_stored_piece_indices(
+    caches: [&DiskPieceCache; 2],
+    expected_piece_indices: [PieceIndex; 2],
+) {
+    tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            let stored_piece_indices = stored_pieces(caches)
+                .await
+                .into_iter()
+                .map(|(piece_index, _piece)| piece_index)
+                .collect::<Vec<_>>();
+            if stored_piece_indices == expected_piece_indices {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn promotion_and_demotion() {
+    let pieces = Arc::default();
+    let (
+        archived_segment_headers_stream_request_sender,
+        mut archived_segment_headers_stream_request_receiver,
+    ) = mpsc::channel(0);
+    let (acknowledge_archived_segment_header_sender, _acknowledge_archived_segment_header_receiver) =
+        mpsc::channel(0);
+
+    let node_client = MockNodeClient {
+        current_segment_index: Arc::new(AtomicU64::new(0)),
+        pieces: Arc::clone(&pieces),
+        archived_segment_headers_stream_request_sender,
+        acknowledge_archived_segment_header_sender,
+    };
+    let piece_getter = MockPieceGetter {
+        pieces: Arc::clone(&pieces),
+    };
+    let public_key =
+        identity::PublicKey::from(identity::ed25519::PublicKey::try_from_bytes(&[42; 32]).unwrap());
+    let path1 = tempdir().unwrap();
+    let path2 = tempdir().unwrap();
+
+    let (farmer_cache, farmer_cache_worker) =
+        FarmerCache::<TestCacheIndex>::new(node_client, public_key.to_peer_id(), None);
+
+    let farmer_cache_worker_exited = tokio::spawn(farmer_cache_worker.run(piece_getter));
+
+    let (sender, receiver) = oneshot::channel();
+    farmer_cache
+        .on_sync_progress(Arc::new({
+            let sender = Mutex::new(Some(sender));
+
+            move |progress| {
+                if *progress == 100.0 {
+                    if let Some(sender) = sender.lock().take() {
+                        sender.send(()).unwrap();
+                    }
+                }
+            }
+        }))
+        .detach();
+    let fast_cache = DiskPieceCache::open(path1.as_ref(), 1, None, None)
+        .unwrap()
+        .with_tier(PieceCacheTier::Fast);
+    let slow_cache = DiskPieceCache::open(path2.as_ref(), 1, None, None).unwrap();
+    let fast_cache_id = *fast_cache.id();
+    let slow_cache_id = *slow_cache.id();
+    farmer_cache
+        .replace_backing_caches(
+            vec![Arc::new(fast_cache.clone()), Arc::new(slow_cache.clone())],
+            vec![],
+        )
+        .await;
+
+    // Wait for piece cache to be initialized
+    receiver.await.unwrap();
+
+    // Worker only processes commands after subscribing to segment headers
+    let (mut archived_segment_headers_sender, archived_segment_headers_receiver) = mpsc::channel(0);
+    archived_segment_headers_stream_request_receiver
+        .next()
+        .await
+        .unwrap()
+        .send(archived_segment_headers_receiver)
+        .unwrap();
+
+    // Both caches are filled during initialization, one piece in each tier
+    let (fast_piece_index, slow_piece_index) = {
+        let stored_pieces = stored_pieces([&fast_cache, &slow_cache]).await;
+        (stored_pieces[0].0, stored_pieces[1].0)
+    };
+    assert_eq!(
+        farmer_cache
+            .find_piece(fast_piece_index)
+            .await
+            .map(|(cache_id, _offset)| cache_id),
+        Some(fast_cache_id)
+    );
+
+    // Reading piece from slow tier promotes it, while the least recently used piece in fast tier
+    // is demoted into its place
+    assert_eq!(
+        farmer_cache
+            .get_piece(slow_piece_index.to_multihash())
+            .await
+            .unwrap(),
+        pieces.lock()[&slow_piece_index]
+    );
+    wait_for_stored_piece_indices(
+        [&fast_cache, &slow_cache],
+        [slow_piece_index, fast_piece_index],
+    )
+    .await;
+
+    // Finding piece for retrieval by cluster cache promotes it as well
+    assert_eq!(
+        farmer_cache
+            .find_piece(fast_piece_index)
+            .await
+            .map(|(cache_id, _offset)| cache_id),
+        Some(slow_cache_id)
+    );
+    wait_for_stored_piece_indices(
+        [&fast_cache, &slow_cache],
+        [fast_piece_index, slow_piece_index],
+    )
+    .await;
+
+    // Pieces are intact after being moved around and are still available from farmer cache
+    for (piece_index, piece) in stored_pieces([&fast_cache, &slow_cache]).await {
+        assert_eq!(piece, pieces.lock()[&piece_index]);
+    }
+    assert_eq!(
+        farmer_cache
+            .get_piece(fast_piece_index.to_multihash())
+            .await
+            .unwrap(),
+        pieces.lock()[&fast_piece_index]
+    );
+    assert_eq!(
+        farmer_cache
+            .find_piece(slow_piece_index)
+            .await
+            .map(|(cache_id, _offset)| cache_id),
+        Some(slow_cache_id)
+    );
+
+    drop(farmer_cache);
+    archived_segment_headers_sender.close().await.unwrap();
+    farmer_cache_worker_exited.await.unwrap();
+}
+
+#[test]
+fn piece_index_from_record_key() {
+    let piece_index = PieceIndex::from(42);
+    assert_eq!(
+        piece_index_from_key(&RecordKey::from(piece_index.to_multihash())),
+        Some(piece_index)
+    );
+    assert_eq!(piece_index_from_key(&RecordKey::from(vec![1, 2, 3])), None);
+}
+
+#[test]
+fn fast_tier_recency() {
+    let keys = (0..3)
+        .map(|piece_index| RecordKey::from(PieceIndex::from(piece_index).to_multihash()))
+        .collect::<Vec<_>>();
+    let mut recency = FastTierRecency::default();
+
+    for key in &keys {
+        recency.insert(key.clone());
+    }
+    assert_eq!(recency.least_recently_used(), Some(&keys[0]));
+
+    // Accessed piece becomes the most recently used one
+    recency.touch(&keys[0]);
+    assert_eq!(recency.least_recently_used(), Some(&keys[1]));
+
+    recency.remove(&keys[1]);
+    assert_eq!(recency.least_recently_used(), Some(&keys[2]));
+
+    // Pieces that are not in fast tier are ignored
+    let unknown_key = RecordKey::from(PieceIndex::from(10).to_multihash());
+    recency.touch(&unknown_key);
+    recency.remove(&keys[2]);
+    assert_eq!(recency.least_recently_used(), Some(&keys[0]));
+
+    recency.remove(&keys[0]);
+    assert_eq!(recency.least_recently_used(), None);
+}
@@ -1,7 +1,16 @@
 //! Farmer-specific piece getter
 
+mod metrics;
+pub mod piece_source;
+pub mod piece_validator;
+mod source_ranking;
+
 use crate::farm::plotted_pieces::PlottedPieces;
 use crate::farmer_cache::FarmerCache;
+use crate::farmer_piece_getter::metrics::{FarmerPieceGetterMetrics, SourceRequestResult};
+use crate::farmer_piece_getter::piece_source::FarmerPieceSource;
+use crate::farmer_piece_getter::piece_validator::SourcePieceValidator;
+use crate::farmer_piece_getter::source_ranking::SourceRanking;
 use crate::node_client::NodeClient;
 use async_lock::{
     Mutex as AsyncMutex, MutexGuardArc as AsyncMutexGuardArc, RwLock as AsyncRwLock, Semaphore,
@@ -10,7 +19,11 @@ use async_trait::async_trait;
 use backoff::backoff::Backoff;
 use backoff::future::retry;
 use backoff::ExponentialBackoff;
+use futures::channel::mpsc;
+use futures::stream::{BoxStream, FuturesUnordered};
+use futures::{future, stream, FutureExt, StreamExt};
 use parking_lot::Mutex;
+use prometheus_client::registry::Registry;
 use std::collections::HashMap;
 use std::error::Error;
 use std::fmt;
@@ -18,13 +31,12 @@ use std::hash::Hash;
 use std::num::NonZeroUsize;
 use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
 use subspace_core_primitives::{Piece, PieceIndex};
 use subspace_farmer_components::PieceGetter;
 use subspace_networking::utils::multihash::ToMultihash;
-use subspace_networking::utils::piece_provider::{PieceProvider, PieceValidator};
-use tracing::{debug, error, trace};
-
-pub mod piece_validator;
+use subspace_networking::utils::piece_provider::PieceProvider;
+use tracing::{debug, trace};
 
 const MAX_RANDOM_WALK_ROUNDS: usize = 15;
 
@@ -108,8 +120,12 @@ struct Inner<FarmIndex, CacheIndex, PV, NC> {
     node_client: NC,
     plotted_pieces: Arc<AsyncRwLock<PlottedPieces<FarmIndex>>>,
     dsn_cache_retry_policy: DsnCacheRetryPolicy,
+    sources: Vec<FarmerPieceSource>,
+    source_ranking: SourceRanking,
     in_progress_pieces: Mutex<HashMap<PieceIndex, Arc<AsyncMutex<Option<Piece>>>>>,
     request_semaphore: Arc<Semaphore>,
+    request_concurrency: NonZeroUsize,
+    metrics: Option<FarmerPieceGetterMetrics>,
 }
 
 /// Farmer-specific piece getter.
@@ -144,10 +160,15 @@ where
     CacheIndex: Hash + Eq + Copy + fmt::Debug + fmt::Display + Send + Sync + 'static,
     usize: From<CacheIndex>,
     CacheIndex: TryFrom<usize>,
-    PV: PieceValidator + Send + 'static,
+    PV: SourcePieceValidator + Send + 'static,
     NC: NodeClient,
 {
-    /// Create new instance
+    /// Create new instance.
+    ///
+    /// Pieces are retrieved from specified `sources`, see [`FarmerPieceSource`] for details about
+    /// the order in which they are used. [`FarmerPieceSource::default_sources()`] are used if
+    /// `sources` is empty.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         piece_provider: PieceProvider<PV>,
         farmer_cache: FarmerCache<CacheIndex>,
@@ -155,8 +176,16 @@ where
         plotted_pieces: Arc<AsyncRwLock<PlottedPieces<FarmIndex>>>,
         dsn_cache_retry_policy: DsnCacheRetryPolicy,
         request_concurrency: NonZeroUsize,
+        mut sources: Vec<FarmerPieceSource>,
+        registry: Option<&mut Registry>,
     ) -> Self {
+        if sources.is_empty() {
+            sources = FarmerPieceSource::default_sources();
+        }
         let request_semaphore = Arc::new(Semaphore::new(request_concurrency.get()));
+        let source_ranking = SourceRanking::new(sources.len());
+        let metrics = registry.map(FarmerPieceGetterMetrics::new);
+
         Self {
             inner: Arc::new(Inner {
                 piece_provider,
@@ -164,8 +193,12 @@ where
                 node_client,
                 plotted_pieces,
                 dsn_cache_retry_policy,
+                sources,
+                source_ranking,
                 in_progress_pieces: Mutex::default(),
                 request_semaphore,
+                request_concurrency,
+                metrics,
             }),
         }
     }
@@ -194,53 +227,7 @@ where
     }
 
     async fn get_piece_fast_internal(&self, piece_index: PieceIndex) -> Option<Piece> {
-        let inner = &self.inner;
-
-        trace!(%piece_index, "Getting piece from farmer cache");
-        if let Some(piece) = inner
-            .farmer_cache
-            .get_piece(piece_index.to_multihash())
-            .await
-        {
-            trace!(%piece_index, "Got piece from farmer cache successfully");
-            return Some(piece);
-        }
-
-        // L2 piece acquisition
-        trace!(%piece_index, "Getting piece from DSN L2 cache");
-        if let Some(piece) = inner.piece_provider.get_piece_from_cache(piece_index).await {
-            trace!(%piece_index, "Got piece from DSN L2 cache");
-            inner
-                .farmer_cache
-                .maybe_store_additional_piece(piece_index, &piece)
-                .await;
-            return Some(piece);
-        }
-
-        // Try node's RPC before reaching to L1 (archival storage on DSN)
-        trace!(%piece_index, "Getting piece from node");
-        match inner.node_client.piece(piece_index).await {
-            Ok(Some(piece)) => {
-                trace!(%piece_index, "Got piece from node successfully");
-                inner
-                    .farmer_cache
-                    .maybe_store_additional_piece(piece_index, &piece)
-                    .await;
-                return Some(piece);
-            }
-            Ok(None) => {
-                // Nothing to do
-            }
-            Err(error) => {
-                error!(
-                    %error,
-                    %piece_index,
-                    "Failed to retrieve first segment piece from node"
-                );
-            }
-        }
-
-        None
+        self.get_piece_from_sources(piece_index, false).await
     }
 
     /// Slow way to get piece using archival storage
@@ -276,43 +263,162 @@ where
 
     /// Slow way to get piece using archival storage
     async fn get_piece_slow_internal(&self, piece_index: PieceIndex) -> Option<Piece> {
+        self.get_piece_from_sources(piece_index, true).await
+    }
+
+    /// Indices of fast or slow sources, starting with the best ranked source
+    fn ranked_sources(&self, slow: bool) -> Vec<usize> {
         let inner = &self.inner;
 
-        trace!(%piece_index, "Getting piece from local plot");
-        let maybe_read_piece_fut = inner
-            .plotted_pieces
-            .try_read()
-            .and_then(|plotted_pieces| plotted_pieces.read_piece(piece_index));
-
-        if let Some(read_piece_fut) = maybe_read_piece_fut {
-            if let Some(piece) = read_piece_fut.await {
-                trace!(%piece_index, "Got piece from local plot successfully");
-                inner
-                    .farmer_cache
-                    .maybe_store_additional_piece(piece_index, &piece)
-                    .await;
+        let source_indices = inner
+            .sources
+            .iter()
+            .enumerate()
+            .filter(|(_source_index, source)| source.is_slow() == slow)
+            .map(|(source_index, _source)| source_index);
+
+        inner.source_ranking.rank(source_indices)
+    }
+
+    /// Try fast or slow sources one by one, starting with the best ranked source
+    async fn get_piece_from_sources(&self, piece_index: PieceIndex, slow: bool) -> Option<Piece> {
+        for source_index in self.ranked_sources(slow) {
+            if let Some(piece) = self
+                .get_piece_from_ranked_source(source_index, piece_index)
+                .await
+            {
                 return Some(piece);
             }
         }
 
-        // L1 piece acquisition
-        trace!(%piece_index, "Getting piece from DSN L1.");
+        None
+    }
 
-        let archival_storage_search_result = inner
-            .piece_provider
-            .get_piece_from_archival_storage(piece_index, MAX_RANDOM_WALK_ROUNDS)
-            .await;
+    /// Get piece from source, updating its ranking and metrics with the result
+    async fn get_piece_from_ranked_source(
+        &self,
+        source_index: usize,
+        piece_index: PieceIndex,
+    ) -> Option<Piece> {
+        let source = &self.inner.sources[source_index];
+
+        trace!(%piece_index, %source, "Getting piece from source");
+        let start = Instant::now();
+        let result = self.get_piece_from_source(source, piece_index).await;
+        self.observe_source_request(source_index, start.elapsed(), &result);
+
+        self.process_source_result(source_index, piece_index, result)
+            .await
+    }
 
-        if let Some(piece) = archival_storage_search_result {
-            trace!(%piece_index, "DSN L1 lookup succeeded");
+    /// Update source ranking and metrics with the result of request to the source
+    fn observe_source_request(
+        &self,
+        source_index: usize,
+        request_time: Duration,
+        result: &Result<Option<Piece>, Box<dyn Error + Send + Sync + 'static>>,
+    ) {
+        let inner = &self.inner;
+
+        let score =
             inner
-                .farmer_cache
-                .maybe_store_additional_piece(piece_index, &piece)
-                .await;
-            return Some(piece);
+                .source_ranking
+                .observe(source_index, request_time, matches!(result, Ok(Some(_))));
+        if let Some(metrics) = &inner.metrics {
+            let request_result = match result {
+                Ok(Some(_piece)) => SourceRequestResult::Found,
+                Ok(None) => SourceRequestResult::NotFound,
+                Err(_error) => SourceRequestResult::Error,
+            };
+            metrics.observe_source_request(
+                inner.sources[source_index].name(),
+                request_time,
+                request_result,
+                score,
+            );
         }
+    }
 
-        None
+    /// Pieces retrieved from sources other than farmer cache are stored in farmer cache if needed
+    async fn process_source_result(
+        &self,
+        source_index: usize,
+        piece_index: PieceIndex,
+        result: Result<Option<Piece>, Box<dyn Error + Send + Sync + 'static>>,
+    ) -> Option<Piece> {
+        let inner = &self.inner;
+        let source = &inner.sources[source_index];
+
+        match result {
+            Ok(Some(piece)) => {
+                trace!(%piece_index, %source, "Got piece from source successfully");
+                if !matches!(source, FarmerPieceSource::FarmerCache) {
+                    inner
+                        .farmer_cache
+                        .maybe_store_additional_piece(piece_index, &piece)
+                        .await;
+                }
+                Some(piece)
+            }
+            Ok(None) => {
+                trace!(%piece_index, %source, "Source doesn't have piece");
+                None
+            }
+            Err(error) => {
+                debug!(%error, %piece_index, %source, "Failed to get piece from source");
+                None
+            }
+        }
+    }
+
+    async fn get_piece_from_source(
+        &self,
+        source: &FarmerPieceSource,
+        piece_index: PieceIndex,
+    ) -> Result<Option<Piece>, Box<dyn Error + Send + Sync + 'static>> {
+        let inner = &self.inner;
+
+        match source {
+            FarmerPieceSource::FarmerCache => Ok(inner
+                .farmer_cache
+                .get_piece(piece_index.to_multihash())
+                .await),
+            FarmerPieceSource::DsnCache => {
+                Ok(inner.piece_provider.get_piece_from_cache(piece_index).await)
+            }
+            FarmerPieceSource::Node => inner.node_client.piece(piece_index).await,
+            FarmerPieceSource::LocalPlots => {
+                let maybe_read_piece_fut = inner
+                    .plotted_pieces
+                    .try_read()
+                    .and_then(|plotted_pieces| plotted_pieces.read_piece(piece_index));
+
+                Ok(match maybe_read_piece_fut {
+                    Some(read_piece_fut) => read_piece_fut.await,
+                    None => None,
+                })
+            }
+            FarmerPieceSource::ArchivalStorage => Ok(inner
+                .piece_provider
+                .get_piece_from_archival_storage(piece_index, MAX_RANDOM_WALK_ROUNDS)
+                .await),
+            FarmerPieceSource::Custom(source) => {
+                let Some(piece) = source.get_piece(piece_index).await? else {
+                    return Ok(None);
+                };
+
+                // Custom sources are not trusted, piece that is invalid or could not be validated
+                // is treated as a failed request
+                Ok(Some(match inner.piece_provider.piece_validator() {
+                    Some(validator) => {
+                        validator
+                            .validate_piece_from_source(source.name(), piece_index, piece)
+                            .await?
+                    }
+                    None => piece,
+                }))
+            }
+        }
     }
 
     async fn get_piece_internal(&self, piece_index: PieceIndex) -> Option<Piece> {
@@ -363,6 +469,221 @@ where
         None
     }
 
+    /// Get a chunk of up to `request_concurrency` pieces, the whole chunk counts as a single
+    /// request towards request concurrency.
+    ///
+    /// Pieces are sent to `pieces_sender` as soon as they are retrieved, `fast_pass_lock` is held
+    /// while fast sources are tried, such that provider lookups of concurrently processed chunks
+    /// don't exceed request concurrency.
+    async fn get_pieces_chunk(
+        &self,
+        piece_indices: Vec<PieceIndex>,
+        fast_pass_lock: &AsyncMutex<()>,
+        pieces_sender: &mpsc::UnboundedSender<(PieceIndex, Option<Piece>)>,
+    ) {
+        let _guard = self.inner.request_semaphore.acquire().await;
+
+        let mut in_progress_pieces_getting = Vec::with_capacity(piece_indices.len());
+        let waiting_pieces = FuturesUnordered::new();
+        for piece_index in piece_indices {
+            match InProgressPiece::new(piece_index, &self.inner.in_progress_pieces) {
+                InProgressPiece::Getting(in_progress_piece_getting) => {
+                    in_progress_pieces_getting.push(in_progress_piece_getting);
+                }
+                InProgressPiece::Waiting {
+                    in_progress_piece_mutex,
+                } => {
+                    waiting_pieces.push(async move {
+                        trace!(
+                            %piece_index,
+                            "Piece is already in progress, waiting for result in batch"
+                        );
+                        if let Some(piece) = in_progress_piece_mutex.lock().await.clone() {
+                            trace!(
+                                %piece_index,
+                                "Piece was already in progress and downloaded successfully in \
+                                batch"
+                            );
+                            return (piece_index, Some(piece));
+                        }
+
+                        // Try again just in case
+                        (piece_index, self.get_piece_internal(piece_index).await)
+                    });
+                }
+            }
+        }
+
+        // Pieces are waited for concurrently with getting, such that the same piece index
+        // requested twice doesn't wait for itself
+        future::join(
+            self.get_pieces_internal(in_progress_pieces_getting, fast_pass_lock, pieces_sender),
+            waiting_pieces.for_each(|piece| {
+                let _ = pieces_sender.unbounded_send(piece);
+
+                future::ready(())
+            }),
+        )
+        .await;
+    }
+
+    /// Batched version of [`Self::get_piece_internal()`], see [`Self::get_pieces_chunk()`] for
+    /// details about arguments
+    async fn get_pieces_internal<'a>(
+        &'a self,
+        in_progress_pieces_getting: Vec<InProgressPieceGetting<'a>>,
+        fast_pass_lock: &AsyncMutex<()>,
+        pieces_sender: &mpsc::UnboundedSender<(PieceIndex, Option<Piece>)>,
+    ) {
+        let mut in_progress_pieces_getting = in_progress_pieces_getting
+            .into_iter()
+            .map(|in_progress_piece_getting| {
+                (
+                    in_progress_piece_getting.piece_index,
+                    in_progress_piece_getting,
+                )
+            })
+            .collect::<HashMap<_, _>>();
+
+        let max_retries = u32::from(self.inner.dsn_cache_retry_policy.max_retries);
+        let mut backoff = self.inner.dsn_cache_retry_policy.backoff.clone();
+        backoff.reset();
+
+        for current_attempt in 0.. {
+            let missing_piece_indices = in_progress_pieces_getting.keys().copied().collect();
+            let pieces = {
+                let _fast_pass_guard = fast_pass_lock.lock().await;
+                self.get_pieces_fast_internal(missing_piece_indices).await
+            };
+            for (piece_index, piece) in pieces {
+                if let Some(in_progress_piece_getting) =
+                    in_progress_pieces_getting.remove(&piece_index)
+                {
+                    trace!(%piece_index, current_attempt, "Got piece fast");
+                    let maybe_piece = Some(piece);
+                    // Store the result for others to observe
+                    in_progress_piece_getting.store_piece_getting_result(&maybe_piece);
+                    let _ = pieces_sender.unbounded_send((piece_index, maybe_piece));
+                }
+            }
+
+            if in_progress_pieces_getting.is_empty() {
+                return;
+            }
+            if current_attempt >= max_retries {
+                if max_retries > 0 {
+                    debug!(
+                        missing_pieces = in_progress_pieces_getting.len(),
+                        current_attempt, max_retries, "Couldn't get pieces fast. No retries left"
+                    );
+                }
+                break;
+            }
+            let Some(delay) = backoff.next_backoff() else {
+                break;
+            };
+
+            trace!(
+                missing_pieces = in_progress_pieces_getting.len(),
+                current_attempt,
+                "Couldn't get pieces fast, retrying..."
+            );
+            tokio::time::sleep(delay).await;
+        }
+
+        in_progress_pieces_getting
+            .into_values()
+            .map(|in_progress_piece_getting| async move {
+                let piece_index = in_progress_piece_getting.piece_index;
+                let maybe_piece = self.get_piece_slow_internal(piece_index).await;
+                if maybe_piece.is_none() {
+                    debug!(
+                        %piece_index,
+                        "Cannot acquire piece: all methods yielded empty result"
+                    );
+                }
+                // Store the result for others to observe
+                in_progress_piece_getting.store_piece_getting_result(&maybe_piece);
+
+                (piece_index, maybe_piece)
+            })
+            .collect::<FuturesUnordered<_>>()
+            .for_each(|piece| {
+                let _ = pieces_sender.unbounded_send(piece);
+
+                future::ready(())
+            })
+            .await;
+    }
+
+    /// Try fast sources in the order of their ranking, requesting pieces from DSN cache in
+    /// batches (see [`PieceProvider::get_pieces_from_cache()`]) and from other sources one by
+    /// one. Returns pieces that were found.
+    async fn get_pieces_fast_internal(
+        &self,
+        piece_indices: Vec<PieceIndex>,
+    ) -> Vec<(PieceIndex, Piece)> {
+        let mut missing_piece_indices = piece_indices;
+        let mut pieces = Vec::with_capacity(missing_piece_indices.len());
+
+        for source_index in self.ranked_sources(false) {
+            if missing_piece_indices.is_empty() {
+                break;
+            }
+
+            let results = if matches!(
+                self.inner.sources[source_index],
+                FarmerPieceSource::DsnCache
+            ) {
+                let start = Instant::now();
+                self.inner
+                    .piece_provider
+                    .get_pieces_from_cache(missing_piece_indices)
+                    .then(|(piece_index, maybe_piece)| async move {
+                        let result = Ok(maybe_piece);
+                        // Time until piece is retrieved, such that batched requests are ranked
+                        // similarly to requests of individual pieces
+                        self.observe_source_request(source_index, start.elapsed(), &result);
+
+                        (
+                            piece_index,
+                            self.process_source_result(source_index, piece_index, result)
+                                .await,
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .await
+            } else {
+                missing_piece_indices
+                    .into_iter()
+                    .map(|piece_index| async move {
+                        (
+                            piece_index,
+                            self.get_piece_from_ranked_source(source_index, piece_index)
+                                .await,
+                        )
+                    })
+                    .collect::<FuturesUnordered<_>>()
+                    .collect::<Vec<_>>()
+                    .await
+            };
+
+            missing_piece_indices = Vec::new();
+            for (piece_index, maybe_piece) in results {
+                match maybe_piece {
+                    Some(piece) => {
+                        pieces.push((piece_index, piece));
+                    }
+                    None => {
+                        missing_piece_indices.push(piece_index);
+                    }
+                }
+            }
+        }
+
+        pieces
+    }
+
     /// Downgrade to [`WeakFarmerPieceGetter`] in order to break reference cycles with internally
     /// used [`Arc`]
     pub fn downgrade(&self) -> WeakFarmerPieceGetter<FarmIndex, CacheIndex, PV, NC> {
@@ -380,7 +701,7 @@ where
     CacheIndex: Hash + Eq + Copy + fmt::Debug + fmt::Display + Send + Sync + 'static,
     usize: From<CacheIndex>,
     CacheIndex: TryFrom<usize>,
-    PV: PieceValidator + Send + 'static,
+    PV: SourcePieceValidator + Send + 'static,
     NC: NodeClient,
 {
     async fn get_piece(
@@ -415,6 +736,57 @@ where
             }
         }
     }
+
+    fn get_pieces<'a>(
+        &'a self,
+        piece_indices: Vec<PieceIndex>,
+    ) -> BoxStream<
+        'a,
+        (
+            PieceIndex,
+            Result<Option<Piece>, Box<dyn Error + Send + Sync + 'static>>,
+        ),
+    > {
+        let use_dsn_cache = self
+            .inner
+            .sources
+            .iter()
+            .any(|source| matches!(source, FarmerPieceSource::DsnCache));
+
+        if !use_dsn_cache {
+            // Nothing to batch, get pieces one by one
+            return piece_indices
+                .into_iter()
+                .map(|piece_index| async move { (piece_index, self.get_piece(piece_index).await) })
+                .collect::<FuturesUnordered<_>>()
+                .boxed();
+        }
+
+        let chunks = piece_indices
+            .chunks(self.inner.request_concurrency.get())
+            .map(<[PieceIndex]>::to_vec)
+            .collect::<Vec<_>>();
+        let (pieces_sender, pieces_receiver) = mpsc::unbounded();
+
+        // Chunks are processed concurrently and pieces are yielded as soon as they are retrieved,
+        // such that pieces that take long to retrieve don't hold back the rest
+        let getting_fut = async move {
+            let fast_pass_lock = AsyncMutex::new(());
+
+            stream::iter(chunks)
+                .for_each_concurrent(None, |piece_indices| {
+                    self.get_pieces_chunk(piece_indices, &fast_pass_lock, &pieces_sender)
+                })
+                .await;
+        };
+
+        stream::select(
+            pieces_receiver.map(|(piece_index, maybe_piece)| Some((piece_index, Ok(maybe_piece)))),
+            getting_fut.into_stream().map(|()| None),
+        )
+        .filter_map(future::ready)
+        .boxed()
+    }
 }
 
 /// Weak farmer piece getter, can be upgraded to [`FarmerPieceGetter`]
@@ -450,7 +822,7 @@ where
     CacheIndex: Hash + Eq + Copy + fmt::Debug + fmt::Display + Send + Sync + 'static,
     usize: From<CacheIndex>,
     CacheIndex: TryFrom<usize>,
-    PV: PieceValidator + Send + 'static,
+    PV: SourcePieceValidator + Send + 'static,
     NC: NodeClient,
 {
     async fn get_piece(
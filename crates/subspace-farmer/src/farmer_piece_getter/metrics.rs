@@ -0,0 +1,96 @@
+//! Metrics for farmer piece getter
+
+use prometheus_client::metrics::counter::Counter;
+use prometheus_client::metrics::family::Family;
+use prometheus_client::metrics::gauge::Gauge;
+use prometheus_client::metrics::histogram::{exponential_buckets, Histogram};
+use prometheus_client::registry::{Registry, Unit};
+use std::sync::atomic::AtomicU64;
+use std::time::Duration;
+
+/// Result of a request to piece source
+#[derive(Debug, Copy, Clone)]
+pub(super) enum SourceRequestResult {
+    Found,
+    NotFound,
+    Error,
+}
+
+impl SourceRequestResult {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Found => "found",
+            Self::NotFound => "not_found",
+            Self::Error => "error",
+        }
+    }
+}
+
+/// Metrics for farmer piece getter
+#[derive(Debug)]
+pub(super) struct FarmerPieceGetterMetrics {
+    source_requests: Family<Vec<(&'static str, String)>, Counter<u64, AtomicU64>>,
+    source_request_time: Family<Vec<(&'static str, String)>, Histogram>,
+    source_score: Family<Vec<(&'static str, String)>, Gauge<f64, AtomicU64>>,
+}
+
+impl FarmerPieceGetterMetrics {
+    /// Create new instance
+    pub(super) fn new(registry: &mut Registry) -> Self {
+        let registry = registry.sub_registry_with_prefix("farmer_piece_getter");
+
+        let source_requests = Family::default();
+        registry.register_with_unit(
+            "source_requests",
+            "Requests to piece sources by source and result",
+            Unit::Other("Requests".to_string()),
+            source_requests.clone(),
+        );
+
+        let source_request_time = Family::<_, _>::new_with_constructor(|| {
+            Histogram::new(exponential_buckets(0.0001, 2.0, 20))
+        });
+        registry.register_with_unit(
+            "source_request_time",
+            "Time of requests to piece sources by source",
+            Unit::Seconds,
+            source_request_time.clone(),
+        );
+
+        let source_score = Family::default();
+        registry.register_with_unit(
+            "source_score",
+            "Expected time to get a piece from piece source, sources with lower score are tried \
+            first",
+            Unit::Seconds,
+            source_score.clone(),
+        );
+
+        Self {
+            source_requests,
+            source_request_time,
+            source_score,
+        }
+    }
+
+    pub(super) fn observe_source_request(
+        &self,
+        source: &str,
+        time: Duration,
+        result: SourceRequestResult,
+        score: f64,
+    ) {
+        self.source_requests
+            .get_or_create(&vec![
+                ("source", source.to_string()),
+                ("result", result.as_str().to_string()),
+            ])
+            .inc();
+        self.source_request_time
+            .get_or_create(&vec![("source", source.to_string())])
+            .observe(time.as_secs_f64());
+        self.source_score
+            .get_or_create(&vec![("source", source.to_string())])
+            .set(score);
+    }
+}
@@ -0,0 +1,127 @@
+//! Sources of pieces used by [`FarmerPieceGetter`](super::FarmerPieceGetter)
+//!
+//! Besides built-in sources (farmer cache, DSN, node RPC, etc.) custom sources can be plugged in by
+//! implementing [`PieceSource`] trait, [`http`] and [`directory`] modules contain implementations
+//! for HTTP mirror and local directory of exported pieces.
+
+pub mod directory;
+pub mod http;
+
+use crate::farmer_piece_getter::piece_source::directory::DirectoryPieceSource;
+use crate::farmer_piece_getter::piece_source::http::HttpPieceSource;
+use async_trait::async_trait;
+use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use subspace_core_primitives::{Piece, PieceIndex};
+
+/// Custom source of pieces.
+///
+/// Pieces returned by custom sources are validated the same way as pieces from DSN, invalid pieces
+/// are treated as failed requests and never stored in farmer cache.
+#[async_trait]
+pub trait PieceSource: fmt::Debug + Send + Sync {
+    /// Name of the source, used in logs and metrics
+    fn name(&self) -> &str;
+
+    /// Get piece from this source, `Ok(None)` means source doesn't have requested piece
+    async fn get_piece(
+        &self,
+        piece_index: PieceIndex,
+    ) -> Result<Option<Piece>, Box<dyn Error + Send + Sync + 'static>>;
+}
+
+/// Source of pieces used by [`FarmerPieceGetter`](super::FarmerPieceGetter).
+///
+/// Sources are split into fast and slow (see [`FarmerPieceSource::is_slow()`]), slow sources are
+/// only used after all fast sources were tried (multiple times if configured with retries). Within
+/// each group sources are ordered by observed latency and success rate, with configured order only
+/// used before there are enough observations.
+#[derive(Debug, Clone)]
+pub enum FarmerPieceSource {
+    /// Pieces stored in farmer cache
+    FarmerCache,
+    /// Pieces cached by other peers on DSN (L2)
+    DsnCache,
+    /// Node RPC
+    Node,
+    /// Pieces decoded from local plots
+    LocalPlots,
+    /// Pieces stored in plots of other farmers on DSN (L1)
+    ArchivalStorage,
+    /// Custom source of pieces
+    Custom(Arc<dyn PieceSource>),
+}
+
+impl fmt::Display for FarmerPieceSource {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for FarmerPieceSource {
+    type Err = String;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "farmer-cache" => Ok(Self::FarmerCache),
+            "dsn-cache" => Ok(Self::DsnCache),
+            "node" => Ok(Self::Node),
+            "local-plots" => Ok(Self::LocalPlots),
+            "archival-storage" => Ok(Self::ArchivalStorage),
+            s => {
+                if let Some(base_url) = s.strip_prefix("http=") {
+                    HttpPieceSource::new(base_url)
+                        .map(|source| Self::Custom(Arc::new(source)))
+                        .map_err(|error| {
+                            format!("Invalid HTTP piece source \"{base_url}\": {error}")
+                        })
+                } else if let Some(directory) = s.strip_prefix("directory=") {
+                    Ok(Self::Custom(Arc::new(DirectoryPieceSource::new(
+                        PathBuf::from(directory),
+                    ))))
+                } else {
+                    Err(format!(
+                        "Unsupported piece source \"{s}\", only `farmer-cache`, `dsn-cache`, \
+                        `node`, `local-plots`, `archival-storage`, `http=<url>` or \
+                        `directory=<path>` are allowed"
+                    ))
+                }
+            }
+        }
+    }
+}
+
+impl FarmerPieceSource {
+    /// Sources used by default, in the order they are tried initially
+    pub fn default_sources() -> Vec<Self> {
+        vec![
+            Self::FarmerCache,
+            Self::DsnCache,
+            Self::Node,
+            Self::LocalPlots,
+            Self::ArchivalStorage,
+        ]
+    }
+
+    /// Name of the source, used in logs and metrics
+    pub fn name(&self) -> &str {
+        match self {
+            Self::FarmerCache => "farmer-cache",
+            Self::DsnCache => "dsn-cache",
+            Self::Node => "node",
+            Self::LocalPlots => "local-plots",
+            Self::ArchivalStorage => "archival-storage",
+            Self::Custom(source) => source.name(),
+        }
+    }
+
+    /// Whether source is slow and should only be used after fast sources were exhausted
+    pub fn is_slow(&self) -> bool {
+        matches!(self, Self::LocalPlots | Self::ArchivalStorage)
+    }
+}
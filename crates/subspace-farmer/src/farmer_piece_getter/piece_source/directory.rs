@@ -0,0 +1,63 @@
+//! Piece source that reads pieces from local directory
+
+use crate::farmer_piece_getter::piece_source::PieceSource;
+use async_trait::async_trait;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+use subspace_core_primitives::{Piece, PieceIndex};
+use tokio::task;
+
+/// Piece source that reads pieces from local directory.
+///
+/// Each piece is stored in a separate file named after its piece index with `.piece` extension
+/// (for example `12345.piece`) and contains raw piece bytes.
+#[derive(Debug)]
+pub struct DirectoryPieceSource {
+    directory: PathBuf,
+    name: String,
+}
+
+#[async_trait]
+impl PieceSource for DirectoryPieceSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn get_piece(
+        &self,
+        piece_index: PieceIndex,
+    ) -> Result<Option<Piece>, Box<dyn Error + Send + Sync + 'static>> {
+        let path = Self::piece_path(&self.directory, piece_index);
+
+        let maybe_bytes = task::spawn_blocking(move || match fs::read(path) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error),
+        })
+        .await??;
+
+        let Some(bytes) = maybe_bytes else {
+            return Ok(None);
+        };
+        let piece_size = bytes.len();
+
+        Piece::try_from(bytes)
+            .map(Some)
+            .map_err(|()| format!("Piece {piece_index} has invalid size {piece_size}").into())
+    }
+}
+
+impl DirectoryPieceSource {
+    /// Create new instance that reads pieces from specified directory
+    pub fn new(directory: PathBuf) -> Self {
+        let name = format!("directory:{}", directory.display());
+
+        Self { directory, name }
+    }
+
+    /// Path of the file where piece with specified index is stored in a directory
+    pub fn piece_path(directory: &Path, piece_index: PieceIndex) -> PathBuf {
+        directory.join(format!("{piece_index}.piece"))
+    }
+}
@@ -0,0 +1,93 @@
+//! Piece source that downloads pieces from HTTP mirror
+
+use crate::farmer_piece_getter::piece_source::PieceSource;
+use async_trait::async_trait;
+use bytes::Bytes;
+use http_body_util::{BodyExt, Empty, Limited};
+use hyper::{Request, StatusCode, Uri};
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::TokioExecutor;
+use std::error::Error;
+use std::time::Duration;
+use subspace_core_primitives::{Piece, PieceIndex};
+
+/// Timeout for the whole request, including connection establishment and downloading of the piece
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Piece source that downloads pieces from HTTP mirror.
+///
+/// Pieces are requested with `GET <base-url>/<piece-index>`, mirror is expected to respond with raw
+/// piece bytes or with `404 Not Found` if it doesn't have requested piece. Only plain HTTP is
+/// supported, TLS can be terminated by reverse proxy in front of the mirror if necessary.
+///
+/// Connections to the mirror are pooled and reused by subsequent requests.
+#[derive(Debug)]
+pub struct HttpPieceSource {
+    base_url: String,
+    name: String,
+    client: Client<HttpConnector, Empty<Bytes>>,
+}
+
+#[async_trait]
+impl PieceSource for HttpPieceSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn get_piece(
+        &self,
+        piece_index: PieceIndex,
+    ) -> Result<Option<Piece>, Box<dyn Error + Send + Sync + 'static>> {
+        tokio::time::timeout(REQUEST_TIMEOUT, self.request_piece(piece_index))
+            .await
+            .map_err(|_elapsed| format!("Request for piece {piece_index} timed out"))?
+    }
+}
+
+impl HttpPieceSource {
+    /// Create new instance for mirror at specified base URL, for example
+    /// `http://mirror.local:8080/pieces`
+    pub fn new(base_url: &str) -> Result<Self, Box<dyn Error + Send + Sync + 'static>> {
+        let uri = base_url.parse::<Uri>()?;
+        if uri.scheme_str() != Some("http") {
+            return Err("Only `http` scheme is supported".into());
+        }
+        let authority = uri.authority().ok_or("Host is missing")?;
+
+        Ok(Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            name: format!("http:{authority}"),
+            client: Client::builder(TokioExecutor::new()).build_http(),
+        })
+    }
+
+    async fn request_piece(
+        &self,
+        piece_index: PieceIndex,
+    ) -> Result<Option<Piece>, Box<dyn Error + Send + Sync + 'static>> {
+        let request =
+            Request::get(format!("{}/{piece_index}", self.base_url)).body(Empty::<Bytes>::new())?;
+        let response = self.client.request(request).await?;
+
+        match response.status() {
+            StatusCode::OK => {}
+            StatusCode::NOT_FOUND => {
+                return Ok(None);
+            }
+            status => {
+                return Err(format!("Unexpected response status {status}").into());
+            }
+        }
+
+        let bytes = Limited::new(response.into_body(), Piece::SIZE)
+            .collect()
+            .await?
+            .to_bytes();
+        let piece_size = bytes.len();
+
+        Piece::try_from(bytes)
+            .map(Some)
+            .map_err(|()| format!("Piece {piece_index} has invalid size {piece_size}").into())
+    }
+}
@@ -4,15 +4,42 @@ use crate::node_client::NodeClient;
 use async_trait::async_trait;
 use subspace_archiving::archiver::is_piece_valid;
 use subspace_core_primitives::crypto::kzg::Kzg;
-use subspace_core_primitives::{Piece, PieceIndex};
+use subspace_core_primitives::{Piece, PieceIndex, SegmentCommitment};
 use subspace_networking::libp2p::PeerId;
-use subspace_networking::utils::piece_provider::PieceValidator;
+use subspace_networking::utils::piece_provider::{
+    NoPieceValidator, PieceValidationError, PieceValidator,
+};
 use subspace_networking::Node;
 use tracing::{error, warn};
 
+/// Validates pieces retrieved from sources other than DSN (like an HTTP mirror or a local
+/// directory), see [`FarmerPieceSource::Custom`](super::piece_source::FarmerPieceSource::Custom).
+#[async_trait]
+pub trait SourcePieceValidator: PieceValidator {
+    /// Validates piece retrieved from a source other than DSN, `source` is its name used in logs
+    async fn validate_piece_from_source(
+        &self,
+        source: &str,
+        piece_index: PieceIndex,
+        piece: Piece,
+    ) -> Result<Piece, PieceValidationError>;
+}
+
+#[async_trait]
+impl SourcePieceValidator for NoPieceValidator {
+    async fn validate_piece_from_source(
+        &self,
+        _: &str,
+        _: PieceIndex,
+        piece: Piece,
+    ) -> Result<Piece, PieceValidationError> {
+        Ok(piece)
+    }
+}
+
 /// Farmer-specific validator for pieces retrieved from the network.
 ///
-/// Implements [`PieceValidator`].
+/// Implements [`PieceValidator`] and [`SourcePieceValidator`].
 #[derive(Debug, Clone)]
 pub struct SegmentCommitmentPieceValidator<NC> {
     dsn_node: Node,
@@ -31,21 +58,11 @@ impl<NC> SegmentCommitmentPieceValidator<NC> {
     }
 }
 
-#[async_trait]
-impl<NC> PieceValidator for SegmentCommitmentPieceValidator<NC>
+impl<NC> SegmentCommitmentPieceValidator<NC>
 where
     NC: NodeClient,
 {
-    async fn validate_piece(
-        &self,
-        source_peer_id: PeerId,
-        piece_index: PieceIndex,
-        piece: Piece,
-    ) -> Option<Piece> {
-        if source_peer_id == self.dsn_node.id() {
-            return Some(piece);
-        }
-
+    async fn segment_commitment(&self, piece_index: PieceIndex) -> Option<SegmentCommitment> {
         let segment_index = piece_index.segment_index();
 
         let segment_headers = match self.node_client.segment_headers(vec![segment_index]).await {
@@ -60,40 +77,103 @@ where
             }
         };
 
-        let segment_commitment = match segment_headers.into_iter().next().flatten() {
-            Some(segment_header) => segment_header.segment_commitment(),
+        match segment_headers.into_iter().next().flatten() {
+            Some(segment_header) => Some(segment_header.segment_commitment()),
             None => {
                 error!(
                     %piece_index,
                     %segment_index,
                     "Segment commitment for segment index wasn't found on node"
                 );
-                return None;
+                None
             }
-        };
+        }
+    }
 
+    async fn is_piece_valid(
+        &self,
+        piece_index: PieceIndex,
+        piece: Piece,
+        segment_commitment: SegmentCommitment,
+    ) -> Result<Piece, PieceValidationError> {
         let is_valid_fut = tokio::task::spawn_blocking({
             let kzg = self.kzg.clone();
 
             move || {
                 is_piece_valid(&kzg, &piece, &segment_commitment, piece_index.position())
                     .then_some(piece)
+                    .ok_or(PieceValidationError::Invalid)
             }
         });
 
-        match is_valid_fut.await.unwrap_or_default() {
-            Some(piece) => Some(piece),
-            None => {
-                warn!(
-                    %piece_index,
-                    %source_peer_id,
-                    "Received invalid piece from peer"
-                );
+        is_valid_fut.await.unwrap_or_else(|error| {
+            error!(%piece_index, %error, "Failed to spawn piece validation task");
+            Err(PieceValidationError::CouldNotValidate)
+        })
+    }
+}
 
-                // We don't care about result here
-                let _ = self.dsn_node.ban_peer(source_peer_id).await;
-                None
-            }
+#[async_trait]
+impl<NC> PieceValidator for SegmentCommitmentPieceValidator<NC>
+where
+    NC: NodeClient,
+{
+    async fn validate_piece(
+        &self,
+        source_peer_id: PeerId,
+        piece_index: PieceIndex,
+        piece: Piece,
+    ) -> Result<Piece, PieceValidationError> {
+        if source_peer_id == self.dsn_node.id() {
+            return Ok(piece);
         }
+
+        let segment_commitment = self
+            .segment_commitment(piece_index)
+            .await
+            .ok_or(PieceValidationError::CouldNotValidate)?;
+
+        let result = self
+            .is_piece_valid(piece_index, piece, segment_commitment)
+            .await;
+        if let Err(PieceValidationError::Invalid) = &result {
+            warn!(
+                %piece_index,
+                %source_peer_id,
+                "Received invalid piece from peer"
+            );
+
+            // We don't care about result here
+            let _ = self.dsn_node.ban_peer(source_peer_id).await;
+        }
+
+        result
+    }
+}
+
+#[async_trait]
+impl<NC> SourcePieceValidator for SegmentCommitmentPieceValidator<NC>
+where
+    NC: NodeClient,
+{
+    async fn validate_piece_from_source(
+        &self,
+        source: &str,
+        piece_index: PieceIndex,
+        piece: Piece,
+    ) -> Result<Piece, PieceValidationError> {
+        let segment_commitment = self
+            .segment_commitment(piece_index)
+            .await
+            .ok_or(PieceValidationError::CouldNotValidate)?;
+
+        let result = self
+            .is_piece_valid(piece_index, piece, segment_commitment)
+            .await;
+        if let Err(PieceValidationError::Invalid) = &result {
+            warn!(%piece_index, %source, "Received invalid piece from source");
+        }
+
+        result
     }
 }
@@ -0,0 +1,127 @@
+//! Ranking of piece sources by observed latency and success rate
+
+#[cfg(test)]
+mod tests;
+
+use parking_lot::Mutex;
+use std::time::{Duration, Instant};
+
+/// Weight of the latest observation in exponential moving averages, higher values make ranking
+/// react to changes faster
+const EMA_WEIGHT: f64 = 0.1;
+/// Lower bound for success rate, such that sources that never succeed still have finite score and
+/// can recover eventually
+const MIN_SUCCESS_RATE: f64 = 0.01;
+/// Observations lose half of their weight after this time.
+///
+/// Score of a source that is not requested decays towards score of a source without observations,
+/// such that demoted sources are probed again eventually, and the next observation after a long
+/// pause outweighs old ones.
+const STATS_HALF_LIFE: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Default, Copy, Clone)]
+struct SourceStats {
+    /// Average latency in seconds
+    latency: f64,
+    /// Share of requests that returned a piece
+    success_rate: f64,
+    last_observation: Option<Instant>,
+}
+
+impl SourceStats {
+    fn observe(&mut self, now: Instant, latency: Duration, success: bool) {
+        let latency = latency.as_secs_f64();
+        let success = if success { 1.0 } else { 0.0 };
+
+        match self.last_observation {
+            Some(last_observation) => {
+                let weight = EMA_WEIGHT.max(1.0 - decay(now, last_observation));
+                self.latency += (latency - self.latency) * weight;
+                self.success_rate += (success - self.success_rate) * weight;
+            }
+            None => {
+                self.latency = latency;
+                self.success_rate = success;
+            }
+        }
+        self.last_observation = Some(now);
+    }
+
+    /// Expected time in seconds to get a piece from this source, lower is better.
+    ///
+    /// Sources without observations have score of zero, such that they are tried before others.
+    fn score(&self, now: Instant) -> f64 {
+        let Some(last_observation) = self.last_observation else {
+            return 0.0;
+        };
+
+        self.latency / self.success_rate.max(MIN_SUCCESS_RATE) * decay(now, last_observation)
+    }
+}
+
+/// Remaining weight of observation made at `then`, from `1.0` right away towards `0.0` as time goes
+fn decay(now: Instant, then: Instant) -> f64 {
+    let elapsed = now.saturating_duration_since(then);
+    0.5_f64.powf(elapsed.as_secs_f64() / STATS_HALF_LIFE.as_secs_f64())
+}
+
+/// Ranking of piece sources by observed latency and success rate
+#[derive(Debug)]
+pub(super) struct SourceRanking {
+    stats: Mutex<Vec<SourceStats>>,
+}
+
+impl SourceRanking {
+    /// Create new instance for specified number of sources
+    pub(super) fn new(num_sources: usize) -> Self {
+        Self {
+            stats: Mutex::new(vec![SourceStats::default(); num_sources]),
+        }
+    }
+
+    /// Record result of a request to source with specified index, returns updated score of the
+    /// source
+    pub(super) fn observe(&self, source_index: usize, latency: Duration, success: bool) -> f64 {
+        self.observe_at(Instant::now(), source_index, latency, success)
+    }
+
+    fn observe_at(
+        &self,
+        now: Instant,
+        source_index: usize,
+        latency: Duration,
+        success: bool,
+    ) -> f64 {
+        let mut stats = self.stats.lock();
+        let source_stats = &mut stats[source_index];
+        source_stats.observe(now, latency, success);
+        source_stats.score(now)
+    }
+
+    /// Sort provided source indices from best to worst, sources with equal score keep their
+    /// relative order
+    pub(super) fn rank<I>(&self, source_indices: I) -> Vec<usize>
+    where
+        I: IntoIterator<Item = usize>,
+    {
+        self.rank_at(Instant::now(), source_indices)
+    }
+
+    fn rank_at<I>(&self, now: Instant, source_indices: I) -> Vec<usize>
+    where
+        I: IntoIterator<Item = usize>,
+    {
+        let stats = self.stats.lock();
+        let mut source_indices = source_indices
+            .into_iter()
+            .map(|source_index| (source_index, stats[source_index].score(now)))
+            .collect::<Vec<_>>();
+        drop(stats);
+
+        source_indices.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+        source_indices
+            .into_iter()
+            .map(|(source_index, _score)| source_index)
+            .collect()
+    }
+}
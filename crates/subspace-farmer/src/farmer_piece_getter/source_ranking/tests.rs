@@ -0,0 +1,64 @@
+use crate::farmer_piece_getter::source_ranking::SourceRanking;
+use std::time::{Duration, Instant};
+
+#[test]
+fn configured_order_without_observations() {
+    let ranking = SourceRanking::new(4);
+
+    assert_eq!(ranking.rank([0, 1, 2, 3]), vec![0, 1, 2, 3]);
+    assert_eq!(ranking.rank([3, 1]), vec![3, 1]);
+}
+
+#[test]
+fn latency_and_success_rate() {
+    let ranking = SourceRanking::new(3);
+
+    // Fast, but rarely has pieces
+    for _ in 0..10 {
+        ranking.observe(0, Duration::from_millis(1), false);
+    }
+    // Slow, but reliable
+    for _ in 0..10 {
+        ranking.observe(1, Duration::from_millis(500), true);
+    }
+    // Faster and reliable
+    for _ in 0..10 {
+        ranking.observe(2, Duration::from_millis(50), true);
+    }
+
+    assert_eq!(ranking.rank([0, 1, 2]), vec![2, 0, 1]);
+
+    // Source becomes slow, it is moved down eventually
+    for _ in 0..20 {
+        ranking.observe(2, Duration::from_secs(2), true);
+    }
+
+    assert_eq!(ranking.rank([0, 1, 2]), vec![0, 1, 2]);
+}
+
+#[test]
+fn demoted_source_is_probed_and_recovers() {
+    let ranking = SourceRanking::new(2);
+    let mut now = Instant::now();
+
+    // Source was failing and got demoted
+    for _ in 0..10 {
+        ranking.observe_at(now, 0, Duration::from_secs(2), false);
+    }
+    for _ in 0..10 {
+        ranking.observe_at(now, 1, Duration::from_millis(100), true);
+    }
+    assert_eq!(ranking.rank_at(now, [0, 1]), vec![1, 0]);
+
+    // Only the better source is used for a while
+    for _ in 0..15 {
+        now += Duration::from_secs(60);
+        ranking.observe_at(now, 1, Duration::from_millis(100), true);
+    }
+
+    // Demoted source is probed again eventually and recovers after a successful request
+    assert_eq!(ranking.rank_at(now, [0, 1]), vec![0, 1]);
+    ranking.observe_at(now, 0, Duration::from_millis(50), true);
+    ranking.observe_at(now, 1, Duration::from_millis(100), true);
+    assert_eq!(ranking.rank_at(now, [0, 1]), vec![0, 1]);
+}
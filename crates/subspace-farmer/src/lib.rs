@@ -58,6 +58,7 @@ pub mod farm;
 pub mod farmer_cache;
 pub mod farmer_piece_getter;
 pub mod node_client;
+pub mod piece_archive;
 pub mod plotter;
 pub mod single_disk_farm;
 pub mod thread_pool_manager;
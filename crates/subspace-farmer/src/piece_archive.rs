@@ -0,0 +1,380 @@
+//! Portable archive of pieces of archived history
+//!
+//! Piece archive is a single file that can be shipped to sites without (fast) DSN access and used
+//! to seed piece caches there instead of downloading pieces from the network. Archive is
+//! self-describing, it contains genesis hash of the chain and segment headers of all segments
+//! pieces belong to, while each piece contains witness for its commitment, which together allows
+//! to verify every piece against segment commitment before it is used.
+//!
+//! File format (integers are little-endian):
+//! * [`MAGIC`] bytes
+//! * version ([`VERSION`]), 1 byte
+//! * length of the header, 4 bytes
+//! * SCALE-encoded [`PieceArchiveHeader`]
+//! * entries until the end of the file, each entry is [`PieceIndex`] followed by [`Piece`]
+
+#[cfg(test)]
+mod tests;
+
+use crate::disk_piece_cache::{DiskPieceCache, DiskPieceCacheError};
+use crate::farm::{PieceCache, PieceCacheOffset};
+use crate::node_client;
+use crate::node_client::NodeClient;
+use parity_scale_codec::{Decode, Encode};
+use std::collections::{BTreeSet, HashSet};
+use std::io;
+use std::io::{Read, Write};
+use subspace_archiving::archiver::is_piece_valid;
+use subspace_core_primitives::crypto::kzg::Kzg;
+use subspace_core_primitives::{Piece, PieceIndex, SegmentHeader, SegmentIndex};
+use subspace_rpc_primitives::MAX_SEGMENT_HEADERS_PER_REQUEST;
+use thiserror::Error;
+use tracing::{debug, warn};
+
+/// Magic bytes at the beginning of piece archive
+pub const MAGIC: [u8; 8] = *b"SSPIECES";
+/// Supported version of piece archive format
+pub const VERSION: u8 = 0;
+
+/// Errors that happen when working with piece archive
+#[derive(Debug, Error)]
+pub enum PieceArchiveError {
+    /// I/O error occurred
+    #[error("Piece archive I/O error: {0}")]
+    Io(#[from] io::Error),
+    /// File is not a piece archive
+    #[error("File is not a piece archive")]
+    InvalidMagic,
+    /// Unsupported archive version
+    #[error("Unsupported piece archive version {0}")]
+    UnsupportedVersion(u8),
+    /// Failed to decode archive header
+    #[error("Failed to decode piece archive header: {0}")]
+    HeaderDecoding(#[from] parity_scale_codec::Error),
+    /// Archive header is too large
+    #[error("Piece archive header is too large")]
+    HeaderTooLarge,
+    /// Piece belongs to segment that is not in the archive header
+    #[error("Piece {piece_index} belongs to segment {segment_index} missing in archive header")]
+    UnknownSegment {
+        /// Piece index
+        piece_index: PieceIndex,
+        /// Segment index
+        segment_index: SegmentIndex,
+    },
+    /// Archive was created for a different chain
+    #[error(
+        "Piece archive was created for genesis hash 0x{}, but node has 0x{}",
+        hex::encode(archive),
+        hex::encode(node)
+    )]
+    GenesisHashMismatch {
+        /// Genesis hash in archive
+        archive: [u8; 32],
+        /// Genesis hash on the node
+        node: [u8; 32],
+    },
+    /// Segment header in the archive doesn't match the one on the node
+    #[error("Segment header {segment_index} in piece archive doesn't match the one on the node")]
+    SegmentHeaderMismatch {
+        /// Segment index
+        segment_index: SegmentIndex,
+    },
+    /// Segment header is not known to the node yet
+    #[error("Segment header {segment_index} is not known to the node")]
+    SegmentHeaderNotFound {
+        /// Segment index
+        segment_index: SegmentIndex,
+    },
+    /// Failed to retrieve information from the node
+    #[error("Failed to retrieve information from the node: {error}")]
+    NodeClient {
+        /// Lower-level error
+        error: node_client::Error,
+    },
+    /// Piece cache already contains pieces
+    #[error("Piece cache is not empty, only fresh piece cache can be seeded from piece archive")]
+    PieceCacheNotEmpty,
+    /// Disk piece cache error
+    #[error("Disk piece cache error: {0}")]
+    DiskPieceCache(#[from] DiskPieceCacheError),
+}
+
+/// Header of the piece archive
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct PieceArchiveHeader {
+    /// Genesis hash of the chain pieces belong to
+    pub genesis_hash: [u8; 32],
+    /// Segment headers of all segments pieces in the archive belong to
+    pub segment_headers: Vec<SegmentHeader>,
+}
+
+impl PieceArchiveHeader {
+    /// Segment header for specified segment index, if present in the archive
+    pub fn segment_header(&self, segment_index: SegmentIndex) -> Option<&SegmentHeader> {
+        self.segment_headers
+            .iter()
+            .find(|segment_header| segment_header.segment_index() == segment_index)
+    }
+
+    /// Check that segment headers in the archive match those known to the node.
+    ///
+    /// Segment headers contained in the archive are not trusted by themselves since they come from
+    /// the same place as pieces, this must be done before relying on [`Self::is_piece_valid()`].
+    pub async fn verify_with_node<NC>(&self, node_client: &NC) -> Result<(), PieceArchiveError>
+    where
+        NC: NodeClient,
+    {
+        let farmer_app_info = node_client
+            .farmer_app_info()
+            .await
+            .map_err(|error| PieceArchiveError::NodeClient { error })?;
+
+        if farmer_app_info.genesis_hash != self.genesis_hash {
+            return Err(PieceArchiveError::GenesisHashMismatch {
+                archive: self.genesis_hash,
+                node: farmer_app_info.genesis_hash,
+            });
+        }
+
+        for segment_headers in self.segment_headers.chunks(MAX_SEGMENT_HEADERS_PER_REQUEST) {
+            let node_segment_headers = node_client
+                .segment_headers(
+                    segment_headers
+                        .iter()
+                        .map(SegmentHeader::segment_index)
+                        .collect(),
+                )
+                .await
+                .map_err(|error| PieceArchiveError::NodeClient { error })?;
+
+            for (segment_header, maybe_node_segment_header) in
+                segment_headers.iter().zip(node_segment_headers)
+            {
+                let segment_index = segment_header.segment_index();
+                match maybe_node_segment_header {
+                    Some(node_segment_header) => {
+                        if &node_segment_header != segment_header {
+                            return Err(PieceArchiveError::SegmentHeaderMismatch { segment_index });
+                        }
+                    }
+                    None => {
+                        return Err(PieceArchiveError::SegmentHeaderNotFound { segment_index });
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check piece against segment commitment of its segment, pieces from segments that are
+    /// missing in the archive are considered invalid
+    pub fn is_piece_valid(&self, kzg: &Kzg, piece_index: PieceIndex, piece: &Piece) -> bool {
+        match self.segment_header(piece_index.segment_index()) {
+            Some(segment_header) => is_piece_valid(
+                kzg,
+                piece,
+                &segment_header.segment_commitment(),
+                piece_index.position(),
+            ),
+            None => false,
+        }
+    }
+}
+
+/// Writer of piece archive
+#[derive(Debug)]
+pub struct PieceArchiveWriter<W> {
+    writer: W,
+    segment_indices: BTreeSet<SegmentIndex>,
+}
+
+impl<W> PieceArchiveWriter<W>
+where
+    W: Write,
+{
+    /// Create new archive with specified header
+    pub fn new(mut writer: W, header: &PieceArchiveHeader) -> Result<Self, PieceArchiveError> {
+        let header_bytes = header.encode();
+        let header_length =
+            u32::try_from(header_bytes.len()).map_err(|_| PieceArchiveError::HeaderTooLarge)?;
+
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        writer.write_all(&header_length.to_le_bytes())?;
+        writer.write_all(&header_bytes)?;
+
+        Ok(Self {
+            writer,
+            segment_indices: header
+                .segment_headers
+                .iter()
+                .map(SegmentHeader::segment_index)
+                .collect(),
+        })
+    }
+
+    /// Append piece to the archive, piece must belong to one of the segments in the header
+    pub fn write_piece(
+        &mut self,
+        piece_index: PieceIndex,
+        piece: &Piece,
+    ) -> Result<(), PieceArchiveError> {
+        let segment_index = piece_index.segment_index();
+        if !self.segment_indices.contains(&segment_index) {
+            return Err(PieceArchiveError::UnknownSegment {
+                piece_index,
+                segment_index,
+            });
+        }
+
+        self.writer.write_all(&piece_index.to_bytes())?;
+        self.writer.write_all(piece.as_ref())?;
+
+        Ok(())
+    }
+
+    /// Flush archive and return inner writer
+    pub fn finish(mut self) -> Result<W, PieceArchiveError> {
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+}
+
+/// Reader of piece archive
+#[derive(Debug)]
+pub struct PieceArchiveReader<R> {
+    reader: R,
+    header: PieceArchiveHeader,
+}
+
+impl<R> PieceArchiveReader<R>
+where
+    R: Read,
+{
+    /// Open archive, reads and decodes its header
+    pub fn new(mut reader: R) -> Result<Self, PieceArchiveError> {
+        let mut magic = [0; MAGIC.len()];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(PieceArchiveError::InvalidMagic);
+        }
+
+        let mut version = [0; 1];
+        reader.read_exact(&mut version)?;
+        if version[0] != VERSION {
+            return Err(PieceArchiveError::UnsupportedVersion(version[0]));
+        }
+
+        let mut header_length = [0; 4];
+        reader.read_exact(&mut header_length)?;
+        let header_length = u32::from_le_bytes(header_length) as usize;
+
+        let mut header_bytes = Vec::new();
+        reader
+            .by_ref()
+            .take(header_length as u64)
+            .read_to_end(&mut header_bytes)?;
+        if header_bytes.len() != header_length {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        let header = PieceArchiveHeader::decode(&mut header_bytes.as_slice())?;
+
+        Ok(Self { reader, header })
+    }
+
+    /// Archive header
+    pub fn header(&self) -> &PieceArchiveHeader {
+        &self.header
+    }
+
+    /// Read next piece from the archive, `None` means the end of the archive was reached.
+    ///
+    /// Pieces are not verified, use [`PieceArchiveHeader::is_piece_valid()`] for that.
+    pub fn read_piece(&mut self) -> Result<Option<(PieceIndex, Piece)>, PieceArchiveError> {
+        let mut piece_index_bytes = [0; PieceIndex::SIZE];
+        // Distinguish clean end of the archive from truncated entry
+        match self.reader.read(&mut piece_index_bytes[..1])? {
+            0 => {
+                return Ok(None);
+            }
+            _ => {
+                self.reader.read_exact(&mut piece_index_bytes[1..])?;
+            }
+        }
+        let piece_index = PieceIndex::from_bytes(piece_index_bytes);
+
+        let mut piece = Piece::default();
+        self.reader.read_exact(piece.as_mut())?;
+
+        Ok(Some((piece_index, piece)))
+    }
+}
+
+/// Summary of piece archive import
+#[derive(Debug, Default, Copy, Clone)]
+pub struct PieceArchiveImportSummary {
+    /// Number of pieces written into piece cache
+    pub imported: u32,
+    /// Number of pieces skipped because they failed verification
+    pub invalid: u64,
+    /// Number of pieces skipped because they were already imported
+    pub duplicate: u64,
+    /// Whether import stopped early because piece cache was full
+    pub cache_full: bool,
+}
+
+/// Seed fresh disk piece cache with pieces from piece archive.
+///
+/// Header of the archive must be verified with [`PieceArchiveHeader::verify_with_node()`] first,
+/// every piece is then verified against segment commitment before being written. Import stops
+/// once the cache is full.
+///
+/// NOTE: This is a blocking function that does a lot of I/O and compute-intensive piece
+/// verification.
+pub fn import_piece_archive<R>(
+    reader: &mut PieceArchiveReader<R>,
+    kzg: &Kzg,
+    piece_cache: &DiskPieceCache,
+) -> Result<PieceArchiveImportSummary, PieceArchiveError>
+where
+    R: Read,
+{
+    if piece_cache
+        .contents()
+        .any(|(_offset, maybe_piece_index)| maybe_piece_index.is_some())
+    {
+        return Err(PieceArchiveError::PieceCacheNotEmpty);
+    }
+
+    let capacity = piece_cache.max_num_elements();
+    let mut summary = PieceArchiveImportSummary::default();
+    let mut imported_piece_indices = HashSet::new();
+
+    while let Some((piece_index, piece)) = reader.read_piece()? {
+        if imported_piece_indices.contains(&piece_index) {
+            debug!(%piece_index, "Skipping duplicate piece");
+            summary.duplicate += 1;
+            continue;
+        }
+
+        if !reader.header().is_piece_valid(kzg, piece_index, &piece) {
+            warn!(%piece_index, "Skipping invalid piece from piece archive");
+            summary.invalid += 1;
+            continue;
+        }
+
+        if summary.imported == capacity {
+            summary.cache_full = true;
+            break;
+        }
+
+        piece_cache.write_piece(PieceCacheOffset(summary.imported), piece_index, &piece)?;
+        imported_piece_indices.insert(piece_index);
+        summary.imported += 1;
+    }
+
+    Ok(summary)
+}
@@ -0,0 +1,82 @@
+use crate::piece_archive::{
+    PieceArchiveError, PieceArchiveHeader, PieceArchiveReader, PieceArchiveWriter,
+};
+use rand::prelude::*;
+use std::assert_matches::assert_matches;
+use subspace_core_primitives::{LastArchivedBlock, Piece, PieceIndex, SegmentHeader, SegmentIndex};
+
+fn header() -> PieceArchiveHeader {
+    PieceArchiveHeader {
+        genesis_hash: [1; 32],
+        segment_headers: vec![SegmentHeader::V0 {
+            segment_index: SegmentIndex::ONE,
+            segment_commitment: Default::default(),
+            prev_segment_header_hash: [0; 32],
+            last_archived_block: LastArchivedBlock {
+                number: 0,
+                archived_progress: Default::default(),
+            },
+        }],
+    }
+}
+
+fn random_piece() -> Piece {
+    let mut piece = Piece::default();
+    thread_rng().fill(piece.as_mut());
+    piece
+}
+
+#[test]
+fn roundtrip() {
+    let header = header();
+    let pieces = SegmentIndex::ONE
+        .segment_piece_indexes()
+        .into_iter()
+        .take(3)
+        .map(|piece_index| (piece_index, random_piece()))
+        .collect::<Vec<_>>();
+
+    let mut writer = PieceArchiveWriter::new(Vec::new(), &header).unwrap();
+    for (piece_index, piece) in &pieces {
+        writer.write_piece(*piece_index, piece).unwrap();
+    }
+    // Pieces from segments that are not in the header can't be written
+    assert_matches!(
+        writer.write_piece(PieceIndex::ZERO, &random_piece()),
+        Err(PieceArchiveError::UnknownSegment { .. })
+    );
+    let bytes = writer.finish().unwrap();
+
+    let mut reader = PieceArchiveReader::new(bytes.as_slice()).unwrap();
+    assert_eq!(reader.header(), &header);
+    for (piece_index, piece) in &pieces {
+        let (read_piece_index, read_piece) = reader.read_piece().unwrap().unwrap();
+        assert_eq!(&read_piece_index, piece_index);
+        assert_eq!(&read_piece, piece);
+    }
+    assert!(reader.read_piece().unwrap().is_none());
+
+    // Truncated entry is an error rather than the end of the archive
+    let mut reader = PieceArchiveReader::new(&bytes[..bytes.len() - 1]).unwrap();
+    reader.read_piece().unwrap();
+    reader.read_piece().unwrap();
+    assert_matches!(reader.read_piece(), Err(PieceArchiveError::Io(_)));
+}
+
+#[test]
+fn invalid_archive() {
+    assert_matches!(
+        PieceArchiveReader::new(b"not a piece archive".as_slice()),
+        Err(PieceArchiveError::InvalidMagic)
+    );
+
+    let mut bytes = PieceArchiveWriter::new(Vec::new(), &header())
+        .unwrap()
+        .finish()
+        .unwrap();
+    bytes[8] += 1;
+    assert_matches!(
+        PieceArchiveReader::new(bytes.as_slice()),
+        Err(PieceArchiveError::UnsupportedVersion(1))
+    );
+}
@@ -6,10 +6,12 @@
 //! implementations without the rest of the library being aware of implementation details.
 
 pub mod cpu;
+pub mod scheduler;
 
 use async_trait::async_trait;
 use futures::channel::mpsc;
 use futures::Stream;
+use parity_scale_codec::{Decode, Encode};
 use std::fmt;
 use std::pin::Pin;
 use std::sync::Arc;
@@ -74,6 +76,32 @@ impl fmt::Debug for SectorPlottingProgress {
     }
 }
 
+/// Priority of sector plotting, plotters shared by multiple farms serve sectors with higher priority
+/// first
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Encode, Decode)]
+pub enum PlottingPriority {
+    /// Initial plotting of a sector that was never plotted before
+    InitialPlotting,
+    /// Replotting of a sector that is expired or about to expire
+    ExpiringReplotting,
+    /// Any other replotting (of a corrupted sector, for example)
+    Replotting,
+}
+
+impl PlottingPriority {
+    /// All priorities from the highest to the lowest
+    pub const ALL: [Self; 3] = [
+        Self::InitialPlotting,
+        Self::ExpiringReplotting,
+        Self::Replotting,
+    ];
+
+    /// Whether sector is being replotted
+    pub fn is_replotting(&self) -> bool {
+        !matches!(self, Self::InitialPlotting)
+    }
+}
+
 /// Abstract plotter implementation
 #[async_trait]
 pub trait Plotter: fmt::Debug {
@@ -89,7 +117,7 @@ pub trait Plotter: fmt::Debug {
         sector_index: SectorIndex,
         farmer_protocol_info: FarmerProtocolInfo,
         pieces_in_sector: u16,
-        replotting: bool,
+        priority: PlottingPriority,
         progress_sender: mpsc::Sender<SectorPlottingProgress>,
     );
 
@@ -103,7 +131,7 @@ pub trait Plotter: fmt::Debug {
         sector_index: SectorIndex,
         farmer_protocol_info: FarmerProtocolInfo,
         pieces_in_sector: u16,
-        replotting: bool,
+        priority: PlottingPriority,
         progress_sender: mpsc::Sender<SectorPlottingProgress>,
     ) -> bool;
 }
@@ -125,7 +153,7 @@ where
         sector_index: SectorIndex,
         farmer_protocol_info: FarmerProtocolInfo,
         pieces_in_sector: u16,
-        replotting: bool,
+        priority: PlottingPriority,
         progress_sender: mpsc::Sender<SectorPlottingProgress>,
     ) {
         self.as_ref()
@@ -134,7 +162,7 @@ where
                 sector_index,
                 farmer_protocol_info,
                 pieces_in_sector,
-                replotting,
+                priority,
                 progress_sender,
             )
             .await
@@ -147,7 +175,7 @@ where
         sector_index: SectorIndex,
         farmer_protocol_info: FarmerProtocolInfo,
         pieces_in_sector: u16,
-        replotting: bool,
+        priority: PlottingPriority,
         progress_sender: mpsc::Sender<SectorPlottingProgress>,
     ) -> bool {
         self.as_ref()
@@ -156,7 +184,7 @@ where
                 sector_index,
                 farmer_protocol_info,
                 pieces_in_sector,
-                replotting,
+                priority,
                 progress_sender,
             )
             .await
@@ -3,7 +3,7 @@
 pub mod metrics;
 
 use crate::plotter::cpu::metrics::CpuPlotterMetrics;
-use crate::plotter::{Plotter, SectorPlottingProgress};
+use crate::plotter::{Plotter, PlottingPriority, SectorPlottingProgress};
 use crate::thread_pool_manager::PlottingThreadPoolManager;
 use crate::utils::AsyncJoinOnDrop;
 use async_lock::Mutex as AsyncMutex;
@@ -92,7 +92,7 @@ where
         sector_index: SectorIndex,
         farmer_protocol_info: FarmerProtocolInfo,
         pieces_in_sector: u16,
-        replotting: bool,
+        priority: PlottingPriority,
         mut progress_sender: mpsc::Sender<SectorPlottingProgress>,
     ) {
         let start = Instant::now();
@@ -134,7 +134,7 @@ where
             sector_index,
             farmer_protocol_info,
             pieces_in_sector,
-            replotting,
+            priority.is_replotting(),
             progress_sender,
         )
         .await
@@ -146,7 +146,7 @@ where
         sector_index: SectorIndex,
         farmer_protocol_info: FarmerProtocolInfo,
         pieces_in_sector: u16,
-        replotting: bool,
+        priority: PlottingPriority,
         progress_sender: mpsc::Sender<SectorPlottingProgress>,
     ) -> bool {
         let start = Instant::now();
@@ -163,7 +163,7 @@ where
             sector_index,
             farmer_protocol_info,
             pieces_in_sector,
-            replotting,
+            priority.is_replotting(),
             progress_sender,
         )
         .await;
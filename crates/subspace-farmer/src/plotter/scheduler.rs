@@ -0,0 +1,441 @@
+//! Plotting scheduler
+//!
+//! Scheduler sits in front of any [`Plotter`] implementation and decides which farm gets to
+//! schedule the next sector. Farms get turns proportionally to their weights, replotting can be
+//! restricted to a time window and all plotting can be paused at runtime. Farming is not affected
+//! by any of this, only plotting and replotting of new sectors is delayed.
+
+#[cfg(test)]
+mod tests;
+
+use crate::plotter::{Plotter, PlottingPriority, SectorPlottingProgress};
+use async_trait::async_trait;
+use futures::channel::{mpsc, oneshot};
+use parking_lot::Mutex;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::num::NonZeroU32;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use subspace_core_primitives::{PublicKey, SectorIndex};
+use subspace_farmer_components::FarmerProtocolInfo;
+use tokio::sync::watch;
+use tracing::debug;
+
+const SECONDS_IN_DAY: u64 = 24 * 60 * 60;
+/// Stride of a farm with weight 1, farms with higher weight move forward proportionally slower
+const BASE_STRIDE: u64 = 1 << 20;
+/// Max time to sleep before checking time window again (in case system time changes)
+const MAX_TIME_WINDOW_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Daily time window in UTC, for example `00:00-06:00`.
+///
+/// Window can wrap around midnight (like `22:00-04:00`), window with the same start and end covers
+/// the whole day.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct TimeWindow {
+    /// Start of the window in seconds since midnight
+    start: u64,
+    /// End of the window in seconds since midnight
+    end: u64,
+}
+
+impl FromStr for TimeWindow {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_time = |time: &str| -> Result<u64, String> {
+            let (hours, minutes) = time
+                .split_once(':')
+                .ok_or_else(|| format!("Time {time} must be in HH:MM format"))?;
+            let hours = hours
+                .parse::<u64>()
+                .map_err(|error| format!("Invalid hours in {time}: {error}"))?;
+            let minutes = minutes
+                .parse::<u64>()
+                .map_err(|error| format!("Invalid minutes in {time}: {error}"))?;
+
+            if hours > 23 || minutes > 59 {
+                return Err(format!("Time {time} is out of range"));
+            }
+
+            Ok(hours * 60 * 60 + minutes * 60)
+        };
+
+        let (start, end) = s
+            .split_once('-')
+            .ok_or_else(|| format!("Time window {s} must be in HH:MM-HH:MM format"))?;
+
+        Ok(Self {
+            start: parse_time(start.trim())?,
+            end: parse_time(end.trim())?,
+        })
+    }
+}
+
+impl fmt::Display for TimeWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:02}:{:02}-{:02}:{:02}",
+            self.start / 3600,
+            self.start % 3600 / 60,
+            self.end / 3600,
+            self.end % 3600 / 60
+        )
+    }
+}
+
+impl TimeWindow {
+    /// How long to wait until window opens at specified time of day (in seconds since midnight),
+    /// returns zero duration if window is already open
+    fn time_until_open(&self, time_of_day: u64) -> Duration {
+        let open = if self.start <= self.end {
+            self.start == self.end || (self.start..self.end).contains(&time_of_day)
+        } else {
+            time_of_day >= self.start || time_of_day < self.end
+        };
+
+        if open {
+            Duration::ZERO
+        } else {
+            Duration::from_secs((self.start + SECONDS_IN_DAY - time_of_day) % SECONDS_IN_DAY)
+        }
+    }
+}
+
+/// Options for [`PlottingScheduler`]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct PlottingSchedulerOptions {
+    /// Replotting is only allowed within this time window if specified, initial plotting is not
+    /// restricted
+    pub replotting_window: Option<TimeWindow>,
+}
+
+#[derive(Debug)]
+struct Client {
+    stride: u64,
+    /// Virtual time of the client, the one with the lowest pass gets the next turn
+    pass: u64,
+}
+
+#[derive(Debug)]
+struct Waiter {
+    client_id: usize,
+    sender: oneshot::Sender<Turn>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    clients: Vec<Client>,
+    /// Waiting clients ordered by pass and arrival order
+    waiting: BTreeMap<(u64, u64), Waiter>,
+    next_sequence: u64,
+    /// Pass of the last client that got the turn
+    global_pass: u64,
+    turn_taken: bool,
+}
+
+#[derive(Debug)]
+struct Inner {
+    state: Mutex<State>,
+    paused: watch::Sender<bool>,
+    options: PlottingSchedulerOptions,
+}
+
+impl Inner {
+    /// Give turn to the next waiting client if turn is not taken already.
+    ///
+    /// Must be called with state lock held.
+    fn grant_next(self: &Arc<Self>, state: &mut State) {
+        while !state.turn_taken
+            && let Some(((pass, _sequence), waiter)) = state.waiting.pop_first()
+        {
+            let turn = Turn {
+                inner: Some(Arc::clone(self)),
+            };
+
+            match waiter.sender.send(turn) {
+                Ok(()) => {
+                    state.turn_taken = true;
+                    state.global_pass = pass;
+                    let client = &mut state.clients[waiter.client_id];
+                    client.pass = pass + client.stride;
+                }
+                Err(mut turn) => {
+                    // Waiter is gone, turn must not be released since it was never taken
+                    turn.inner.take();
+                }
+            }
+        }
+    }
+
+    fn release_turn(self: &Arc<Self>) {
+        let mut state = self.state.lock();
+        state.turn_taken = false;
+        self.grant_next(&mut state);
+    }
+
+    fn is_allowed(&self, replotting: bool) -> bool {
+        !*self.paused.borrow() && self.time_until_allowed(replotting).is_zero()
+    }
+
+    fn time_until_allowed(&self, replotting: bool) -> Duration {
+        match (replotting, &self.options.replotting_window) {
+            (true, Some(replotting_window)) => {
+                let time_of_day = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs()
+                    % SECONDS_IN_DAY;
+
+                replotting_window.time_until_open(time_of_day)
+            }
+            _ => Duration::ZERO,
+        }
+    }
+
+    /// Wait for plotting to be unpaused and for time window to open (for replotting)
+    async fn wait_until_allowed(&self, replotting: bool) {
+        let mut paused = self.paused.subscribe();
+
+        loop {
+            if *paused.borrow_and_update() {
+                debug!("Plotting is paused, waiting");
+                // Sender is stored in `self` and can't be dropped
+                let _ = paused.wait_for(|paused| !paused).await;
+            }
+
+            let time_until_allowed = self.time_until_allowed(replotting);
+            if time_until_allowed.is_zero() {
+                return;
+            }
+
+            debug!(
+                ?time_until_allowed,
+                "Replotting is outside of time window, waiting"
+            );
+            tokio::time::sleep(time_until_allowed.min(MAX_TIME_WINDOW_CHECK_INTERVAL)).await;
+        }
+    }
+
+    async fn acquire_turn(self: &Arc<Self>, client_id: usize) -> Option<Turn> {
+        let receiver = {
+            let mut state = self.state.lock();
+            let global_pass = state.global_pass;
+            let client = &mut state.clients[client_id];
+            // Clients that were idle do not accumulate credit
+            let pass = client.pass.max(global_pass);
+            client.pass = pass;
+
+            let sequence = state.next_sequence;
+            state.next_sequence += 1;
+
+            let (sender, receiver) = oneshot::channel();
+            state
+                .waiting
+                .insert((pass, sequence), Waiter { client_id, sender });
+            self.grant_next(&mut state);
+
+            receiver
+        };
+
+        receiver.await.ok()
+    }
+
+    fn try_acquire_turn(self: &Arc<Self>, client_id: usize) -> Option<Turn> {
+        let mut state = self.state.lock();
+        if state.turn_taken || !state.waiting.is_empty() {
+            return None;
+        }
+
+        let global_pass = state.global_pass;
+        let client = &mut state.clients[client_id];
+        let pass = client.pass.max(global_pass);
+        client.pass = pass + client.stride;
+        state.global_pass = pass;
+        state.turn_taken = true;
+
+        Some(Turn {
+            inner: Some(Arc::clone(self)),
+        })
+    }
+}
+
+/// Exclusive right to schedule one sector with the inner plotter, released on drop
+#[derive(Debug)]
+struct Turn {
+    inner: Option<Arc<Inner>>,
+}
+
+impl Drop for Turn {
+    fn drop(&mut self) {
+        if let Some(inner) = self.inner.take() {
+            inner.release_turn();
+        }
+    }
+}
+
+/// Plotting scheduler shared by all farms, see module-level documentation for details
+#[derive(Debug, Clone)]
+pub struct PlottingScheduler {
+    inner: Arc<Inner>,
+}
+
+impl PlottingScheduler {
+    /// Create new scheduler
+    pub fn new(options: PlottingSchedulerOptions) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                state: Mutex::default(),
+                paused: watch::channel(false).0,
+                options,
+            }),
+        }
+    }
+
+    /// Register farm with specified weight, farm with weight 2 will get twice as many turns as
+    /// farm with weight 1 when both are plotting concurrently
+    pub fn register_farm(&self, weight: NonZeroU32) -> PlottingSchedulerFarm {
+        let client_id = {
+            let mut state = self.inner.state.lock();
+            let pass = state.global_pass;
+            state.clients.push(Client {
+                stride: BASE_STRIDE / u64::from(weight.get()),
+                pass,
+            });
+            state.clients.len() - 1
+        };
+
+        PlottingSchedulerFarm {
+            inner: Arc::clone(&self.inner),
+            client_id,
+        }
+    }
+
+    /// Pause plotting of all farms, sectors that are already being plotted will finish
+    pub fn pause(&self) {
+        self.inner.paused.send_replace(true);
+    }
+
+    /// Resume plotting of all farms
+    pub fn resume(&self) {
+        self.inner.paused.send_replace(false);
+    }
+
+    /// Whether plotting is paused
+    pub fn is_paused(&self) -> bool {
+        *self.inner.paused.borrow()
+    }
+}
+
+/// Farm registered with [`PlottingScheduler`]
+#[derive(Debug, Clone)]
+pub struct PlottingSchedulerFarm {
+    inner: Arc<Inner>,
+    client_id: usize,
+}
+
+impl PlottingSchedulerFarm {
+    /// Wrap plotter such that it is used according to scheduling policy, the same farm can wrap
+    /// multiple plotters (for different farm versions, for example) while sharing its turns
+    pub fn wrap<P>(&self, plotter: P) -> ScheduledPlotter<P> {
+        ScheduledPlotter {
+            farm: self.clone(),
+            plotter,
+        }
+    }
+}
+
+/// Plotter that schedules sectors with inner plotter according to [`PlottingScheduler`] policy
+#[derive(Debug)]
+pub struct ScheduledPlotter<P> {
+    farm: PlottingSchedulerFarm,
+    plotter: P,
+}
+
+#[async_trait]
+impl<P> Plotter for ScheduledPlotter<P>
+where
+    P: Plotter + Send + Sync,
+{
+    async fn has_free_capacity(&self) -> Result<bool, String> {
+        if *self.farm.inner.paused.borrow() {
+            return Ok(false);
+        }
+
+        self.plotter.has_free_capacity().await
+    }
+
+    async fn plot_sector(
+        &self,
+        public_key: PublicKey,
+        sector_index: SectorIndex,
+        farmer_protocol_info: FarmerProtocolInfo,
+        pieces_in_sector: u16,
+        priority: PlottingPriority,
+        progress_sender: mpsc::Sender<SectorPlottingProgress>,
+    ) {
+        let inner = &self.farm.inner;
+        let replotting = priority.is_replotting();
+
+        let _turn = loop {
+            inner.wait_until_allowed(replotting).await;
+
+            let Some(turn) = inner.acquire_turn(self.farm.client_id).await else {
+                // Not possible since scheduler is alive, but doesn't hurt to try again
+                continue;
+            };
+
+            // Conditions might have changed while waiting for the turn
+            if inner.is_allowed(replotting) {
+                break turn;
+            }
+        };
+
+        // Turn is held until inner plotter accepts the sector (for backpressure purposes)
+        self.plotter
+            .plot_sector(
+                public_key,
+                sector_index,
+                farmer_protocol_info,
+                pieces_in_sector,
+                priority,
+                progress_sender,
+            )
+            .await
+    }
+
+    async fn try_plot_sector(
+        &self,
+        public_key: PublicKey,
+        sector_index: SectorIndex,
+        farmer_protocol_info: FarmerProtocolInfo,
+        pieces_in_sector: u16,
+        priority: PlottingPriority,
+        progress_sender: mpsc::Sender<SectorPlottingProgress>,
+    ) -> bool {
+        let inner = &self.farm.inner;
+        let replotting = priority.is_replotting();
+
+        if !inner.is_allowed(replotting) {
+            return false;
+        }
+
+        let Some(_turn) = inner.try_acquire_turn(self.farm.client_id) else {
+            return false;
+        };
+
+        self.plotter
+            .try_plot_sector(
+                public_key,
+                sector_index,
+                farmer_protocol_info,
+                pieces_in_sector,
+                priority,
+                progress_sender,
+            )
+            .await
+    }
+}
@@ -0,0 +1,120 @@
+use crate::plotter::scheduler::{PlottingScheduler, PlottingSchedulerOptions, TimeWindow};
+use parking_lot::Mutex;
+use std::num::NonZeroU32;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[test]
+fn time_window() {
+    let window = "00:00-06:00".parse::<TimeWindow>().unwrap();
+    assert_eq!(window.to_string(), "00:00-06:00");
+    assert_eq!(window.time_until_open(0), Duration::ZERO);
+    assert_eq!(window.time_until_open(5 * 3600 + 59 * 60), Duration::ZERO);
+    assert_eq!(
+        window.time_until_open(6 * 3600),
+        Duration::from_secs(18 * 3600)
+    );
+    assert_eq!(window.time_until_open(23 * 3600), Duration::from_secs(3600));
+
+    // Wraps around midnight
+    let window = "22:30-04:00".parse::<TimeWindow>().unwrap();
+    assert_eq!(window.time_until_open(23 * 3600), Duration::ZERO);
+    assert_eq!(window.time_until_open(3 * 3600), Duration::ZERO);
+    assert_eq!(
+        window.time_until_open(22 * 3600),
+        Duration::from_secs(30 * 60)
+    );
+
+    // The whole day
+    let window = "12:00-12:00".parse::<TimeWindow>().unwrap();
+    assert_eq!(window.time_until_open(0), Duration::ZERO);
+
+    assert!("24:00-06:00".parse::<TimeWindow>().is_err());
+    assert!("00:00".parse::<TimeWindow>().is_err());
+    assert!("00:00-6".parse::<TimeWindow>().is_err());
+}
+
+#[tokio::test]
+async fn turns_proportional_to_weights() {
+    const TOTAL_TURNS: usize = 30;
+
+    let scheduler = PlottingScheduler::new(PlottingSchedulerOptions::default());
+    let blocking_farm = scheduler.register_farm(NonZeroU32::MIN);
+    let farms = [
+        scheduler.register_farm(NonZeroU32::new(2).unwrap()),
+        scheduler.register_farm(NonZeroU32::MIN),
+    ];
+    let turns = Arc::new(Mutex::new(Vec::new()));
+
+    // Hold the turn until both farms are waiting for it
+    let initial_turn = blocking_farm
+        .inner
+        .try_acquire_turn(blocking_farm.client_id)
+        .unwrap();
+
+    let tasks = farms
+        .iter()
+        .enumerate()
+        .map(|(farm_index, farm)| {
+            let farm = farm.clone();
+            let turns = Arc::clone(&turns);
+
+            tokio::spawn(async move {
+                loop {
+                    let turn = farm.inner.acquire_turn(farm.client_id).await.unwrap();
+                    {
+                        let mut turns = turns.lock();
+                        if turns.len() == TOTAL_TURNS {
+                            break;
+                        }
+                        turns.push(farm_index);
+                    }
+                    drop(turn);
+                    tokio::task::yield_now().await;
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for _ in 0..10 {
+        tokio::task::yield_now().await;
+    }
+    drop(initial_turn);
+
+    for task in tasks {
+        task.await.unwrap();
+    }
+
+    let turns = turns.lock();
+    let first_farm_turns = turns.iter().filter(|&&farm_index| farm_index == 0).count();
+    assert!(
+        (19..=21).contains(&first_farm_turns),
+        "First farm got {first_farm_turns} turns out of {TOTAL_TURNS}: {turns:?}"
+    );
+}
+
+#[tokio::test]
+async fn paused() {
+    let scheduler = PlottingScheduler::new(PlottingSchedulerOptions::default());
+    let farm = scheduler.register_farm(NonZeroU32::MIN);
+
+    assert!(farm.inner.is_allowed(false));
+    scheduler.pause();
+    assert!(scheduler.is_paused());
+    assert!(!farm.inner.is_allowed(false));
+    assert!(!farm.inner.is_allowed(true));
+
+    let wait_fut = tokio::spawn({
+        let farm = farm.clone();
+
+        async move { farm.inner.wait_until_allowed(true).await }
+    });
+    tokio::task::yield_now().await;
+    assert!(!wait_fut.is_finished());
+
+    scheduler.resume();
+    tokio::time::timeout(Duration::from_secs(5), wait_fut)
+        .await
+        .unwrap()
+        .unwrap();
+}
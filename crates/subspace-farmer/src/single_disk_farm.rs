@@ -6,19 +6,27 @@
 
 pub mod farming;
 pub mod identity;
+mod integrity_check;
+#[cfg(target_os = "linux")]
+pub mod io_uring_file_linux;
 mod metrics;
+mod migration;
 pub mod piece_cache;
 pub mod piece_reader;
 pub mod plot_cache;
 mod plotted_sectors;
 mod plotting;
+#[cfg(unix)]
+pub mod remote_signer;
+mod resizing;
+pub mod reward_address;
 mod reward_signing;
 pub mod unbuffered_io_file_windows;
 
 use crate::disk_piece_cache::{DiskPieceCache, DiskPieceCacheError};
 use crate::farm::{
-    Farm, FarmId, FarmingError, FarmingNotification, HandlerFn, PieceCacheId, PieceReader,
-    PlottedSectors, SectorUpdate,
+    Farm, FarmId, FarmingError, FarmingNotification, HandlerFn, PieceCacheId, PieceCacheTier,
+    PieceReader, PlottedSectors, SectorUpdate,
 };
 use crate::node_client::NodeClient;
 use crate::plotter::Plotter;
@@ -26,8 +34,12 @@ use crate::single_disk_farm::farming::rayon_files::RayonFiles;
 use crate::single_disk_farm::farming::{
     farming, slot_notification_forwarder, FarmingOptions, PlotAudit,
 };
-use crate::single_disk_farm::identity::{Identity, IdentityError};
+use crate::single_disk_farm::identity::{Identity, IdentityError, RewardSigner};
+use crate::single_disk_farm::integrity_check::{integrity_check, IntegrityCheckOptions};
+#[cfg(target_os = "linux")]
+use crate::single_disk_farm::io_uring_file_linux::IoUringFileLinux;
 use crate::single_disk_farm::metrics::SingleDiskFarmMetrics;
+pub use crate::single_disk_farm::migration::SingleDiskFarmMigrationError;
 use crate::single_disk_farm::piece_cache::SingleDiskPieceCache;
 use crate::single_disk_farm::piece_reader::DiskPieceReader;
 use crate::single_disk_farm::plot_cache::DiskPlotCache;
@@ -36,6 +48,9 @@ pub use crate::single_disk_farm::plotting::PlottingError;
 use crate::single_disk_farm::plotting::{
     plotting, plotting_scheduler, PlottingOptions, PlottingSchedulerOptions, SectorPlottingOptions,
 };
+#[cfg(unix)]
+use crate::single_disk_farm::remote_signer::{RemoteRewardSigner, RemoteSigner, RemoteSignerError};
+use crate::single_disk_farm::reward_address::RewardAddressPolicy;
 use crate::single_disk_farm::reward_signing::reward_signing;
 #[cfg(windows)]
 use crate::single_disk_farm::unbuffered_io_file_windows::UnbufferedIoFileWindows;
@@ -61,6 +76,8 @@ use std::error::Error;
 use std::fs::{File, OpenOptions};
 use std::future::Future;
 use std::io::Write;
+#[cfg(target_os = "linux")]
+use std::num::NonZeroU32;
 use std::path::{Path, PathBuf};
 use std::pin::Pin;
 use std::str::FromStr;
@@ -85,7 +102,7 @@ use subspace_proof_of_space::Table;
 use subspace_rpc_primitives::{FarmerAppInfo, SolutionResponse};
 use thiserror::Error;
 use tokio::runtime::Handle;
-use tokio::sync::{broadcast, Barrier, Semaphore};
+use tokio::sync::{broadcast, watch, Barrier, Semaphore};
 use tokio::task;
 use tracing::{debug, error, info, trace, warn, Instrument, Span};
 
@@ -107,7 +124,18 @@ const INTERNAL_BENCHMARK_READ_TIMEOUT: Duration = Duration::from_millis(3500);
 #[derive(Debug)]
 #[must_use = "Lock file must be kept around or as long as farm is used"]
 pub struct SingleDiskFarmInfoLock {
-    _file: File,
+    file: File,
+}
+
+impl SingleDiskFarmInfoLock {
+    /// Store `SingleDiskFarm` info through the locked file, [`SingleDiskFarmInfo::store_to()`]
+    /// can't be used while the lock is held
+    pub fn store(&self, info: &SingleDiskFarmInfo) -> io::Result<()> {
+        let bytes = serde_json::to_vec(info).expect("Info serialization never fails; qed");
+        self.file.set_len(0)?;
+        self.file.write_all_at(&bytes, 0)?;
+        self.file.sync_data()
+    }
 }
 
 /// Important information about the contents of the `SingleDiskFarm`
@@ -199,10 +227,13 @@ impl SingleDiskFarmInfo {
     /// Try to acquire exclusive lock on the single disk farm info file, ensuring no concurrent edits by cooperating
     /// processes is done
     pub fn try_lock(directory: &Path) -> io::Result<SingleDiskFarmInfoLock> {
-        let file = File::open(directory.join(Self::FILE_NAME))?;
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(directory.join(Self::FILE_NAME))?;
         fs4::FileExt::try_lock_exclusive(&file)?;
 
-        Ok(SingleDiskFarmInfoLock { _file: file })
+        Ok(SingleDiskFarmInfoLock { file })
     }
 
     /// ID of the farm
@@ -246,6 +277,21 @@ impl SingleDiskFarmInfo {
             } => *allocated_space,
         }
     }
+
+    fn set_allocated_space(&mut self, new_allocated_space: u64) {
+        match self {
+            SingleDiskFarmInfo::V0 {
+                allocated_space, ..
+            } => {
+                *allocated_space = new_allocated_space;
+            }
+            SingleDiskFarmInfo::V1 {
+                allocated_space, ..
+            } => {
+                *allocated_space = new_allocated_space;
+            }
+        }
+    }
 }
 
 /// Summary of single disk farm for presentational purposes
@@ -306,8 +352,9 @@ where
     pub max_pieces_in_sector: u16,
     /// RPC client connected to Subspace node
     pub node_client: NC,
-    /// Address where farming rewards should go
-    pub reward_address: PublicKey,
+    /// Policy that selects address where farming rewards should go, [`PublicKey`] can be used for
+    /// a single address
+    pub reward_addresses: Arc<dyn RewardAddressPolicy>,
     /// Plotter
     pub plotter_legacy: Arc<dyn Plotter + Send + Sync>,
     /// Plotter
@@ -318,6 +365,8 @@ where
     pub erasure_coding: ErasureCoding,
     /// Percentage of allocated space dedicated for caching purposes
     pub cache_percentage: u8,
+    /// Performance tier of the piece cache of this farm
+    pub piece_cache_tier: PieceCacheTier,
     /// Thread pool size used for farming (mostly for blocking I/O, but also for some
     /// compute-intensive operations during proving)
     pub farming_thread_pool_size: usize,
@@ -341,6 +390,17 @@ where
     pub registry: Option<&'a Mutex<&'a mut Registry>>,
     /// Whether to create a farm if it doesn't yet exist
     pub create: bool,
+    /// Delay between checking integrity of consecutive sectors in the background, corrupted sectors
+    /// are replotted automatically. `None` disables background integrity check.
+    pub integrity_check_interval: Option<Duration>,
+    /// Use io_uring with specified queue depth for reading from plot during auditing and piece
+    /// reading instead of blocking reads on farming thread pool, beneficial for HDDs
+    #[cfg(target_os = "linux")]
+    pub io_uring_queue_depth: Option<NonZeroU32>,
+    /// Remote signer that holds farm identity instead of the farm itself, farm only stores public
+    /// key in this case
+    #[cfg(unix)]
+    pub remote_signer: Option<RemoteSigner>,
 }
 
 /// Errors happening when trying to create/open single disk farm
@@ -444,6 +504,10 @@ pub enum SingleDiskFarmError {
     /// Failed to create thread pool
     #[error("Failed to create thread pool: {0}")]
     FailedToCreateThreadPool(ThreadPoolBuildError),
+    /// Remote signer error
+    #[cfg(unix)]
+    #[error("Remote signer error: {0}")]
+    RemoteSigner(#[from] RemoteSignerError),
 }
 
 /// Errors happening during scrubbing
@@ -749,8 +813,40 @@ struct Handlers {
     solution: Handler<SolutionResponse>,
 }
 
+/// Handle that allows to pause and resume plotting of a farm while it is running.
+///
+/// Pausing only prevents new sectors from being plotted or replotted, sectors that are already
+/// being plotted will finish and farming continues as usual.
+#[derive(Debug, Clone)]
+pub struct PlottingPauseHandle {
+    paused: Arc<watch::Sender<bool>>,
+}
+
+impl PlottingPauseHandle {
+    fn new() -> Self {
+        Self {
+            paused: Arc::new(watch::channel(false).0),
+        }
+    }
+
+    /// Pause plotting
+    pub fn pause(&self) {
+        self.paused.send_replace(true);
+    }
+
+    /// Resume plotting
+    pub fn resume(&self) {
+        self.paused.send_replace(false);
+    }
+
+    /// Whether plotting is paused
+    pub fn is_paused(&self) -> bool {
+        *self.paused.borrow()
+    }
+}
+
 struct SingleDiskFarmInit {
-    identity: Identity,
+    reward_signer: Arc<dyn RewardSigner>,
     single_disk_farm_info: SingleDiskFarmInfo,
     single_disk_farm_info_lock: Option<SingleDiskFarmInfoLock>,
     #[cfg(not(windows))]
@@ -787,6 +883,7 @@ pub struct SingleDiskFarm {
     piece_cache: SingleDiskPieceCache,
     plot_cache: DiskPlotCache,
     piece_reader: DiskPieceReader,
+    plotting_pause_handle: PlottingPauseHandle,
     /// Sender that will be used to signal to background threads that they should start
     start_sender: Option<broadcast::Sender<()>>,
     /// Sender that will be used to signal to background threads that they must stop
@@ -871,12 +968,13 @@ impl SingleDiskFarm {
             allocated_space,
             max_pieces_in_sector,
             node_client,
-            reward_address,
+            reward_addresses,
             plotter_legacy,
             plotter,
             kzg,
             erasure_coding,
             cache_percentage,
+            piece_cache_tier,
             farming_thread_pool_size,
             plotting_delay,
             global_mutex,
@@ -886,8 +984,23 @@ impl SingleDiskFarm {
             faster_read_sector_record_chunks_mode_concurrency,
             registry,
             create,
+            integrity_check_interval,
+            #[cfg(target_os = "linux")]
+            io_uring_queue_depth,
+            #[cfg(unix)]
+            remote_signer,
         } = options;
 
+        #[cfg(unix)]
+        let remote_reward_signer = match remote_signer {
+            Some(remote_signer) => Some(Arc::new(
+                Self::remote_reward_signer(&directory, &remote_signer, create).await?,
+            ) as Arc<dyn RewardSigner>),
+            None => None,
+        };
+        #[cfg(not(unix))]
+        let remote_reward_signer = None;
+
         let single_disk_farm_init_fut = task::spawn_blocking({
             let directory = directory.clone();
             let farmer_app_info = farmer_app_info.clone();
@@ -903,6 +1016,7 @@ impl SingleDiskFarm {
                     cache_percentage,
                     disable_farm_locking,
                     create,
+                    remote_reward_signer,
                 )
             }
         });
@@ -911,7 +1025,7 @@ impl SingleDiskFarm {
             AsyncJoinOnDrop::new(single_disk_farm_init_fut, false).await??;
 
         let SingleDiskFarmInit {
-            identity,
+            reward_signer,
             single_disk_farm_info,
             single_disk_farm_info_lock,
             plot_file,
@@ -933,18 +1047,26 @@ impl SingleDiskFarm {
                 if piece_cache_capacity == 0 {
                     None
                 } else {
-                    Some(task::block_in_place(|| {
-                        if let Some(registry) = registry {
-                            DiskPieceCache::open(
-                                &directory,
-                                piece_cache_capacity,
-                                Some(id),
-                                Some(*registry.lock()),
-                            )
-                        } else {
-                            DiskPieceCache::open(&directory, piece_cache_capacity, Some(id), None)
-                        }
-                    })?)
+                    Some(
+                        task::block_in_place(|| {
+                            if let Some(registry) = registry {
+                                DiskPieceCache::open(
+                                    &directory,
+                                    piece_cache_capacity,
+                                    Some(id),
+                                    Some(*registry.lock()),
+                                )
+                            } else {
+                                DiskPieceCache::open(
+                                    &directory,
+                                    piece_cache_capacity,
+                                    Some(id),
+                                    None,
+                                )
+                            }
+                        })?
+                        .with_tier(piece_cache_tier),
+                    )
                 },
             )
         };
@@ -980,10 +1102,20 @@ impl SingleDiskFarm {
         let (stop_sender, mut stop_receiver) = broadcast::channel::<()>(1);
         let sectors_being_modified = Arc::<AsyncRwLock<HashSet<SectorIndex>>>::default();
         let (sectors_to_plot_sender, sectors_to_plot_receiver) = mpsc::channel(1);
+        let plotting_pause_handle = PlottingPauseHandle::new();
         // Some sectors may already be plotted, skip them
         let sectors_indices_left_to_plot =
             metadata_header.plotted_sector_count..target_sector_count;
 
+        #[cfg(target_os = "linux")]
+        let async_plot_file = io_uring_queue_depth
+            .map(|queue_depth| {
+                IoUringFileLinux::open(&directory.join(Self::PLOT_FILE), queue_depth).map(Arc::new)
+            })
+            .transpose()?;
+        #[cfg(not(target_os = "linux"))]
+        let async_plot_file = None::<Arc<!>>;
+
         let farming_thread_pool = ThreadPoolBuilder::new()
             .thread_name(move |thread_index| format!("farming-{farm_index}.{thread_index}"))
             .num_threads(farming_thread_pool_size)
@@ -1045,16 +1177,20 @@ impl SingleDiskFarm {
 
         faster_read_sector_record_chunks_mode_barrier.wait().await;
 
+        let metadata_file = Arc::new(metadata_file);
+
         let plotting_join_handle = task::spawn_blocking({
             let sectors_metadata = Arc::clone(&sectors_metadata);
             let handlers = Arc::clone(&handlers);
             let sectors_being_modified = Arc::clone(&sectors_being_modified);
             let node_client = node_client.clone();
             let plot_file = Arc::clone(&plot_file);
+            let metadata_file = Arc::clone(&metadata_file);
             let error_sender = Arc::clone(&error_sender);
             let span = span.clone();
             let global_mutex = Arc::clone(&global_mutex);
             let metrics = metrics.clone();
+            let plotting_paused = plotting_pause_handle.paused.subscribe();
 
             move || {
                 let _span_guard = span.enter();
@@ -1069,13 +1205,14 @@ impl SingleDiskFarm {
                     sectors_metadata: &sectors_metadata,
                     sectors_being_modified: &sectors_being_modified,
                     sectors_to_plot_receiver,
+                    plotting_paused,
                     sector_plotting_options: SectorPlottingOptions {
                         public_key,
                         node_client: &node_client,
                         pieces_in_sector,
                         sector_size,
                         plot_file,
-                        metadata_file: Arc::new(metadata_file),
+                        metadata_file,
                         handlers: &handlers,
                         global_mutex: &global_mutex,
                         plotter,
@@ -1130,6 +1267,78 @@ impl SingleDiskFarm {
             })
         }));
 
+        if let Some(integrity_check_interval) = integrity_check_interval {
+            let integrity_check_join_handle = task::spawn_blocking({
+                let sectors_metadata = Arc::clone(&sectors_metadata);
+                let sectors_being_modified = Arc::clone(&sectors_being_modified);
+                let sectors_to_plot_sender = sectors_to_plot_sender.clone();
+                let erasure_coding = erasure_coding.clone();
+                let handlers = Arc::clone(&handlers);
+                let plot_file = Arc::clone(&plot_file);
+                let global_mutex = Arc::clone(&global_mutex);
+                let span = span.clone();
+                let mut start_receiver = start_sender.subscribe();
+                let mut stop_receiver = stop_sender.subscribe();
+
+                move || {
+                    let _span_guard = span.enter();
+
+                    let integrity_check_options = IntegrityCheckOptions {
+                        public_key,
+                        pieces_in_sector,
+                        plot_file: &*plot_file,
+                        metadata_file: &*metadata_file,
+                        sectors_metadata: &sectors_metadata,
+                        sectors_being_modified: &sectors_being_modified,
+                        sectors_to_plot_sender,
+                        erasure_coding: &erasure_coding,
+                        read_sector_record_chunks_mode,
+                        handlers: &handlers,
+                        global_mutex: &global_mutex,
+                        interval: integrity_check_interval,
+                    };
+
+                    let integrity_check_fut = async {
+                        if start_receiver.recv().await.is_err() {
+                            // Dropped before starting
+                            return;
+                        }
+
+                        match single_disk_farm_info {
+                            SingleDiskFarmInfo::V0 { .. } => {
+                                integrity_check::<PosTableLegacy, _>(integrity_check_options).await
+                            }
+                            SingleDiskFarmInfo::V1 { .. } => {
+                                integrity_check::<PosTable, _>(integrity_check_options).await
+                            }
+                        }
+                    };
+
+                    Handle::current().block_on(async {
+                        select! {
+                            _ = integrity_check_fut.fuse() => {
+                                // Nothing, just exit
+                            }
+                            _ = stop_receiver.recv().fuse() => {
+                                // Nothing, just exit
+                            }
+                        }
+                    });
+                }
+            });
+            let integrity_check_join_handle =
+                AsyncJoinOnDrop::new(integrity_check_join_handle, false);
+
+            tasks.push(Box::pin(async move {
+                // Panic will already be printed by now
+                integrity_check_join_handle.await.map_err(|_error| {
+                    BackgroundTaskError::BackgroundTaskPanicked {
+                        task: format!("integrity-check-{farm_index}"),
+                    }
+                })
+            }));
+        }
+
         let plotting_scheduler_options = PlottingSchedulerOptions {
             public_key_hash: public_key.hash(),
             sectors_indices_left_to_plot,
@@ -1168,6 +1377,8 @@ impl SingleDiskFarm {
             let node_client = node_client.clone();
             let span = span.clone();
             let global_mutex = Arc::clone(&global_mutex);
+            #[cfg(target_os = "linux")]
+            let async_plot_file = async_plot_file.clone();
 
             move || {
                 let _span_guard = span.enter();
@@ -1178,11 +1389,15 @@ impl SingleDiskFarm {
                         return Ok(());
                     }
 
+                    #[cfg(target_os = "linux")]
+                    let plot_audit =
+                        PlotAudit::with_async_plot(&farming_plot, async_plot_file.as_deref());
+                    #[cfg(not(target_os = "linux"))]
                     let plot_audit = PlotAudit::new(&farming_plot);
 
                     let farming_options = FarmingOptions {
                         public_key,
-                        reward_address,
+                        reward_addresses,
                         node_client,
                         plot_audit,
                         sectors_metadata,
@@ -1198,10 +1413,10 @@ impl SingleDiskFarm {
                     };
                     match single_disk_farm_info {
                         SingleDiskFarmInfo::V0 { .. } => {
-                            farming::<PosTableLegacy, _, _>(farming_options).await
+                            farming::<PosTableLegacy, _, _, _>(farming_options).await
                         }
                         SingleDiskFarmInfo::V1 { .. } => {
-                            farming::<PosTable, _, _>(farming_options).await
+                            farming::<PosTable, _, _, _>(farming_options).await
                         }
                     }
                 };
@@ -1243,6 +1458,7 @@ impl SingleDiskFarm {
                     public_key,
                     pieces_in_sector,
                     plot_file,
+                    async_plot_file,
                     Arc::clone(&sectors_metadata),
                     erasure_coding,
                     sectors_being_modified,
@@ -1275,6 +1491,7 @@ impl SingleDiskFarm {
                     public_key,
                     pieces_in_sector,
                     plot_file,
+                    async_plot_file,
                     Arc::clone(&sectors_metadata),
                     erasure_coding,
                     sectors_being_modified,
@@ -1316,7 +1533,7 @@ impl SingleDiskFarm {
         }));
 
         tasks.push(Box::pin(async move {
-            match reward_signing(node_client, identity).await {
+            match reward_signing(node_client, reward_signer).await {
                 Ok(reward_signing_fut) => {
                     reward_signing_fut.await;
                 }
@@ -1343,6 +1560,7 @@ impl SingleDiskFarm {
             piece_cache,
             plot_cache,
             piece_reader,
+            plotting_pause_handle,
             start_sender: Some(start_sender),
             stop_sender: Some(stop_sender),
             _single_disk_farm_info_lock: single_disk_farm_info_lock,
@@ -1350,6 +1568,34 @@ impl SingleDiskFarm {
         Ok(farm)
     }
 
+    /// Get reward signer from remote signer, new identity is created in remote signer for new farm
+    #[cfg(unix)]
+    async fn remote_reward_signer(
+        directory: &Path,
+        remote_signer: &RemoteSigner,
+        create: bool,
+    ) -> Result<RemoteRewardSigner, SingleDiskFarmError> {
+        let public_key = match SingleDiskFarmInfo::load_from(directory)? {
+            Some(single_disk_farm_info) => *single_disk_farm_info.public_key(),
+            None => {
+                if !create {
+                    return Err(SingleDiskFarmError::FailedToOpenIdentity(
+                        IdentityError::Io(io::Error::new(
+                            io::ErrorKind::NotFound,
+                            "Farm does not exist and creation was explicitly disabled",
+                        )),
+                    ));
+                }
+
+                let public_key = remote_signer.create_identity().await?;
+                info!(%public_key, "Created new identity in remote signer");
+                public_key
+            }
+        };
+
+        Ok(remote_signer.reward_signer(public_key).await?)
+    }
+
     fn init(
         directory: &PathBuf,
         farmer_app_info: &FarmerAppInfo,
@@ -1358,21 +1604,49 @@ impl SingleDiskFarm {
         cache_percentage: u8,
         disable_farm_locking: bool,
         create: bool,
+        remote_reward_signer: Option<Arc<dyn RewardSigner>>,
     ) -> Result<SingleDiskFarmInit, SingleDiskFarmError> {
         fs::create_dir_all(directory)?;
 
-        let identity = if create {
-            Identity::open_or_create(directory)?
-        } else {
-            Identity::open(directory)?.ok_or_else(|| {
-                IdentityError::Io(io::Error::new(
-                    io::ErrorKind::NotFound,
-                    "Farm does not exist and creation was explicitly disabled",
-                ))
-            })?
+        let reward_signer = match remote_reward_signer {
+            Some(remote_reward_signer) => {
+                if directory.join(Identity::FILE_NAME).exists() {
+                    warn!(
+                        "Remote signer is used, but identity file is still present in the farm, \
+                        consider removing it from the farm"
+                    );
+                }
+
+                remote_reward_signer
+            }
+            None => {
+                let identity = if create {
+                    Identity::open_or_create(directory)?
+                } else {
+                    Identity::open(directory)?.ok_or_else(|| {
+                        IdentityError::Io(io::Error::new(
+                            io::ErrorKind::NotFound,
+                            "Farm does not exist and creation was explicitly disabled",
+                        ))
+                    })?
+                };
+
+                Arc::new(identity) as Arc<dyn RewardSigner>
+            }
+        };
+        let public_key = reward_signer.public_key();
+
+        let lock_farm = || {
+            if disable_farm_locking {
+                Ok(None)
+            } else {
+                SingleDiskFarmInfo::try_lock(directory)
+                    .map(Some)
+                    .map_err(SingleDiskFarmError::LikelyAlreadyInUse)
+            }
         };
-        let public_key = identity.public_key().to_bytes().into();
 
+        let single_disk_farm_info_lock;
         let single_disk_farm_info = match SingleDiskFarmInfo::load_from(directory)? {
             Some(mut single_disk_farm_info) => {
                 if &farmer_app_info.genesis_hash != single_disk_farm_info.genesis_hash() {
@@ -1410,31 +1684,18 @@ impl SingleDiskFarm {
                     );
                 }
 
-                if allocated_space != single_disk_farm_info.allocated_space() {
-                    info!(
-                        old_space = %bytesize::to_string(single_disk_farm_info.allocated_space(), true),
-                        new_space = %bytesize::to_string(allocated_space, true),
-                        "Farm size has changed"
-                    );
+                // Farm must be locked before it is modified in any way
+                single_disk_farm_info_lock = lock_farm()?;
 
-                    {
-                        let new_allocated_space = allocated_space;
-                        match &mut single_disk_farm_info {
-                            SingleDiskFarmInfo::V0 {
-                                allocated_space, ..
-                            } => {
-                                *allocated_space = new_allocated_space;
-                            }
-                            SingleDiskFarmInfo::V1 {
-                                allocated_space, ..
-                            } => {
-                                *allocated_space = new_allocated_space;
-                            }
-                        }
-                    }
-
-                    single_disk_farm_info.store_to(directory)?;
-                }
+                // Grows or shrinks the farm if allocated space has changed, also finishes
+                // previously interrupted resizing if there was any
+                resizing::resize(
+                    directory,
+                    &mut single_disk_farm_info,
+                    allocated_space,
+                    cache_percentage,
+                    single_disk_farm_info_lock.as_ref(),
+                )?;
 
                 single_disk_farm_info
             }
@@ -1449,19 +1710,12 @@ impl SingleDiskFarm {
 
                 single_disk_farm_info.store_to(directory)?;
 
+                single_disk_farm_info_lock = lock_farm()?;
+
                 single_disk_farm_info
             }
         };
 
-        let single_disk_farm_info_lock = if disable_farm_locking {
-            None
-        } else {
-            Some(
-                SingleDiskFarmInfo::try_lock(directory)
-                    .map_err(SingleDiskFarmError::LikelyAlreadyInUse)?,
-            )
-        };
-
         let pieces_in_sector = single_disk_farm_info.pieces_in_sector();
         let sector_size = sector_size(pieces_in_sector) as u64;
         let sector_metadata_size = SectorMetadataChecksummed::encoded_size();
@@ -1610,7 +1864,7 @@ impl SingleDiskFarm {
         );
 
         Ok(SingleDiskFarmInit {
-            identity,
+            reward_signer,
             single_disk_farm_info,
             single_disk_farm_info_lock,
             plot_file,
@@ -1641,6 +1895,71 @@ impl SingleDiskFarm {
         }
     }
 
+    /// Resize existing farm to specified allocated space without wiping it.
+    ///
+    /// Growing makes space for new sectors that will be plotted on next start, shrinking drops
+    /// sectors with the highest indices. Piece cache is resized accordingly with cached pieces
+    /// preserved where possible. Progress is persisted, so interrupted resizing is finished safely
+    /// next time farm is resized or opened.
+    pub fn resize(
+        directory: &Path,
+        allocated_space: u64,
+        cache_percentage: u8,
+        disable_farm_locking: bool,
+    ) -> Result<(), SingleDiskFarmError> {
+        let not_found = || {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "Single disk farm info not found at {}",
+                    directory.join(SingleDiskFarmInfo::FILE_NAME).display()
+                ),
+            )
+        };
+
+        // Farm must be locked before its info is read, such that it can't be modified concurrently
+        let single_disk_farm_info_lock = if disable_farm_locking {
+            None
+        } else {
+            Some(SingleDiskFarmInfo::try_lock(directory).map_err(|error| {
+                if error.kind() == io::ErrorKind::NotFound {
+                    SingleDiskFarmError::Io(not_found())
+                } else {
+                    SingleDiskFarmError::LikelyAlreadyInUse(error)
+                }
+            })?)
+        };
+
+        let mut single_disk_farm_info =
+            SingleDiskFarmInfo::load_from(directory)?.ok_or_else(not_found)?;
+
+        resizing::resize(
+            directory,
+            &mut single_disk_farm_info,
+            allocated_space,
+            cache_percentage,
+            single_disk_farm_info_lock.as_ref(),
+        )
+    }
+
+    /// Copy farm from one directory into another (typically on a different disk), verifying
+    /// checksums of sectors and cached pieces along the way.
+    ///
+    /// Progress is persisted in the target directory, so interrupted migration can be resumed by
+    /// calling this function again with the same arguments. With `allow_source_in_use` source
+    /// farm can continue farming while being copied, sectors replotted in the meantime are copied
+    /// again, running migration once more after source farm is stopped catches up with remaining
+    /// changes. Sectors that can't be copied consistently are marked as expired, such that target
+    /// farm replots them.
+    pub fn migrate(
+        from: &Path,
+        to: &Path,
+        allow_source_in_use: bool,
+        disable_farm_locking: bool,
+    ) -> Result<(), SingleDiskFarmMigrationError> {
+        migration::migrate(from, to, allow_source_in_use, disable_farm_locking)
+    }
+
     /// Effective on-disk allocation of the files related to the farm (takes some buffer space
     /// into consideration).
     ///
@@ -1825,6 +2144,11 @@ impl SingleDiskFarm {
         self.piece_reader.clone()
     }
 
+    /// Get handle that allows to pause and resume plotting of this farm
+    pub fn plotting_pause_handle(&self) -> PlottingPauseHandle {
+        self.plotting_pause_handle.clone()
+    }
+
     /// Subscribe to sector updates
     pub fn on_sector_update(&self, callback: HandlerFn<(SectorIndex, SectorUpdate)>) -> HandlerId {
         self.handlers.sector_update.add(callback)
@@ -1900,6 +2224,7 @@ impl SingleDiskFarm {
         }
 
         DiskPieceCache::wipe(directory)?;
+        resizing::wipe(directory)?;
 
         info!(
             "Deleting info file at {}",
@@ -1910,11 +2235,15 @@ impl SingleDiskFarm {
 
     /// Check the farm for corruption and repair errors (caused by disk errors or something else),
     /// returns an error when irrecoverable errors occur.
+    ///
+    /// `remote_signer` indicates that farm identity is held by remote signer, in which case
+    /// identity file is not expected to exist.
     pub fn scrub(
         directory: &Path,
         disable_farm_locking: bool,
         target: ScrubTarget,
         dry_run: bool,
+        remote_signer: bool,
     ) -> Result<(), SingleDiskFarmScrubError> {
         let span = Span::current();
 
@@ -1947,26 +2276,36 @@ impl SingleDiskFarm {
                 )
             };
 
-            let identity = {
+            {
                 let file = directory.join(Identity::FILE_NAME);
                 info!(path = %file.display(), "Checking identity file");
 
                 match Identity::open(directory) {
-                    Ok(Some(identity)) => identity,
+                    Ok(Some(identity)) => {
+                        if PublicKey::from(identity.public.to_bytes()) != *info.public_key() {
+                            return Err(SingleDiskFarmScrubError::PublicKeyMismatch {
+                                identity: PublicKey::from(identity.public.to_bytes()),
+                                info: *info.public_key(),
+                            });
+                        }
+                    }
                     Ok(None) => {
-                        return Err(SingleDiskFarmScrubError::IdentityFileDoesNotExist { file });
+                        if !remote_signer {
+                            return Err(SingleDiskFarmScrubError::IdentityFileDoesNotExist {
+                                file,
+                            });
+                        }
+
+                        // Farms that use remote signer only store public key in farm info
+                        info!(
+                            path = %file.display(),
+                            "Identity file doesn't exist, farm uses remote signer"
+                        );
                     }
                     Err(error) => {
                         return Err(SingleDiskFarmScrubError::IdentityCantBeOpened { file, error });
                     }
                 }
-            };
-
-            if PublicKey::from(identity.public.to_bytes()) != *info.public_key() {
-                return Err(SingleDiskFarmScrubError::PublicKeyMismatch {
-                    identity: PublicKey::from(identity.public.to_bytes()),
-                    info: *info.public_key(),
-                });
             }
 
             let sector_metadata_size = SectorMetadataChecksummed::encoded_size();
@@ -2243,36 +2582,28 @@ impl SingleDiskFarm {
                         }
 
                         if target.plot() {
-                            let mut hasher = blake3::Hasher::new();
                             // Read sector bytes and compute checksum
-                            for offset_in_sector in
-                                sector_bytes_range.clone().step_by(scratch_buffer.len())
-                            {
-                                let offset =
-                                    u64::from(sector_index) * sector_size + offset_in_sector as u64;
-                                let bytes_to_read = (offset_in_sector + scratch_buffer.len())
-                                    .min(sector_bytes_range.end)
-                                    - offset_in_sector;
-
-                                let bytes = &mut scratch_buffer[..bytes_to_read];
-
-                                if let Err(error) = plot_file.read_exact_at(bytes, offset) {
-                                    warn!(
-                                        path = %plot_file_path.display(),
-                                        %error,
-                                        %sector_index,
-                                        %offset,
-                                        size = %bytes.len() as u64,
-                                        "Failed to read sector bytes"
-                                    );
-
-                                    continue;
-                                }
-
-                                hasher.update(bytes);
-                            }
+                            let actual_checksum = sector_checksum(
+                                sector_size as usize,
+                                scratch_buffer,
+                                |bytes, offset_in_sector| -> Result<(), SingleDiskFarmScrubError> {
+                                    let offset =
+                                        u64::from(sector_index) * sector_size + offset_in_sector;
+
+                                    if let Err(error) = plot_file.read_exact_at(bytes, offset) {
+                                        warn!(
+                                            path = %plot_file_path.display(),
+                                            %error,
+                                            %sector_index,
+                                            %offset,
+                                            size = %bytes.len() as u64,
+                                            "Failed to read sector bytes"
+                                        );
+                                    }
 
-                            let actual_checksum = *hasher.finalize().as_bytes();
+                                    Ok(())
+                                },
+                            )?;
                             let mut expected_checksum = [0; mem::size_of::<Blake3Hash>()];
                             {
                                 let offset = u64::from(sector_index) * sector_size
@@ -2311,38 +2642,35 @@ impl SingleDiskFarm {
                                     )?;
                                 }
 
-                                scratch_buffer.fill(0);
-
-                                hasher.reset();
                                 // Fill sector with zeroes and compute checksum
-                                for offset_in_sector in
-                                    sector_bytes_range.clone().step_by(scratch_buffer.len())
-                                {
-                                    let offset = u64::from(sector_index) * sector_size
-                                        + offset_in_sector as u64;
-                                    let bytes_to_write = (offset_in_sector + scratch_buffer.len())
-                                        .min(sector_bytes_range.end)
-                                        - offset_in_sector;
-                                    let bytes = &mut scratch_buffer[..bytes_to_write];
-
-                                    if !dry_run {
-                                        if let Err(error) = plot_file.write_all_at(bytes, offset) {
-                                            return Err(
-                                                SingleDiskFarmScrubError::FailedToWriteBytes {
-                                                    file: plot_file_path.clone(),
-                                                    size: scratch_buffer.len() as u64,
-                                                    offset,
-                                                    error,
-                                                },
-                                            );
+                                let checksum = sector_checksum(
+                                    sector_size as usize,
+                                    scratch_buffer,
+                                    |bytes, offset_in_sector| {
+                                        bytes.fill(0);
+
+                                        let offset = u64::from(sector_index) * sector_size
+                                            + offset_in_sector;
+                                        if !dry_run {
+                                            if let Err(error) =
+                                                plot_file.write_all_at(bytes, offset)
+                                            {
+                                                return Err(
+                                                    SingleDiskFarmScrubError::FailedToWriteBytes {
+                                                        file: plot_file_path.clone(),
+                                                        size: bytes.len() as u64,
+                                                        offset,
+                                                        error,
+                                                    },
+                                                );
+                                            }
                                         }
-                                    }
 
-                                    hasher.update(bytes);
-                                }
+                                        Ok(())
+                                    },
+                                )?;
                                 // Write checksum
                                 {
-                                    let checksum = *hasher.finalize().as_bytes();
                                     let offset = u64::from(sector_index) * sector_size
                                         + sector_bytes_range.end as u64;
                                     if !dry_run {
@@ -2538,6 +2866,36 @@ fn write_dummy_sector_metadata(
         })
 }
 
+/// Compute checksum of sector bytes (everything except the checksum stored at the end of the
+/// sector).
+///
+/// `process_chunk` is called for every chunk of sector bytes with its offset within the sector and
+/// must fill the chunk with sector bytes, it is typically reading them from (or writing them to)
+/// the plot along the way.
+fn sector_checksum<E, F>(
+    sector_size: usize,
+    scratch_buffer: &mut [u8],
+    mut process_chunk: F,
+) -> Result<Blake3Hash, E>
+where
+    F: FnMut(&mut [u8], u64) -> Result<(), E>,
+{
+    let sector_bytes_size = sector_size - mem::size_of::<Blake3Hash>();
+    let mut hasher = blake3::Hasher::new();
+
+    for offset_in_sector in (0..sector_bytes_size).step_by(scratch_buffer.len()) {
+        let chunk_size = scratch_buffer
+            .len()
+            .min(sector_bytes_size - offset_in_sector);
+        let bytes = &mut scratch_buffer[..chunk_size];
+
+        process_chunk(bytes, offset_in_sector as u64)?;
+        hasher.update(bytes);
+    }
+
+    Ok(*hasher.finalize().as_bytes())
+}
+
 fn faster_read_sector_record_chunks_mode<OP, FP>(
     original_plot: &OP,
     farming_plot: &FP,
@@ -10,6 +10,7 @@ use crate::farm::{
 };
 use crate::node_client::NodeClient;
 use crate::single_disk_farm::metrics::SingleDiskFarmMetrics;
+use crate::single_disk_farm::reward_address::RewardAddressPolicy;
 use crate::single_disk_farm::Handlers;
 use async_lock::{Mutex as AsyncMutex, RwLock as AsyncRwLock};
 use futures::channel::mpsc;
@@ -22,11 +23,13 @@ use std::time::Instant;
 use subspace_core_primitives::crypto::kzg::Kzg;
 use subspace_core_primitives::{PosSeed, PublicKey, SectorIndex, Solution, SolutionRange};
 use subspace_erasure_coding::ErasureCoding;
-use subspace_farmer_components::auditing::{audit_plot_sync, AuditingError};
+use subspace_farmer_components::auditing::{
+    audit_plot_async, audit_plot_sync, AuditResult, AuditingError,
+};
 use subspace_farmer_components::proving::{ProvableSolutions, ProvingError};
 use subspace_farmer_components::reading::ReadSectorRecordChunksMode;
 use subspace_farmer_components::sector::SectorMetadataChecksummed;
-use subspace_farmer_components::ReadAtSync;
+use subspace_farmer_components::{ReadAtAsync, ReadAtSync};
 use subspace_proof_of_space::{Table, TableGenerator};
 use subspace_rpc_primitives::{SlotInfo, SolutionResponse};
 use tracing::{debug, error, info, trace, warn, Span};
@@ -108,20 +111,40 @@ impl<'a, 'b, PosTable> Copy for PlotAuditOptions<'a, 'b, PosTable> where PosTabl
 
 /// Plot auditing implementation
 #[derive(Debug)]
-pub struct PlotAudit<Plot>(Plot)
+pub struct PlotAudit<Plot, AsyncPlot = !>
 where
-    Plot: ReadAtSync;
+    Plot: ReadAtSync,
+    AsyncPlot: ReadAtAsync,
+{
+    plot: Plot,
+    async_plot: Option<AsyncPlot>,
+}
 
-impl<'a, Plot> PlotAudit<Plot>
+impl<Plot> PlotAudit<Plot, !>
 where
-    Plot: ReadAtSync + 'a,
+    Plot: ReadAtSync,
 {
     /// Create new instance
     pub fn new(plot: Plot) -> Self {
-        Self(plot)
+        Self {
+            plot,
+            async_plot: None,
+        }
     }
+}
 
-    /// Audit this plot
+impl<'a, Plot, AsyncPlot> PlotAudit<Plot, AsyncPlot>
+where
+    Plot: ReadAtSync + 'a,
+    AsyncPlot: ReadAtAsync,
+{
+    /// Create new instance that reads s-buckets with `async_plot` (if provided) during
+    /// [`Self::audit_async`], see [`audit_plot_async`] for details
+    pub fn with_async_plot(plot: Plot, async_plot: Option<AsyncPlot>) -> Self {
+        Self { plot, async_plot }
+    }
+
+    /// Audit this plot, s-buckets are always read with synchronous plot
     pub fn audit<'b, PosTable>(
         &'a self,
         options: PlotAuditOptions<'a, 'b, PosTable>,
@@ -135,66 +158,133 @@ where
     where
         PosTable: Table,
     {
-        let PlotAuditOptions {
-            public_key,
-            reward_address,
-            slot_info,
-            sectors_metadata,
-            kzg,
-            erasure_coding,
-            sectors_being_modified,
-            read_sector_record_chunks_mode: mode,
-            table_generator,
-        } = options;
-
         let audit_results = audit_plot_sync(
-            public_key,
-            &slot_info.global_challenge,
-            slot_info.voting_solution_range,
-            &self.0,
-            sectors_metadata,
-            sectors_being_modified,
+            options.public_key,
+            &options.slot_info.global_challenge,
+            options.slot_info.voting_solution_range,
+            &self.plot,
+            options.sectors_metadata,
+            options.sectors_being_modified,
         )?;
 
-        Ok(audit_results
-            .into_iter()
-            .filter_map(|audit_results| {
-                let sector_index = audit_results.sector_index;
-
-                let sector_solutions = audit_results.solution_candidates.into_solutions(
-                    reward_address,
-                    kzg,
-                    erasure_coding,
-                    mode,
-                    |seed: &PosSeed| table_generator.lock().generate_parallel(seed),
-                );
-
-                let sector_solutions = match sector_solutions {
-                    Ok(solutions) => solutions,
-                    Err(error) => {
-                        warn!(
-                            %error,
-                            %sector_index,
-                            "Failed to turn solution candidates into solutions",
-                        );
+        Ok(audit_results_into_solutions(options, audit_results))
+    }
 
-                        return None;
-                    }
-                };
+    /// Audit this plot on provided thread pool.
+    ///
+    /// If async plot was provided, s-buckets are read with it and reads are awaited by the caller
+    /// rather than by thread pool threads, otherwise this is equivalent to calling [`Self::audit`]
+    /// on thread pool.
+    pub async fn audit_async<'b, PosTable>(
+        &'a self,
+        options: PlotAuditOptions<'a, 'b, PosTable>,
+        thread_pool: &ThreadPool,
+    ) -> Result<
+        Vec<(
+            SectorIndex,
+            impl ProvableSolutions<Item = Result<Solution<PublicKey, PublicKey>, ProvingError>> + 'a,
+        )>,
+        AuditingError,
+    >
+    where
+        PosTable: Table,
+    {
+        let span = Span::current();
+
+        let audit_results = match &self.async_plot {
+            Some(async_plot) => {
+                audit_plot_async(
+                    options.public_key,
+                    &options.slot_info.global_challenge,
+                    options.slot_info.voting_solution_range,
+                    &self.plot,
+                    async_plot,
+                    options.sectors_metadata,
+                    options.sectors_being_modified,
+                    thread_pool,
+                )
+                .await?
+            }
+            None => thread_pool.install(|| {
+                let _span_guard = span.enter();
+
+                audit_plot_sync(
+                    options.public_key,
+                    &options.slot_info.global_challenge,
+                    options.slot_info.voting_solution_range,
+                    &self.plot,
+                    options.sectors_metadata,
+                    options.sectors_being_modified,
+                )
+            })?,
+        };
+
+        Ok(thread_pool.install(|| {
+            let _span_guard = span.enter();
+
+            audit_results_into_solutions(options, audit_results)
+        }))
+    }
+}
+
+fn audit_results_into_solutions<'a, PosTable, Sector>(
+    options: PlotAuditOptions<'a, '_, PosTable>,
+    audit_results: Vec<AuditResult<'a, Sector>>,
+) -> Vec<(
+    SectorIndex,
+    impl ProvableSolutions<Item = Result<Solution<PublicKey, PublicKey>, ProvingError>> + 'a,
+)>
+where
+    PosTable: Table,
+    Sector: ReadAtSync + 'a,
+{
+    let PlotAuditOptions {
+        reward_address,
+        kzg,
+        erasure_coding,
+        read_sector_record_chunks_mode: mode,
+        table_generator,
+        ..
+    } = options;
+
+    audit_results
+        .into_iter()
+        .filter_map(|audit_results| {
+            let sector_index = audit_results.sector_index;
+
+            let sector_solutions = audit_results.solution_candidates.into_solutions(
+                reward_address,
+                kzg,
+                erasure_coding,
+                mode,
+                |seed: &PosSeed| table_generator.lock().generate_parallel(seed),
+            );
+
+            let sector_solutions = match sector_solutions {
+                Ok(solutions) => solutions,
+                Err(error) => {
+                    warn!(
+                        %error,
+                        %sector_index,
+                        "Failed to turn solution candidates into solutions",
+                    );
 
-                if sector_solutions.len() == 0 {
                     return None;
                 }
+            };
 
-                Some((sector_index, sector_solutions))
-            })
-            .collect())
-    }
+            if sector_solutions.len() == 0 {
+                return None;
+            }
+
+            Some((sector_index, sector_solutions))
+        })
+        .collect()
 }
 
 pub(super) struct FarmingOptions<NC, PlotAudit> {
     pub(super) public_key: PublicKey,
-    pub(super) reward_address: PublicKey,
+    pub(super) reward_addresses: Arc<dyn RewardAddressPolicy>,
     pub(super) node_client: NC,
     pub(super) plot_audit: PlotAudit,
     pub(super) sectors_metadata: Arc<AsyncRwLock<Vec<SectorMetadataChecksummed>>>,
@@ -213,17 +303,18 @@ pub(super) struct FarmingOptions<NC, PlotAudit> {
 ///
 /// NOTE: Returned future is async, but does blocking operations and should be running in dedicated
 /// thread.
-pub(super) async fn farming<'a, PosTable, NC, Plot>(
-    farming_options: FarmingOptions<NC, PlotAudit<Plot>>,
+pub(super) async fn farming<'a, PosTable, NC, Plot, AsyncPlot>(
+    farming_options: FarmingOptions<NC, PlotAudit<Plot, AsyncPlot>>,
 ) -> Result<(), FarmingError>
 where
     PosTable: Table,
     NC: NodeClient,
     Plot: ReadAtSync + 'a,
+    AsyncPlot: ReadAtAsync + Sync + 'a,
 {
     let FarmingOptions {
         public_key,
-        reward_address,
+        reward_addresses,
         node_client,
         plot_audit,
         sectors_metadata,
@@ -253,6 +344,7 @@ where
 
     while let Some(slot_info) = slot_info_notifications.next().await {
         let slot = slot_info.slot_number;
+        let reward_address = reward_addresses.reward_address(slot);
 
         // Take mutex briefly to make sure farming is allowed right now
         global_mutex.lock().await;
@@ -266,21 +358,22 @@ where
             let mut sectors_solutions = {
                 let sectors_being_modified = &*sectors_being_modified.read().await;
 
-                thread_pool.install(|| {
-                    let _span_guard = span.enter();
-
-                    plot_audit.audit(PlotAuditOptions::<PosTable> {
-                        public_key: &public_key,
-                        reward_address: &reward_address,
-                        slot_info,
-                        sectors_metadata: &sectors_metadata,
-                        kzg: &kzg,
-                        erasure_coding: &erasure_coding,
-                        sectors_being_modified,
-                        read_sector_record_chunks_mode,
-                        table_generator: &table_generator,
-                    })
-                })?
+                plot_audit
+                    .audit_async(
+                        PlotAuditOptions::<PosTable> {
+                            public_key: &public_key,
+                            reward_address: &reward_address,
+                            slot_info,
+                            sectors_metadata: &sectors_metadata,
+                            kzg: &kzg,
+                            erasure_coding: &erasure_coding,
+                            sectors_being_modified,
+                            read_sector_record_chunks_mode,
+                            table_generator: &table_generator,
+                        },
+                        &thread_pool,
+                    )
+                    .await?
             };
 
             sectors_solutions.sort_by(|a, b| {
@@ -322,10 +415,17 @@ where
                     let solution = match maybe_solution {
                         Ok(solution) => solution,
                         Err(error) => {
+                            let time = start.elapsed();
                             if let Some(metrics) = &metrics {
-                                metrics
-                                    .observe_proving_time(&start.elapsed(), ProvingResult::Failed);
+                                metrics.observe_proving_time(&time, ProvingResult::Failed);
+                                metrics.note_solution(&reward_address, ProvingResult::Failed);
                             }
+                            handlers.farming_notification.call_simple(
+                                &FarmingNotification::Proving(ProvingDetails {
+                                    result: ProvingResult::Failed,
+                                    time,
+                                }),
+                            );
                             error!(%slot, %sector_index, %error, "Failed to prove");
                             // Do not error completely as disk corruption or other reasons why
                             // proving might fail
@@ -342,6 +442,7 @@ where
                         if time >= farming_timeout {
                             if let Some(metrics) = &metrics {
                                 metrics.observe_proving_time(&time, ProvingResult::Timeout);
+                                metrics.note_solution(&reward_address, ProvingResult::Timeout);
                             }
                             handlers.farming_notification.call_simple(
                                 &FarmingNotification::Proving(ProvingDetails {
@@ -370,6 +471,7 @@ where
                         let time = start.elapsed();
                         if let Some(metrics) = &metrics {
                             metrics.observe_proving_time(&time, ProvingResult::Rejected);
+                            metrics.note_solution(&reward_address, ProvingResult::Rejected);
                         }
                         handlers
                             .farming_notification
@@ -386,9 +488,12 @@ where
                         break 'solutions_processing;
                     }
 
+                    reward_addresses.solution_submitted(&reward_address);
+
                     let time = start.elapsed();
                     if let Some(metrics) = &metrics {
                         metrics.observe_proving_time(&time, ProvingResult::Success);
+                        metrics.note_solution(&reward_address, ProvingResult::Success);
                     }
                     handlers
                         .farming_notification
@@ -1,12 +1,13 @@
 //! Farm identity
 
+use async_trait::async_trait;
 use parity_scale_codec::{Decode, Encode};
 use schnorrkel::context::SigningContext;
 use schnorrkel::{ExpansionMode, Keypair, PublicKey, SecretKey, Signature};
 use std::ops::Deref;
 use std::path::Path;
 use std::{fmt, fs, io};
-use subspace_core_primitives::REWARD_SIGNING_CONTEXT;
+use subspace_core_primitives::{RewardSignature, REWARD_SIGNING_CONTEXT};
 use substrate_bip39::mini_secret_from_entropy;
 use thiserror::Error;
 use tracing::debug;
@@ -37,6 +38,19 @@ pub enum IdentityError {
     Decoding(#[from] parity_scale_codec::Error),
 }
 
+/// Abstraction over signing of reward hashes with farm identity.
+///
+/// [`Identity`] signs locally, while other implementations allow to keep secret key outside of the
+/// farm (see [`remote_signer`](super::remote_signer) module).
+#[async_trait]
+pub trait RewardSigner: fmt::Debug + Send + Sync {
+    /// Public key of the identity
+    fn public_key(&self) -> subspace_core_primitives::PublicKey;
+
+    /// Sign reward hash
+    async fn sign_reward_hash(&self, hash: &[u8; 32]) -> Result<RewardSignature, String>;
+}
+
 /// `Identity` struct is an abstraction of public & secret key related operations.
 ///
 /// It is basically a wrapper of the keypair (which holds public & secret keys)
@@ -167,3 +181,15 @@ impl Identity {
         self.keypair.sign(self.substrate_ctx.bytes(header_hash))
     }
 }
+
+#[async_trait]
+impl RewardSigner for Identity {
+    #[inline]
+    fn public_key(&self) -> subspace_core_primitives::PublicKey {
+        self.keypair.public.to_bytes().into()
+    }
+
+    async fn sign_reward_hash(&self, hash: &[u8; 32]) -> Result<RewardSignature, String> {
+        Ok(Identity::sign_reward_hash(self, hash).to_bytes().into())
+    }
+}
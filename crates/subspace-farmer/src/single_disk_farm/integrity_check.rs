@@ -0,0 +1,251 @@
+//! Background integrity check of plotted sectors
+//!
+//! Sectors are checked one by one in a loop with a delay between them, so the check has minimal
+//! impact on farming. Corrupted sectors are reported with [`SectorUpdate::Corrupted`] and
+//! scheduled for replotting.
+
+use crate::farm::{SectorCorruptionDetails, SectorUpdate};
+use crate::single_disk_farm::plotting::SectorToPlot;
+use crate::single_disk_farm::{Handlers, RESERVED_PLOT_METADATA};
+use async_lock::{Mutex as AsyncMutex, RwLock as AsyncRwLock};
+use futures::channel::mpsc;
+use futures::SinkExt;
+use parity_scale_codec::{Decode, Encode};
+use std::collections::HashSet;
+use std::mem;
+use std::time::Duration;
+use subspace_core_primitives::{Blake3Hash, PieceOffset, PublicKey, SectorId, SectorIndex};
+use subspace_erasure_coding::ErasureCoding;
+use subspace_farmer_components::reading::{read_piece, ReadSectorRecordChunksMode};
+use subspace_farmer_components::sector::{sector_size, SectorMetadataChecksummed};
+use subspace_farmer_components::{ReadAt, ReadAtSync};
+use subspace_proof_of_space::Table;
+use tracing::{debug, trace, warn};
+
+/// Size of a single read when computing sector checksum
+const READ_CHUNK_SIZE: usize = 1024 * 1024;
+
+pub(super) struct IntegrityCheckOptions<'a, S> {
+    pub(super) public_key: PublicKey,
+    pub(super) pieces_in_sector: u16,
+    pub(super) plot_file: &'a S,
+    pub(super) metadata_file: &'a S,
+    pub(super) sectors_metadata: &'a AsyncRwLock<Vec<SectorMetadataChecksummed>>,
+    pub(super) sectors_being_modified: &'a AsyncRwLock<HashSet<SectorIndex>>,
+    pub(super) sectors_to_plot_sender: mpsc::Sender<SectorToPlot>,
+    pub(super) erasure_coding: &'a ErasureCoding,
+    pub(super) read_sector_record_chunks_mode: ReadSectorRecordChunksMode,
+    pub(super) handlers: &'a Handlers,
+    pub(super) global_mutex: &'a AsyncMutex<()>,
+    /// Delay between checking consecutive sectors
+    pub(super) interval: Duration,
+}
+
+/// Checks plotted sectors one by one in a loop, never returns.
+///
+/// NOTE: Returned future is async, but does blocking operations and should be running in dedicated
+/// thread.
+pub(super) async fn integrity_check<PosTable, S>(
+    integrity_check_options: IntegrityCheckOptions<'_, S>,
+) where
+    PosTable: Table,
+    S: ReadAtSync,
+{
+    let IntegrityCheckOptions {
+        public_key,
+        pieces_in_sector,
+        plot_file,
+        metadata_file,
+        sectors_metadata,
+        sectors_being_modified,
+        mut sectors_to_plot_sender,
+        erasure_coding,
+        read_sector_record_chunks_mode,
+        handlers,
+        global_mutex,
+        interval,
+    } = integrity_check_options;
+
+    let mut table_generator = PosTable::generator();
+    let mut next_sector_index: SectorIndex = 0;
+
+    loop {
+        tokio::time::sleep(interval).await;
+
+        let sector_metadata = {
+            let sectors_metadata = sectors_metadata.read().await;
+
+            if usize::from(next_sector_index) >= sectors_metadata.len() {
+                // Start next round from the beginning
+                next_sector_index = 0;
+            }
+
+            match sectors_metadata.get(usize::from(next_sector_index)) {
+                Some(sector_metadata) => sector_metadata.clone(),
+                None => {
+                    // Nothing is plotted yet
+                    continue;
+                }
+            }
+        };
+        let sector_index = next_sector_index;
+        next_sector_index += 1;
+
+        if sectors_being_modified.read().await.contains(&sector_index) {
+            trace!(%sector_index, "Sector is being modified, skipping integrity check");
+            continue;
+        }
+
+        // Take mutex briefly to make sure reading is allowed right now
+        global_mutex.lock().await;
+
+        let result = check_sector::<PosTable, _>(
+            &public_key,
+            pieces_in_sector,
+            plot_file,
+            metadata_file,
+            &sector_metadata,
+            erasure_coding,
+            read_sector_record_chunks_mode,
+            &mut table_generator,
+        )
+        .await;
+
+        let corruption_details = match result {
+            Ok(()) => {
+                debug!(%sector_index, "Sector passed integrity check");
+                continue;
+            }
+            Err(corruption_details) => corruption_details,
+        };
+
+        // Sector might have been replotted while it was being checked, in which case mismatch is
+        // expected
+        if sectors_being_modified.read().await.contains(&sector_index)
+            || sectors_metadata
+                .read()
+                .await
+                .get(usize::from(sector_index))
+                .map(Encode::encode)
+                != Some(sector_metadata.encode())
+        {
+            debug!(
+                %sector_index,
+                %corruption_details,
+                "Sector was modified during integrity check, ignoring"
+            );
+            continue;
+        }
+
+        warn!(
+            %sector_index,
+            %corruption_details,
+            "Sector is corrupted, scheduling replotting"
+        );
+
+        handlers
+            .sector_update
+            .call_simple(&(sector_index, SectorUpdate::Corrupted(corruption_details)));
+
+        let (sector_to_plot, acknowledgement_receiver) = SectorToPlot::replot(sector_index);
+        if let Err(error) = sectors_to_plot_sender.send(sector_to_plot).await {
+            warn!(%error, "Failed to send corrupted sector index for replotting");
+            return;
+        }
+
+        // We do not care if message was sent back or sender was just dropped
+        let _ = acknowledgement_receiver.await;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn check_sector<PosTable, S>(
+    public_key: &PublicKey,
+    pieces_in_sector: u16,
+    plot_file: &S,
+    metadata_file: &S,
+    sector_metadata: &SectorMetadataChecksummed,
+    erasure_coding: &ErasureCoding,
+    read_sector_record_chunks_mode: ReadSectorRecordChunksMode,
+    table_generator: &mut PosTable::Generator,
+) -> Result<(), SectorCorruptionDetails>
+where
+    PosTable: Table,
+    S: ReadAtSync,
+{
+    let sector_index = sector_metadata.sector_index;
+
+    // Metadata on disk must decode (checksum is verified during decoding) and match metadata in
+    // memory
+    {
+        let sector_metadata_size = SectorMetadataChecksummed::encoded_size();
+        let mut sector_metadata_bytes = vec![0; sector_metadata_size];
+        metadata_file
+            .read_at(
+                &mut sector_metadata_bytes,
+                RESERVED_PLOT_METADATA + u64::from(sector_index) * sector_metadata_size as u64,
+            )
+            .map_err(|error| SectorCorruptionDetails::Read(error.to_string()))?;
+
+        match SectorMetadataChecksummed::decode(&mut sector_metadata_bytes.as_slice()) {
+            Ok(sector_metadata_on_disk) => {
+                if sector_metadata_on_disk.encode() != sector_metadata.encode() {
+                    return Err(SectorCorruptionDetails::Metadata);
+                }
+            }
+            Err(error) => {
+                debug!(%sector_index, %error, "Failed to decode sector metadata");
+                return Err(SectorCorruptionDetails::Metadata);
+            }
+        }
+    }
+
+    let sector_size = sector_size(pieces_in_sector);
+    let sector = plot_file.offset(u64::from(sector_index) * sector_size as u64);
+
+    // The last bytes of the sector are checksum of everything before it
+    {
+        let sector_bytes_size = sector_size - mem::size_of::<Blake3Hash>();
+        let mut hasher = blake3::Hasher::new();
+        let mut buffer = vec![0; READ_CHUNK_SIZE];
+
+        for offset in (0..sector_bytes_size).step_by(READ_CHUNK_SIZE) {
+            let bytes = &mut buffer[..READ_CHUNK_SIZE.min(sector_bytes_size - offset)];
+            sector
+                .read_at(bytes, offset as u64)
+                .map_err(|error| SectorCorruptionDetails::Read(error.to_string()))?;
+            hasher.update(bytes);
+        }
+
+        let mut expected_checksum = Blake3Hash::default();
+        sector
+            .read_at(&mut expected_checksum, sector_bytes_size as u64)
+            .map_err(|error| SectorCorruptionDetails::Read(error.to_string()))?;
+
+        if *hasher.finalize().as_bytes() != expected_checksum {
+            return Err(SectorCorruptionDetails::Checksum);
+        }
+    }
+
+    // Checksum only confirms that sector wasn't modified since it was written, recover one random
+    // piece to make sure it matches record commitment and witness stored in the sector
+    let piece_offset = PieceOffset::from(rand::random::<u16>() % pieces_in_sector);
+    let sector_id = SectorId::new(public_key.hash(), sector_index);
+
+    read_piece::<PosTable, _, _>(
+        piece_offset,
+        &sector_id,
+        sector_metadata,
+        &ReadAt::from_sync(&sector),
+        erasure_coding,
+        read_sector_record_chunks_mode,
+        table_generator,
+    )
+    .await
+    .map_err(|error| SectorCorruptionDetails::Piece {
+        piece_offset,
+        error: error.to_string(),
+    })?;
+
+    Ok(())
+}
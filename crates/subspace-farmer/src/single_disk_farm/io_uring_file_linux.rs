@@ -0,0 +1,366 @@
+//! Wrapper data structure for asynchronous I/O using io_uring on Linux
+
+use crate::single_disk_farm::unbuffered_io_file_windows::DISK_SECTOR_SIZE;
+use futures::channel::oneshot;
+use futures::stream::FuturesUnordered;
+use futures::StreamExt;
+use io_uring::{opcode, types, IoUring};
+use static_assertions::const_assert_eq;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::num::NonZeroU32;
+use std::os::fd::AsRawFd;
+use std::path::Path;
+use std::sync::mpsc;
+use std::{io, mem, slice, thread};
+use subspace_farmer_components::file_ext::OpenOptionsExt;
+use subspace_farmer_components::{AsyncReadBytes, ReadAtAsync};
+use tracing::{debug, error};
+
+/// Restrict how much data to read from disk in a single request to avoid very large memory usage
+const MAX_READ_SIZE: usize = 1024 * 1024;
+
+const_assert_eq!(MAX_READ_SIZE % DISK_SECTOR_SIZE, 0);
+
+/// Memory that satisfies alignment requirements of `O_DIRECT`
+#[derive(Debug, Copy, Clone)]
+#[repr(C, align(4096))]
+struct AlignedSector([u8; DISK_SECTOR_SIZE]);
+
+const_assert_eq!(mem::align_of::<AlignedSector>(), DISK_SECTOR_SIZE);
+const_assert_eq!(mem::size_of::<AlignedSector>(), DISK_SECTOR_SIZE);
+
+#[derive(Debug)]
+struct ReadRequest {
+    /// Aligned offset
+    offset: u64,
+    buffer: Vec<AlignedSector>,
+    /// How many bytes were already read into the buffer
+    bytes_read: usize,
+    response_sender: oneshot::Sender<io::Result<(Vec<AlignedSector>, usize)>>,
+}
+
+/// Wrapper data structure for asynchronous reads using io_uring on Linux.
+///
+/// Reads are submitted to a dedicated thread that owns io_uring instance and keeps up to queue
+/// depth reads in flight, such that deep I/O queue can be achieved without a large number of
+/// threads doing blocking reads. File is opened with `O_DIRECT` when file system supports it, all
+/// reads are aligned internally.
+#[derive(Debug)]
+pub struct IoUringFileLinux {
+    read_request_sender: mpsc::Sender<ReadRequest>,
+}
+
+impl ReadAtAsync for IoUringFileLinux {
+    async fn read_at<B>(&self, buf: B, offset: u64) -> io::Result<B>
+    where
+        AsyncReadBytes<B>: From<B>,
+        B: AsMut<[u8]> + Unpin + 'static,
+    {
+        let mut buf = AsyncReadBytes::from(buf);
+
+        let mut reads = buf
+            .as_mut()
+            .chunks_mut(MAX_READ_SIZE)
+            .enumerate()
+            .map(|(chunk_index, chunk)| {
+                self.read_exact_at_internal(chunk, offset + (chunk_index * MAX_READ_SIZE) as u64)
+            })
+            .collect::<FuturesUnordered<_>>();
+
+        while let Some(result) = reads.next().await {
+            result?;
+        }
+        drop(reads);
+
+        Ok(buf.into_inner())
+    }
+}
+
+impl ReadAtAsync for &IoUringFileLinux {
+    #[inline]
+    async fn read_at<B>(&self, buf: B, offset: u64) -> io::Result<B>
+    where
+        AsyncReadBytes<B>: From<B>,
+        B: AsMut<[u8]> + Unpin + 'static,
+    {
+        (*self).read_at(buf, offset).await
+    }
+}
+
+impl IoUringFileLinux {
+    /// Open file at specified path for reading with io_uring, up to `queue_depth` reads will be in
+    /// flight at the same time
+    pub fn open(path: &Path, queue_depth: NonZeroU32) -> io::Result<Self> {
+        let file = match OpenOptions::new().read(true).advise_unbuffered().open(path) {
+            Ok(file) => file,
+            Err(error) if error.raw_os_error() == Some(libc::EINVAL) => {
+                debug!(
+                    path = %path.display(),
+                    "File system doesn't support O_DIRECT, using buffered reads"
+                );
+                OpenOptions::new().read(true).open(path)?
+            }
+            Err(error) => {
+                return Err(error);
+            }
+        };
+        let ring = IoUring::new(queue_depth.get())?;
+
+        let (read_request_sender, read_request_receiver) = mpsc::channel();
+
+        thread::Builder::new()
+            .name("io-uring".to_string())
+            .spawn(move || {
+                run_reads(
+                    ring,
+                    file,
+                    queue_depth.get() as usize,
+                    read_request_receiver,
+                )
+            })?;
+
+        Ok(Self {
+            read_request_sender,
+        })
+    }
+
+    async fn read_exact_at_internal(&self, buf: &mut [u8], offset: u64) -> io::Result<()> {
+        // Make buffer of a size that is necessary to read aligned memory, accounting for extra
+        // bytes at the beginning and the end that will be thrown away
+        let offset_in_buffer = (offset % DISK_SECTOR_SIZE as u64) as usize;
+        let bytes_to_read = offset_in_buffer + buf.len();
+        let buffer =
+            vec![AlignedSector([0; DISK_SECTOR_SIZE]); bytes_to_read.div_ceil(DISK_SECTOR_SIZE)];
+
+        let (response_sender, response_receiver) = oneshot::channel();
+        self.read_request_sender
+            .send(ReadRequest {
+                offset: offset - offset_in_buffer as u64,
+                buffer,
+                bytes_read: 0,
+                response_sender,
+            })
+            .map_err(|_error| io::Error::other("io_uring thread has exited"))?;
+
+        let (buffer, bytes_read) = response_receiver
+            .await
+            .map_err(|_error| io::Error::other("io_uring thread has exited"))??;
+
+        // End of the file can be reached before the end of the aligned buffer, but not before the
+        // end of requested bytes
+        if bytes_read < bytes_to_read {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "failed to fill whole buffer",
+            ));
+        }
+
+        // SAFETY: `AlignedSector` is a plain byte array with no padding
+        let buffer = unsafe {
+            slice::from_raw_parts(
+                buffer.as_ptr().cast::<u8>(),
+                buffer.len() * DISK_SECTOR_SIZE,
+            )
+        };
+        buf.copy_from_slice(&buffer[offset_in_buffer..][..buf.len()]);
+
+        Ok(())
+    }
+}
+
+/// Processes read requests until all senders are dropped and reads in flight are finished
+fn run_reads(
+    mut ring: IoUring,
+    file: File,
+    queue_depth: usize,
+    read_request_receiver: mpsc::Receiver<ReadRequest>,
+) {
+    let fd = types::Fd(file.as_raw_fd());
+    let mut reads_in_flight = HashMap::<u64, ReadRequest>::with_capacity(queue_depth);
+    let mut completions = Vec::with_capacity(queue_depth);
+    let mut next_read_id = 0_u64;
+    let mut senders_dropped = false;
+
+    loop {
+        // Fill submission queue, block waiting for new requests only if there is nothing else to do
+        while !senders_dropped && reads_in_flight.len() < queue_depth {
+            let result = if reads_in_flight.is_empty() {
+                read_request_receiver
+                    .recv()
+                    .map_err(|_error| mpsc::TryRecvError::Disconnected)
+            } else {
+                read_request_receiver.try_recv()
+            };
+            let mut read_request = match result {
+                Ok(read_request) => read_request,
+                Err(mpsc::TryRecvError::Empty) => {
+                    break;
+                }
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    senders_dropped = true;
+                    break;
+                }
+            };
+
+            let read_id = next_read_id;
+            next_read_id = next_read_id.wrapping_add(1);
+
+            // SAFETY: File descriptor is valid for the lifetime of this function and request is
+            // stored in `reads_in_flight` right away
+            unsafe {
+                submit_read(&mut ring, fd, read_id, &mut read_request);
+            }
+            reads_in_flight.insert(read_id, read_request);
+        }
+
+        if reads_in_flight.is_empty() {
+            if senders_dropped {
+                return;
+            }
+            continue;
+        }
+
+        if let Err(error) = ring.submit_and_wait(1) {
+            if matches!(error.raw_os_error(), Some(libc::EINTR | libc::EBUSY)) {
+                continue;
+            }
+
+            error!(%error, "Failed to submit reads to io_uring");
+            for (_read_id, read_request) in reads_in_flight.drain() {
+                // Kernel might still write into the buffer, so it can't be freed
+                mem::forget(read_request.buffer);
+                // Doesn't matter if receiver still cares about it
+                let _ = read_request
+                    .response_sender
+                    .send(Err(io::Error::new(error.kind(), error.to_string())));
+            }
+            return;
+        }
+
+        completions.extend(
+            ring.completion()
+                .map(|completion| (completion.user_data(), completion.result())),
+        );
+
+        for (read_id, result) in completions.drain(..) {
+            let Some(mut read_request) = reads_in_flight.remove(&read_id) else {
+                error!(
+                    %read_id,
+                    "Received completion for unknown read, this is an implementation bug"
+                );
+                continue;
+            };
+
+            let result = if result < 0 {
+                Err(io::Error::from_raw_os_error(-result))
+            } else {
+                read_request.bytes_read += result as usize;
+
+                // Short read that didn't reach the end of the file, read the rest of the buffer
+                if result > 0
+                    && read_request.bytes_read < read_request.buffer.len() * DISK_SECTOR_SIZE
+                {
+                    // SAFETY: File descriptor is valid for the lifetime of this function and
+                    // request is stored in `reads_in_flight` right away
+                    unsafe {
+                        submit_read(&mut ring, fd, read_id, &mut read_request);
+                    }
+                    reads_in_flight.insert(read_id, read_request);
+                    continue;
+                }
+
+                Ok((read_request.buffer, read_request.bytes_read))
+            };
+
+            // Doesn't matter if receiver still cares about it
+            let _ = read_request.response_sender.send(result);
+        }
+    }
+}
+
+/// Submit read into the part of the buffer that wasn't filled yet.
+///
+/// # Safety
+/// File descriptor must be valid and read request must not be dropped (moving is fine, `Vec`
+/// doesn't move its heap allocation) until completion is received.
+unsafe fn submit_read(
+    ring: &mut IoUring,
+    fd: types::Fd,
+    read_id: u64,
+    read_request: &mut ReadRequest,
+) {
+    let entry = opcode::Read::new(
+        fd,
+        read_request
+            .buffer
+            .as_mut_ptr()
+            .cast::<u8>()
+            .add(read_request.bytes_read),
+        (read_request.buffer.len() * DISK_SECTOR_SIZE - read_request.bytes_read) as u32,
+    )
+    .offset(read_request.offset + read_request.bytes_read as u64)
+    .build()
+    .user_data(read_id);
+
+    ring.submission()
+        .push(&entry)
+        .expect("Number of reads in flight never exceeds queue depth; qed");
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::single_disk_farm::io_uring_file_linux::{IoUringFileLinux, MAX_READ_SIZE};
+    use crate::single_disk_farm::unbuffered_io_file_windows::DISK_SECTOR_SIZE;
+    use rand::prelude::*;
+    use std::fs;
+    use std::num::NonZeroU32;
+    use subspace_farmer_components::ReadAtAsync;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn basic() {
+        let tempdir = tempdir().unwrap();
+        let file_path = tempdir.as_ref().join("file.bin");
+        let mut data = vec![0u8; MAX_READ_SIZE * 3];
+        thread_rng().fill(data.as_mut_slice());
+        fs::write(&file_path, &data).unwrap();
+
+        let file = IoUringFileLinux::open(&file_path, NonZeroU32::new(4).unwrap()).unwrap();
+
+        for (offset, size) in [
+            (0_usize, 512_usize),
+            (0_usize, 4096_usize),
+            (0, 500),
+            (0, 4000),
+            (5, 50),
+            (12, 500),
+            (96, 4000),
+            (4000, 96),
+            (10000, 5),
+            (0, MAX_READ_SIZE),
+            (0, MAX_READ_SIZE * 2),
+            (5, MAX_READ_SIZE - 5),
+            (5, MAX_READ_SIZE * 2 - 5),
+            (5, MAX_READ_SIZE),
+            (5, MAX_READ_SIZE * 2),
+            (MAX_READ_SIZE, MAX_READ_SIZE),
+            (MAX_READ_SIZE, MAX_READ_SIZE * 2),
+            (MAX_READ_SIZE + 5, MAX_READ_SIZE - 5),
+            (MAX_READ_SIZE * 3 - DISK_SECTOR_SIZE - 5, DISK_SECTOR_SIZE),
+        ] {
+            let buffer = file.read_at(vec![0; size], offset as u64).await.unwrap();
+            assert_eq!(
+                buffer.as_slice(),
+                &data[offset..][..size],
+                "({offset}, {size})"
+            );
+        }
+
+        // Reading beyond the end of the file must fail
+        assert!(file
+            .read_at(vec![0; 10], (MAX_READ_SIZE * 3 - 5) as u64)
+            .await
+            .is_err());
+    }
+}
@@ -9,7 +9,7 @@ use prometheus_client::registry::{Registry, Unit};
 use std::fmt;
 use std::sync::atomic::{AtomicI64, AtomicU64};
 use std::time::Duration;
-use subspace_core_primitives::SectorIndex;
+use subspace_core_primitives::{PublicKey, SectorIndex};
 
 #[derive(Debug, Copy, Clone)]
 pub(super) enum SectorState {
@@ -37,6 +37,7 @@ pub(super) struct SingleDiskFarmMetrics {
     pub(super) auditing_time: Histogram,
     pub(super) skipped_slots: Counter<u64, AtomicU64>,
     proving_time: Family<Vec<(&'static str, String)>, Histogram>,
+    solutions: Family<Vec<(&'static str, String)>, Counter<u64, AtomicU64>>,
     farming_errors: Family<Vec<(&'static str, String)>, Counter<u64, AtomicU64>>,
     pub(super) sector_downloading_time: Histogram,
     pub(super) sector_encoding_time: Histogram,
@@ -91,6 +92,13 @@ impl SingleDiskFarmMetrics {
             proving_time.clone(),
         );
 
+        let solutions = Family::default();
+        sub_registry.register(
+            "solutions",
+            "Solutions by reward address and proving result",
+            solutions.clone(),
+        );
+
         let farming_errors = Family::default();
         sub_registry.register(
             "farming_errors",
@@ -214,6 +222,7 @@ impl SingleDiskFarmMetrics {
             auditing_time,
             skipped_slots,
             proving_time,
+            solutions,
             farming_errors,
             sector_downloading_time,
             sector_encoding_time,
@@ -246,6 +255,15 @@ impl SingleDiskFarmMetrics {
             .observe(time.as_secs_f64());
     }
 
+    pub(super) fn note_solution(&self, reward_address: &PublicKey, result: ProvingResult) {
+        self.solutions
+            .get_or_create(&vec![
+                ("reward_address", reward_address.to_string()),
+                ("result", result.to_string()),
+            ])
+            .inc();
+    }
+
     pub(super) fn note_farming_error(&self, error: &FarmingError) {
         self.farming_errors
             .get_or_create(&vec![("error", error.str_variant().to_string())])
@@ -0,0 +1,646 @@
+#[cfg(test)]
+mod tests;
+
+use crate::disk_piece_cache::DiskPieceCache;
+use crate::farm::FarmId;
+use crate::single_disk_farm::identity::Identity;
+use crate::single_disk_farm::{
+    sector_checksum, PlotMetadataHeader, SingleDiskFarm, SingleDiskFarmInfo,
+    SingleDiskFarmInfoLock, RESERVED_PLOT_METADATA,
+};
+use parity_scale_codec::Encode;
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use std::{fs, io, mem, thread};
+use subspace_core_primitives::crypto::blake3_hash;
+use subspace_core_primitives::{Blake3Hash, HistorySize, Record, SectorIndex, SegmentIndex};
+use subspace_farmer_components::file_ext::FileExt;
+use subspace_farmer_components::sector::{sector_size, SectorMetadata, SectorMetadataChecksummed};
+use thiserror::Error;
+use tracing::{debug, info, warn};
+
+/// How many times to try reading a sector that fails checksum verification (might be replotted
+/// concurrently if source farm is in use) before giving up on it
+const SECTOR_COPY_ATTEMPTS: usize = 3;
+/// Delay between attempts to copy a sector
+const SECTOR_COPY_RETRY_DELAY: Duration = Duration::from_secs(1);
+/// Max number of passes over sectors that changed in source farm while being copied
+const MAX_CATCH_UP_PASSES: usize = 3;
+/// How often to persist progress of piece cache copying (in elements)
+const CACHE_PROGRESS_INTERVAL: u32 = 1000;
+
+/// Errors happening during farm migration
+#[derive(Debug, Error)]
+pub enum SingleDiskFarmMigrationError {
+    /// Source and target are the same directory
+    #[error("Source and target are the same directory {directory}")]
+    SameDirectory {
+        /// Farm directory
+        directory: PathBuf,
+    },
+    /// Source farm is likely in use
+    #[error(
+        "Source farm is likely in use, stop it or explicitly allow migration of farm that is in \
+        use: {0}"
+    )]
+    SourceLikelyInUse(io::Error),
+    /// Target farm is likely already in use, make sure no other farmer is using it
+    #[error("Target farm is likely already in use, make sure no other farmer is using it: {0}")]
+    TargetLikelyInUse(io::Error),
+    /// Farm info file does not exist
+    #[error("Farm info file does not exist at {file}")]
+    FarmInfoFileDoesNotExist {
+        /// Info file
+        file: PathBuf,
+    },
+    /// Farm info can't be opened
+    #[error("Farm info at {file} can't be opened: {error}")]
+    FarmInfoCantBeOpened {
+        /// Info file
+        file: PathBuf,
+        /// Low-level error
+        error: io::Error,
+    },
+    /// Target directory already contains a farm
+    #[error(
+        "Target directory {directory} already contains farm {id} that is not being migrated from \
+        source farm"
+    )]
+    TargetAlreadyContainsFarm {
+        /// Target directory
+        directory: PathBuf,
+        /// ID of the farm in target directory
+        id: FarmId,
+    },
+    /// Identity copy doesn't match the original
+    #[error("Identity copy at {file} doesn't match the original")]
+    IdentityMismatch {
+        /// Identity file
+        file: PathBuf,
+    },
+    /// Failed to read sectors metadata
+    #[error("Failed to read sectors metadata from {directory}: {error}")]
+    FailedToReadSectorsMetadata {
+        /// Farm directory
+        directory: PathBuf,
+        /// Low-level error
+        error: io::Error,
+    },
+    /// Sector written to target farm doesn't match its checksum
+    #[error(
+        "Sector {sector_index} written to target farm doesn't match its checksum, target disk \
+        might be faulty"
+    )]
+    SectorVerificationFailed {
+        /// Sector index
+        sector_index: SectorIndex,
+    },
+    /// I/O error occurred
+    #[error("Farm migration I/O error: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// Progress of farm migration, only exists in target directory while migration is in progress,
+/// such that interrupted migration can be resumed
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MigrationProgress {
+    /// ID of the farm being migrated
+    farm_id: FarmId,
+    /// Number of sectors copied during the first pass
+    copied_sectors: SectorIndex,
+    /// Number of piece cache elements copied
+    copied_cache_elements: u32,
+}
+
+impl MigrationProgress {
+    const FILE_NAME: &'static str = "migration.json";
+
+    fn load_from(directory: &Path) -> io::Result<Option<Self>> {
+        let bytes = match fs::read(directory.join(Self::FILE_NAME)) {
+            Ok(bytes) => bytes,
+            Err(error) => {
+                return if error.kind() == io::ErrorKind::NotFound {
+                    Ok(None)
+                } else {
+                    Err(error)
+                };
+            }
+        };
+
+        serde_json::from_slice(&bytes)
+            .map(Some)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+
+    fn store_to(&self, directory: &Path) -> io::Result<()> {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(directory.join(Self::FILE_NAME))?;
+        file.write_all(
+            &serde_json::to_vec(self).expect("Progress serialization never fails; qed"),
+        )?;
+        file.sync_all()
+    }
+
+    fn remove(directory: &Path) -> io::Result<()> {
+        fs::remove_file(directory.join(Self::FILE_NAME))
+    }
+}
+
+struct FarmFiles {
+    metadata_file: File,
+    plot_file: File,
+}
+
+/// Copy farm from one directory into another, see [`SingleDiskFarm::migrate()`] for details
+pub(super) fn migrate(
+    from: &Path,
+    to: &Path,
+    allow_source_in_use: bool,
+    disable_farm_locking: bool,
+) -> Result<(), SingleDiskFarmMigrationError> {
+    let info = {
+        let file = from.join(SingleDiskFarmInfo::FILE_NAME);
+        match SingleDiskFarmInfo::load_from(from) {
+            Ok(Some(info)) => info,
+            Ok(None) => {
+                return Err(SingleDiskFarmMigrationError::FarmInfoFileDoesNotExist { file });
+            }
+            Err(error) => {
+                return Err(SingleDiskFarmMigrationError::FarmInfoCantBeOpened { file, error });
+            }
+        }
+    };
+
+    fs::create_dir_all(to)?;
+
+    if from.canonicalize()? == to.canonicalize()? {
+        return Err(SingleDiskFarmMigrationError::SameDirectory {
+            directory: to.to_path_buf(),
+        });
+    }
+
+    let _source_lock = if disable_farm_locking || allow_source_in_use {
+        None
+    } else {
+        Some(
+            SingleDiskFarmInfo::try_lock(from)
+                .map_err(SingleDiskFarmMigrationError::SourceLikelyInUse)?,
+        )
+    };
+
+    // Target info is read before target farm is locked, since locking creates it if necessary
+    let target_info = SingleDiskFarmInfo::load_from(to)?;
+    if let Some(target_info) = &target_info {
+        if target_info.id() != info.id() {
+            return Err(SingleDiskFarmMigrationError::TargetAlreadyContainsFarm {
+                directory: to.to_path_buf(),
+                id: *target_info.id(),
+            });
+        }
+    }
+
+    // Target farm must be locked before anything is written into it
+    let _target_lock = if disable_farm_locking {
+        if target_info.is_none() {
+            info.store_to(to)?;
+        }
+
+        None
+    } else {
+        Some(lock_target(to, &info)?)
+    };
+
+    let mut progress = match MigrationProgress::load_from(to)? {
+        Some(progress) => {
+            if &progress.farm_id != info.id() {
+                return Err(SingleDiskFarmMigrationError::TargetAlreadyContainsFarm {
+                    directory: to.to_path_buf(),
+                    id: progress.farm_id,
+                });
+            }
+
+            info!(
+                copied_sectors = %progress.copied_sectors,
+                copied_cache_elements = %progress.copied_cache_elements,
+                "Resuming interrupted migration"
+            );
+
+            progress
+        }
+        None => {
+            // Info file alone is created when target farm is locked, previously completed
+            // migration also has metadata file
+            let copied_sectors =
+                if target_info.is_some() && to.join(SingleDiskFarm::METADATA_FILE).exists() {
+                    // Previously completed migration of the same farm, only catch up with changes
+                    // made in source farm since then
+                    let copied_sectors = read_sectors_metadata(to)?.len() as SectorIndex;
+
+                    info!(
+                        %copied_sectors,
+                        "Target already contains this farm, catching up with changes"
+                    );
+
+                    copied_sectors
+                } else {
+                    0
+                };
+
+            let progress = MigrationProgress {
+                farm_id: *info.id(),
+                copied_sectors,
+                copied_cache_elements: 0,
+            };
+            progress.store_to(to)?;
+
+            progress
+        }
+    };
+
+    // Identity file is absent if farm uses remote signer
+    if from.join(Identity::FILE_NAME).exists() {
+        let file = to.join(Identity::FILE_NAME);
+        info!(path = %file.display(), "Copying identity file");
+
+        fs::copy(from.join(Identity::FILE_NAME), &file)?;
+        if fs::read(from.join(Identity::FILE_NAME))? != fs::read(&file)? {
+            return Err(SingleDiskFarmMigrationError::IdentityMismatch { file });
+        }
+    }
+
+    let source = FarmFiles {
+        metadata_file: open_source(&from.join(SingleDiskFarm::METADATA_FILE))?,
+        plot_file: open_source(&from.join(SingleDiskFarm::PLOT_FILE))?,
+    };
+    let target = FarmFiles {
+        metadata_file: open_target(
+            &to.join(SingleDiskFarm::METADATA_FILE),
+            source.metadata_file.size()?,
+        )?,
+        plot_file: open_target(
+            &to.join(SingleDiskFarm::PLOT_FILE),
+            source.plot_file.size()?,
+        )?,
+    };
+
+    let mut target_metadata_header = if progress.copied_sectors == 0 {
+        let metadata_header = PlotMetadataHeader {
+            version: SingleDiskFarm::SUPPORTED_PLOT_VERSION,
+            plotted_sector_count: 0,
+        };
+        target
+            .metadata_file
+            .write_all_at(&metadata_header.encode(), 0)?;
+
+        metadata_header
+    } else {
+        PlotMetadataHeader {
+            version: SingleDiskFarm::SUPPORTED_PLOT_VERSION,
+            plotted_sector_count: progress.copied_sectors,
+        }
+    };
+
+    let pieces_in_sector = info.pieces_in_sector();
+    let sector_size = sector_size(pieces_in_sector) as u64;
+
+    // The first pass copies all sectors, subsequent passes catch up with sectors that were
+    // replotted in the source farm while being copied
+    for pass in 0..=MAX_CATCH_UP_PASSES {
+        let source_sectors_metadata = read_sectors_metadata(from)?;
+        let target_sectors_metadata = if target_metadata_header.plotted_sector_count == 0 {
+            Vec::new()
+        } else {
+            read_sectors_metadata(to)?
+        };
+
+        let sectors_to_copy = source_sectors_metadata
+            .iter()
+            .filter(|sector_metadata| {
+                let sector_index = sector_metadata.sector_index;
+
+                sector_index >= progress.copied_sectors
+                    || target_sectors_metadata
+                        .get(usize::from(sector_index))
+                        .map(|target_sector_metadata| {
+                            target_sector_metadata.encode() != sector_metadata.encode()
+                        })
+                        .unwrap_or(true)
+            })
+            .map(|sector_metadata| sector_metadata.sector_index)
+            .collect::<Vec<_>>();
+
+        if sectors_to_copy.is_empty() {
+            break;
+        }
+
+        if pass == MAX_CATCH_UP_PASSES {
+            warn!(
+                changed_sectors = %sectors_to_copy.len(),
+                "Source farm keeps changing, stop it and run migration again to catch up with \
+                remaining changes"
+            );
+            break;
+        }
+
+        info!(
+            %pass,
+            sectors = %sectors_to_copy.len(),
+            "Copying sectors"
+        );
+
+        for (copied, sector_index) in sectors_to_copy.into_iter().enumerate() {
+            copy_sector(
+                &source,
+                &target,
+                sector_index,
+                sector_size,
+                pieces_in_sector,
+            )?;
+
+            if sector_index >= target_metadata_header.plotted_sector_count {
+                target_metadata_header.plotted_sector_count = sector_index + 1;
+                target
+                    .metadata_file
+                    .write_all_at(&target_metadata_header.encode(), 0)?;
+            }
+
+            if sector_index >= progress.copied_sectors {
+                progress.copied_sectors = sector_index + 1;
+                progress.store_to(to)?;
+            }
+
+            if copied > 0 && copied % 10 == 0 {
+                info!("Copied {copied} sectors");
+            }
+        }
+    }
+
+    copy_cache(from, to, &mut progress)?;
+
+    target.metadata_file.sync_all()?;
+    target.plot_file.sync_all()?;
+    MigrationProgress::remove(to)?;
+
+    info!("Farm migration completed");
+
+    Ok(())
+}
+
+/// Lock target farm, info file is created if it doesn't exist yet, such that it can be locked
+/// before anything else is written into target directory
+fn lock_target(
+    to: &Path,
+    info: &SingleDiskFarmInfo,
+) -> Result<SingleDiskFarmInfoLock, SingleDiskFarmMigrationError> {
+    let info_file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(to.join(SingleDiskFarmInfo::FILE_NAME))?;
+    let lock = SingleDiskFarmInfo::try_lock(to)
+        .map_err(SingleDiskFarmMigrationError::TargetLikelyInUse)?;
+
+    // Just created
+    if info_file.size()? == 0 {
+        lock.store(info)?;
+    }
+
+    Ok(lock)
+}
+
+fn read_sectors_metadata(
+    directory: &Path,
+) -> Result<Vec<SectorMetadataChecksummed>, SingleDiskFarmMigrationError> {
+    SingleDiskFarm::read_all_sectors_metadata(directory).map_err(|error| {
+        SingleDiskFarmMigrationError::FailedToReadSectorsMetadata {
+            directory: directory.to_path_buf(),
+            error,
+        }
+    })
+}
+
+fn open_source(path: &Path) -> io::Result<File> {
+    let file = OpenOptions::new().read(true).open(path)?;
+    // Error doesn't matter here
+    let _ = file.advise_sequential_access();
+
+    Ok(file)
+}
+
+fn open_target(path: &Path, size: u64) -> io::Result<File> {
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)?;
+
+    if file.size()? != size {
+        // Allocating the whole file (`set_len` below can create a sparse file, which will cause
+        // writes to fail later)
+        file.preallocate(size)?;
+        // Truncating file (if necessary)
+        file.set_len(size)?;
+    }
+
+    Ok(file)
+}
+
+/// Copy sector with its metadata, verifying sector checksum both before and after writing.
+///
+/// Sectors that can't be read consistently (corrupted or constantly being replotted in the source
+/// farm) are replaced with dummy expired sector metadata, such that they are replotted by the
+/// target farm.
+fn copy_sector(
+    source: &FarmFiles,
+    target: &FarmFiles,
+    sector_index: SectorIndex,
+    sector_size: u64,
+    pieces_in_sector: u16,
+) -> Result<(), SingleDiskFarmMigrationError> {
+    let sector_metadata_size = SectorMetadataChecksummed::encoded_size();
+    let sector_metadata_offset =
+        RESERVED_PLOT_METADATA + u64::from(sector_index) * sector_metadata_size as u64;
+    let sector_offset = u64::from(sector_index) * sector_size;
+    let sector_bytes_end = sector_size - mem::size_of::<Blake3Hash>() as u64;
+
+    let mut sector_metadata_before = vec![0; sector_metadata_size];
+    let mut sector_metadata_after = vec![0; sector_metadata_size];
+    let mut scratch_buffer = vec![0; Record::SIZE];
+
+    for attempt in 1..=SECTOR_COPY_ATTEMPTS {
+        source
+            .metadata_file
+            .read_exact_at(&mut sector_metadata_before, sector_metadata_offset)?;
+
+        // Copy sector bytes while computing their checksum
+        let actual_checksum = sector_checksum(
+            sector_size as usize,
+            &mut scratch_buffer,
+            |bytes, offset_in_sector| {
+                source
+                    .plot_file
+                    .read_exact_at(bytes, sector_offset + offset_in_sector)?;
+                target
+                    .plot_file
+                    .write_all_at(bytes, sector_offset + offset_in_sector)
+            },
+        )?;
+
+        let mut expected_checksum = Blake3Hash::default();
+        source
+            .plot_file
+            .read_exact_at(&mut expected_checksum, sector_offset + sector_bytes_end)?;
+        target
+            .plot_file
+            .write_all_at(&expected_checksum, sector_offset + sector_bytes_end)?;
+
+        source
+            .metadata_file
+            .read_exact_at(&mut sector_metadata_after, sector_metadata_offset)?;
+
+        if actual_checksum != expected_checksum {
+            warn!(
+                %sector_index,
+                %attempt,
+                actual_checksum = %hex::encode(actual_checksum),
+                expected_checksum = %hex::encode(expected_checksum),
+                "Source sector checksum mismatch"
+            );
+        } else if sector_metadata_before != sector_metadata_after {
+            debug!(
+                %sector_index,
+                %attempt,
+                "Sector was replotted in source farm while being copied"
+            );
+        } else {
+            verify_written_sector(
+                target,
+                sector_index,
+                sector_offset,
+                sector_size,
+                &expected_checksum,
+                &mut scratch_buffer,
+            )?;
+
+            target
+                .metadata_file
+                .write_all_at(&sector_metadata_after, sector_metadata_offset)?;
+
+            return Ok(());
+        }
+
+        thread::sleep(SECTOR_COPY_RETRY_DELAY);
+    }
+
+    warn!(
+        %sector_index,
+        "Failed to copy sector consistently, replacing with dummy expired sector"
+    );
+
+    let dummy_sector_metadata = SectorMetadataChecksummed::from(SectorMetadata {
+        sector_index,
+        pieces_in_sector,
+        s_bucket_sizes: Box::new([0; Record::NUM_S_BUCKETS]),
+        history_size: HistorySize::from(SegmentIndex::ZERO),
+    });
+    target
+        .metadata_file
+        .write_all_at(&dummy_sector_metadata.encode(), sector_metadata_offset)?;
+
+    Ok(())
+}
+
+fn verify_written_sector(
+    target: &FarmFiles,
+    sector_index: SectorIndex,
+    sector_offset: u64,
+    sector_size: u64,
+    expected_checksum: &Blake3Hash,
+    scratch_buffer: &mut [u8],
+) -> Result<(), SingleDiskFarmMigrationError> {
+    let actual_checksum = sector_checksum(
+        sector_size as usize,
+        scratch_buffer,
+        |bytes, offset_in_sector| {
+            target
+                .plot_file
+                .read_exact_at(bytes, sector_offset + offset_in_sector)
+        },
+    )?;
+
+    if &actual_checksum != expected_checksum {
+        return Err(SingleDiskFarmMigrationError::SectorVerificationFailed { sector_index });
+    }
+
+    Ok(())
+}
+
+/// Copy piece cache, elements that fail checksum verification are replaced with dummy elements
+fn copy_cache(
+    from: &Path,
+    to: &Path,
+    progress: &mut MigrationProgress,
+) -> Result<(), SingleDiskFarmMigrationError> {
+    let source_file = match open_source(&from.join(DiskPieceCache::FILE_NAME)) {
+        Ok(source_file) => source_file,
+        Err(error) => {
+            return if error.kind() == io::ErrorKind::NotFound {
+                debug!("Cache file does not exist, this is expected in farming cluster");
+                Ok(())
+            } else {
+                Err(error.into())
+            };
+        }
+    };
+    let cache_size = source_file.size()?;
+    let target_file = open_target(&to.join(DiskPieceCache::FILE_NAME), cache_size)?;
+
+    let element_size = DiskPieceCache::element_size();
+    let number_of_cached_elements = (cache_size / u64::from(element_size)) as u32;
+    let dummy_element = vec![0; element_size as usize];
+    let mut element = vec![0; element_size as usize];
+
+    info!(
+        elements = %number_of_cached_elements,
+        copied_elements = %progress.copied_cache_elements,
+        "Copying cache"
+    );
+
+    for cache_offset in progress.copied_cache_elements..number_of_cached_elements {
+        let offset = u64::from(cache_offset) * u64::from(element_size);
+        source_file.read_exact_at(&mut element, offset)?;
+
+        let (index_and_piece_bytes, expected_checksum) =
+            element.split_at(element_size as usize - mem::size_of::<Blake3Hash>());
+        let actual_checksum = blake3_hash(index_and_piece_bytes);
+        if actual_checksum != expected_checksum && element != dummy_element {
+            debug!(
+                %cache_offset,
+                "Cached piece checksum mismatch, replacing with dummy element"
+            );
+
+            target_file.write_all_at(&dummy_element, offset)?;
+        } else {
+            target_file.write_all_at(&element, offset)?;
+        }
+
+        if (cache_offset + 1) % CACHE_PROGRESS_INTERVAL == 0 {
+            progress.copied_cache_elements = cache_offset + 1;
+            progress.store_to(to)?;
+        }
+    }
+
+    target_file.sync_all()?;
+    progress.copied_cache_elements = number_of_cached_elements;
+    progress.store_to(to)?;
+
+    Ok(())
+}
@@ -0,0 +1,191 @@
+use crate::farm::FarmId;
+use crate::single_disk_farm::migration::{MigrationProgress, SingleDiskFarmMigrationError};
+use crate::single_disk_farm::{
+    PlotMetadataHeader, SingleDiskFarm, SingleDiskFarmInfo, RESERVED_PLOT_METADATA,
+};
+use parity_scale_codec::Encode;
+use std::fs;
+use std::path::Path;
+use subspace_core_primitives::crypto::blake3_hash;
+use subspace_core_primitives::{HistorySize, PublicKey, Record, SectorIndex, SegmentIndex};
+use subspace_farmer_components::file_ext::FileExt;
+use subspace_farmer_components::sector::{sector_size, SectorMetadata, SectorMetadataChecksummed};
+use tempfile::tempdir;
+
+const PIECES_IN_SECTOR: u16 = 1;
+const SECTOR_COUNT: SectorIndex = 3;
+
+fn sector_metadata_offset(sector_index: SectorIndex) -> u64 {
+    RESERVED_PLOT_METADATA
+        + u64::from(sector_index) * SectorMetadataChecksummed::encoded_size() as u64
+}
+
+/// Write sector with valid checksum and its metadata into farm files
+fn write_sector(directory: &Path, sector_index: SectorIndex, segment_index: u64) {
+    let sector_size = sector_size(PIECES_IN_SECTOR);
+    let sector_metadata = SectorMetadataChecksummed::from(SectorMetadata {
+        sector_index,
+        pieces_in_sector: PIECES_IN_SECTOR,
+        s_bucket_sizes: Box::new([0; Record::NUM_S_BUCKETS]),
+        history_size: HistorySize::from(SegmentIndex::new(segment_index)),
+    });
+    let mut sector = (0..sector_size)
+        .map(|offset| (offset as u64 + segment_index) as u8)
+        .collect::<Vec<_>>();
+    let checksum = blake3_hash(&sector[..sector_size - 32]);
+    sector[sector_size - 32..].copy_from_slice(&checksum);
+
+    let metadata_file = fs::OpenOptions::new()
+        .write(true)
+        .open(directory.join(SingleDiskFarm::METADATA_FILE))
+        .unwrap();
+    metadata_file
+        .write_all_at(
+            &sector_metadata.encode(),
+            sector_metadata_offset(sector_index),
+        )
+        .unwrap();
+    let plot_file = fs::OpenOptions::new()
+        .write(true)
+        .open(directory.join(SingleDiskFarm::PLOT_FILE))
+        .unwrap();
+    plot_file
+        .write_all_at(&sector, u64::from(sector_index) * sector_size as u64)
+        .unwrap();
+}
+
+/// Create farm with [`SECTOR_COUNT`] plotted sectors
+fn create_farm(directory: &Path) -> SingleDiskFarmInfo {
+    let info = SingleDiskFarmInfo::new(
+        FarmId::new(),
+        [0; 32],
+        PublicKey::from([1; 32]),
+        PIECES_IN_SECTOR,
+        0,
+    );
+    info.store_to(directory).unwrap();
+
+    let mut metadata = vec![0; sector_metadata_offset(SECTOR_COUNT) as usize];
+    metadata[..PlotMetadataHeader::encoded_size()].copy_from_slice(
+        &PlotMetadataHeader {
+            version: SingleDiskFarm::SUPPORTED_PLOT_VERSION,
+            plotted_sector_count: SECTOR_COUNT,
+        }
+        .encode(),
+    );
+    fs::write(directory.join(SingleDiskFarm::METADATA_FILE), metadata).unwrap();
+    fs::write(
+        directory.join(SingleDiskFarm::PLOT_FILE),
+        vec![0; sector_size(PIECES_IN_SECTOR) * usize::from(SECTOR_COUNT)],
+    )
+    .unwrap();
+
+    for sector_index in 0..SECTOR_COUNT {
+        write_sector(directory, sector_index, 1);
+    }
+
+    info
+}
+
+fn assert_farms_equal(from: &Path, to: &Path) {
+    for file_name in [SingleDiskFarm::METADATA_FILE, SingleDiskFarm::PLOT_FILE] {
+        assert!(
+            fs::read(from.join(file_name)).unwrap() == fs::read(to.join(file_name)).unwrap(),
+            "{file_name} doesn't match"
+        );
+    }
+    assert!(!to.join(MigrationProgress::FILE_NAME).exists());
+}
+
+#[test]
+fn resume_interrupted_migration() {
+    let from = tempdir().unwrap();
+    let to = tempdir().unwrap();
+    let info = create_farm(from.path());
+
+    // Simulate migration interrupted after the first sector was copied
+    info.store_to(to.path()).unwrap();
+    MigrationProgress {
+        farm_id: *info.id(),
+        copied_sectors: 1,
+        copied_cache_elements: 0,
+    }
+    .store_to(to.path())
+    .unwrap();
+    {
+        let source_metadata = fs::read(from.path().join(SingleDiskFarm::METADATA_FILE)).unwrap();
+        let mut target_metadata = source_metadata[..sector_metadata_offset(1) as usize].to_vec();
+        target_metadata[..PlotMetadataHeader::encoded_size()].copy_from_slice(
+            &PlotMetadataHeader {
+                version: SingleDiskFarm::SUPPORTED_PLOT_VERSION,
+                plotted_sector_count: 1,
+            }
+            .encode(),
+        );
+        fs::write(
+            to.path().join(SingleDiskFarm::METADATA_FILE),
+            target_metadata,
+        )
+        .unwrap();
+
+        let source_plot = fs::read(from.path().join(SingleDiskFarm::PLOT_FILE)).unwrap();
+        fs::write(
+            to.path().join(SingleDiskFarm::PLOT_FILE),
+            &source_plot[..sector_size(PIECES_IN_SECTOR)],
+        )
+        .unwrap();
+    }
+
+    SingleDiskFarm::migrate(from.path(), to.path(), false, false).unwrap();
+
+    assert_farms_equal(from.path(), to.path());
+}
+
+#[test]
+fn catch_up_with_source_changes() {
+    let from = tempdir().unwrap();
+    let to = tempdir().unwrap();
+    create_farm(from.path());
+
+    SingleDiskFarm::migrate(from.path(), to.path(), false, false).unwrap();
+    assert_farms_equal(from.path(), to.path());
+
+    // Sector replotted in source farm after migration
+    write_sector(from.path(), 1, 2);
+
+    SingleDiskFarm::migrate(from.path(), to.path(), false, false).unwrap();
+    assert_farms_equal(from.path(), to.path());
+}
+
+#[test]
+fn locked_target_is_not_written_to() {
+    let from = tempdir().unwrap();
+    let to = tempdir().unwrap();
+    let info = create_farm(from.path());
+
+    info.store_to(to.path()).unwrap();
+    let _lock = SingleDiskFarmInfo::try_lock(to.path()).unwrap();
+
+    assert!(matches!(
+        SingleDiskFarm::migrate(from.path(), to.path(), false, false),
+        Err(SingleDiskFarmMigrationError::TargetLikelyInUse(_))
+    ));
+    assert!(!to.path().join(MigrationProgress::FILE_NAME).exists());
+    assert!(!to.path().join(SingleDiskFarm::METADATA_FILE).exists());
+    assert!(!to.path().join(SingleDiskFarm::PLOT_FILE).exists());
+}
+
+#[test]
+fn target_with_another_farm_is_rejected() {
+    let from = tempdir().unwrap();
+    let to = tempdir().unwrap();
+    create_farm(from.path());
+    let other_info = create_farm(to.path());
+
+    match SingleDiskFarm::migrate(from.path(), to.path(), false, false) {
+        Err(SingleDiskFarmMigrationError::TargetAlreadyContainsFarm { id, .. }) => {
+            assert_eq!(&id, other_info.id());
+        }
+        result => panic!("Unexpected result: {result:?}"),
+    }
+}
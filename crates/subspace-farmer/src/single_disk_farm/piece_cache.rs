@@ -2,7 +2,7 @@
 
 use crate::disk_piece_cache::DiskPieceCache;
 use crate::farm;
-use crate::farm::{FarmError, PieceCacheId, PieceCacheOffset};
+use crate::farm::{FarmError, PieceCacheId, PieceCacheOffset, PieceCacheTier};
 use async_trait::async_trait;
 use futures::{stream, Stream};
 use subspace_core_primitives::{Piece, PieceIndex};
@@ -29,6 +29,14 @@ impl farm::PieceCache for SingleDiskPieceCache {
         }
     }
 
+    fn tier(&self) -> PieceCacheTier {
+        if let Some(piece_cache) = &self.maybe_piece_cache {
+            farm::PieceCache::tier(piece_cache)
+        } else {
+            PieceCacheTier::default()
+        }
+    }
+
     async fn contents(
         &self,
     ) -> Result<
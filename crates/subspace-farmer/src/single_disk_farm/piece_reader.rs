@@ -48,14 +48,17 @@ impl PieceReader for DiskPieceReader {
 impl DiskPieceReader {
     /// Creates new piece reader instance and background future that handles reads internally.
     ///
+    /// Pieces are read with `async_plot_file` instead of `plot_file` if provided.
+    ///
     /// NOTE: Background future is async, but does blocking operations and should be running in
     /// dedicated thread.
     #[allow(clippy::too_many_arguments)]
-    pub(super) fn new<PosTable>(
+    pub(super) fn new<PosTable, AsyncPlot>(
         public_key: PublicKey,
         pieces_in_sector: u16,
         #[cfg(not(windows))] plot_file: Arc<File>,
         #[cfg(windows)] plot_file: Arc<UnbufferedIoFileWindows>,
+        async_plot_file: Option<Arc<AsyncPlot>>,
         sectors_metadata: Arc<AsyncRwLock<Vec<SectorMetadataChecksummed>>>,
         erasure_coding: ErasureCoding,
         sectors_being_modified: Arc<AsyncRwLock<HashSet<SectorIndex>>>,
@@ -64,14 +67,16 @@ impl DiskPieceReader {
     ) -> (Self, impl Future<Output = ()>)
     where
         PosTable: Table,
+        AsyncPlot: ReadAtAsync,
     {
         let (read_piece_sender, read_piece_receiver) = mpsc::channel(10);
 
         let reading_fut = async move {
-            read_pieces::<PosTable, _>(
+            read_pieces::<PosTable, _, _>(
                 public_key,
                 pieces_in_sector,
                 &*plot_file,
+                async_plot_file.as_deref(),
                 sectors_metadata,
                 erasure_coding,
                 sectors_being_modified,
@@ -111,10 +116,11 @@ impl DiskPieceReader {
 }
 
 #[allow(clippy::too_many_arguments)]
-async fn read_pieces<PosTable, S>(
+async fn read_pieces<PosTable, S, A>(
     public_key: PublicKey,
     pieces_in_sector: u16,
     plot_file: S,
+    async_plot_file: Option<&A>,
     sectors_metadata: Arc<AsyncRwLock<Vec<SectorMetadataChecksummed>>>,
     erasure_coding: ErasureCoding,
     sectors_being_modified: Arc<AsyncRwLock<HashSet<SectorIndex>>>,
@@ -124,6 +130,7 @@ async fn read_pieces<PosTable, S>(
 ) where
     PosTable: Table,
     S: ReadAtSync,
+    A: ReadAtAsync,
 {
     let mut table_generator = PosTable::generator();
 
@@ -191,22 +198,37 @@ async fn read_pieces<PosTable, S>(
         }
 
         let sector_size = sector_size(pieces_in_sector);
-        let sector = plot_file.offset(u64::from(sector_index) * sector_size as u64);
+        let sector_offset = u64::from(sector_index) * sector_size as u64;
 
         // Take mutex briefly to make sure piece reading is allowed right now
         global_mutex.lock().await;
 
-        let maybe_piece = read_piece::<PosTable, _, _>(
-            &public_key,
-            piece_offset,
-            &sector_metadata,
-            // TODO: Async
-            &ReadAt::from_sync(&sector),
-            &erasure_coding,
-            mode,
-            &mut table_generator,
-        )
-        .await;
+        let maybe_piece = match async_plot_file {
+            Some(async_plot_file) => {
+                read_piece::<PosTable, _, _>(
+                    &public_key,
+                    piece_offset,
+                    &sector_metadata,
+                    &ReadAt::from_async(&ReadAtAsync::offset(async_plot_file, sector_offset)),
+                    &erasure_coding,
+                    mode,
+                    &mut table_generator,
+                )
+                .await
+            }
+            None => {
+                read_piece::<PosTable, _, _>(
+                    &public_key,
+                    piece_offset,
+                    &sector_metadata,
+                    &ReadAt::from_sync(&plot_file.offset(sector_offset)),
+                    &erasure_coding,
+                    mode,
+                    &mut table_generator,
+                )
+                .await
+            }
+        };
 
         // Doesn't matter if receiver still cares about it
         let _ = response_sender.send(maybe_piece);
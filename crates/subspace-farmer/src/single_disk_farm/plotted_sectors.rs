@@ -3,7 +3,7 @@ use async_lock::RwLock as AsyncRwLock;
 use async_trait::async_trait;
 use futures::{stream, Stream};
 use std::sync::Arc;
-use subspace_core_primitives::{PieceOffset, PublicKey, SectorId};
+use subspace_core_primitives::{PieceOffset, PublicKey, SectorId, SectorIndex};
 use subspace_farmer_components::plotting::PlottedSector;
 use subspace_farmer_components::sector::SectorMetadataChecksummed;
 use subspace_farmer_components::FarmerProtocolInfo;
@@ -54,4 +54,8 @@ impl PlottedSectors for SingleDiskPlottedSectors {
             },
         ))))
     }
+
+    async fn count(&self) -> Result<SectorIndex, FarmError> {
+        Ok(self.sectors_metadata.read().await.len() as SectorIndex)
+    }
 }
@@ -1,6 +1,9 @@
+#[cfg(test)]
+mod tests;
+
 use crate::farm::{SectorExpirationDetails, SectorPlottingDetails, SectorUpdate};
 use crate::node_client::{Error as NodeClientError, NodeClient};
-use crate::plotter::{Plotter, SectorPlottingProgress};
+use crate::plotter::{Plotter, PlottingPriority, SectorPlottingProgress};
 use crate::single_disk_farm::metrics::{SectorState, SingleDiskFarmMetrics};
 #[cfg(windows)]
 use crate::single_disk_farm::unbuffered_io_file_windows::UnbufferedIoFileWindows;
@@ -44,9 +47,33 @@ pub(super) struct SectorToPlot {
     progress: f32,
     /// Whether this is the last sector queued so far
     last_queued: bool,
+    /// Whether sector is replotted because it is expired or about to expire
+    expiring: bool,
+    /// Plot sector even if existing sector is not older than current history (for example because
+    /// existing sector is corrupted)
+    force: bool,
     acknowledgement_sender: oneshot::Sender<()>,
 }
 
+impl SectorToPlot {
+    /// Single sector that needs to be replotted out of order (for example because it is corrupted),
+    /// receiver resolves once plotting of the sector has started
+    pub(super) fn replot(sector_index: SectorIndex) -> (Self, oneshot::Receiver<()>) {
+        let (acknowledgement_sender, acknowledgement_receiver) = oneshot::channel();
+
+        let sector_to_plot = Self {
+            sector_index,
+            progress: 0.0,
+            last_queued: true,
+            expiring: false,
+            force: true,
+            acknowledgement_sender,
+        };
+
+        (sector_to_plot, acknowledgement_receiver)
+    }
+}
+
 /// Errors that happen during plotting
 #[derive(Debug, Error)]
 pub enum PlottingError {
@@ -109,6 +136,8 @@ pub(super) struct PlottingOptions<'a, NC> {
     pub(super) sectors_metadata: &'a AsyncRwLock<Vec<SectorMetadataChecksummed>>,
     pub(super) sectors_being_modified: &'a AsyncRwLock<HashSet<SectorIndex>>,
     pub(super) sectors_to_plot_receiver: mpsc::Receiver<SectorToPlot>,
+    /// New sectors are not plotted while paused
+    pub(super) plotting_paused: watch::Receiver<bool>,
     pub(super) sector_plotting_options: SectorPlottingOptions<'a, NC>,
 }
 
@@ -127,6 +156,7 @@ where
         sectors_metadata,
         sectors_being_modified,
         mut sectors_to_plot_receiver,
+        mut plotting_paused,
         sector_plotting_options,
     } = plotting_options;
 
@@ -136,7 +166,10 @@ where
     // already started plotting to finish plotting and then update metadata header
     loop {
         select! {
-            maybe_sector_to_plot = sectors_to_plot_receiver.next() => {
+            maybe_sector_to_plot = next_sector_to_plot(
+                &mut plotting_paused,
+                &mut sectors_to_plot_receiver,
+            ).fuse() => {
                 let Some(sector_to_plot) = maybe_sector_to_plot else {
                     break;
                 };
@@ -199,6 +232,21 @@ where
     Ok(())
 }
 
+/// Wait for the next sector to plot, but only receive it once plotting is not paused
+async fn next_sector_to_plot(
+    plotting_paused: &mut watch::Receiver<bool>,
+    sectors_to_plot_receiver: &mut mpsc::Receiver<SectorToPlot>,
+) -> Option<SectorToPlot> {
+    if *plotting_paused.borrow() {
+        debug!("Plotting paused");
+        // Error means pause handle was dropped, which means plotting can't be paused anymore
+        let _ = plotting_paused.wait_for(|paused| !paused).await;
+        debug!("Plotting resumed");
+    }
+
+    sectors_to_plot_receiver.next().await
+}
+
 async fn process_plotting_result(
     sector_plotting_result: SectorPlottingResult,
     metadata_header: &mut PlotMetadataHeader,
@@ -301,6 +349,8 @@ where
         sector_index,
         progress,
         last_queued,
+        expiring,
+        force,
         acknowledgement_sender: _acknowledgement_sender,
     } = sector_to_plot;
     trace!("Preparing to plot sector");
@@ -311,6 +361,11 @@ where
         .get(sector_index as usize)
         .cloned();
     let replotting = maybe_old_sector_metadata.is_some();
+    let priority = match (replotting, expiring) {
+        (false, _) => PlottingPriority::InitialPlotting,
+        (true, true) => PlottingPriority::ExpiringReplotting,
+        (true, false) => PlottingPriority::Replotting,
+    };
 
     if let Some(metrics) = metrics {
         metrics.sector_plotting.inc();
@@ -341,7 +396,9 @@ where
             }
         };
 
-        if let Some(old_sector_metadata) = &maybe_old_sector_metadata {
+        if let Some(old_sector_metadata) = &maybe_old_sector_metadata
+            && !force
+        {
             if farmer_app_info.protocol_info.history_size <= old_sector_metadata.history_size {
                 if farmer_app_info.protocol_info.min_sector_lifetime == HistorySize::ONE {
                     debug!(
@@ -376,7 +433,7 @@ where
             sector_index,
             farmer_app_info.protocol_info,
             *pieces_in_sector,
-            replotting,
+            priority,
             progress_sender,
         )
         .await;
@@ -425,7 +482,7 @@ where
                     sector_index,
                     farmer_app_info.protocol_info,
                     *pieces_in_sector,
-                    replotting,
+                    priority,
                     retry_progress_sender,
                 )
                 .await;
@@ -825,6 +882,8 @@ where
                 sector_index,
                 progress: sector_index as f32 / target_sector_count as f32 * 100.0,
                 last_queued: sector_index + 1 == target_sector_count,
+                expiring: false,
+                force: false,
                 acknowledgement_sender,
             })
             .await
@@ -1005,6 +1064,8 @@ where
                     sector_index,
                     progress: index as f32 / sectors_queued as f32 * 100.0,
                     last_queued: index + 1 == sectors_queued,
+                    expiring: true,
+                    force: false,
                     acknowledgement_sender,
                 })
                 .await
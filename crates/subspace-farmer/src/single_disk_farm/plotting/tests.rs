@@ -0,0 +1,277 @@
+use crate::node_client::{Error, NodeClient};
+use crate::plotter::{Plotter, PlottingPriority, SectorPlottingProgress};
+use crate::single_disk_farm::integrity_check::{integrity_check, IntegrityCheckOptions};
+use crate::single_disk_farm::plotting::{
+    plot_single_sector, PlotSingleSectorResult, SectorPlottingOptions, SectorToPlot,
+};
+#[cfg(windows)]
+use crate::single_disk_farm::unbuffered_io_file_windows::UnbufferedIoFileWindows;
+use crate::single_disk_farm::{Handlers, RESERVED_PLOT_METADATA};
+use async_lock::{Mutex as AsyncMutex, RwLock as AsyncRwLock};
+use async_trait::async_trait;
+use futures::channel::mpsc;
+use futures::{select, FutureExt, Stream, StreamExt};
+use parity_scale_codec::Encode;
+use parking_lot::Mutex;
+use std::collections::HashSet;
+use std::fs;
+#[cfg(not(windows))]
+use std::fs::{File, OpenOptions};
+use std::num::{NonZeroU64, NonZeroUsize};
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use subspace_core_primitives::{
+    HistorySize, Piece, PieceIndex, PublicKey, Record, SectorIndex, SegmentHeader, SegmentIndex,
+};
+use subspace_erasure_coding::ErasureCoding;
+use subspace_farmer_components::reading::ReadSectorRecordChunksMode;
+use subspace_farmer_components::sector::{sector_size, SectorMetadata, SectorMetadataChecksummed};
+use subspace_farmer_components::FarmerProtocolInfo;
+use subspace_proof_of_space::shim::ShimTable;
+use subspace_rpc_primitives::{
+    FarmerAppInfo, RewardSignatureResponse, RewardSigningInfo, SlotInfo, SolutionResponse,
+};
+use tempfile::tempdir;
+
+const PIECES_IN_SECTOR: u16 = 1;
+
+#[derive(Debug)]
+struct MockNodeClient {
+    history_size: HistorySize,
+}
+
+#[async_trait]
+impl NodeClient for MockNodeClient {
+    async fn farmer_app_info(&self) -> Result<FarmerAppInfo, Error> {
+        Ok(FarmerAppInfo {
+            genesis_hash: [0; 32],
+            dsn_bootstrap_nodes: Vec::new(),
+            syncing: false,
+            farming_timeout: Duration::default(),
+            protocol_info: FarmerProtocolInfo {
+                history_size: self.history_size,
+                max_pieces_in_sector: PIECES_IN_SECTOR,
+                recent_segments: HistorySize::from(SegmentIndex::ZERO),
+                recent_history_fraction: (
+                    HistorySize::from(NonZeroU64::new(1).unwrap()),
+                    HistorySize::from(NonZeroU64::new(10).unwrap()),
+                ),
+                min_sector_lifetime: HistorySize::from(NonZeroU64::new(4).unwrap()),
+            },
+        })
+    }
+
+    async fn subscribe_slot_info(
+        &self,
+    ) -> Result<Pin<Box<dyn Stream<Item = SlotInfo> + Send + 'static>>, Error> {
+        unimplemented!()
+    }
+
+    async fn submit_solution_response(
+        &self,
+        _solution_response: SolutionResponse,
+    ) -> Result<(), Error> {
+        unimplemented!()
+    }
+
+    async fn subscribe_reward_signing(
+        &self,
+    ) -> Result<Pin<Box<dyn Stream<Item = RewardSigningInfo> + Send + 'static>>, Error> {
+        unimplemented!()
+    }
+
+    async fn submit_reward_signature(
+        &self,
+        _reward_signature: RewardSignatureResponse,
+    ) -> Result<(), Error> {
+        unimplemented!()
+    }
+
+    async fn subscribe_archived_segment_headers(
+        &self,
+    ) -> Result<Pin<Box<dyn Stream<Item = SegmentHeader> + Send + 'static>>, Error> {
+        unimplemented!()
+    }
+
+    async fn segment_headers(
+        &self,
+        _segment_indexes: Vec<SegmentIndex>,
+    ) -> Result<Vec<Option<SegmentHeader>>, Error> {
+        unimplemented!()
+    }
+
+    async fn piece(&self, _piece_index: PieceIndex) -> Result<Option<Piece>, Error> {
+        unimplemented!()
+    }
+
+    async fn acknowledge_archived_segment_header(
+        &self,
+        _segment_index: SegmentIndex,
+    ) -> Result<(), Error> {
+        unimplemented!()
+    }
+}
+
+/// Plotter that only records sectors it was asked to plot
+#[derive(Debug, Default)]
+struct MockPlotter {
+    plotted_sectors: Mutex<Vec<SectorIndex>>,
+}
+
+#[async_trait]
+impl Plotter for MockPlotter {
+    async fn has_free_capacity(&self) -> Result<bool, String> {
+        Ok(true)
+    }
+
+    async fn plot_sector(
+        &self,
+        _public_key: PublicKey,
+        sector_index: SectorIndex,
+        _farmer_protocol_info: FarmerProtocolInfo,
+        _pieces_in_sector: u16,
+        _priority: PlottingPriority,
+        _progress_sender: mpsc::Sender<SectorPlottingProgress>,
+    ) {
+        self.plotted_sectors.lock().push(sector_index);
+    }
+
+    async fn try_plot_sector(
+        &self,
+        _public_key: PublicKey,
+        sector_index: SectorIndex,
+        _farmer_protocol_info: FarmerProtocolInfo,
+        _pieces_in_sector: u16,
+        _priority: PlottingPriority,
+        _progress_sender: mpsc::Sender<SectorPlottingProgress>,
+    ) -> bool {
+        self.plotted_sectors.lock().push(sector_index);
+        true
+    }
+}
+
+#[cfg(not(windows))]
+fn open_file(path: &Path) -> File {
+    OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .unwrap()
+}
+
+#[cfg(windows)]
+fn open_file(path: &Path) -> UnbufferedIoFileWindows {
+    UnbufferedIoFileWindows::open(path).unwrap()
+}
+
+#[tokio::test]
+async fn corrupted_sector_is_replotted() {
+    let directory = tempdir().unwrap();
+    let public_key = PublicKey::from([1; 32]);
+    let sector_size = sector_size(PIECES_IN_SECTOR);
+    let history_size = HistorySize::from(SegmentIndex::ONE);
+    let sector_metadata = SectorMetadataChecksummed::from(SectorMetadata {
+        sector_index: 0,
+        pieces_in_sector: PIECES_IN_SECTOR,
+        s_bucket_sizes: Box::new([0; Record::NUM_S_BUCKETS]),
+        history_size,
+    });
+
+    // Metadata is valid, but sector contents don't match its checksum
+    let plot_file_path = directory.path().join("plot.bin");
+    let metadata_file_path = directory.path().join("metadata.bin");
+    fs::write(&plot_file_path, vec![0; sector_size]).unwrap();
+    {
+        let mut metadata = vec![0; RESERVED_PLOT_METADATA as usize];
+        metadata.extend_from_slice(&sector_metadata.encode());
+        fs::write(&metadata_file_path, metadata).unwrap();
+    }
+    let plot_file = Arc::new(open_file(&plot_file_path));
+    let metadata_file = Arc::new(open_file(&metadata_file_path));
+
+    let sectors_metadata = AsyncRwLock::new(vec![sector_metadata]);
+    let sectors_being_modified = AsyncRwLock::new(HashSet::new());
+    let handlers = Handlers::default();
+    let global_mutex = AsyncMutex::new(());
+    let erasure_coding = ErasureCoding::new(
+        NonZeroUsize::new(Record::NUM_S_BUCKETS.next_power_of_two().ilog2() as usize).unwrap(),
+    )
+    .unwrap();
+    // History didn't grow since sector was plotted, which is when redundant plotting requests are
+    // normally skipped
+    let node_client = MockNodeClient { history_size };
+    let plotter = Arc::new(MockPlotter::default());
+    let (sectors_to_plot_sender, mut sectors_to_plot_receiver) = mpsc::channel(1);
+
+    let integrity_check_fut = integrity_check::<ShimTable, _>(IntegrityCheckOptions {
+        public_key,
+        pieces_in_sector: PIECES_IN_SECTOR,
+        plot_file: &*plot_file,
+        metadata_file: &*metadata_file,
+        sectors_metadata: &sectors_metadata,
+        sectors_being_modified: &sectors_being_modified,
+        sectors_to_plot_sender,
+        erasure_coding: &erasure_coding,
+        read_sector_record_chunks_mode: ReadSectorRecordChunksMode::WholeSector,
+        handlers: &handlers,
+        global_mutex: &global_mutex,
+        interval: Duration::ZERO,
+    });
+
+    let sector_plotting_options = SectorPlottingOptions {
+        public_key,
+        node_client: &node_client,
+        pieces_in_sector: PIECES_IN_SECTOR,
+        sector_size,
+        plot_file: Arc::clone(&plot_file),
+        metadata_file: Arc::clone(&metadata_file),
+        handlers: &handlers,
+        global_mutex: &global_mutex,
+        plotter: Arc::clone(&plotter) as Arc<dyn Plotter>,
+        metrics: None,
+    };
+    let replotting_fut = async {
+        let sector_to_plot =
+            tokio::time::timeout(Duration::from_secs(10), sectors_to_plot_receiver.next())
+                .await
+                .unwrap()
+                .unwrap();
+        assert_eq!(sector_to_plot.sector_index, 0);
+
+        let result = plot_single_sector(
+            sector_to_plot,
+            &sector_plotting_options,
+            &sectors_metadata,
+            &sectors_being_modified,
+        )
+        .await;
+        assert!(matches!(result, PlotSingleSectorResult::Scheduled(_)));
+    };
+
+    select! {
+        _ = integrity_check_fut.fuse() => {
+            unreachable!("Integrity check never exits; qed");
+        }
+        _ = replotting_fut.fuse() => {}
+    }
+
+    assert_eq!(*plotter.plotted_sectors.lock(), vec![0]);
+
+    // The same request without force flag is considered redundant
+    let (sector_to_plot, _acknowledgement_receiver) = SectorToPlot::replot(0);
+    let sector_to_plot = SectorToPlot {
+        force: false,
+        ..sector_to_plot
+    };
+    let result = plot_single_sector(
+        sector_to_plot,
+        &sector_plotting_options,
+        &sectors_metadata,
+        &sectors_being_modified,
+    )
+    .await;
+    assert!(matches!(result, PlotSingleSectorResult::Skipped));
+    assert_eq!(*plotter.plotted_sectors.lock(), vec![0]);
+}
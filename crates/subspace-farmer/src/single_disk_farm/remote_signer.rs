@@ -0,0 +1,385 @@
+//! Remote signer
+//!
+//! Remote signer allows to keep farm identities (secret keys) outside of farms. Farm only stores
+//! its public key (in farm info) and requests reward signatures from a separate signer process
+//! over Unix socket, which limits exposure if farming machine is compromised.
+//!
+//! Protocol is a sequence of SCALE-encoded requests and responses, each prefixed with its length as
+//! little-endian `u32`. Every request gets exactly one response, requests on a single connection
+//! are processed sequentially.
+
+#[cfg(test)]
+mod tests;
+
+use crate::single_disk_farm::identity::{Identity, IdentityError, RewardSigner};
+use async_lock::Mutex as AsyncMutex;
+use async_trait::async_trait;
+use parity_scale_codec::{Decode, Encode};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use std::{fs, io};
+use subspace_core_primitives::{PublicKey, RewardSignature};
+use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{UnixListener, UnixStream};
+use tracing::{debug, info, warn};
+
+/// Max size of a single message, actual messages are much smaller
+const MAX_MESSAGE_SIZE: u32 = 1024;
+/// Timeout for a single request to remote signer
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Errors happening when interacting with remote signer
+#[derive(Debug, Error)]
+pub enum RemoteSignerError {
+    /// I/O error occurred
+    #[error("Remote signer I/O error: {0}")]
+    Io(#[from] io::Error),
+    /// Decoding error
+    #[error("Remote signer decoding error: {0}")]
+    Decoding(#[from] parity_scale_codec::Error),
+    /// Identity error
+    #[error("Remote signer identity error: {0}")]
+    Identity(#[from] IdentityError),
+    /// Message is too large
+    #[error("Remote signer message of {size} bytes is too large")]
+    MessageTooLarge {
+        /// Message size
+        size: u32,
+    },
+    /// Request timed out
+    #[error("Remote signer request timed out")]
+    Timeout,
+    /// Unexpected response
+    #[error("Unexpected response from remote signer")]
+    UnexpectedResponse,
+    /// Remote signer doesn't have identity
+    #[error("Remote signer doesn't have identity with public key {public_key}")]
+    UnknownIdentity {
+        /// Public key
+        public_key: PublicKey,
+    },
+    /// Remote signer returned an error
+    #[error("Remote signer error: {0}")]
+    Signer(String),
+}
+
+#[derive(Debug, Encode, Decode)]
+enum RemoteSignerRequest {
+    /// Check whether signer has an identity with specified public key
+    HasIdentity { public_key: PublicKey },
+    /// Create new identity
+    CreateIdentity,
+    /// Sign reward hash with identity that has specified public key
+    SignRewardHash {
+        public_key: PublicKey,
+        hash: [u8; 32],
+    },
+}
+
+#[derive(Debug, Encode, Decode)]
+enum RemoteSignerResponse {
+    HasIdentity(bool),
+    IdentityCreated(Result<PublicKey, String>),
+    RewardSignature(Result<RewardSignature, String>),
+}
+
+async fn write_message<S, T>(stream: &mut S, message: &T) -> io::Result<()>
+where
+    S: AsyncWrite + Unpin,
+    T: Encode,
+{
+    let encoded = message.encode();
+    stream.write_u32_le(encoded.len() as u32).await?;
+    stream.write_all(&encoded).await?;
+    stream.flush().await
+}
+
+async fn read_message<S, T>(stream: &mut S) -> Result<T, RemoteSignerError>
+where
+    S: AsyncRead + Unpin,
+    T: Decode,
+{
+    let size = stream.read_u32_le().await?;
+    if size > MAX_MESSAGE_SIZE {
+        return Err(RemoteSignerError::MessageTooLarge { size });
+    }
+
+    let mut buffer = vec![0; size as usize];
+    stream.read_exact(&mut buffer).await?;
+
+    Ok(T::decode(&mut buffer.as_slice())?)
+}
+
+#[derive(Debug)]
+struct Inner {
+    socket_path: PathBuf,
+    connection: AsyncMutex<Option<UnixStream>>,
+}
+
+/// Client of the remote signer, can be shared by multiple farms.
+///
+/// Connection is established lazily and re-established automatically if signer was restarted.
+#[derive(Debug, Clone)]
+pub struct RemoteSigner {
+    inner: Arc<Inner>,
+}
+
+impl RemoteSigner {
+    /// Create new instance that will connect to remote signer at specified socket path
+    pub fn new(socket_path: PathBuf) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                socket_path,
+                connection: AsyncMutex::new(None),
+            }),
+        }
+    }
+
+    /// Check whether remote signer has identity with specified public key
+    pub async fn has_identity(&self, public_key: PublicKey) -> Result<bool, RemoteSignerError> {
+        match self
+            .request(RemoteSignerRequest::HasIdentity { public_key })
+            .await?
+        {
+            RemoteSignerResponse::HasIdentity(has_identity) => Ok(has_identity),
+            _ => Err(RemoteSignerError::UnexpectedResponse),
+        }
+    }
+
+    /// Create new identity in remote signer, returns its public key
+    pub async fn create_identity(&self) -> Result<PublicKey, RemoteSignerError> {
+        match self.request(RemoteSignerRequest::CreateIdentity).await? {
+            RemoteSignerResponse::IdentityCreated(result) => {
+                result.map_err(RemoteSignerError::Signer)
+            }
+            _ => Err(RemoteSignerError::UnexpectedResponse),
+        }
+    }
+
+    /// Reward signer for identity with specified public key, checks that remote signer actually
+    /// has it
+    pub async fn reward_signer(
+        &self,
+        public_key: PublicKey,
+    ) -> Result<RemoteRewardSigner, RemoteSignerError> {
+        if !self.has_identity(public_key).await? {
+            return Err(RemoteSignerError::UnknownIdentity { public_key });
+        }
+
+        Ok(RemoteRewardSigner {
+            remote_signer: self.clone(),
+            public_key,
+        })
+    }
+
+    async fn sign_reward_hash(
+        &self,
+        public_key: PublicKey,
+        hash: [u8; 32],
+    ) -> Result<RewardSignature, RemoteSignerError> {
+        match self
+            .request(RemoteSignerRequest::SignRewardHash { public_key, hash })
+            .await?
+        {
+            RemoteSignerResponse::RewardSignature(result) => {
+                result.map_err(RemoteSignerError::Signer)
+            }
+            _ => Err(RemoteSignerError::UnexpectedResponse),
+        }
+    }
+
+    async fn request(
+        &self,
+        request: RemoteSignerRequest,
+    ) -> Result<RemoteSignerResponse, RemoteSignerError> {
+        let mut connection = self.inner.connection.lock().await;
+
+        // One retry with a fresh connection in case signer was restarted since last request
+        for attempt in 1..=2 {
+            if connection.is_none() {
+                connection.replace(UnixStream::connect(&self.inner.socket_path).await?);
+            }
+            let stream = connection.as_mut().expect("Connection was just set; qed");
+
+            let result = tokio::time::timeout(REQUEST_TIMEOUT, async {
+                write_message(stream, &request).await?;
+                read_message::<_, RemoteSignerResponse>(stream).await
+            })
+            .await
+            .unwrap_or(Err(RemoteSignerError::Timeout));
+
+            match result {
+                Ok(response) => {
+                    return Ok(response);
+                }
+                Err(error) => {
+                    // Connection is in unknown state after error
+                    connection.take();
+
+                    if attempt == 1 && matches!(error, RemoteSignerError::Io(_)) {
+                        debug!(%error, "Remote signer request failed, reconnecting");
+                        continue;
+                    }
+
+                    return Err(error);
+                }
+            }
+        }
+
+        unreachable!("Returns on second attempt regardless of result; qed");
+    }
+}
+
+/// Reward signer that delegates signing to [`RemoteSigner`]
+#[derive(Debug, Clone)]
+pub struct RemoteRewardSigner {
+    remote_signer: RemoteSigner,
+    public_key: PublicKey,
+}
+
+#[async_trait]
+impl RewardSigner for RemoteRewardSigner {
+    #[inline]
+    fn public_key(&self) -> PublicKey {
+        self.public_key
+    }
+
+    async fn sign_reward_hash(&self, hash: &[u8; 32]) -> Result<RewardSignature, String> {
+        self.remote_signer
+            .sign_reward_hash(self.public_key, *hash)
+            .await
+            .map_err(|error| error.to_string())
+    }
+}
+
+/// Remote signer server that holds identities and signs reward hashes on request.
+///
+/// Each identity is stored in its own subdirectory of identities directory (the same way as in the
+/// farm), so existing identity can be moved from the farm into a subdirectory with any name.
+#[derive(Debug)]
+pub struct RemoteSignerServer {
+    identities_directory: PathBuf,
+    identities: Mutex<HashMap<PublicKey, Identity>>,
+}
+
+impl RemoteSignerServer {
+    /// Open identities stored in specified directory, directory is created if it doesn't exist
+    pub fn open(identities_directory: PathBuf) -> Result<Self, RemoteSignerError> {
+        fs::create_dir_all(&identities_directory)?;
+
+        let mut identities = HashMap::new();
+        for entry in fs::read_dir(&identities_directory)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_dir() {
+                continue;
+            }
+
+            if let Some(identity) = Identity::open(entry.path())? {
+                identities.insert(RewardSigner::public_key(&identity), identity);
+            }
+        }
+
+        Ok(Self {
+            identities_directory,
+            identities: Mutex::new(identities),
+        })
+    }
+
+    /// Public keys of all identities
+    pub fn public_keys(&self) -> Vec<PublicKey> {
+        self.identities.lock().keys().copied().collect()
+    }
+
+    /// Serve connections on provided listener, returns only in case of error
+    pub async fn run(self, listener: UnixListener) -> io::Result<()> {
+        let server = Arc::new(self);
+
+        loop {
+            let (stream, _address) = listener.accept().await?;
+            debug!("Accepted remote signer connection");
+
+            tokio::spawn({
+                let server = Arc::clone(&server);
+
+                async move {
+                    if let Err(error) = server.serve_connection(stream).await {
+                        debug!(%error, "Remote signer connection closed with error");
+                    }
+                }
+            });
+        }
+    }
+
+    async fn serve_connection(&self, mut stream: UnixStream) -> Result<(), RemoteSignerError> {
+        loop {
+            let request = match read_message::<_, RemoteSignerRequest>(&mut stream).await {
+                Ok(request) => request,
+                Err(RemoteSignerError::Io(error))
+                    if error.kind() == io::ErrorKind::UnexpectedEof =>
+                {
+                    // Connection closed by client
+                    return Ok(());
+                }
+                Err(error) => {
+                    return Err(error);
+                }
+            };
+
+            let response = self.process_request(request);
+            write_message(&mut stream, &response).await?;
+        }
+    }
+
+    fn process_request(&self, request: RemoteSignerRequest) -> RemoteSignerResponse {
+        match request {
+            RemoteSignerRequest::HasIdentity { public_key } => {
+                RemoteSignerResponse::HasIdentity(self.identities.lock().contains_key(&public_key))
+            }
+            RemoteSignerRequest::CreateIdentity => {
+                RemoteSignerResponse::IdentityCreated(self.create_identity().map_err(|error| {
+                    warn!(%error, "Failed to create identity");
+                    error.to_string()
+                }))
+            }
+            RemoteSignerRequest::SignRewardHash { public_key, hash } => {
+                let identities = self.identities.lock();
+                let result = match identities.get(&public_key) {
+                    Some(identity) => {
+                        info!(%public_key, "Signing reward hash 0x{}", hex::encode(hash));
+                        Ok(identity.sign_reward_hash(&hash).to_bytes().into())
+                    }
+                    None => {
+                        warn!(%public_key, "Requested signature for unknown identity");
+                        Err(RemoteSignerError::UnknownIdentity { public_key }.to_string())
+                    }
+                };
+
+                RemoteSignerResponse::RewardSignature(result)
+            }
+        }
+    }
+
+    fn create_identity(&self) -> Result<PublicKey, RemoteSignerError> {
+        // Create identity in temporary directory first since public key is not known upfront
+        let tmp_directory = self
+            .identities_directory
+            .join(format!(".new-{}", hex::encode(rand::random::<[u8; 8]>())));
+        fs::create_dir(&tmp_directory)?;
+        let identity = Identity::create(&tmp_directory)?;
+        let public_key = RewardSigner::public_key(&identity);
+        fs::rename(&tmp_directory, self.identity_directory(&public_key))?;
+
+        info!(%public_key, "Created new identity");
+        self.identities.lock().insert(public_key, identity);
+
+        Ok(public_key)
+    }
+
+    fn identity_directory(&self, public_key: &PublicKey) -> PathBuf {
+        self.identities_directory.join(public_key.to_string())
+    }
+}
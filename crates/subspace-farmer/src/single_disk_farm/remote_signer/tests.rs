@@ -0,0 +1,49 @@
+use crate::single_disk_farm::identity::RewardSigner;
+use crate::single_disk_farm::remote_signer::{RemoteSigner, RemoteSignerError, RemoteSignerServer};
+use subspace_core_primitives::{PublicKey, REWARD_SIGNING_CONTEXT};
+use tempfile::tempdir;
+use tokio::net::UnixListener;
+
+#[tokio::test]
+async fn basic() {
+    let directory = tempdir().unwrap();
+    let identities_directory = directory.path().join("identities");
+    let socket_path = directory.path().join("signer.sock");
+
+    let server = RemoteSignerServer::open(identities_directory.clone()).unwrap();
+    assert!(server.public_keys().is_empty());
+    let server_task = tokio::spawn(server.run(UnixListener::bind(&socket_path).unwrap()));
+
+    let remote_signer = RemoteSigner::new(socket_path);
+
+    let public_key = remote_signer.create_identity().await.unwrap();
+    assert!(remote_signer.has_identity(public_key).await.unwrap());
+    assert!(!remote_signer
+        .has_identity(PublicKey::from([1; 32]))
+        .await
+        .unwrap());
+    assert!(matches!(
+        remote_signer.reward_signer(PublicKey::from([1; 32])).await,
+        Err(RemoteSignerError::UnknownIdentity { .. })
+    ));
+
+    let reward_signer = remote_signer.reward_signer(public_key).await.unwrap();
+    assert_eq!(reward_signer.public_key(), public_key);
+
+    let hash = [42; 32];
+    let signature = reward_signer.sign_reward_hash(&hash).await.unwrap();
+    let schnorrkel_public_key = schnorrkel::PublicKey::from_bytes(public_key.as_ref()).unwrap();
+    let schnorrkel_signature = schnorrkel::Signature::from_bytes(signature.as_ref()).unwrap();
+    assert!(schnorrkel_public_key
+        .verify(
+            schnorrkel::context::signing_context(REWARD_SIGNING_CONTEXT).bytes(&hash),
+            &schnorrkel_signature,
+        )
+        .is_ok());
+
+    // Identity is persisted and loaded by a new instance of the server
+    server_task.abort();
+    let _ = server_task.await;
+    let server = RemoteSignerServer::open(identities_directory).unwrap();
+    assert_eq!(server.public_keys(), vec![public_key]);
+}
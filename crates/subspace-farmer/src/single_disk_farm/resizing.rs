@@ -0,0 +1,241 @@
+use crate::disk_piece_cache::DiskPieceCache;
+use crate::single_disk_farm::unbuffered_io_file_windows::DISK_SECTOR_SIZE;
+use crate::single_disk_farm::{
+    AllocatedSpaceDistribution, PlotMetadataHeader, SingleDiskFarm, SingleDiskFarmError,
+    SingleDiskFarmInfo, SingleDiskFarmInfoLock,
+};
+use parity_scale_codec::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::{fs, io};
+use subspace_farmer_components::file_ext::FileExt;
+use subspace_farmer_components::sector::{sector_size, SectorMetadataChecksummed};
+use tracing::{debug, info};
+
+/// State of farm resizing, only exists on disk while resizing is in progress, such that interrupted
+/// resizing can be finished on next attempt
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ResizeState {
+    /// How much space in bytes farm is being resized to
+    allocated_space: u64,
+    /// Percentage of allocated space dedicated for caching purposes
+    cache_percentage: u8,
+}
+
+impl ResizeState {
+    const FILE_NAME: &'static str = "resize.json";
+
+    fn load_from(directory: &Path) -> io::Result<Option<Self>> {
+        let bytes = match fs::read(directory.join(Self::FILE_NAME)) {
+            Ok(bytes) => bytes,
+            Err(error) => {
+                return if error.kind() == io::ErrorKind::NotFound {
+                    Ok(None)
+                } else {
+                    Err(error)
+                };
+            }
+        };
+
+        serde_json::from_slice(&bytes)
+            .map(Some)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+
+    fn store_to(&self, directory: &Path) -> io::Result<()> {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(directory.join(Self::FILE_NAME))?;
+        file.write_all(&serde_json::to_vec(self).expect("State serialization never fails; qed"))?;
+        file.sync_all()
+    }
+
+    fn remove(directory: &Path) -> io::Result<()> {
+        match fs::remove_file(directory.join(Self::FILE_NAME)) {
+            Ok(()) => Ok(()),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(error) => Err(error),
+        }
+    }
+}
+
+/// Resize farm to specified allocated space, finishing previously interrupted resizing first if
+/// there was any.
+///
+/// Growing appends space for new sectors to the plot and metadata files and extends piece cache,
+/// newly available sectors are then plotted as usual. Shrinking drops sectors with the highest
+/// indices and moves pieces stored beyond the new piece cache capacity into vacant cache elements.
+///
+/// `single_disk_farm_info` is updated and stored on disk once resizing is complete. Farm must be
+/// locked by the caller already (unless locking is disabled), in which case info is stored through
+/// `single_disk_farm_info_lock`.
+pub(super) fn resize(
+    directory: &Path,
+    single_disk_farm_info: &mut SingleDiskFarmInfo,
+    allocated_space: u64,
+    cache_percentage: u8,
+    single_disk_farm_info_lock: Option<&SingleDiskFarmInfoLock>,
+) -> Result<(), SingleDiskFarmError> {
+    let store_info = |single_disk_farm_info: &SingleDiskFarmInfo| match single_disk_farm_info_lock {
+        Some(single_disk_farm_info_lock) => single_disk_farm_info_lock.store(single_disk_farm_info),
+        None => single_disk_farm_info.store_to(directory),
+    };
+
+    let new_resize_state = ResizeState {
+        allocated_space,
+        cache_percentage,
+    };
+
+    let interrupted = match ResizeState::load_from(directory)? {
+        Some(resize_state) => {
+            if resize_state != new_resize_state {
+                info!(
+                    allocated_space = %bytesize::to_string(resize_state.allocated_space, true),
+                    "Finishing previously interrupted farm resizing"
+                );
+
+                resize_files(
+                    directory,
+                    single_disk_farm_info.pieces_in_sector(),
+                    resize_state,
+                )?;
+                single_disk_farm_info.set_allocated_space(resize_state.allocated_space);
+                store_info(single_disk_farm_info)?;
+            }
+
+            true
+        }
+        None => false,
+    };
+
+    if !interrupted && single_disk_farm_info.allocated_space() == allocated_space {
+        return Ok(());
+    }
+
+    info!(
+        old_space = %bytesize::to_string(single_disk_farm_info.allocated_space(), true),
+        new_space = %bytesize::to_string(allocated_space, true),
+        "Resizing farm"
+    );
+
+    new_resize_state.store_to(directory)?;
+    resize_files(
+        directory,
+        single_disk_farm_info.pieces_in_sector(),
+        new_resize_state,
+    )?;
+    single_disk_farm_info.set_allocated_space(allocated_space);
+    store_info(single_disk_farm_info)?;
+    ResizeState::remove(directory)?;
+
+    info!("Farm resized successfully");
+
+    Ok(())
+}
+
+/// Remove resizing state (if any) as part of farm wiping
+pub(super) fn wipe(directory: &Path) -> io::Result<()> {
+    ResizeState::remove(directory)
+}
+
+/// Bring sizes of farm files in accordance with resize state. Every step is idempotent, which
+/// means it is safe to call this function again if it was interrupted previously.
+fn resize_files(
+    directory: &Path,
+    pieces_in_sector: u16,
+    resize_state: ResizeState,
+) -> Result<(), SingleDiskFarmError> {
+    let sector_size = sector_size(pieces_in_sector) as u64;
+    let allocated_space_distribution = AllocatedSpaceDistribution::new(
+        resize_state.allocated_space,
+        sector_size,
+        resize_state.cache_percentage,
+        SectorMetadataChecksummed::encoded_size() as u64,
+    )?;
+    let target_sector_count = allocated_space_distribution.target_sector_count;
+
+    // Metadata header must be updated before plot file is truncated, such that sectors that are
+    // about to be removed are never considered plotted
+    if let Some(metadata_file) = open_existing(&directory.join(SingleDiskFarm::METADATA_FILE))? {
+        let metadata_size = metadata_file.size()?;
+
+        if metadata_size > 0 {
+            let mut metadata_header_bytes = vec![0; PlotMetadataHeader::encoded_size()];
+            metadata_file.read_exact_at(&mut metadata_header_bytes, 0)?;
+
+            let mut metadata_header =
+                PlotMetadataHeader::decode(&mut metadata_header_bytes.as_ref())
+                    .map_err(SingleDiskFarmError::FailedToDecodeMetadataHeader)?;
+
+            if metadata_header.version != SingleDiskFarm::SUPPORTED_PLOT_VERSION {
+                return Err(SingleDiskFarmError::UnexpectedMetadataVersion(
+                    metadata_header.version,
+                ));
+            }
+
+            if metadata_header.plotted_sector_count > target_sector_count {
+                debug!(
+                    plotted_sector_count = %metadata_header.plotted_sector_count,
+                    %target_sector_count,
+                    "Dropping sectors that do not fit into new allocated space"
+                );
+
+                metadata_header.plotted_sector_count = target_sector_count;
+                metadata_file.write_all_at(&metadata_header.encode(), 0)?;
+                metadata_file.sync_data()?;
+            }
+
+            // Align metadata file size for disk sector size
+            let expected_metadata_size = allocated_space_distribution
+                .metadata_file_size
+                .div_ceil(DISK_SECTOR_SIZE as u64)
+                * DISK_SECTOR_SIZE as u64;
+            resize_file(&metadata_file, expected_metadata_size)
+                .map_err(SingleDiskFarmError::CantPreallocateMetadataFile)?;
+        }
+    }
+
+    // Farms without piece cache (like in farming cluster) do not touch cache file at all
+    if allocated_space_distribution.piece_cache_capacity > 0 {
+        DiskPieceCache::shrink(directory, allocated_space_distribution.piece_cache_capacity)?;
+        // Opening piece cache will extend it to the new capacity if necessary
+        DiskPieceCache::open(
+            directory,
+            allocated_space_distribution.piece_cache_capacity,
+            None,
+            None,
+        )?;
+    }
+
+    if let Some(plot_file) = open_existing(&directory.join(SingleDiskFarm::PLOT_FILE))? {
+        resize_file(&plot_file, allocated_space_distribution.plot_file_size)
+            .map_err(SingleDiskFarmError::CantPreallocatePlotFile)?;
+    }
+
+    Ok(())
+}
+
+fn open_existing(path: &Path) -> io::Result<Option<File>> {
+    match OpenOptions::new().read(true).write(true).open(path) {
+        Ok(file) => Ok(Some(file)),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+fn resize_file(file: &File, size: u64) -> io::Result<()> {
+    if file.size()? != size {
+        // Allocating the whole file (`set_len` below can create a sparse file, which will cause
+        // writes to fail later)
+        file.preallocate(size)?;
+        // Truncating file (if necessary)
+        file.set_len(size)?;
+    }
+
+    Ok(())
+}
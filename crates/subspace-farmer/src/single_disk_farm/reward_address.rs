@@ -0,0 +1,116 @@
+//! Reward address selection
+//!
+//! Farm can use a single reward address or multiple addresses that are rotated according to
+//! [`RewardAddressRotation`]. Selection is abstracted with [`RewardAddressPolicy`] trait, so custom
+//! policies can be plugged in when library is used directly.
+
+#[cfg(test)]
+mod tests;
+
+use std::fmt;
+use std::num::NonZeroU64;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use subspace_core_primitives::{PublicKey, SlotNumber};
+
+/// Policy that decides which reward address farm uses for its solutions
+pub trait RewardAddressPolicy: fmt::Debug + Send + Sync {
+    /// Reward address to use for solutions found in specified slot
+    fn reward_address(&self, slot: SlotNumber) -> PublicKey;
+
+    /// Notification about solution with specified reward address being submitted successfully
+    fn solution_submitted(&self, _reward_address: &PublicKey) {}
+}
+
+impl RewardAddressPolicy for PublicKey {
+    #[inline]
+    fn reward_address(&self, _slot: SlotNumber) -> PublicKey {
+        *self
+    }
+}
+
+/// How multiple reward addresses are rotated
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum RewardAddressRotation {
+    /// Switch to the next address after every successfully submitted solution
+    EverySolution,
+    /// Switch to the next address every specified number of slots
+    EverySlots(NonZeroU64),
+}
+
+impl fmt::Display for RewardAddressRotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EverySolution => f.write_str("solution"),
+            Self::EverySlots(slots) => write!(f, "slots:{slots}"),
+        }
+    }
+}
+
+impl FromStr for RewardAddressRotation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "solution" {
+            return Ok(Self::EverySolution);
+        }
+
+        if let Some(slots) = s.strip_prefix("slots:") {
+            return slots
+                .parse::<NonZeroU64>()
+                .map(Self::EverySlots)
+                .map_err(|error| format!("Invalid number of slots \"{slots}\": {error}"));
+        }
+
+        Err(format!(
+            "Unsupported rotation \"{s}\", only `solution` or `slots:<number>` are allowed"
+        ))
+    }
+}
+
+/// Multiple reward addresses rotated according to [`RewardAddressRotation`]
+#[derive(Debug)]
+pub struct RotatingRewardAddresses {
+    addresses: Vec<PublicKey>,
+    rotation: RewardAddressRotation,
+    submitted_solutions: AtomicUsize,
+}
+
+impl RewardAddressPolicy for RotatingRewardAddresses {
+    fn reward_address(&self, slot: SlotNumber) -> PublicKey {
+        let index = match self.rotation {
+            RewardAddressRotation::EverySolution => {
+                self.submitted_solutions.load(Ordering::Relaxed) % self.addresses.len()
+            }
+            RewardAddressRotation::EverySlots(slots) => {
+                (slot / slots.get() % self.addresses.len() as u64) as usize
+            }
+        };
+
+        self.addresses[index]
+    }
+
+    fn solution_submitted(&self, _reward_address: &PublicKey) {
+        self.submitted_solutions.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl RotatingRewardAddresses {
+    /// Create new instance, returns `None` if no addresses were provided
+    pub fn new(addresses: Vec<PublicKey>, rotation: RewardAddressRotation) -> Option<Self> {
+        if addresses.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            addresses,
+            rotation,
+            submitted_solutions: AtomicUsize::new(0),
+        })
+    }
+
+    /// Addresses that are being rotated
+    pub fn addresses(&self) -> &[PublicKey] {
+        &self.addresses
+    }
+}
@@ -0,0 +1,63 @@
+use crate::single_disk_farm::reward_address::{
+    RewardAddressPolicy, RewardAddressRotation, RotatingRewardAddresses,
+};
+use std::num::NonZeroU64;
+use subspace_core_primitives::PublicKey;
+
+#[test]
+fn rotation_parsing() {
+    assert_eq!(
+        "solution".parse::<RewardAddressRotation>().unwrap(),
+        RewardAddressRotation::EverySolution
+    );
+    assert_eq!(
+        "slots:3600".parse::<RewardAddressRotation>().unwrap(),
+        RewardAddressRotation::EverySlots(NonZeroU64::new(3600).unwrap())
+    );
+    assert_eq!(
+        RewardAddressRotation::EverySlots(NonZeroU64::new(10).unwrap()).to_string(),
+        "slots:10"
+    );
+
+    assert!("slots:0".parse::<RewardAddressRotation>().is_err());
+    assert!("slots".parse::<RewardAddressRotation>().is_err());
+    assert!("block".parse::<RewardAddressRotation>().is_err());
+}
+
+#[test]
+fn rotating_reward_addresses() {
+    let addresses = [
+        PublicKey::from([1; 32]),
+        PublicKey::from([2; 32]),
+        PublicKey::from([3; 32]),
+    ];
+
+    assert!(
+        RotatingRewardAddresses::new(Vec::new(), RewardAddressRotation::EverySolution).is_none()
+    );
+
+    let policy =
+        RotatingRewardAddresses::new(addresses.to_vec(), RewardAddressRotation::EverySolution)
+            .unwrap();
+    // Slot doesn't matter, only submitted solutions
+    assert_eq!(policy.reward_address(0), addresses[0]);
+    assert_eq!(policy.reward_address(100), addresses[0]);
+    for expected_address in addresses.iter().cycle().skip(1).take(4) {
+        policy.solution_submitted(&policy.reward_address(0));
+        assert_eq!(policy.reward_address(0), *expected_address);
+    }
+
+    let policy = RotatingRewardAddresses::new(
+        addresses.to_vec(),
+        RewardAddressRotation::EverySlots(NonZeroU64::new(10).unwrap()),
+    )
+    .unwrap();
+    assert_eq!(policy.reward_address(0), addresses[0]);
+    assert_eq!(policy.reward_address(9), addresses[0]);
+    assert_eq!(policy.reward_address(10), addresses[1]);
+    assert_eq!(policy.reward_address(25), addresses[2]);
+    assert_eq!(policy.reward_address(30), addresses[0]);
+    // Submitted solutions do not affect slot-based rotation
+    policy.solution_submitted(&addresses[0]);
+    assert_eq!(policy.reward_address(30), addresses[0]);
+}
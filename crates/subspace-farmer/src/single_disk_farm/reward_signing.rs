@@ -1,13 +1,14 @@
 use crate::node_client::NodeClient;
-use crate::single_disk_farm::identity::Identity;
+use crate::single_disk_farm::identity::RewardSigner;
 use futures::StreamExt;
 use std::future::Future;
+use std::sync::Arc;
 use subspace_rpc_primitives::{RewardSignatureResponse, RewardSigningInfo};
 use tracing::{info, warn};
 
 pub(super) async fn reward_signing<NC>(
     node_client: NC,
-    identity: Identity,
+    reward_signer: Arc<dyn RewardSigner>,
 ) -> Result<impl Future<Output = ()>, Box<dyn std::error::Error + Send + Sync>>
 where
     NC: NodeClient,
@@ -17,20 +18,32 @@ where
     let mut reward_signing_info_notifications = node_client.subscribe_reward_signing().await?;
 
     let reward_signing_fut = async move {
+        let reward_signer_public_key = reward_signer.public_key();
+
         while let Some(RewardSigningInfo { hash, public_key }) =
             reward_signing_info_notifications.next().await
         {
             // Multiple plots might have solved, only sign with correct one
-            if identity.public_key().to_bytes() != public_key {
+            if reward_signer_public_key.as_ref() != public_key.as_slice() {
                 continue;
             }
 
-            let signature = identity.sign_reward_hash(&hash);
+            let signature = match reward_signer.sign_reward_hash(&hash).await {
+                Ok(signature) => signature,
+                Err(error) => {
+                    warn!(
+                        %error,
+                        "Failed to sign reward hash 0x{}",
+                        hex::encode(hash),
+                    );
+                    continue;
+                }
+            };
 
             match node_client
                 .submit_reward_signature(RewardSignatureResponse {
                     hash,
-                    signature: Some(signature.to_bytes().into()),
+                    signature: Some(signature),
                 })
                 .await
             {
@@ -192,6 +192,7 @@ fn main() -> Result<(), Error> {
                     max_pending_in_connections: 100,
                     max_pending_out_connections: 150,
                     external_addresses: vec![],
+                    mdns: false,
                 }
             };
 
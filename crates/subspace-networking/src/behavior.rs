@@ -16,12 +16,19 @@ use derive_more::From;
 use libp2p::allow_block_list::{Behaviour as AllowBlockListBehaviour, BlockedPeers};
 use libp2p::autonat::Event as AutonatEvent;
 use libp2p::connection_limits::ConnectionLimits;
+use libp2p::dcutr::{Behaviour as Dcutr, Event as DcutrEvent};
 use libp2p::gossipsub::{
     Behaviour as Gossipsub, Config as GossipsubConfig, Event as GossipsubEvent, MessageAuthenticity,
 };
 use libp2p::identify::{Behaviour as Identify, Config as IdentifyConfig, Event as IdentifyEvent};
 use libp2p::kad::{Behaviour as Kademlia, Config as KademliaConfig, Event as KademliaEvent};
+use libp2p::mdns::tokio::Behaviour as Mdns;
+use libp2p::mdns::Event as MdnsEvent;
 use libp2p::ping::{Behaviour as Ping, Event as PingEvent};
+use libp2p::relay::client::{Behaviour as RelayClient, Event as RelayClientEvent};
+use libp2p::relay::{
+    Behaviour as RelayServer, Config as RelayServerConfig, Event as RelayServerEvent,
+};
 use libp2p::swarm::behaviour::toggle::Toggle;
 use libp2p::swarm::NetworkBehaviour;
 use libp2p::PeerId;
@@ -48,6 +55,12 @@ pub(crate) struct BehaviorConfig<RecordStore> {
     pub(crate) reserved_peers: ReservedPeersConfig,
     /// Autonat configuration.
     pub(crate) autonat: AutonatWrapperConfig,
+    /// The configuration for the [`RelayServer`] behaviour, `None` disables it.
+    pub(crate) relay_server: Option<RelayServerConfig>,
+    /// Relay client behaviour, created together with corresponding transport.
+    pub(crate) relay_client: RelayClient,
+    /// mDNS behaviour for discovery of peers on local network, `None` disables it.
+    pub(crate) mdns: Option<Mdns>,
 }
 
 #[derive(NetworkBehaviour)]
@@ -64,6 +77,10 @@ pub(crate) struct Behavior<RecordStore> {
     pub(crate) block_list: BlockListBehaviour,
     pub(crate) reserved_peers: ReservedPeersBehaviour,
     pub(crate) autonat: AutonatWrapper,
+    pub(crate) relay_server: Toggle<RelayServer>,
+    pub(crate) relay_client: RelayClient,
+    pub(crate) dcutr: Dcutr,
+    pub(crate) mdns: Toggle<Mdns>,
 }
 
 impl<RecordStore> Behavior<RecordStore>
@@ -103,6 +120,13 @@ where
             block_list: BlockListBehaviour::default(),
             reserved_peers: ReservedPeersBehaviour::new(config.reserved_peers),
             autonat: AutonatWrapper::new(config.autonat),
+            relay_server: config
+                .relay_server
+                .map(|relay_server_config| RelayServer::new(config.peer_id, relay_server_config))
+                .into(),
+            relay_client: config.relay_client,
+            dcutr: Dcutr::new(config.peer_id),
+            mdns: config.mdns.into(),
         }
     }
 }
@@ -118,4 +142,8 @@ pub(crate) enum Event {
     VoidEventStub(VoidEvent),
     ReservedPeers(ReservedPeersEvent),
     Autonat(AutonatEvent),
+    RelayServer(RelayServerEvent),
+    RelayClient(RelayClientEvent),
+    Dcutr(DcutrEvent),
+    Mdns(MdnsEvent),
 }
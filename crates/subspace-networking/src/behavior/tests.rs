@@ -2,11 +2,12 @@ use super::persistent_parameters::remove_known_peer_addresses_internal;
 use crate::behavior::persistent_parameters::{append_p2p_suffix, remove_p2p_suffix};
 use crate::{
     Config, GenericRequest, GenericRequestHandler, KnownPeersManager, KnownPeersManagerConfig,
-    KnownPeersRegistry,
+    KnownPeersRegistry, PieceByIndexRequest, PieceByIndexRequestHandler, PieceByIndexResponse,
 };
 use futures::channel::oneshot;
 use futures::future::pending;
 use libp2p::multiaddr::Protocol;
+use libp2p::relay::Config as RelayServerConfig;
 use libp2p::{Multiaddr, PeerId};
 use parity_scale_codec::{Decode, Encode};
 use parking_lot::Mutex;
@@ -17,6 +18,7 @@ use std::str::FromStr;
 use std::sync::Arc;
 use std::task::{Context, Poll};
 use std::time::Duration;
+use subspace_core_primitives::{Piece, PieceIndex};
 use tokio::time::sleep;
 
 #[tokio::test()]
@@ -251,6 +253,116 @@ async fn test_async_handler_works_with_pending_internal_future() {
     assert_eq!(resp.counter, 1);
 }
 
+#[tokio::test]
+async fn test_request_through_circuit_relay() {
+    let mut piece = Piece::default();
+    piece.as_mut()[0] = 1;
+
+    let config_1 = Config {
+        listen_on: vec!["/ip4/127.0.0.1/tcp/0".parse().unwrap()],
+        allow_non_global_addresses_in_dht: true,
+        // Default limits are too low for a piece to be transferred through relay
+        relay_server: Some(RelayServerConfig {
+            max_circuit_bytes: 16 * 1024 * 1024,
+            max_circuit_duration: Duration::from_secs(600),
+            ..RelayServerConfig::default()
+        }),
+        ..Config::default()
+    };
+    let (node_1, mut node_runner_1) = crate::construct(config_1).unwrap();
+
+    let (node_1_address_sender, node_1_address_receiver) = oneshot::channel();
+    let on_new_listener_handler = node_1.on_new_listener(Arc::new({
+        let node_1_address_sender = Mutex::new(Some(node_1_address_sender));
+
+        move |address| {
+            if let Some(node_1_address_sender) = node_1_address_sender.lock().take() {
+                node_1_address_sender.send(address.clone()).unwrap();
+            }
+        }
+    }));
+
+    tokio::spawn(async move {
+        node_runner_1.run().await;
+    });
+
+    // Wait for relay server to know its address
+    let node_1_addr = node_1_address_receiver.await.unwrap();
+    drop(on_new_listener_handler);
+
+    let circuit_address = node_1_addr
+        .with(Protocol::P2p(node_1.id()))
+        .with(Protocol::P2pCircuit);
+    let config_2 = Config {
+        listen_on: vec![circuit_address.clone()],
+        allow_non_global_addresses_in_dht: true,
+        request_response_protocols: vec![PieceByIndexRequestHandler::create({
+            let piece = piece.clone();
+
+            move |_, _| {
+                let piece = piece.clone();
+
+                async move { Some(PieceByIndexResponse { piece: Some(piece) }) }
+            }
+        })],
+        ..Config::default()
+    };
+    let (node_2, mut node_runner_2) = crate::construct(config_2).unwrap();
+
+    let (node_2_address_sender, node_2_address_receiver) = oneshot::channel();
+    let on_new_listener_handler = node_2.on_new_listener(Arc::new({
+        let node_2_address_sender = Mutex::new(Some(node_2_address_sender));
+
+        move |address| {
+            if address
+                .iter()
+                .any(|protocol| protocol == Protocol::P2pCircuit)
+            {
+                if let Some(node_2_address_sender) = node_2_address_sender.lock().take() {
+                    node_2_address_sender.send(address.clone()).unwrap();
+                }
+            }
+        }
+    }));
+
+    tokio::spawn(async move {
+        node_runner_2.run().await;
+    });
+
+    // Wait for reservation on relay server to be accepted
+    node_2_address_receiver.await.unwrap();
+    drop(on_new_listener_handler);
+
+    let config_3 = Config {
+        allow_non_global_addresses_in_dht: true,
+        request_response_protocols: vec![PieceByIndexRequestHandler::create(|_, _| async { None })],
+        ..Config::default()
+    };
+    let (node_3, mut node_runner_3) = crate::construct(config_3).unwrap();
+
+    tokio::spawn(async move {
+        node_runner_3.run().await;
+    });
+
+    node_3
+        .dial(circuit_address.with(Protocol::P2p(node_2.id())))
+        .await
+        .unwrap();
+
+    // Piece-sized response must fit into relayed connection limits
+    let response = node_3
+        .send_generic_request(
+            node_2.id(),
+            PieceByIndexRequest {
+                piece_index: PieceIndex::ONE,
+            },
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.piece, Some(piece));
+}
+
 #[tokio::test]
 async fn test_address_p2p_prefix_removal() {
     let short_addr: Multiaddr = "/ip4/127.0.0.1/tcp/50000".parse().unwrap();
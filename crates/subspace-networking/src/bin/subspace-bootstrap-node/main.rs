@@ -6,6 +6,7 @@ use clap::Parser;
 use futures::{select, FutureExt};
 use libp2p::identity::ed25519::Keypair;
 use libp2p::kad::Mode;
+use libp2p::relay::Config as RelayServerConfig;
 use libp2p::{identity, Multiaddr, PeerId};
 use prometheus_client::registry::Registry;
 use serde::{Deserialize, Serialize};
@@ -13,6 +14,7 @@ use std::error::Error;
 use std::fmt::{Display, Formatter};
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 use std::sync::Arc;
+use std::time::Duration;
 use subspace_metrics::{start_prometheus_metrics_server, RegistryAdapter};
 use subspace_networking::libp2p::multiaddr::Protocol;
 use subspace_networking::{peer_id, Config, KademliaMode};
@@ -68,6 +70,26 @@ enum Command {
         /// Known external addresses
         #[arg(long, alias = "external-address")]
         external_addresses: Vec<Multiaddr>,
+        /// Act as circuit relay v2 server, such that peers behind NAT can be reached through this
+        /// node and establish direct connections with each other using hole punching.
+        ///
+        /// Peers use `<bootstrap node address>/p2p/<bootstrap node peer ID>/p2p-circuit` as listen
+        /// address to be reachable through it.
+        #[arg(long, default_value_t = false)]
+        relay_server: bool,
+        /// Defines max number of peers that can be reachable through relay server at the same time.
+        #[arg(long, default_value_t = 128)]
+        relay_max_reservations: usize,
+        /// Defines max number of simultaneously relayed connections.
+        #[arg(long, default_value_t = 16)]
+        relay_max_circuits: usize,
+        /// Defines max number of bytes transferred over a single relayed connection in each
+        /// direction, must be large enough for pieces (1 MiB each) to be served through relay.
+        #[arg(long, default_value_t = 64 * 1024 * 1024)]
+        relay_max_circuit_bytes: u64,
+        /// Defines max duration of a single relayed connection in seconds.
+        #[arg(long, default_value_t = 600)]
+        relay_max_circuit_duration: u64,
         /// Defines endpoints for the prometheus metrics server. It doesn't start without at least
         /// one specified endpoint. Format: 127.0.0.1:8080
         #[arg(long, aliases = ["metrics-endpoint", "metrics-endpoints"])]
@@ -134,6 +156,11 @@ async fn main() -> Result<(), Box<dyn Error>> {
             allow_private_ips,
             protocol_version,
             external_addresses,
+            relay_server,
+            relay_max_reservations,
+            relay_max_circuits,
+            relay_max_circuit_bytes,
+            relay_max_circuit_duration,
             prometheus_listen_on,
         } => {
             debug!(
@@ -161,6 +188,13 @@ async fn main() -> Result<(), Box<dyn Error>> {
                 bootstrap_addresses: bootstrap_nodes,
                 kademlia_mode: KademliaMode::Static(Mode::Server),
                 external_addresses,
+                relay_server: relay_server.then(|| RelayServerConfig {
+                    max_reservations: relay_max_reservations,
+                    max_circuits: relay_max_circuits,
+                    max_circuit_bytes: relay_max_circuit_bytes,
+                    max_circuit_duration: Duration::from_secs(relay_max_circuit_duration),
+                    ..RelayServerConfig::default()
+                }),
 
                 ..Config::new(
                     protocol_version.to_string(),
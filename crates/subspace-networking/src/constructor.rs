@@ -7,10 +7,11 @@ use crate::constructor::temporary_bans::TemporaryBans;
 use crate::constructor::transport::build_transport;
 use crate::node::Node;
 use crate::node_runner::{NodeRunner, NodeRunnerConfig};
+use crate::peer_reputation::{PeerReputation, PeerReputationConfig};
 use crate::protocols::autonat_wrapper::Config as AutonatWrapperConfig;
 use crate::protocols::request_response::request_response_factory::RequestHandler;
 use crate::protocols::reserved_peers::Config as ReservedPeersConfig;
-use crate::shared::Shared;
+use crate::shared::{LocalPeers, Shared};
 use crate::utils::rate_limiter::RateLimiter;
 use crate::utils::{strip_peer_id, SubspaceMetrics};
 use backoff::{ExponentialBackoff, SystemClock};
@@ -27,10 +28,14 @@ use libp2p::kad::{
     store, BucketInserts, Config as KademliaConfig, Mode, ProviderRecord, Record, RecordKey,
     StoreInserts,
 };
+use libp2p::mdns::tokio::Behaviour as Mdns;
+use libp2p::mdns::Config as MdnsConfig;
 use libp2p::metrics::Metrics;
 use libp2p::multiaddr::Protocol;
+use libp2p::quic::Config as QuicConfig;
+use libp2p::relay::Config as RelayServerConfig;
 use libp2p::yamux::Config as YamuxConfig;
-use libp2p::{identity, Multiaddr, PeerId, StreamProtocol, SwarmBuilder, TransportError};
+use libp2p::{identity, relay, Multiaddr, PeerId, StreamProtocol, SwarmBuilder, TransportError};
 use parking_lot::Mutex;
 use prometheus_client::registry::Registry;
 use std::borrow::Cow;
@@ -186,6 +191,9 @@ pub struct Config<LocalRecordProvider> {
     /// Identity keypair of a node used for authenticated connections.
     pub keypair: identity::Keypair,
     /// List of [`Multiaddr`] on which to listen for incoming connections.
+    ///
+    /// Addresses in `<relay server address>/p2p/<relay server peer ID>/p2p-circuit` form make node
+    /// reachable through circuit relay server, which is useful for nodes behind NAT.
     pub listen_on: Vec<Multiaddr>,
     /// Fallback to random port if specified (or default) port is already occupied.
     pub listen_on_fallback_to_random_port: bool,
@@ -202,6 +210,24 @@ pub struct Config<LocalRecordProvider> {
     pub local_records_provider: LocalRecordProvider,
     /// Yamux multiplexing configuration.
     pub yamux_config: YamuxConfig,
+    /// The configuration for QUIC transport, `None` (default) disables QUIC and only TCP is used.
+    ///
+    /// With QUIC enabled `listen_on` can contain `/udp/<port>/quic-v1` addresses alongside TCP
+    /// ones and peers are dialed over both transports, see [`Config::enable_quic_if_listening()`].
+    pub quic: Option<QuicConfig>,
+    /// The configuration for circuit relay v2 server, `None` means node doesn't relay connections
+    /// of other peers.
+    ///
+    /// Relay client and DCUtR hole punching are always enabled, such that peers behind NAT can be
+    /// reached through relay servers and upgrade relayed connections to direct ones.
+    pub relay_server: Option<RelayServerConfig>,
+    /// The configuration for mDNS discovery of peers on local network, `None` disables it.
+    ///
+    /// Peers discovered this way are dialed immediately and preferred for piece requests (see
+    /// [`LocalPeersPolicy`](crate::utils::piece_provider::LocalPeersPolicy)), which is useful when
+    /// many farmers and nodes share the same local network. Discovered peers are dialed even if
+    /// non-global addresses are not allowed otherwise.
+    pub mdns: Option<MdnsConfig>,
     /// Should non-global addresses be added to the DHT?
     pub allow_non_global_addresses_in_dht: bool,
     /// How frequently should random queries be done using Kademlia DHT to populate routing table.
@@ -224,6 +250,8 @@ pub struct Config<LocalRecordProvider> {
     pub temporary_bans_cache_size: u32,
     /// Backoff policy for temporary banning of unreachable peers.
     pub temporary_ban_backoff: ExponentialBackoff,
+    /// Reputation of peers based on outcomes of requests sent to them.
+    pub peer_reputation: PeerReputationConfig,
     /// Optional libp2p prometheus metrics. None will disable metrics gathering.
     pub libp2p_metrics: Option<Metrics>,
     /// Internal prometheus metrics. None will disable metrics gathering.
@@ -344,6 +372,9 @@ where
             known_peers_registry: StubNetworkingParametersManager.boxed(),
             request_response_protocols: Vec::new(),
             yamux_config,
+            quic: None,
+            relay_server: None,
+            mdns: None,
             reserved_peers: Vec::new(),
             max_established_incoming_connections: SWARM_MAX_ESTABLISHED_INCOMING_CONNECTIONS,
             max_established_outgoing_connections: SWARM_MAX_ESTABLISHED_OUTGOING_CONNECTIONS,
@@ -351,6 +382,7 @@ where
             max_pending_outgoing_connections: SWARM_MAX_PENDING_OUTGOING_CONNECTIONS,
             temporary_bans_cache_size: TEMPORARY_BANS_CACHE_SIZE,
             temporary_ban_backoff,
+            peer_reputation: PeerReputationConfig::default(),
             libp2p_metrics,
             metrics,
             protocol_version,
@@ -359,12 +391,28 @@ where
             external_addresses: Vec::new(),
         }
     }
+
+    /// Enable QUIC transport with default configuration if `listen_on` contains QUIC addresses
+    /// (`/udp/<port>/quic-v1`) and QUIC is not configured yet, such that QUIC is opt-in by
+    /// listening on it.
+    pub fn enable_quic_if_listening(&mut self) {
+        let listens_on_quic = self
+            .listen_on
+            .iter()
+            .any(|address| address.iter().any(|protocol| protocol == Protocol::QuicV1));
+
+        if listens_on_quic && self.quic.is_none() {
+            let mut quic = QuicConfig::new(&self.keypair);
+            quic.max_concurrent_stream_limit = YAMUX_MAX_STREAMS as u32;
+            self.quic = Some(quic);
+        }
+    }
 }
 
 /// Errors that might happen during network creation.
 #[derive(Debug, Error)]
 pub enum CreationError {
-    /// Circuit relay client error.
+    /// Circuit relay listen address doesn't specify relay server peer ID.
     #[error("Expected relay server node.")]
     RelayServerExpected,
     /// I/O error.
@@ -403,6 +451,9 @@ where
         gossipsub,
         local_records_provider,
         yamux_config,
+        quic,
+        relay_server,
+        mdns,
         allow_non_global_addresses_in_dht,
         initial_random_query_interval,
         known_peers_registry,
@@ -414,6 +465,7 @@ where
         max_pending_outgoing_connections,
         temporary_bans_cache_size,
         temporary_ban_backoff,
+        peer_reputation,
         libp2p_metrics,
         metrics,
         protocol_version,
@@ -423,6 +475,19 @@ where
     } = config;
     let local_peer_id = peer_id(&keypair);
 
+    // Relay server must be known to make a reservation with it
+    for address in &listen_on {
+        let mut previous_protocol = None;
+        for protocol in address.iter() {
+            if matches!(protocol, Protocol::P2pCircuit)
+                && !matches!(previous_protocol, Some(Protocol::P2p(_)))
+            {
+                return Err(CreationError::RelayServerExpected);
+            }
+            previous_protocol.replace(protocol);
+        }
+    }
+
     info!(
         %allow_non_global_addresses_in_dht,
         peer_id = %local_peer_id,
@@ -452,6 +517,11 @@ where
         "Autonat boot delay set."
     );
 
+    let (relay_transport, relay_client) = relay::client::new(local_peer_id);
+    let mdns = mdns
+        .map(|mdns_config| Mdns::new(mdns_config, local_peer_id))
+        .transpose()?;
+
     let mut behaviour = Behavior::new(BehaviorConfig {
         peer_id: local_peer_id,
         identify,
@@ -475,6 +545,9 @@ where
             local_peer_id,
             servers: bootstrap_addresses.clone(),
         },
+        relay_server,
+        relay_client,
+        mdns,
     });
 
     match (kademlia_mode, external_addresses.is_empty()) {
@@ -494,6 +567,10 @@ where
         temporary_ban_backoff,
     )));
 
+    // Addresses of peers discovered using mDNS are dialed even if non-global addresses are not
+    // allowed otherwise
+    let local_peers = LocalPeers::default();
+
     let mut swarm = SwarmBuilder::with_existing_identity(keypair)
         .with_tokio()
         .with_other_transport(|keypair| {
@@ -501,8 +578,11 @@ where
                 allow_non_global_addresses_in_dht,
                 keypair,
                 Arc::clone(&temporary_bans),
+                Arc::clone(&local_peers),
                 timeout,
                 yamux_config,
+                quic,
+                relay_transport,
             )?)
         })
         .map_err(|error| CreationError::TransportCreationError(error.into()))?
@@ -526,10 +606,26 @@ where
 
             let addr_string = addr.to_string();
             // Listen on random port if specified is already occupied
-            if let Some(Protocol::Tcp(_port)) = addr.pop() {
-                info!("Failed to listen on {addr_string} ({error}), falling back to random port");
-                addr.push(Protocol::Tcp(0));
-                swarm.listen_on(addr)?;
+            match addr.pop() {
+                Some(Protocol::Tcp(_port)) => {
+                    info!(
+                        "Failed to listen on {addr_string} ({error}), falling back to random port"
+                    );
+                    addr.push(Protocol::Tcp(0));
+                    swarm.listen_on(addr)?;
+                }
+                Some(Protocol::QuicV1) => {
+                    if let Some(Protocol::Udp(_port)) = addr.pop() {
+                        info!(
+                            "Failed to listen on {addr_string} ({error}), falling back to random \
+                            port"
+                        );
+                        addr.push(Protocol::Udp(0));
+                        addr.push(Protocol::QuicV1);
+                        swarm.listen_on(addr)?;
+                    }
+                }
+                _ => {}
             }
         }
     }
@@ -548,7 +644,13 @@ where
         max_pending_outgoing_connections,
     );
 
-    let shared = Arc::new(Shared::new(local_peer_id, command_sender, rate_limiter));
+    let shared = Arc::new(Shared::new(
+        local_peer_id,
+        command_sender,
+        rate_limiter,
+        PeerReputation::new(peer_reputation),
+        local_peers,
+    ));
     let shared_weak = Arc::downgrade(&shared);
 
     let node = Node::new(shared);
@@ -1,9 +1,17 @@
+#[cfg(test)]
+mod tests;
+
+use crate::behavior::persistent_parameters::remove_p2p_suffix;
 use crate::constructor::temporary_bans::TemporaryBans;
+use crate::shared::LocalPeers;
 use libp2p::core::multiaddr::{Multiaddr, Protocol};
 use libp2p::core::muxing::StreamMuxerBox;
 use libp2p::core::transport::{Boxed, DialOpts, ListenerId, TransportError, TransportEvent};
 use libp2p::core::Transport;
 use libp2p::dns::tokio::Transport as TokioTransport;
+use libp2p::quic::tokio::Transport as QuicTransport;
+use libp2p::quic::Config as QuicConfig;
+use libp2p::relay::client::Transport as RelayClientTransport;
 use libp2p::tcp::tokio::Transport as TokioTcpTransport;
 use libp2p::tcp::Config as GenTcpConfig;
 use libp2p::yamux::Config as YamuxConfig;
@@ -17,20 +25,47 @@ use std::time::Duration;
 use tracing::debug;
 
 // Builds the transport stack that LibP2P will communicate over along with a relay client.
+//
+// TCP is always available, QUIC is added in front of it when configured, such that each address is
+// handled by the transport it is meant for. Relay client transport handles `/p2p-circuit`
+// addresses, both for listening through relay server and dialing peers behind relay servers.
 pub(super) fn build_transport(
     allow_non_global_addresses_in_dht: bool,
     keypair: &identity::Keypair,
     temporary_bans: Arc<Mutex<TemporaryBans>>,
+    local_peers: LocalPeers,
     timeout: Duration,
     yamux_config: YamuxConfig,
+    quic_config: Option<QuicConfig>,
+    relay_transport: RelayClientTransport,
 ) -> io::Result<Boxed<(PeerId, StreamMuxerBox)>> {
+    let relay_upgraded = {
+        // Temporary bans apply to peers dialed through relay servers just like to any other peers
+        let wrapped_relay = CustomTransportWrapper::new(
+            relay_transport.map_err(io::Error::other),
+            allow_non_global_addresses_in_dht,
+            Arc::clone(&temporary_bans),
+            Arc::clone(&local_peers),
+        );
+        let noise =
+            noise::Config::new(keypair).expect("Signing libp2p-noise static DH keypair failed.");
+
+        wrapped_relay
+            .upgrade(core::upgrade::Version::V1Lazy)
+            .authenticate(noise)
+            .multiplex(yamux_config.clone())
+            .timeout(timeout)
+            .boxed()
+    };
+
     let wrapped_tcp = {
         let tcp_config = GenTcpConfig::default().nodelay(true);
 
         CustomTransportWrapper::new(
             TokioTcpTransport::new(tcp_config.clone()),
             allow_non_global_addresses_in_dht,
-            temporary_bans.clone(),
+            Arc::clone(&temporary_bans),
+            Arc::clone(&local_peers),
         )
     };
 
@@ -46,7 +81,30 @@ pub(super) fn build_transport(
             .boxed()
     };
 
-    Ok(TokioTransport::system(tcp_upgraded)?.boxed())
+    let transport = match quic_config {
+        Some(mut quic_config) => {
+            quic_config.handshake_timeout = timeout;
+
+            let wrapped_quic = CustomTransportWrapper::new(
+                QuicTransport::new(quic_config),
+                allow_non_global_addresses_in_dht,
+                temporary_bans,
+                local_peers,
+            );
+
+            wrapped_quic
+                .map(|(peer_id, connection), _| (peer_id, StreamMuxerBox::new(connection)))
+                .or_transport(tcp_upgraded)
+                .map(|either_output, _| either_output.into_inner())
+                .boxed()
+        }
+        None => tcp_upgraded,
+    };
+    let transport = relay_upgraded
+        .or_transport(transport)
+        .map(|either_output, _| either_output.into_inner());
+
+    Ok(TokioTransport::system(transport)?.boxed())
 }
 
 #[derive(Debug, Clone)]
@@ -54,6 +112,7 @@ struct CustomTransportWrapper<T> {
     base_transport: T,
     allow_non_global_addresses: bool,
     temporary_bans: Arc<Mutex<TemporaryBans>>,
+    local_peers: LocalPeers,
 }
 
 impl<T> CustomTransportWrapper<T> {
@@ -61,11 +120,32 @@ impl<T> CustomTransportWrapper<T> {
         base_transport: T,
         allow_non_global_addresses: bool,
         temporary_bans: Arc<Mutex<TemporaryBans>>,
+        local_peers: LocalPeers,
     ) -> Self {
         CustomTransportWrapper {
             base_transport,
             allow_non_global_addresses,
             temporary_bans,
+            local_peers,
+        }
+    }
+
+    /// Whether address belongs to a peer discovered on local network using mDNS, such addresses
+    /// are typically non-global
+    fn is_local_peer_address(&self, addr: &Multiaddr) -> bool {
+        let maybe_peer_id = match addr.iter().last() {
+            Some(Protocol::P2p(peer_id)) => Some(peer_id),
+            _ => None,
+        };
+        let addr = remove_p2p_suffix(addr.clone());
+        let is_same_address = |address: &Multiaddr| remove_p2p_suffix(address.clone()) == addr;
+
+        let local_peers = self.local_peers.lock();
+        match maybe_peer_id {
+            Some(peer_id) => local_peers
+                .get(&peer_id)
+                .is_some_and(|addresses| addresses.iter().any(is_same_address)),
+            None => local_peers.values().flatten().any(is_same_address),
         }
     }
 }
@@ -101,13 +181,19 @@ where
 
         match addr_iter.next() {
             Some(Protocol::Ip4(a)) => {
-                if !(self.allow_non_global_addresses || a.is_global()) {
+                if !(self.allow_non_global_addresses
+                    || a.is_global()
+                    || self.is_local_peer_address(&addr))
+                {
                     debug!(?a, "Not dialing non global IP address.",);
                     return Err(TransportError::MultiaddrNotSupported(addr));
                 }
             }
             Some(Protocol::Ip6(a)) => {
-                if !(self.allow_non_global_addresses || a.is_global()) {
+                if !(self.allow_non_global_addresses
+                    || a.is_global()
+                    || self.is_local_peer_address(&addr))
+                {
                     debug!(?a, "Not dialing non global IP address.");
                     return Err(TransportError::MultiaddrNotSupported(addr));
                 }
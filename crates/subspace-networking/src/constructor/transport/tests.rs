@@ -0,0 +1,61 @@
+use crate::constructor::temporary_bans::TemporaryBans;
+use crate::constructor::transport::CustomTransportWrapper;
+use crate::shared::LocalPeers;
+use backoff::ExponentialBackoff;
+use libp2p::core::multiaddr::Protocol;
+use libp2p::core::transport::{DialOpts, PortUse, TransportError};
+use libp2p::core::{Endpoint, Transport};
+use libp2p::tcp::tokio::Transport as TokioTcpTransport;
+use libp2p::tcp::Config as GenTcpConfig;
+use libp2p::{Multiaddr, PeerId};
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+fn dial(transport: &mut CustomTransportWrapper<TokioTcpTransport>, address: Multiaddr) -> bool {
+    let result = transport.dial(
+        address,
+        DialOpts {
+            role: Endpoint::Dialer,
+            port_use: PortUse::New,
+        },
+    );
+
+    !matches!(result, Err(TransportError::MultiaddrNotSupported(_)))
+}
+
+#[tokio::test]
+async fn local_peer_addresses_are_dialed() {
+    let local_peers = LocalPeers::default();
+    let mut transport = CustomTransportWrapper::new(
+        TokioTcpTransport::new(GenTcpConfig::default()),
+        false,
+        Arc::new(Mutex::new(TemporaryBans::new(
+            10,
+            ExponentialBackoff::default(),
+        ))),
+        Arc::clone(&local_peers),
+    );
+    let local_peer_id = PeerId::random();
+    let local_address = "/ip4/192.168.1.2/tcp/30533".parse::<Multiaddr>().unwrap();
+    let other_address = "/ip4/192.168.1.3/tcp/30533".parse::<Multiaddr>().unwrap();
+
+    // Non-global addresses are not dialed by default
+    assert!(!dial(&mut transport, local_address.clone()));
+
+    local_peers
+        .lock()
+        .insert(local_peer_id, vec![local_address.clone()]);
+
+    // Unless they were discovered on local network
+    assert!(dial(&mut transport, local_address.clone()));
+    assert!(dial(
+        &mut transport,
+        local_address.clone().with(Protocol::P2p(local_peer_id))
+    ));
+    // Address must belong to the same peer
+    assert!(!dial(
+        &mut transport,
+        local_address.with(Protocol::P2p(PeerId::random()))
+    ));
+    assert!(!dial(&mut transport, other_address));
+}
@@ -22,6 +22,7 @@ mod behavior;
 mod constructor;
 mod node;
 mod node_runner;
+mod peer_reputation;
 mod protocols;
 
 mod shared;
@@ -35,6 +36,7 @@ pub use crate::node::{
     GetClosestPeersError, Node, SendRequestError, SubscribeError, TopicSubscription,
 };
 pub use crate::node_runner::NodeRunner;
+pub use crate::peer_reputation::{PeerReputationConfig, ReputationChange};
 pub use constructor::{
     construct, peer_id, Config, CreationError, KademliaMode, LocalRecordProvider,
 };
@@ -45,6 +47,10 @@ pub use protocols::request_response::handlers::generic_request_handler::{
 pub use protocols::request_response::handlers::piece_by_index::{
     PieceByIndexRequest, PieceByIndexRequestHandler, PieceByIndexResponse,
 };
+pub use protocols::request_response::handlers::pieces_by_index::{
+    PiecesByIndexRequest, PiecesByIndexRequestHandler, PiecesByIndexResponse,
+    MAX_PIECES_PER_REQUEST,
+};
 pub use protocols::request_response::handlers::segment_header::{
     SegmentHeaderBySegmentIndexesRequestHandler, SegmentHeaderRequest, SegmentHeaderResponse,
 };
@@ -1,5 +1,9 @@
+use crate::peer_reputation::ReputationChange;
 use crate::protocols::request_response::handlers::generic_request_handler::GenericRequest;
 use crate::protocols::request_response::request_response_factory;
+use crate::protocols::request_response::request_response_factory::{
+    OutboundFailure, RequestFailure,
+};
 use crate::shared::{Command, CreatedSubscription, PeerDiscovered, Shared};
 use crate::utils::multihash::Multihash;
 use crate::utils::HandlerFn;
@@ -14,6 +18,7 @@ use parity_scale_codec::Decode;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
+use std::time::Instant;
 use thiserror::Error;
 use tokio::sync::OwnedSemaphorePermit;
 use tracing::{debug, error, trace};
@@ -246,7 +251,7 @@ impl From<oneshot::Canceled> for BootstrapError {
 #[derive(Debug, Clone)]
 #[must_use = "Node doesn't do anything if dropped"]
 pub struct Node {
-    shared: Arc<Shared>,
+    pub(crate) shared: Arc<Shared>,
 }
 
 impl Node {
@@ -376,9 +381,41 @@ impl Node {
 
         self.shared.command_sender.clone().send(command).await?;
 
-        let result = result_receiver.await??;
+        let request_start = Instant::now();
+        let result = result_receiver
+            .await?
+            .map_err(SendRequestError::from)
+            .and_then(|response| {
+                Request::Response::decode(&mut response.as_slice()).map_err(SendRequestError::from)
+            });
+
+        let reputation_change = match &result {
+            Ok(_) => {
+                if request_start.elapsed()
+                    > self.shared.peer_reputation.lock().slow_response_threshold()
+                {
+                    ReputationChange::SlowResponse
+                } else {
+                    ReputationChange::SuccessfulResponse
+                }
+            }
+            Err(SendRequestError::ProtocolFailure(RequestFailure::Network(
+                OutboundFailure::Timeout,
+            ))) => ReputationChange::Timeout,
+            Err(SendRequestError::IncorrectResponseFormat(_)) => ReputationChange::InvalidResponse,
+            Err(SendRequestError::ProtocolFailure(
+                RequestFailure::UnknownProtocol
+                | RequestFailure::Network(OutboundFailure::UnsupportedProtocols),
+            ))
+            | Err(SendRequestError::SendCommand(_) | SendRequestError::NodeRunnerDropped) => {
+                // Not peer's fault, unsupported protocols are handled by the caller
+                return result;
+            }
+            Err(SendRequestError::ProtocolFailure(_)) => ReputationChange::FailedRequest,
+        };
+        self.report_peer(peer_id, reputation_change);
 
-        Request::Response::decode(&mut result.as_slice()).map_err(Into::into)
+        result
     }
 
     /// Sends the generic request to the peer and awaits the result.
@@ -500,6 +537,35 @@ impl Node {
         self.shared.external_addresses.lock().clone()
     }
 
+    /// Change reputation of the peer, for instance after receiving invalid piece from it.
+    ///
+    /// Outcomes of requests sent with [`Node::send_generic_request()`] are reported automatically.
+    pub fn report_peer(&self, peer_id: PeerId, reputation_change: ReputationChange) {
+        let score = self
+            .shared
+            .peer_reputation
+            .lock()
+            .report(peer_id, reputation_change);
+
+        trace!(%peer_id, ?reputation_change, %score, "Peer reputation changed");
+    }
+
+    /// Current reputation score of the peer, positive is good, negative is bad and `0` is neutral
+    /// (also used for unknown peers).
+    pub fn peer_score(&self, peer_id: &PeerId) -> f64 {
+        self.shared.peer_reputation.lock().score(peer_id)
+    }
+
+    /// Peers discovered on local network using mDNS, only populated when mDNS is enabled.
+    pub fn local_peers(&self) -> Vec<PeerId> {
+        self.shared.local_peers.lock().keys().copied().collect()
+    }
+
+    /// Whether peer was discovered on local network using mDNS.
+    pub fn is_local_peer(&self, peer_id: &PeerId) -> bool {
+        self.shared.local_peers.lock().contains_key(peer_id)
+    }
+
     /// Callback is called when node starts listening on new address.
     pub fn on_new_listener(&self, callback: HandlerFn<Multiaddr>) -> HandlerId {
         self.shared.handlers.new_listener.add(callback)
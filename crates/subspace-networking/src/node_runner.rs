@@ -5,11 +5,12 @@ use crate::behavior::{Behavior, Event};
 use crate::constructor;
 use crate::constructor::temporary_bans::TemporaryBans;
 use crate::constructor::LocalOnlyRecordStore;
+use crate::peer_reputation::run_peer_reputation_persistence;
 use crate::protocols::request_response::request_response_factory::{
     Event as RequestResponseEvent, IfDisconnected,
 };
 use crate::shared::{Command, CreatedSubscription, PeerDiscovered, Shared};
-use crate::utils::{is_global_address_or_dns, strip_peer_id, SubspaceMetrics};
+use crate::utils::{is_global_address_or_dns, is_relayed_address, strip_peer_id, SubspaceMetrics};
 use async_mutex::Mutex as AsyncMutex;
 use bytes::Bytes;
 use event_listener_primitives::HandlerId;
@@ -18,6 +19,7 @@ use futures::future::Fuse;
 use futures::{FutureExt, StreamExt};
 use libp2p::autonat::{Event as AutonatEvent, NatStatus, OutboundProbeEvent};
 use libp2p::core::ConnectedPoint;
+use libp2p::dcutr::Event as DcutrEvent;
 use libp2p::gossipsub::{Event as GossipsubEvent, TopicHash};
 use libp2p::identify::Event as IdentifyEvent;
 use libp2p::kad::{
@@ -26,8 +28,11 @@ use libp2p::kad::{
     InboundRequest, PeerRecord, ProgressStep, PutRecordOk, QueryId, QueryResult, Quorum, Record,
     RecordKey,
 };
+use libp2p::mdns::Event as MdnsEvent;
 use libp2p::metrics::{Metrics, Recorder};
 use libp2p::multiaddr::Protocol;
+use libp2p::relay::client::Event as RelayClientEvent;
+use libp2p::relay::Event as RelayServerEvent;
 use libp2p::swarm::dial_opts::DialOpts;
 use libp2p::swarm::{DialError, SwarmEvent};
 use libp2p::{Multiaddr, PeerId, Swarm, TransportError};
@@ -245,6 +250,9 @@ where
 
         self.bootstrap().await;
 
+        let mut peer_reputation_persistence =
+            Box::pin(run_peer_reputation_persistence(self.shared_weak.clone()).fuse());
+
         loop {
             futures::select! {
                 _ = &mut self.random_query_timeout => {
@@ -273,6 +281,9 @@ where
                 _ = self.known_peers_registry.run().fuse() => {
                     trace!("Network parameters registry runner exited.")
                 },
+                _ = &mut peer_reputation_persistence => {
+                    trace!("Peer reputation persistence exited.")
+                },
                 _ = &mut self.periodical_tasks_interval => {
                     self.handle_periodical_tasks().await;
 
@@ -408,6 +419,25 @@ where
         }
 
         self.log_kademlia_stats();
+
+        if let Some(shared) = self.shared_weak.upgrade() {
+            let peers_to_evict = shared
+                .peer_reputation
+                .lock()
+                .peers_to_evict(self.swarm.connected_peers());
+
+            for peer_id in peers_to_evict {
+                if self.reserved_peers.contains_key(&peer_id) {
+                    continue;
+                }
+
+                debug!(%peer_id, "Evicting peer with poor reputation");
+
+                // Temporary ban prevents peer from being dialed again right away
+                self.temporary_bans.lock().create_or_extend(&peer_id);
+                let _ = self.swarm.disconnect_peer_id(peer_id);
+            }
+        }
     }
 
     fn handle_random_query_interval(&mut self) {
@@ -468,7 +498,26 @@ where
             SwarmEvent::Behaviour(Event::Autonat(event)) => {
                 self.handle_autonat_event(event).await;
             }
+            SwarmEvent::Behaviour(Event::RelayServer(event)) => {
+                self.handle_relay_server_event(event);
+            }
+            SwarmEvent::Behaviour(Event::RelayClient(event)) => {
+                self.handle_relay_client_event(event);
+            }
+            SwarmEvent::Behaviour(Event::Dcutr(event)) => {
+                self.handle_dcutr_event(event);
+            }
+            SwarmEvent::Behaviour(Event::Mdns(event)) => {
+                self.handle_mdns_event(event);
+            }
             SwarmEvent::NewListenAddr { address, .. } => {
+                // Relayed address is reachable by other peers as long as reservation is active,
+                // advertise it such that peers behind NAT can serve requests too
+                if is_relayed_address(&address) {
+                    debug!(%address, "Listening through relay server");
+                    self.swarm.add_external_address(address.clone());
+                }
+
                 let shared = match self.shared_weak.upgrade() {
                     Some(shared) => shared,
                     None => {
@@ -478,6 +527,19 @@ where
                 shared.listeners.lock().push(address.clone());
                 shared.handlers.new_listener.call_simple(&address);
             }
+            SwarmEvent::ExpiredListenAddr { address, .. } => {
+                if is_relayed_address(&address) {
+                    debug!(%address, "No longer listening through relay server");
+                    self.swarm.remove_external_address(&address);
+                }
+
+                if let Some(shared) = self.shared_weak.upgrade() {
+                    shared
+                        .listeners
+                        .lock()
+                        .retain(|listener| listener != &address);
+                }
+            }
             SwarmEvent::ConnectionEstablished {
                 peer_id,
                 endpoint,
@@ -544,11 +606,12 @@ where
                 }
 
                 if let Some(metrics) = self.metrics.as_mut() {
-                    metrics.inc_established_connections()
+                    metrics.inc_established_connections(endpoint.get_remote_address())
                 }
             }
             SwarmEvent::ConnectionClosed {
                 peer_id,
+                endpoint,
                 num_established,
                 cause,
                 ..
@@ -586,7 +649,7 @@ where
                 }
 
                 if let Some(metrics) = self.metrics.as_mut() {
-                    metrics.dec_established_connections()
+                    metrics.dec_established_connections(endpoint.get_remote_address())
                 };
             }
             SwarmEvent::OutgoingConnectionError { peer_id, error, .. } => {
@@ -1241,6 +1304,120 @@ where
         }
     }
 
+    fn handle_relay_server_event(&mut self, event: RelayServerEvent) {
+        trace!(?event, "Relay server event received.");
+
+        match event {
+            RelayServerEvent::ReservationReqAccepted { src_peer_id, .. } => {
+                debug!(%src_peer_id, "Relay reservation accepted");
+            }
+            RelayServerEvent::CircuitReqAccepted {
+                src_peer_id,
+                dst_peer_id,
+            } => {
+                debug!(%src_peer_id, %dst_peer_id, "Relay circuit accepted");
+            }
+            _ => {
+                // Other events are only interesting for debugging purposes
+            }
+        }
+    }
+
+    fn handle_relay_client_event(&mut self, event: RelayClientEvent) {
+        match event {
+            RelayClientEvent::ReservationReqAccepted {
+                relay_peer_id,
+                renewal,
+                ..
+            } => {
+                debug!(%relay_peer_id, %renewal, "Reservation with relay server accepted");
+            }
+            event => {
+                trace!(?event, "Relay client event received.");
+            }
+        }
+    }
+
+    fn handle_dcutr_event(&mut self, event: DcutrEvent) {
+        let DcutrEvent {
+            remote_peer_id,
+            result,
+        } = event;
+
+        match result {
+            Ok(connection_id) => {
+                debug!(
+                    %remote_peer_id,
+                    ?connection_id,
+                    "Relayed connection upgraded to direct connection"
+                );
+            }
+            Err(error) => {
+                debug!(%remote_peer_id, %error, "Failed to upgrade relayed connection");
+            }
+        }
+    }
+
+    fn handle_mdns_event(&mut self, event: MdnsEvent) {
+        let shared = match self.shared_weak.upgrade() {
+            Some(shared) => shared,
+            None => {
+                return;
+            }
+        };
+
+        match event {
+            MdnsEvent::Discovered(discovered) => {
+                let mut new_addresses = HashMap::<PeerId, Vec<Multiaddr>>::new();
+                {
+                    let mut local_peers = shared.local_peers.lock();
+
+                    for (peer_id, address) in discovered {
+                        let addresses = local_peers.entry(peer_id).or_default();
+                        if !addresses.contains(&address) {
+                            addresses.push(address.clone());
+                            new_addresses.entry(peer_id).or_default().push(address);
+                        }
+                    }
+                }
+
+                for (peer_id, addresses) in new_addresses {
+                    debug!(%peer_id, ?addresses, "Peer discovered on local network");
+
+                    if self.swarm.is_connected(&peer_id) {
+                        continue;
+                    }
+
+                    // Local peers are dialed right away, Kademlia will learn about them from
+                    // identify protocol once connected
+                    if let Err(error) = self
+                        .swarm
+                        .dial(DialOpts::peer_id(peer_id).addresses(addresses).build())
+                    {
+                        debug!(%peer_id, %error, "Failed to dial peer discovered on local network");
+                    }
+                }
+            }
+            MdnsEvent::Expired(expired) => {
+                let mut local_peers = shared.local_peers.lock();
+
+                for (peer_id, address) in expired {
+                    if let Entry::Occupied(mut entry) = local_peers.entry(peer_id) {
+                        entry
+                            .get_mut()
+                            .retain(|existing_address| existing_address != &address);
+
+                        if entry.get().is_empty() {
+                            debug!(%peer_id, "Peer is no longer present on local network");
+
+                            entry.remove();
+                        }
+                    }
+                }
+            }
+        }
+    }
+
     fn handle_command(&mut self, command: Command) {
         match command {
             Command::GetValue {
@@ -1503,6 +1680,12 @@ where
                 SwarmEvent::Behaviour(Event::Gossipsub(gossipsub_event)) => {
                     metrics.record(gossipsub_event);
                 }
+                SwarmEvent::Behaviour(Event::RelayServer(relay_server_event)) => {
+                    metrics.record(relay_server_event);
+                }
+                SwarmEvent::Behaviour(Event::Dcutr(dcutr_event)) => {
+                    metrics.record(dcutr_event);
+                }
                 // TODO: implement in the upstream repository
                 // SwarmEvent::Behaviour(Event::RequestResponse(request_response_event)) => {
                 //     self.metrics.record(request_response_event);
@@ -0,0 +1,318 @@
+//! Reputation of peers based on outcomes of requests sent to them.
+//!
+//! Every request outcome changes peer's score (see [`ReputationChange`]), scores decay towards
+//! neutral over time such that temporary issues are eventually forgiven. Scores are used to pick
+//! peers for piece requests and to disconnect peers that misbehave consistently.
+
+#[cfg(test)]
+mod tests;
+
+use crate::shared::Shared;
+use crate::utils::AsyncJoinOnDrop;
+use libp2p::PeerId;
+use parity_scale_codec::{Decode, Encode};
+use schnellru::{ByLength, LruMap};
+use std::path::Path;
+use std::sync::Weak;
+use std::time::{Duration, SystemTime};
+use std::{fs, io};
+use tracing::{debug, error, warn};
+
+/// Max number of peers to keep reputation for.
+const PEER_REPUTATION_CACHE_SIZE: u32 = 1_000;
+/// Score will be halved after this much time.
+const SCORE_HALF_LIFE: Duration = Duration::from_secs(3600);
+/// Responses that took longer than this are considered slow.
+const SLOW_RESPONSE_THRESHOLD: Duration = Duration::from_secs(5);
+/// Peers with score below this are disconnected and temporarily banned.
+const EVICTION_THRESHOLD: f64 = -100.0;
+/// Min possible score.
+const MIN_SCORE: f64 = -1_000.0;
+/// Max possible score, low enough for misbehavior to quickly outweigh good history.
+const MAX_SCORE: f64 = 100.0;
+/// Changed scores are written to disk at most this often.
+const PERSISTENCE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Change of peer reputation caused by an outcome of request sent to it.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ReputationChange {
+    /// Peer returned a response in time
+    SuccessfulResponse,
+    /// Peer returned a response, but it took longer than
+    /// [`PeerReputationConfig::slow_response_threshold`]
+    SlowResponse,
+    /// Request to peer timed out
+    Timeout,
+    /// Request to peer failed for reasons other than timeout
+    FailedRequest,
+    /// Peer returned a response that can't be decoded or failed validation (invalid piece, for
+    /// instance)
+    InvalidResponse,
+}
+
+impl ReputationChange {
+    fn score_delta(self) -> f64 {
+        match self {
+            Self::SuccessfulResponse => 1.0,
+            Self::SlowResponse => -2.0,
+            Self::FailedRequest => -5.0,
+            Self::Timeout => -10.0,
+            Self::InvalidResponse => -100.0,
+        }
+    }
+}
+
+/// Configuration for [`PeerReputation`].
+#[derive(Debug, Clone)]
+pub struct PeerReputationConfig {
+    /// Path to the file where scores are persisted, `None` disables persistence.
+    pub path: Option<Box<Path>>,
+    /// Max number of peers to keep reputation for.
+    pub cache_size: u32,
+    /// Score is halved after this much time passes without changes.
+    pub score_half_life: Duration,
+    /// Successful responses that took longer than this are considered slow.
+    pub slow_response_threshold: Duration,
+    /// Connected peers with score below this threshold are disconnected and temporarily banned.
+    pub eviction_threshold: f64,
+}
+
+impl Default for PeerReputationConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            cache_size: PEER_REPUTATION_CACHE_SIZE,
+            score_half_life: SCORE_HALF_LIFE,
+            slow_response_threshold: SLOW_RESPONSE_THRESHOLD,
+            eviction_threshold: EVICTION_THRESHOLD,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+struct PeerScore {
+    score: f64,
+    updated_at: SystemTime,
+}
+
+impl PeerScore {
+    /// Score decayed to specified point in time
+    fn score_at(&self, now: SystemTime, half_life: Duration) -> f64 {
+        let elapsed = now
+            .duration_since(self.updated_at)
+            .unwrap_or_default()
+            .as_secs_f64();
+
+        self.score * 0.5_f64.powf(elapsed / half_life.as_secs_f64())
+    }
+}
+
+#[derive(Debug, Encode, Decode)]
+struct EncodablePeerScore {
+    peer_id: Vec<u8>,
+    /// Score as bits of `f64`
+    score: u64,
+    /// Time of last update as Unix timestamp in seconds
+    updated_at: u64,
+}
+
+#[derive(Debug, Default, Encode, Decode)]
+struct EncodablePeerReputation {
+    peers: Vec<EncodablePeerScore>,
+}
+
+/// Reputation of peers, see module documentation for details.
+#[derive(Debug)]
+pub(crate) struct PeerReputation {
+    scores: LruMap<PeerId, PeerScore>,
+    need_saving: bool,
+    config: PeerReputationConfig,
+}
+
+impl Drop for PeerReputation {
+    fn drop(&mut self) {
+        if self.need_saving {
+            if let Some(path) = &self.config.path {
+                if let Err(error) = write_to_file(path, &self.encode()) {
+                    warn!(%error, "Failed to write peer reputation to disk");
+                }
+            }
+        }
+    }
+}
+
+impl PeerReputation {
+    /// Create new instance, reading previously persisted scores if there are any
+    pub(crate) fn new(config: PeerReputationConfig) -> Self {
+        let mut scores = LruMap::new(ByLength::new(config.cache_size));
+
+        if let Some(path) = &config.path {
+            match fs::read(path) {
+                Ok(bytes) => match EncodablePeerReputation::decode(&mut bytes.as_slice()) {
+                    Ok(encodable_peer_reputation) => {
+                        for peer_score in encodable_peer_reputation.peers {
+                            let peer_id = match PeerId::from_bytes(&peer_score.peer_id) {
+                                Ok(peer_id) => peer_id,
+                                Err(error) => {
+                                    debug!(%error, "Failed to decode peer ID, skipping entry");
+                                    continue;
+                                }
+                            };
+
+                            scores.insert(
+                                peer_id,
+                                PeerScore {
+                                    score: f64::from_bits(peer_score.score)
+                                        .clamp(MIN_SCORE, MAX_SCORE),
+                                    updated_at: SystemTime::UNIX_EPOCH
+                                        + Duration::from_secs(peer_score.updated_at),
+                                },
+                            );
+                        }
+                    }
+                    Err(error) => {
+                        warn!(
+                            %error,
+                            path = %path.display(),
+                            "Failed to decode peer reputation, ignoring"
+                        );
+                    }
+                },
+                Err(error) => {
+                    debug!(%error, path = %path.display(), "Failed to read peer reputation");
+                }
+            }
+        }
+
+        Self {
+            scores,
+            need_saving: false,
+            config,
+        }
+    }
+
+    /// Successful responses that took longer than this are considered slow
+    pub(crate) fn slow_response_threshold(&self) -> Duration {
+        self.config.slow_response_threshold
+    }
+
+    /// Current score of the peer, `0` for unknown peers
+    pub(crate) fn score(&self, peer_id: &PeerId) -> f64 {
+        self.scores
+            .peek(peer_id)
+            .map(|peer_score| peer_score.score_at(SystemTime::now(), self.config.score_half_life))
+            .unwrap_or_default()
+    }
+
+    /// Apply reputation change to the peer, returns new score
+    pub(crate) fn report(&mut self, peer_id: PeerId, change: ReputationChange) -> f64 {
+        let now = SystemTime::now();
+        let half_life = self.config.score_half_life;
+        let peer_score = self.scores.get_or_insert(peer_id, || PeerScore {
+            score: 0.0,
+            updated_at: now,
+        });
+
+        let score = match peer_score {
+            Some(peer_score) => {
+                peer_score.score = (peer_score.score_at(now, half_life) + change.score_delta())
+                    .clamp(MIN_SCORE, MAX_SCORE);
+                peer_score.updated_at = now;
+                peer_score.score
+            }
+            None => {
+                // Can only happen with zero cache size
+                return 0.0;
+            }
+        };
+
+        self.need_saving = true;
+
+        score
+    }
+
+    /// Peers out of provided ones that have score below eviction threshold
+    pub(crate) fn peers_to_evict<'a, I>(&self, peer_ids: I) -> Vec<PeerId>
+    where
+        I: IntoIterator<Item = &'a PeerId>,
+    {
+        peer_ids
+            .into_iter()
+            .filter(|peer_id| self.score(peer_id) < self.config.eviction_threshold)
+            .copied()
+            .collect()
+    }
+
+    /// Returns encoded scores and path to write them to, if persistence is enabled and there were
+    /// changes since last call.
+    pub(crate) fn take_changes(&mut self) -> Option<(Box<Path>, Vec<u8>)> {
+        if !self.need_saving {
+            return None;
+        }
+        let path = self.config.path.clone()?;
+
+        self.need_saving = false;
+
+        Some((path, self.encode()))
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        EncodablePeerReputation {
+            peers: self
+                .scores
+                .iter()
+                .map(|(peer_id, peer_score)| EncodablePeerScore {
+                    peer_id: peer_id.to_bytes(),
+                    score: peer_score.score.to_bits(),
+                    updated_at: peer_score
+                        .updated_at
+                        .duration_since(SystemTime::UNIX_EPOCH)
+                        .expect("Never before Unix epoch; qed")
+                        .as_secs(),
+                })
+                .collect(),
+        }
+        .encode()
+    }
+}
+
+/// Periodically write changed scores to disk, exits once node is dropped
+pub(crate) async fn run_peer_reputation_persistence(shared_weak: Weak<Shared>) {
+    loop {
+        tokio::time::sleep(PERSISTENCE_INTERVAL).await;
+
+        let maybe_peer_reputation_changes = match shared_weak.upgrade() {
+            Some(shared) => shared.peer_reputation.lock().take_changes(),
+            None => {
+                return;
+            }
+        };
+        if let Some((path, bytes)) = maybe_peer_reputation_changes {
+            persist_peer_reputation(path, bytes).await;
+        }
+    }
+}
+
+/// Write encoded scores to disk in background
+async fn persist_peer_reputation(path: Box<Path>, bytes: Vec<u8>) {
+    let write_fut = AsyncJoinOnDrop::new(tokio::task::spawn_blocking(move || {
+        write_to_file(&path, &bytes)
+    }));
+
+    match write_fut.await {
+        Ok(Ok(())) => {}
+        Ok(Err(error)) => {
+            warn!(%error, "Failed to write peer reputation to disk");
+        }
+        Err(error) => {
+            error!(%error, "Failed to write peer reputation");
+        }
+    }
+}
+
+/// Write through temporary file, such that file on disk is never partially written
+fn write_to_file(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, bytes)?;
+    fs::rename(tmp_path, path)
+}
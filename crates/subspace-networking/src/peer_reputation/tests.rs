@@ -0,0 +1,76 @@
+use crate::peer_reputation::{
+    PeerReputation, PeerReputationConfig, PeerScore, ReputationChange, MAX_SCORE, MIN_SCORE,
+};
+use libp2p::PeerId;
+use std::time::{Duration, SystemTime};
+
+#[test]
+fn score_changes() {
+    let mut peer_reputation = PeerReputation::new(PeerReputationConfig::default());
+    let peer_id = PeerId::random();
+
+    assert_eq!(peer_reputation.score(&PeerId::random()), 0.0);
+
+    peer_reputation.report(peer_id, ReputationChange::SuccessfulResponse);
+    assert!(peer_reputation.score(&peer_id) > 0.0);
+
+    peer_reputation.report(peer_id, ReputationChange::Timeout);
+    assert!(peer_reputation.score(&peer_id) < 0.0);
+    assert!(peer_reputation.peers_to_evict(&[peer_id]).is_empty());
+
+    peer_reputation.report(peer_id, ReputationChange::InvalidResponse);
+    assert_eq!(peer_reputation.peers_to_evict(&[peer_id]), vec![peer_id]);
+
+    for _ in 0..100 {
+        peer_reputation.report(peer_id, ReputationChange::InvalidResponse);
+    }
+    assert!(peer_reputation.score(&peer_id) >= MIN_SCORE);
+}
+
+#[test]
+fn score_decay() {
+    let half_life = Duration::from_secs(60);
+    let now = SystemTime::now();
+    let peer_score = PeerScore {
+        score: MAX_SCORE,
+        updated_at: now - half_life * 2,
+    };
+
+    let decayed_score = peer_score.score_at(now, half_life);
+    assert!((decayed_score - MAX_SCORE / 4.0).abs() < 0.001);
+
+    // Clock going backwards doesn't change the score
+    assert_eq!(
+        peer_score.score_at(now - half_life * 3, half_life),
+        MAX_SCORE
+    );
+}
+
+#[test]
+fn persistence() {
+    let directory = tempfile::tempdir().unwrap();
+    let config = PeerReputationConfig {
+        path: Some(
+            directory
+                .path()
+                .join("peer_reputation.bin")
+                .into_boxed_path(),
+        ),
+        ..PeerReputationConfig::default()
+    };
+    let good_peer_id = PeerId::random();
+    let bad_peer_id = PeerId::random();
+
+    {
+        let mut peer_reputation = PeerReputation::new(config.clone());
+        assert!(peer_reputation.take_changes().is_none());
+
+        peer_reputation.report(good_peer_id, ReputationChange::SuccessfulResponse);
+        peer_reputation.report(bad_peer_id, ReputationChange::InvalidResponse);
+        // Written on drop
+    }
+
+    let peer_reputation = PeerReputation::new(config);
+    assert!(peer_reputation.score(&good_peer_id) > 0.0);
+    assert!(peer_reputation.score(&bad_peer_id) < 0.0);
+}
@@ -0,0 +1,102 @@
+//! Helper for incoming batched pieces requests.
+//!
+//! Handle (i.e. answer) incoming requests for multiple pieces at once from a remote peer received
+//! via `RequestResponsesBehaviour` with generic [`GenericRequestHandler`].
+//!
+//! Peers that don't support this protocol yet are still served with
+//! [`PieceByIndexRequest`](super::piece_by_index::PieceByIndexRequest), one piece per request.
+
+#[cfg(test)]
+mod tests;
+
+use super::generic_request_handler::{GenericRequest, GenericRequestHandler};
+use parity_scale_codec::{Decode, Encode};
+use subspace_core_primitives::{Piece, PieceIndex};
+
+/// Max number of pieces that can be requested at once, such that response fits into max response
+/// size of request-response protocol.
+pub const MAX_PIECES_PER_REQUEST: usize = 8;
+
+/// Pieces-by-index protocol request.
+#[derive(Debug, Clone, Eq, PartialEq, Encode, Decode)]
+pub struct PiecesByIndexRequest {
+    /// Request key - piece indices, at most [`MAX_PIECES_PER_REQUEST`]
+    pub piece_indices: Vec<PieceIndex>,
+}
+
+impl GenericRequest for PiecesByIndexRequest {
+    const PROTOCOL_NAME: &'static str = "/subspace/pieces-by-index/0.1.0";
+    const LOG_TARGET: &'static str = "pieces-by-index-request-response-handler";
+    type Response = PiecesByIndexResponse;
+}
+
+/// Pieces-by-index protocol response.
+#[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
+pub struct PiecesByIndexResponse {
+    /// Bitmap with a bit for every requested piece (least significant bit first), set bit means
+    /// piece is not available.
+    pub missing: Vec<u8>,
+    /// Available pieces in the same order as requested.
+    pub pieces: Vec<Piece>,
+}
+
+impl PiecesByIndexResponse {
+    /// Create response from pieces in the same order as requested piece indices, `None` means
+    /// piece is not available.
+    pub fn from_pieces(maybe_pieces: Vec<Option<Piece>>) -> Self {
+        let mut missing = vec![0; maybe_pieces.len().div_ceil(u8::BITS as usize)];
+        let mut pieces = Vec::with_capacity(maybe_pieces.len());
+
+        for (position, maybe_piece) in maybe_pieces.into_iter().enumerate() {
+            match maybe_piece {
+                Some(piece) => {
+                    pieces.push(piece);
+                }
+                None => {
+                    missing[position / u8::BITS as usize] |= 1 << (position % u8::BITS as usize);
+                }
+            }
+        }
+
+        Self { missing, pieces }
+    }
+
+    /// Pieces in the same order as requested piece indices, `None` is returned for pieces that are
+    /// not available.
+    ///
+    /// Returns `None` if response doesn't match the number of requested pieces.
+    pub fn into_pieces(self, num_requested: usize) -> Option<Vec<Option<Piece>>> {
+        let Self { missing, pieces } = self;
+
+        if missing.len() != num_requested.div_ceil(u8::BITS as usize) {
+            return None;
+        }
+
+        let is_missing = |position: usize| {
+            missing[position / u8::BITS as usize] & (1 << (position % u8::BITS as usize)) != 0
+        };
+        let num_available = (0..num_requested)
+            .filter(|&position| !is_missing(position))
+            .count();
+        if num_available != pieces.len() {
+            return None;
+        }
+
+        let mut pieces = pieces.into_iter();
+
+        Some(
+            (0..num_requested)
+                .map(|position| {
+                    if is_missing(position) {
+                        None
+                    } else {
+                        pieces.next()
+                    }
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Create a new pieces-by-index request handler.
+pub type PiecesByIndexRequestHandler = GenericRequestHandler<PiecesByIndexRequest>;
@@ -0,0 +1,28 @@
+use crate::protocols::request_response::handlers::pieces_by_index::PiecesByIndexResponse;
+use subspace_core_primitives::Piece;
+
+#[test]
+fn response_pieces_roundtrip() {
+    let mut piece = Piece::default();
+    piece.as_mut()[0] = 1;
+    let maybe_pieces = (0..10)
+        .map(|position| (position % 3 == 0).then(|| piece.clone()))
+        .collect::<Vec<_>>();
+
+    let response = PiecesByIndexResponse::from_pieces(maybe_pieces.clone());
+    assert_eq!(response.missing.len(), 2);
+    assert_eq!(response.pieces.len(), 4);
+
+    assert_eq!(response.clone().into_pieces(10), Some(maybe_pieces));
+    // Number of requested pieces doesn't match bitmap
+    assert_eq!(response.clone().into_pieces(20), None);
+    // Number of pieces doesn't match bitmap
+    assert_eq!(
+        PiecesByIndexResponse {
+            pieces: Vec::new(),
+            ..response
+        }
+        .into_pieces(10),
+        None
+    );
+}
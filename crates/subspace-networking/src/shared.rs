@@ -1,6 +1,7 @@
 //! Data structures shared between node and node runner, facilitating exchange and creation of
 //! queries, subscriptions, various events and shared information.
 
+use crate::peer_reputation::PeerReputation;
 use crate::protocols::request_response::request_response_factory::RequestFailure;
 use crate::utils::multihash::Multihash;
 use crate::utils::rate_limiter::RateLimiter;
@@ -11,6 +12,7 @@ use libp2p::gossipsub::{PublishError, Sha256Topic, SubscriptionError};
 use libp2p::kad::{PeerRecord, RecordKey};
 use libp2p::{Multiaddr, PeerId};
 use parking_lot::Mutex;
+use std::collections::HashMap;
 use std::sync::atomic::AtomicUsize;
 use std::sync::Arc;
 use tokio::sync::OwnedSemaphorePermit;
@@ -117,6 +119,9 @@ pub(crate) struct Handlers {
     pub(crate) peer_discovered: Handler<PeerDiscovered>,
 }
 
+/// Peers discovered on local network using mDNS along with their addresses
+pub(crate) type LocalPeers = Arc<Mutex<HashMap<PeerId, Vec<Multiaddr>>>>;
+
 #[derive(Debug)]
 pub(crate) struct Shared {
     pub(crate) handlers: Handlers,
@@ -124,10 +129,14 @@ pub(crate) struct Shared {
     /// Addresses on which node is listening for incoming requests.
     pub(crate) listeners: Mutex<Vec<Multiaddr>>,
     pub(crate) external_addresses: Mutex<Vec<Multiaddr>>,
+    /// Peers discovered on local network using mDNS along with their addresses.
+    pub(crate) local_peers: LocalPeers,
     pub(crate) num_established_peer_connections: Arc<AtomicUsize>,
     /// Sender end of the channel for sending commands to the swarm.
     pub(crate) command_sender: mpsc::Sender<Command>,
     pub(crate) rate_limiter: RateLimiter,
+    /// Reputation of peers based on outcomes of requests sent to them.
+    pub(crate) peer_reputation: Mutex<PeerReputation>,
 }
 
 impl Shared {
@@ -135,15 +144,19 @@ impl Shared {
         id: PeerId,
         command_sender: mpsc::Sender<Command>,
         rate_limiter: RateLimiter,
+        peer_reputation: PeerReputation,
+        local_peers: LocalPeers,
     ) -> Self {
         Self {
             handlers: Handlers::default(),
             id,
             listeners: Mutex::default(),
             external_addresses: Mutex::default(),
+            local_peers,
             num_established_peer_connections: Arc::new(AtomicUsize::new(0)),
             command_sender,
             rate_limiter,
+            peer_reputation: Mutex::new(peer_reputation),
         }
     }
 }
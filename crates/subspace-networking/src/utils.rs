@@ -9,6 +9,7 @@ use event_listener_primitives::Bag;
 use futures::future::{Fuse, FusedFuture, FutureExt};
 use libp2p::multiaddr::Protocol;
 use libp2p::{Multiaddr, PeerId};
+use prometheus_client::metrics::family::Family;
 use prometheus_client::metrics::gauge::Gauge;
 use prometheus_client::registry::Registry;
 use std::future::Future;
@@ -24,6 +25,7 @@ const NETWORKING_REGISTRY_PREFIX: &str = "subspace";
 /// Metrics for Subspace networking
 pub struct SubspaceMetrics {
     established_connections: Gauge,
+    established_connections_by_transport: Family<Vec<(&'static str, String)>, Gauge>,
 }
 
 impl SubspaceMetrics {
@@ -38,18 +40,54 @@ impl SubspaceMetrics {
             gauge.clone(),
         );
 
+        let established_connections_by_transport = Family::default();
+        sub_registry.register(
+            "established_connections_by_transport",
+            "The current number of established connections by transport",
+            established_connections_by_transport.clone(),
+        );
+
         Self {
             established_connections: gauge,
+            established_connections_by_transport,
         }
     }
 
-    pub(crate) fn inc_established_connections(&mut self) {
+    pub(crate) fn inc_established_connections(&mut self, remote_address: &Multiaddr) {
         self.established_connections.inc();
+        self.established_connections_by_transport
+            .get_or_create(&vec![(
+                "transport",
+                transport_name(remote_address).to_string(),
+            )])
+            .inc();
     }
 
-    pub(crate) fn dec_established_connections(&mut self) {
+    pub(crate) fn dec_established_connections(&mut self, remote_address: &Multiaddr) {
         self.established_connections.dec();
+        self.established_connections_by_transport
+            .get_or_create(&vec![(
+                "transport",
+                transport_name(remote_address).to_string(),
+            )])
+            .dec();
+    }
+}
+
+/// Name of the transport used by connection with specified remote address
+fn transport_name(remote_address: &Multiaddr) -> &'static str {
+    if is_relayed_address(remote_address) {
+        return "relay";
     }
+
+    remote_address
+        .iter()
+        .find_map(|protocol| match protocol {
+            Protocol::QuicV1 => Some("quic"),
+            Protocol::Tcp(_) => Some("tcp"),
+            _ => None,
+        })
+        .unwrap_or("other")
 }
 
 /// Joins async join handle on drop
@@ -91,6 +129,12 @@ pub(crate) fn is_global_address_or_dns(addr: &Multiaddr) -> bool {
     }
 }
 
+/// Whether address goes through circuit relay server.
+pub(crate) fn is_relayed_address(addr: &Multiaddr) -> bool {
+    addr.iter()
+        .any(|protocol| matches!(protocol, Protocol::P2pCircuit))
+}
+
 /// Convenience alias for peer ID and its multiaddresses.
 pub type PeerAddress = (PeerId, Multiaddr);
 
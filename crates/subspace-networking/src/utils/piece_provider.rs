@@ -1,16 +1,45 @@
 //! Provides methods to retrieve pieces from DSN.
 
+#[cfg(test)]
+mod tests;
+
+use crate::protocols::request_response::request_response_factory::{
+    OutboundFailure, RequestFailure,
+};
 use crate::utils::multihash::ToMultihash;
-use crate::{Node, PieceByIndexRequest, PieceByIndexResponse};
+use crate::{
+    Node, NodeRequestsBatchHandle, PieceByIndexRequest, PieceByIndexResponse, PiecesByIndexRequest,
+    ReputationChange, SendRequestError, MAX_PIECES_PER_REQUEST,
+};
 use async_trait::async_trait;
-use futures::StreamExt;
+use futures::stream::FuturesUnordered;
+use futures::{stream, Stream, StreamExt};
 use libp2p::kad::RecordKey;
 use libp2p::PeerId;
-use std::collections::HashSet;
+use parking_lot::Mutex;
+use schnellru::{ByLength, LruMap};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use subspace_core_primitives::{Piece, PieceIndex};
+use thiserror::Error;
 use tracing::{debug, trace, warn};
 
+/// Max number of peers to remember as not supporting batched pieces requests.
+const PEERS_WITHOUT_BATCH_SUPPORT_CACHE_SIZE: u32 = 1_000;
+
+/// Piece validation error
+#[derive(Debug, Error)]
+pub enum PieceValidationError {
+    /// Piece is invalid, source of the piece is penalized
+    #[error("Piece is invalid")]
+    Invalid,
+    /// Piece could not be validated (segment commitment is not available, for instance), source of
+    /// the piece is not penalized
+    #[error("Piece could not be validated")]
+    CouldNotValidate,
+}
+
 /// Validates piece against using its commitment.
 #[async_trait]
 pub trait PieceValidator: Sync + Send {
@@ -20,7 +49,7 @@ pub trait PieceValidator: Sync + Send {
         source_peer_id: PeerId,
         piece_index: PieceIndex,
         piece: Piece,
-    ) -> Option<Piece>;
+    ) -> Result<Piece, PieceValidationError>;
 }
 
 /// Stub implementation for piece validation.
@@ -28,15 +57,34 @@ pub struct NoPieceValidator;
 
 #[async_trait]
 impl PieceValidator for NoPieceValidator {
-    async fn validate_piece(&self, _: PeerId, _: PieceIndex, piece: Piece) -> Option<Piece> {
-        Some(piece)
+    async fn validate_piece(
+        &self,
+        _: PeerId,
+        _: PieceIndex,
+        piece: Piece,
+    ) -> Result<Piece, PieceValidationError> {
+        Ok(piece)
     }
 }
 
+/// Policy for requesting pieces from peers discovered on local network using mDNS (see
+/// [`Config::mdns`](crate::Config::mdns)).
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum LocalPeersPolicy {
+    /// Peers on local network are treated the same way as any other peers
+    Ignore,
+    /// Peers on local network are requested before any other peers
+    #[default]
+    Prefer,
+}
+
 /// Piece provider with cancellation and optional piece validator.
 pub struct PieceProvider<PV> {
     node: Node,
     piece_validator: Option<PV>,
+    local_peers_policy: LocalPeersPolicy,
+    /// Peers that don't support [`PiecesByIndexRequest`], such that it is not attempted again
+    peers_without_batch_support: Mutex<LruMap<PeerId, ()>>,
 }
 
 impl<PV> fmt::Debug for PieceProvider<PV> {
@@ -55,18 +103,86 @@ where
         Self {
             node,
             piece_validator,
+            local_peers_policy: LocalPeersPolicy::default(),
+            peers_without_batch_support: Mutex::new(LruMap::new(ByLength::new(
+                PEERS_WITHOUT_BATCH_SUPPORT_CACHE_SIZE,
+            ))),
         }
     }
 
-    /// Returns piece by its index from farmer's piece cache (L2)
+    /// Set policy for peers discovered on local network, [`LocalPeersPolicy::Prefer`] is used by
+    /// default.
+    pub fn with_local_peers_policy(mut self, local_peers_policy: LocalPeersPolicy) -> Self {
+        self.local_peers_policy = local_peers_policy;
+        self
+    }
+
+    /// Whether peers on local network should be requested before other peers
+    fn prefer_local_peers(&self) -> bool {
+        self.local_peers_policy == LocalPeersPolicy::Prefer && !self.node.local_peers().is_empty()
+    }
+
+    /// Whether provider should only be requested after all other providers, which is the case for
+    /// remote providers (if local peers are preferred) and providers with poor reputation
+    fn is_deferred_provider(&self, prefer_local_peers: bool, provider_id: &PeerId) -> bool {
+        (prefer_local_peers && !self.node.is_local_peer(provider_id))
+            || self.node.peer_score(provider_id) < 0.0
+    }
+
+    /// Sort peers such that peers on local network (if preferred) come first, followed by peers
+    /// with higher reputation
+    fn sort_peers(&self, peer_ids: Vec<PeerId>) -> Vec<PeerId> {
+        let prefer_local_peers = self.prefer_local_peers();
+        let mut peers = peer_ids
+            .into_iter()
+            .map(|peer_id| {
+                let is_remote = prefer_local_peers && !self.node.is_local_peer(&peer_id);
+                (is_remote, self.node.peer_score(&peer_id), peer_id)
+            })
+            .collect::<Vec<_>>();
+
+        peers.sort_by(|(a_is_remote, a_score, _), (b_is_remote, b_score, _)| {
+            a_is_remote
+                .cmp(b_is_remote)
+                .then_with(|| b_score.total_cmp(a_score))
+        });
+
+        peers.into_iter().map(|(_, _, peer_id)| peer_id).collect()
+    }
+
+    /// Returns piece by its index from farmer's piece cache (L2).
+    ///
+    /// Peers on local network (if preferred) are requested directly and concurrently before
+    /// looking for providers in DHT. Providers are then requested as soon as they are found, except
+    /// those with poor reputation, which are only requested after all other providers.
     pub async fn get_piece_from_cache(&self, piece_index: PieceIndex) -> Option<Piece> {
         let key = RecordKey::from(piece_index.to_multihash());
 
+        let local_peers = if self.prefer_local_peers() {
+            self.node.local_peers()
+        } else {
+            Vec::new()
+        };
+        if !local_peers.is_empty() {
+            let mut local_requests = local_peers
+                .iter()
+                .map(|&peer_id| self.get_piece_from_peer(peer_id, piece_index))
+                .collect::<FuturesUnordered<_>>();
+
+            while let Some(maybe_piece) = local_requests.next().await {
+                if maybe_piece.is_some() {
+                    return maybe_piece;
+                }
+            }
+        }
+
         let mut request_batch = self.node.get_requests_batch_handle().await;
         let get_providers_result = request_batch.get_providers(key.clone()).await;
 
         match get_providers_result {
             Ok(mut get_providers_stream) => {
+                let mut deferred_provider_ids = Vec::new();
+
                 while let Some(provider_id) = get_providers_stream.next().await {
                     trace!(
                         %piece_index,
@@ -75,44 +191,38 @@ where
                         "get_providers returned an item"
                     );
 
-                    let request_result = request_batch
-                        .send_generic_request(provider_id, PieceByIndexRequest { piece_index })
-                        .await;
+                    if local_peers.contains(&provider_id) {
+                        // Already requested above
+                        continue;
+                    }
 
-                    match request_result {
-                        Ok(PieceByIndexResponse { piece: Some(piece) }) => {
-                            trace!(
-                                %piece_index,
-                                key = hex::encode(&key),
-                                %provider_id,
-                                "Piece request succeeded"
-                            );
-
-                            if let Some(validator) = &self.piece_validator {
-                                return validator
-                                    .validate_piece(provider_id, piece_index, piece)
-                                    .await;
-                            } else {
-                                return Some(piece);
-                            }
-                        }
-                        Ok(PieceByIndexResponse { piece: None }) => {
-                            debug!(
-                                %piece_index,
-                                key = hex::encode(&key),
-                                %provider_id,
-                                "Piece request returned empty piece"
-                            );
-                        }
-                        Err(error) => {
-                            debug!(
-                                %piece_index,
-                                key = hex::encode(&key),
-                                %provider_id,
-                                ?error,
-                                "Piece request failed"
-                            );
-                        }
+                    if self.node.peer_score(&provider_id) < 0.0 {
+                        deferred_provider_ids.push(provider_id);
+                        continue;
+                    }
+
+                    if let Some(piece) = Self::request_piece_from_provider(
+                        &mut request_batch,
+                        provider_id,
+                        piece_index,
+                        &key,
+                    )
+                    .await
+                    {
+                        return self.validate_piece(provider_id, piece_index, piece).await;
+                    }
+                }
+
+                for provider_id in self.sort_peers(deferred_provider_ids) {
+                    if let Some(piece) = Self::request_piece_from_provider(
+                        &mut request_batch,
+                        provider_id,
+                        piece_index,
+                        &key,
+                    )
+                    .await
+                    {
+                        return self.validate_piece(provider_id, piece_index, piece).await;
                     }
                 }
             }
@@ -124,6 +234,225 @@ where
         None
     }
 
+    /// Returns pieces by their indices from farmer's piece cache (L2).
+    ///
+    /// Providers of all pieces are discovered first, such that pieces held by the same provider
+    /// can be requested from it in batches (see [`Self::get_pieces_from_peer()`]). Pieces that
+    /// were not returned by assigned provider are requested from other providers one by one.
+    /// Pieces are yielded as soon as they are retrieved, `None` means piece was not found.
+    pub fn get_pieces_from_cache(
+        &self,
+        piece_indices: Vec<PieceIndex>,
+    ) -> impl Stream<Item = (PieceIndex, Option<Piece>)> + '_ {
+        stream::once(async move {
+            let mut providers =
+                piece_indices
+                    .into_iter()
+                    .collect::<HashSet<_>>()
+                    .into_iter()
+                    .map(|piece_index| async move {
+                        (piece_index, self.find_providers(piece_index).await)
+                    })
+                    .collect::<FuturesUnordered<_>>()
+                    .collect::<HashMap<_, _>>()
+                    .await;
+
+            let requests = self
+                .assign_pieces_to_providers(&providers)
+                .into_iter()
+                .map(|(provider_id, piece_indices)| {
+                    let mut fallback_provider_ids = piece_indices
+                        .iter()
+                        .map(|piece_index| {
+                            (
+                                *piece_index,
+                                providers.remove(piece_index).unwrap_or_default(),
+                            )
+                        })
+                        .collect::<HashMap<_, _>>();
+
+                    async move {
+                        let mut pieces =
+                            self.get_pieces_from_peer(provider_id, piece_indices).await;
+
+                        for (piece_index, maybe_piece) in &mut pieces {
+                            if maybe_piece.is_some() {
+                                continue;
+                            }
+
+                            let piece_index = *piece_index;
+                            let provider_ids = fallback_provider_ids
+                                .remove(&piece_index)
+                                .unwrap_or_default()
+                                .into_iter()
+                                .filter(|fallback_provider_id| *fallback_provider_id != provider_id)
+                                .collect();
+
+                            for fallback_provider_id in self.sort_peers(provider_ids) {
+                                *maybe_piece = self
+                                    .get_piece_from_peer(fallback_provider_id, piece_index)
+                                    .await;
+
+                                if maybe_piece.is_some() {
+                                    break;
+                                }
+                            }
+                        }
+
+                        stream::iter(pieces)
+                    }
+                })
+                .collect::<FuturesUnordered<_>>();
+
+            // Pieces that were not assigned to any provider have no providers at all
+            let not_found = providers
+                .into_keys()
+                .map(|piece_index| {
+                    debug!(%piece_index, "No providers found for piece");
+
+                    (piece_index, None)
+                })
+                .collect::<Vec<_>>();
+
+            stream::iter(not_found).chain(requests.flatten())
+        })
+        .flatten()
+    }
+
+    /// Find all providers of the piece in DHT
+    async fn find_providers(&self, piece_index: PieceIndex) -> Vec<PeerId> {
+        let key = RecordKey::from(piece_index.to_multihash());
+
+        match self.node.get_providers(key.clone()).await {
+            Ok(get_providers_stream) => get_providers_stream.collect().await,
+            Err(err) => {
+                warn!(%piece_index, ?key, ?err, "get_providers returned an error");
+
+                Vec::new()
+            }
+        }
+    }
+
+    /// Assign every piece to one of its providers such that as many pieces as possible are
+    /// requested from the same provider, while deferred providers (see
+    /// [`Self::is_deferred_provider()`]) are only used for pieces no other provider has
+    fn assign_pieces_to_providers(
+        &self,
+        providers: &HashMap<PieceIndex, Vec<PeerId>>,
+    ) -> Vec<(PeerId, Vec<PieceIndex>)> {
+        let mut pieces_by_provider = HashMap::<PeerId, Vec<PieceIndex>>::new();
+        for (piece_index, provider_ids) in providers {
+            for provider_id in provider_ids {
+                pieces_by_provider
+                    .entry(*provider_id)
+                    .or_default()
+                    .push(*piece_index);
+            }
+        }
+
+        let prefer_local_peers = self.prefer_local_peers();
+        let mut provider_ids = self.sort_peers(pieces_by_provider.keys().copied().collect());
+        // Stable sort, such that providers holding the same number of pieces remain in order of
+        // their reputation
+        provider_ids.sort_by_key(|provider_id| {
+            (
+                self.is_deferred_provider(prefer_local_peers, provider_id),
+                Reverse(pieces_by_provider[provider_id].len()),
+            )
+        });
+
+        let mut assigned_piece_indices = HashSet::new();
+        provider_ids
+            .into_iter()
+            .filter_map(|provider_id| {
+                let piece_indices = pieces_by_provider
+                    .remove(&provider_id)?
+                    .into_iter()
+                    .filter(|piece_index| assigned_piece_indices.insert(*piece_index))
+                    .collect::<Vec<_>>();
+
+                (!piece_indices.is_empty()).then_some((provider_id, piece_indices))
+            })
+            .collect()
+    }
+
+    /// Request piece from provider found in DHT
+    async fn request_piece_from_provider(
+        request_batch: &mut NodeRequestsBatchHandle,
+        provider_id: PeerId,
+        piece_index: PieceIndex,
+        key: &RecordKey,
+    ) -> Option<Piece> {
+        let request_result = request_batch
+            .send_generic_request(provider_id, PieceByIndexRequest { piece_index })
+            .await;
+
+        match request_result {
+            Ok(PieceByIndexResponse { piece: Some(piece) }) => {
+                trace!(
+                    %piece_index,
+                    key = hex::encode(key),
+                    %provider_id,
+                    "Piece request succeeded"
+                );
+
+                return Some(piece);
+            }
+            Ok(PieceByIndexResponse { piece: None }) => {
+                debug!(
+                    %piece_index,
+                    key = hex::encode(key),
+                    %provider_id,
+                    "Piece request returned empty piece"
+                );
+            }
+            Err(error) => {
+                debug!(
+                    %piece_index,
+                    key = hex::encode(key),
+                    %provider_id,
+                    ?error,
+                    "Piece request failed"
+                );
+            }
+        }
+
+        None
+    }
+
+    /// Validate piece with piece validator, if there is one
+    async fn validate_piece(
+        &self,
+        peer_id: PeerId,
+        piece_index: PieceIndex,
+        piece: Piece,
+    ) -> Option<Piece> {
+        let validator = match &self.piece_validator {
+            Some(validator) => validator,
+            None => {
+                return Some(piece);
+            }
+        };
+
+        match validator.validate_piece(peer_id, piece_index, piece).await {
+            Ok(piece) => Some(piece),
+            Err(PieceValidationError::Invalid) => {
+                self.node
+                    .report_peer(peer_id, ReputationChange::InvalidResponse);
+                None
+            }
+            Err(PieceValidationError::CouldNotValidate) => {
+                debug!(%peer_id, %piece_index, "Piece could not be validated");
+                None
+            }
+        }
+    }
+
+    /// Piece validator, if there is one
+    pub fn piece_validator(&self) -> Option<&PV> {
+        self.piece_validator.as_ref()
+    }
+
     /// Get piece from a particular peer.
     pub async fn get_piece_from_peer(
         &self,
@@ -139,11 +468,7 @@ where
             Ok(PieceByIndexResponse { piece: Some(piece) }) => {
                 trace!(%peer_id, %piece_index, "Piece request succeeded");
 
-                if let Some(validator) = &self.piece_validator {
-                    return validator.validate_piece(peer_id, piece_index, piece).await;
-                } else {
-                    return Some(piece);
-                }
+                return self.validate_piece(peer_id, piece_index, piece).await;
             }
             Ok(PieceByIndexResponse { piece: None }) => {
                 debug!(%peer_id, %piece_index, "Piece request returned empty piece");
@@ -156,6 +481,120 @@ where
         None
     }
 
+    /// Get multiple pieces from a particular peer.
+    ///
+    /// Pieces are requested in batches of up to [`MAX_PIECES_PER_REQUEST`] using
+    /// [`PiecesByIndexRequest`], peers that don't support it are requested one piece at a time
+    /// instead. Returned pieces are in the same order as requested, `None` means piece was not
+    /// returned by the peer.
+    pub async fn get_pieces_from_peer(
+        &self,
+        peer_id: PeerId,
+        piece_indices: Vec<PieceIndex>,
+    ) -> Vec<(PieceIndex, Option<Piece>)> {
+        let mut pieces = Vec::with_capacity(piece_indices.len());
+
+        for piece_indices in piece_indices.chunks(MAX_PIECES_PER_REQUEST) {
+            let use_batch_request = piece_indices.len() > 1
+                && self
+                    .peers_without_batch_support
+                    .lock()
+                    .peek(&peer_id)
+                    .is_none();
+
+            let maybe_pieces = if use_batch_request {
+                self.request_pieces_from_peer(peer_id, piece_indices).await
+            } else {
+                None
+            };
+
+            match maybe_pieces {
+                Some(maybe_pieces) => {
+                    for (piece_index, maybe_piece) in piece_indices.iter().zip(maybe_pieces) {
+                        let maybe_piece = match maybe_piece {
+                            Some(piece) => self.validate_piece(peer_id, *piece_index, piece).await,
+                            None => None,
+                        };
+
+                        pieces.push((*piece_index, maybe_piece));
+                    }
+                }
+                None => {
+                    for piece_index in piece_indices {
+                        pieces.push((
+                            *piece_index,
+                            self.get_piece_from_peer(peer_id, *piece_index).await,
+                        ));
+                    }
+                }
+            }
+        }
+
+        pieces
+    }
+
+    /// Request multiple pieces with a single request, returns `None` if peer doesn't support
+    /// batched requests and pieces need to be requested one by one instead
+    async fn request_pieces_from_peer(
+        &self,
+        peer_id: PeerId,
+        piece_indices: &[PieceIndex],
+    ) -> Option<Vec<Option<Piece>>> {
+        let request_result = self
+            .node
+            .send_generic_request(
+                peer_id,
+                PiecesByIndexRequest {
+                    piece_indices: piece_indices.to_vec(),
+                },
+            )
+            .await;
+
+        match request_result {
+            Ok(response) => match response.into_pieces(piece_indices.len()) {
+                Some(maybe_pieces) => {
+                    trace!(%peer_id, ?piece_indices, "Pieces request succeeded");
+
+                    Some(maybe_pieces)
+                }
+                None => {
+                    debug!(%peer_id, ?piece_indices, "Pieces request returned malformed response");
+                    self.node
+                        .report_peer(peer_id, ReputationChange::InvalidResponse);
+
+                    Some(vec![None; piece_indices.len()])
+                }
+            },
+            Err(SendRequestError::ProtocolFailure(RequestFailure::Network(
+                OutboundFailure::UnsupportedProtocols,
+            ))) => {
+                debug!(
+                    %peer_id,
+                    "Peer doesn't support batched pieces requests, falling back to single piece \
+                    requests"
+                );
+                self.peers_without_batch_support.lock().insert(peer_id, ());
+
+                None
+            }
+            Err(SendRequestError::ProtocolFailure(RequestFailure::UnknownProtocol)) => {
+                // Not peer's fault, protocol is not registered on this node
+                debug!(
+                    %peer_id,
+                    "Batched pieces requests are not supported by this node, falling back to \
+                    single piece requests"
+                );
+
+                None
+            }
+            Err(error) => {
+                debug!(%peer_id, ?piece_indices, ?error, "Pieces request failed");
+
+                Some(vec![None; piece_indices.len()])
+            }
+        }
+    }
+
     /// Get piece from archival storage (L1). The algorithm tries to get a piece from currently
     /// connected peers and falls back to random walking.
     pub async fn get_piece_from_archival_storage(
@@ -176,7 +615,11 @@ where
                 }
             };
 
-            HashSet::<PeerId>::from_iter(connected_peers)
+            self.sort_peers(
+                HashSet::<PeerId>::from_iter(connected_peers)
+                    .into_iter()
+                    .collect(),
+            )
         };
 
         if connected_peers.is_empty() {
@@ -263,11 +706,7 @@ where
                         Ok(PieceByIndexResponse { piece: Some(piece) }) => {
                             trace!(%peer_id, %piece_index, ?key, %round,  "Piece request succeeded.");
 
-                            if let Some(validator) = &self.piece_validator {
-                                return validator.validate_piece(peer_id, piece_index, piece).await;
-                            } else {
-                                return Some(piece);
-                            }
+                            return self.validate_piece(peer_id, piece_index, piece).await;
                         }
                         Ok(PieceByIndexResponse { piece: None }) => {
                             debug!(%peer_id, %piece_index, ?key, %round, "Piece request returned empty piece.");
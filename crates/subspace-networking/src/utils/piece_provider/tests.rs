@@ -0,0 +1,199 @@
+use crate::utils::piece_provider::{
+    LocalPeersPolicy, NoPieceValidator, PieceProvider, PieceValidationError, PieceValidator,
+};
+use crate::{
+    construct, Config, Node, PieceByIndexRequestHandler, PieceByIndexResponse,
+    PiecesByIndexRequestHandler, PiecesByIndexResponse,
+};
+use async_trait::async_trait;
+use futures::channel::oneshot;
+use libp2p::multiaddr::Protocol;
+use libp2p::{Multiaddr, PeerId};
+use parking_lot::Mutex;
+use std::sync::Arc;
+use subspace_core_primitives::{Piece, PieceIndex};
+
+/// Start node that serves a single piece, returns node with its address
+async fn start_node_with_piece(piece_index: PieceIndex, piece: Piece) -> (Node, Multiaddr) {
+    let config = Config {
+        listen_on: vec!["/ip4/127.0.0.1/tcp/0".parse().unwrap()],
+        request_response_protocols: vec![PieceByIndexRequestHandler::create(move |_, request| {
+            let piece = (request.piece_index == piece_index).then(|| piece.clone());
+
+            async move { Some(PieceByIndexResponse { piece }) }
+        })],
+        ..Config::default()
+    };
+    let (node, mut node_runner) = construct(config).unwrap();
+
+    let (address_sender, address_receiver) = oneshot::channel();
+    let on_new_listener_handler = node.on_new_listener(Arc::new({
+        let address_sender = Mutex::new(Some(address_sender));
+
+        move |address| {
+            if let Some(address_sender) = address_sender.lock().take() {
+                address_sender.send(address.clone()).unwrap();
+            }
+        }
+    }));
+
+    tokio::spawn(async move {
+        node_runner.run().await;
+    });
+
+    let address = address_receiver.await.unwrap();
+    drop(on_new_listener_handler);
+
+    (node, address)
+}
+
+/// Start node with `config` without non-global addresses allowed and connect it to `node` as if it
+/// was discovered on local network using mDNS
+async fn connect_local_peer(node: &Node, address: Multiaddr, config: Config) -> Node {
+    let (local_node, mut local_node_runner) = construct(config).unwrap();
+    tokio::spawn(async move {
+        local_node_runner.run().await;
+    });
+
+    let (connected_sender, connected_receiver) = oneshot::channel();
+    let on_connected_peer_handler = local_node.on_connected_peer(Arc::new({
+        let connected_sender = Mutex::new(Some(connected_sender));
+
+        move |_peer_id| {
+            if let Some(connected_sender) = connected_sender.lock().take() {
+                connected_sender.send(()).unwrap();
+            }
+        }
+    }));
+
+    // Simulate discovery using mDNS
+    local_node
+        .shared
+        .local_peers
+        .lock()
+        .insert(node.id(), vec![address.clone()]);
+    local_node
+        .dial(address.with(Protocol::P2p(node.id())))
+        .await
+        .unwrap();
+    connected_receiver.await.unwrap();
+    drop(on_connected_peer_handler);
+
+    local_node
+}
+
+#[tokio::test]
+async fn local_peers_policy() {
+    let piece_index = PieceIndex::from(1);
+    let mut piece = Piece::default();
+    piece.as_mut()[0] = 1;
+
+    let (node_1, node_1_address) = start_node_with_piece(piece_index, piece.clone()).await;
+    // Non-global addresses are not allowed, but peers on local network are dialed regardless
+    let node_2 = connect_local_peer(&node_1, node_1_address, Config::default()).await;
+
+    // Node 1 doesn't announce itself as a provider of the piece in DHT, so it is only found by
+    // requesting local peers directly
+    let piece_provider = PieceProvider::new(node_2.clone(), None::<NoPieceValidator>)
+        .with_local_peers_policy(LocalPeersPolicy::Ignore);
+    assert!(piece_provider
+        .get_piece_from_cache(piece_index)
+        .await
+        .is_none());
+
+    let piece_provider = PieceProvider::new(node_2, None::<NoPieceValidator>)
+        .with_local_peers_policy(LocalPeersPolicy::Prefer);
+    assert_eq!(
+        piece_provider.get_piece_from_cache(piece_index).await,
+        Some(piece)
+    );
+    assert!(piece_provider
+        .get_piece_from_cache(PieceIndex::from(2))
+        .await
+        .is_none());
+}
+
+/// Validator that fails every piece, either as invalid or as one that could not be validated
+struct FailingPieceValidator {
+    invalid: bool,
+}
+
+#[async_trait]
+impl PieceValidator for FailingPieceValidator {
+    async fn validate_piece(
+        &self,
+        _: PeerId,
+        _: PieceIndex,
+        _: Piece,
+    ) -> Result<Piece, PieceValidationError> {
+        Err(if self.invalid {
+            PieceValidationError::Invalid
+        } else {
+            PieceValidationError::CouldNotValidate
+        })
+    }
+}
+
+#[tokio::test]
+async fn only_invalid_pieces_are_penalized() {
+    let piece_index = PieceIndex::from(1);
+
+    let (node_1, node_1_address) = start_node_with_piece(piece_index, Piece::default()).await;
+    let node_2 = connect_local_peer(&node_1, node_1_address, Config::default()).await;
+
+    let piece_provider = PieceProvider::new(
+        node_2.clone(),
+        Some(FailingPieceValidator { invalid: false }),
+    );
+    assert!(piece_provider
+        .get_piece_from_peer(node_1.id(), piece_index)
+        .await
+        .is_none());
+    // Peer is not penalized for a piece that could not be validated
+    assert!(node_2.peer_score(&node_1.id()) > -10.0);
+
+    let piece_provider = PieceProvider::new(
+        node_2.clone(),
+        Some(FailingPieceValidator { invalid: true }),
+    );
+    assert!(piece_provider
+        .get_piece_from_peer(node_1.id(), piece_index)
+        .await
+        .is_none());
+    assert!(node_2.peer_score(&node_1.id()) < -10.0);
+}
+
+#[tokio::test]
+async fn pieces_from_peer_without_batch_support() {
+    let piece_index = PieceIndex::from(1);
+    let mut piece = Piece::default();
+    piece.as_mut()[0] = 1;
+
+    // Node 1 only supports single piece requests
+    let (node_1, node_1_address) = start_node_with_piece(piece_index, piece.clone()).await;
+    let node_2 = connect_local_peer(
+        &node_1,
+        node_1_address,
+        Config {
+            request_response_protocols: vec![PiecesByIndexRequestHandler::create(|_, _| async {
+                Some(PiecesByIndexResponse::from_pieces(Vec::new()))
+            })],
+            ..Config::default()
+        },
+    )
+    .await;
+
+    let piece_provider = PieceProvider::new(node_2, None::<NoPieceValidator>);
+    assert_eq!(
+        piece_provider
+            .get_pieces_from_peer(node_1.id(), vec![piece_index, PieceIndex::from(2)])
+            .await,
+        vec![(piece_index, Some(piece)), (PieceIndex::from(2), None)]
+    );
+    // Batched requests are not attempted again
+    assert!(piece_provider
+        .peers_without_batch_support
+        .lock()
+        .peek(&node_1.id())
+        .is_some());
+}
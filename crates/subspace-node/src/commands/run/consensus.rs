@@ -121,6 +121,9 @@ struct SubstrateNetworkOptions {
 #[derive(Debug, Parser)]
 struct DsnOptions {
     /// Where local DSN node will listen for incoming connections.
+    ///
+    /// QUIC transport is only enabled when listening on QUIC address, for instance
+    /// `/ip4/0.0.0.0/udp/30433/quic-v1`.
     #[arg(long, default_values_t = [
         Multiaddr::from(IpAddr::V4(Ipv4Addr::UNSPECIFIED))
             .with(Protocol::Tcp(30433)),
@@ -156,6 +159,11 @@ struct DsnOptions {
     /// Known external addresses
     #[arg(long, alias = "dsn-external-address")]
     dsn_external_addresses: Vec<Multiaddr>,
+
+    /// Discover farmers and nodes on local network using mDNS, such that they are connected
+    /// immediately and preferred when requesting pieces.
+    #[arg(long, default_value_t = false)]
+    dsn_mdns: bool,
 }
 
 /// This mode specifies when the block's state (ie, storage) should be pruned (ie, removed) from
@@ -670,6 +678,7 @@ pub(super) fn create_consensus_chain_configuration(
             max_pending_in_connections: dsn_options.dsn_pending_in_connections,
             max_pending_out_connections: dsn_options.dsn_pending_out_connections,
             external_addresses: dsn_options.dsn_external_addresses,
+            mdns: dsn_options.dsn_mdns,
         }
     };
 
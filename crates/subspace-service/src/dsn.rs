@@ -3,12 +3,13 @@ use std::collections::HashSet;
 use std::fs;
 use std::path::PathBuf;
 use subspace_networking::libp2p::kad::Mode;
+use subspace_networking::libp2p::mdns::Config as MdnsConfig;
 use subspace_networking::libp2p::{identity, Multiaddr};
 use subspace_networking::utils::strip_peer_id;
 use subspace_networking::{
     CreationError, KademliaMode, KnownPeersManager, KnownPeersManagerConfig,
-    KnownPeersManagerPersistenceError, Node, NodeRunner, PieceByIndexRequestHandler,
-    SegmentHeaderBySegmentIndexesRequestHandler,
+    KnownPeersManagerPersistenceError, Node, NodeRunner, PeerReputationConfig,
+    PieceByIndexRequestHandler, SegmentHeaderBySegmentIndexesRequestHandler,
 };
 use thiserror::Error;
 use tracing::{error, trace};
@@ -62,6 +63,9 @@ pub struct DsnConfig {
 
     /// Known external addresses
     pub external_addresses: Vec<Multiaddr>,
+
+    /// Determines whether peers on local network are discovered using mDNS.
+    pub mdns: bool,
 }
 
 pub(crate) fn create_dsn_instance(
@@ -72,10 +76,10 @@ pub(crate) fn create_dsn_instance(
     trace!("Subspace networking starting.");
 
     let known_peers_registry = {
-        let network_path = dsn_config.network_path;
+        let network_path = &dsn_config.network_path;
 
         if !network_path.is_dir() {
-            fs::create_dir(&network_path)
+            fs::create_dir(network_path)
                 .map_err(|error| DsnConfigurationError::CreationError(CreationError::Io(error)))?;
         }
         let file_path = network_path.join("known_addresses.bin");
@@ -96,11 +100,20 @@ pub(crate) fn create_dsn_instance(
     let default_networking_config =
         subspace_networking::Config::new(dsn_protocol_version, keypair, (), prometheus_registry);
 
-    let networking_config = subspace_networking::Config {
+    let mut networking_config = subspace_networking::Config {
         keypair: dsn_config.keypair.clone(),
         listen_on: dsn_config.listen_on,
         allow_non_global_addresses_in_dht: dsn_config.allow_non_global_addresses_in_dht,
         known_peers_registry,
+        peer_reputation: PeerReputationConfig {
+            path: Some(
+                dsn_config
+                    .network_path
+                    .join("peer_reputation.bin")
+                    .into_boxed_path(),
+            ),
+            ..PeerReputationConfig::default()
+        },
         request_response_protocols: vec![
             // We need to enable protocol to request pieces
             PieceByIndexRequestHandler::create(|_, _| async { None }),
@@ -113,10 +126,12 @@ pub(crate) fn create_dsn_instance(
         reserved_peers: dsn_config.reserved_peers,
         bootstrap_addresses: dsn_config.bootstrap_nodes,
         external_addresses: dsn_config.external_addresses,
+        mdns: dsn_config.mdns.then(MdnsConfig::default),
         kademlia_mode: KademliaMode::Static(Mode::Client),
 
         ..default_networking_config
     };
+    networking_config.enable_quic_if_listening();
 
     subspace_networking::construct(networking_config).map_err(Into::into)
 }
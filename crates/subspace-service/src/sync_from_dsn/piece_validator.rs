@@ -5,7 +5,7 @@ use subspace_archiving::archiver::is_piece_valid;
 use subspace_core_primitives::crypto::kzg::Kzg;
 use subspace_core_primitives::{Piece, PieceIndex};
 use subspace_networking::libp2p::PeerId;
-use subspace_networking::utils::piece_provider::PieceValidator;
+use subspace_networking::utils::piece_provider::{PieceValidationError, PieceValidator};
 use subspace_networking::Node;
 use tracing::{error, warn};
 
@@ -43,9 +43,9 @@ where
         source_peer_id: PeerId,
         piece_index: PieceIndex,
         piece: Piece,
-    ) -> Option<Piece> {
+    ) -> Result<Piece, PieceValidationError> {
         if source_peer_id == self.dsn_node.id() {
-            return Some(piece);
+            return Ok(piece);
         }
 
         let segment_index = piece_index.segment_index();
@@ -56,7 +56,7 @@ where
             None => {
                 error!(%segment_index, "No segment commitment in the cache.");
 
-                return None;
+                return Err(PieceValidationError::CouldNotValidate);
             }
         };
 
@@ -66,22 +66,25 @@ where
             move || {
                 is_piece_valid(&kzg, &piece, &segment_commitment, piece_index.position())
                     .then_some(piece)
+                    .ok_or(PieceValidationError::Invalid)
             }
         });
 
-        match is_valid_fut.await.unwrap_or_default() {
-            Some(piece) => Some(piece),
-            None => {
-                warn!(
-                    %piece_index,
-                    %source_peer_id,
-                    "Received invalid piece from peer"
-                );
+        let result = is_valid_fut.await.unwrap_or_else(|error| {
+            error!(%piece_index, %error, "Failed to spawn piece validation task");
+            Err(PieceValidationError::CouldNotValidate)
+        });
+        if let Err(PieceValidationError::Invalid) = &result {
+            warn!(
+                %piece_index,
+                %source_peer_id,
+                "Received invalid piece from peer"
+            );
 
-                // We don't care about result here
-                let _ = self.dsn_node.ban_peer(source_peer_id).await;
-                None
-            }
+            // We don't care about result here
+            let _ = self.dsn_node.ban_peer(source_peer_id).await;
         }
+
+        result
     }
 }